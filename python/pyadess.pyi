@@ -6,15 +6,41 @@ class AdEss:
     def __init__(self, threshold: int, n_max: int, weights: ArrayLike) -> None: ...
     def new_for_distribution_threshold(threshold: int, n_max: int, distribution: ArrayLike, res_factor: float) -> AdEss: ...
     def new_for_distribution_num_bits(num_bits: int, n_max: int, distribution: ArrayLike, res_factor: float) -> AdEss: ...
-    def new_for_distribution_optimal_threshold(n_max: int, distribution: ArrayLike, res_factor: float, search_width: int, rev_trellis_calculation_fraction: float) -> AdEss: ...
+    def new_for_distribution_optimal_threshold(n_max: int, distribution: ArrayLike, res_factor: float, search_width: int) -> AdEss: ...
+    def from_preset(name: str) -> AdEss: ...
+    def from_trellis_file(path: str) -> AdEss: ...
+    def save_trellis(self, path: str) -> None: ...
+    def preset_names() -> list[str]: ...
     def weights_from_distribution(self, distribution: ArrayLike, res_factor: float) -> NDArray[np.uint64]: ...
-    def encode(self, index_bits: ArrayLike) -> NDArray[np.uint64]: ...
-    def multi_encode(self, multi_index_bits: ArrayLike) -> NDArray[np.uint64]: ...
-    def decode(self, sequence: ArrayLike) -> NDArray[np.uint64]: ...
-    def multi_decode(self, sequences: ArrayLike) -> NDArray[np.uint64]: ...
+    def encode(self, index_bits: ArrayLike, bit_order: str = "msb") -> NDArray[np.uint64]: ...
+    def multi_encode(self, multi_index_bits: ArrayLike, bit_order: str = "msb") -> NDArray[np.uint64]: ...
+    def decode(self, sequence: ArrayLike, bit_order: str = "msb") -> NDArray[np.uint64]: ...
+    def multi_decode(self, sequences: ArrayLike, bit_order: str = "msb") -> NDArray[np.uint64]: ...
+    def multi_encode_frames(self, frames: NDArray[np.uint8], bit_order: str = "msb") -> NDArray[np.uint64]: ...
+    def multi_decode_frames(self, frames: NDArray[np.uint64], bit_order: str = "msb") -> NDArray[np.uint8]: ...
     def num_data_bits(self) -> int: ...
     def get_weights(self) -> NDArray[np.uint64]: ...
     def get_distribution(self, res_factor: float) -> NDArray[np.float32]: ...
     def amplitude_distribution(self) -> NDArray[np.float32]: ...
     def average_energy(self) -> float: ...
-    def num_sequences_possible(self) -> str: ...
+    def num_sequences_total(self) -> int: ...
+    def num_sequences_used(self) -> int: ...
+    def utilization(self) -> float: ...
+    def benchmark(self, duration: float) -> dict[str, float]: ...
+    def session(self, bit_order: str = "msb") -> Session: ...
+
+class Session:
+    def encode_into(self, index_bits: NDArray[np.uint8], out: NDArray[np.uint64]) -> None: ...
+    def decode_into(self, sequence: NDArray[np.uint64], out: NDArray[np.uint8]) -> None: ...
+
+class RTS:
+    def __init__(self, num_bits: int, n_max: int, weights: ArrayLike) -> None: ...
+    def encode(self, index_bits: ArrayLike, bit_order: str = "msb") -> NDArray[np.uint64]: ...
+    def decode(self, sequence: ArrayLike, bit_order: str = "msb") -> NDArray[np.uint8]: ...
+    def num_data_bits(self) -> int: ...
+    def get_weights(self) -> NDArray[np.uint64]: ...
+    def amplitude_distribution(self) -> NDArray[np.float32]: ...
+    def average_energy(self) -> float: ...
+    def num_sequences_total(self) -> int: ...
+    def num_sequences_used(self) -> int: ...
+    def utilization(self) -> float: ...
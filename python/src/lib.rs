@@ -1,8 +1,19 @@
-use numpy::{IntoPyArray, PyArray, PyArray1, PyArray2};
-use pyo3::exceptions::PyValueError;
+use ndarray::Array3;
+use std::sync::Arc;
+
+use numpy::{
+    IntoPyArray, PyArray, PyArray1, PyArray2, PyArray3, PyReadonlyArray1, PyReadonlyArray3,
+};
+use pyo3::exceptions::{PyIOError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyLong};
 
 use ad_ess::ad_ess::AdEss as Rust_AdEss;
+use ad_ess::matcher::DistributionMatcher;
+use ad_ess::presets;
+use ad_ess::rts::RTS as Rust_RTS;
+use ad_ess::utils::{self, BitOrder};
+use ad_ess::workspace::Workspace;
 
 use rug::Integer;
 
@@ -14,7 +25,7 @@ use rug::Integer;
 /// $a=3$, ...
 #[pyclass]
 pub struct AdEss {
-    adess: Rust_AdEss,
+    adess: Arc<Rust_AdEss>,
 }
 
 #[pymethods]
@@ -28,7 +39,9 @@ impl AdEss {
     #[new]
     pub fn new(threshold: usize, n_max: usize, weights: Vec<usize>) -> PyResult<Self> {
         let adess = Rust_AdEss::new(threshold, n_max, &weights);
-        Ok(AdEss { adess })
+        Ok(AdEss {
+            adess: Arc::new(adess),
+        })
     }
 
     /// Returns a new instance for a given distribution and threshold
@@ -48,7 +61,9 @@ impl AdEss {
         let adess =
             Rust_AdEss::new_for_distribution_threshold(threshold, n_max, &distribution, res_factor);
         match adess {
-            Ok((adess, _)) => Ok(AdEss { adess }),
+            Ok((adess, _)) => Ok(AdEss {
+                adess: Arc::new(adess),
+            }),
             Err(_) => Err(PyValueError::new_err(
                 "AdEss could not be created with given configuration",
             )),
@@ -72,7 +87,9 @@ impl AdEss {
         let adess =
             Rust_AdEss::new_for_distribution_num_bits(num_bits, n_max, &distribution, res_factor);
         match adess {
-            Ok((adess, _)) => Ok(AdEss { adess }),
+            Ok((adess, _)) => Ok(AdEss {
+                adess: Arc::new(adess),
+            }),
             Err(_) => Err(PyValueError::new_err(
                 "AdEss could not be created with given configuration",
             )),
@@ -89,32 +106,72 @@ impl AdEss {
     /// High `res_factor` leads to fine quantisation but a potentially large trellis.
     /// - `search_width`:
     ///     Number of weight levels to check below and above the initial estimated optimal threshold
-    /// - `rev_trellis_calculation_fraction`:
-    ///     The fraction of the reverse trellis that should be calculated. If the calculated
-    ///     fraction is to small, the optimal threshold can not be found.
     #[staticmethod]
     pub fn new_for_distribution_optimal_threshold(
         n_max: usize,
         distribution: Vec<f32>,
         res_factor: f32,
         search_width: usize,
-        rev_trellis_calculation_fraction: f32,
     ) -> PyResult<AdEss> {
         let adess = Rust_AdEss::new_for_distribution_optimal_threshold(
             n_max,
             &distribution,
             res_factor,
             search_width,
-            rev_trellis_calculation_fraction,
         );
         match adess {
-            Ok((adess, _)) => Ok(AdEss { adess }),
+            Ok((adess, _)) => Ok(AdEss {
+                adess: Arc::new(adess),
+            }),
             Err(_) => Err(PyValueError::new_err(
                 "AdEss could not be created with given configuration",
             )),
         }
     }
 
+    /// Returns a new instance for a preset configuration
+    ///
+    /// - `name`: Name of the preset, e.g., `"64qam-r1.5-n96"`, see `preset_names()`
+    #[staticmethod]
+    pub fn from_preset(name: &str) -> PyResult<AdEss> {
+        match presets::adess_for_preset(name) {
+            Ok(adess) => Ok(AdEss {
+                adess: Arc::new(adess),
+            }),
+            Err(err) => Err(PyValueError::new_err(err.message())),
+        }
+    }
+
+    /// Returns a new instance with the trellis read from the file at `path`
+    ///
+    /// - `path`: File written by `save_trellis()`
+    #[staticmethod]
+    pub fn from_trellis_file(path: &str) -> PyResult<AdEss> {
+        match Rust_AdEss::from_trellis_file(path) {
+            Ok(adess) => Ok(AdEss {
+                adess: Arc::new(adess),
+            }),
+            Err(err) => Err(PyIOError::new_err(err.to_string())),
+        }
+    }
+
+    /// Writes the trellis to the file at `path` in a compact binary format
+    pub fn save_trellis(&self, path: &str) -> PyResult<()> {
+        self.adess
+            .trellis
+            .save(path)
+            .map_err(|err| PyIOError::new_err(err.to_string()))
+    }
+
+    /// Returns the names of all preset configurations
+    #[staticmethod]
+    pub fn preset_names() -> Vec<&'static str> {
+        presets::presets()
+            .iter()
+            .map(|preset| preset.name)
+            .collect()
+    }
+
     /// Calculates the trellis weights for a given distribution
     ///
     /// - `distribution`: Array of probabilities $[P(a=1), P(a=3), P(a=5), ...]$
@@ -128,7 +185,7 @@ impl AdEss {
         let weights_result = Rust_AdEss::calc_weights(&distribution, res_factor);
         match weights_result {
             Ok(weights) => Ok(weights),
-            Err(msg) => Err(PyValueError::new_err(msg)),
+            Err(err) => Err(PyValueError::new_err(err.message())),
         }
     }
 
@@ -140,17 +197,20 @@ impl AdEss {
     /// This function raises an exception if `index_bits` is invalid.
     ///
     /// - `index_bits` - numpy array or list of length `num_data_bits()`
+    /// - `bit_order` - `"msb"` (most significant bit first) or `"lsb"`
+    #[args(bit_order = "\"msb\"")]
     pub fn encode<'py>(
         &self,
         py: Python<'py>,
         index_bits: Vec<u8>,
+        bit_order: &str,
     ) -> PyResult<&'py PyArray1<usize>> {
-        // convert vec of index bits to Integer
-        let index = index_bits
-            .into_iter()
-            .fold(Integer::new(), |integer, bit| (integer << 1) + bit);
+        let index = utils::bits_to_integer_with_order(&index_bits, parse_bit_order(bit_order)?);
 
-        let sequence = self.adess.sequence_for_index(&index);
+        let sequence = self
+            .adess
+            .try_sequence_for_index(&index)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
         Ok(sequence.into_pyarray(py))
     }
 
@@ -161,19 +221,23 @@ impl AdEss {
     /// This function raises an exception if one of the index bit strings in `multi_index_bits` is invalid.
     ///
     /// - `index_bits` - 2D numpy array of dimension [arbitrary, `num_data_bits()`]
+    /// - `bit_order` - `"msb"` (most significant bit first) or `"lsb"`
+    #[args(bit_order = "\"msb\"")]
     pub fn multi_encode<'py>(
         &self,
         py: Python<'py>,
-        multi_index_bits: Vec<Vec<u32>>,
+        multi_index_bits: Vec<Vec<u8>>,
+        bit_order: &str,
     ) -> PyResult<&'py PyArray2<usize>> {
+        let bit_order = parse_bit_order(bit_order)?;
         let mut sequences: Vec<Vec<usize>> = Vec::with_capacity(multi_index_bits.len());
         for index_bits in multi_index_bits {
-            // convert vec of index bits to Integer
-            let index = index_bits
-                .into_iter()
-                .fold(Integer::new(), |integer, bit| (integer << 1) + bit);
+            let index = utils::bits_to_integer_with_order(&index_bits, bit_order);
 
-            let sequence = self.adess.sequence_for_index(&index);
+            let sequence = self
+                .adess
+                .try_sequence_for_index(&index)
+                .map_err(|err| PyValueError::new_err(err.to_string()))?;
             sequences.push(sequence)
         }
         let arr = PyArray::from_vec2(py, &sequences).expect("Should be valid ndarray");
@@ -186,28 +250,20 @@ impl AdEss {
     /// Raises an exception if `sequence` is invalid.
     ///
     /// - `sequence` - numpy array or list of length `n_max` (as passed to constructor)
+    /// - `bit_order` - `"msb"` (most significant bit first) or `"lsb"`
+    #[args(bit_order = "\"msb\"")]
     pub fn decode<'py>(
         &self,
         py: Python<'py>,
         sequence: Vec<usize>,
+        bit_order: &str,
     ) -> PyResult<&'py PyArray1<u32>> {
-        // decodes a sequence of amplitudes to a bit array with same length specified by `get_num_bits`
-
-        let index = self.adess.index_for_sequence(&sequence);
-
-        // convert index to numpy array
-        let len = self.adess.num_bits() as usize;
-        let mut bits_vec = vec![0; len];
-        let mut mask = Integer::from(1);
-        let zero = Integer::from(0);
-        for i in (0..len).rev() {
-            let masked_index: Integer = (&mask & &index).into();
-            if masked_index != zero {
-                bits_vec[i] = 1;
-            }
-            mask <<= 1;
-        }
-        Ok(bits_vec.into_pyarray(py))
+        let bit_order = parse_bit_order(bit_order)?;
+        let index = self
+            .adess
+            .try_index_for_sequence(&sequence)
+            .map_err(|err| PyValueError::new_err(err.message()))?;
+        Ok(self.index_to_bit_vec(&index, bit_order).into_pyarray(py))
     }
 
     /// Returns the indexes corresponding to the provided amplitude sequence as a 2D numpy
@@ -216,33 +272,156 @@ impl AdEss {
     /// Raises an exception if any amplitude sequence in `sequences` is invalid.
     ///
     /// - `sequences` - 2D numpy array of dimension [arbitrary, `n_max` (as passed to constructor)]
+    /// - `bit_order` - `"msb"` (most significant bit first) or `"lsb"`
+    #[args(bit_order = "\"msb\"")]
     pub fn multi_decode<'py>(
         &self,
         py: Python<'py>,
         sequences: Vec<Vec<usize>>,
+        bit_order: &str,
     ) -> PyResult<&'py PyArray2<u32>> {
+        let bit_order = parse_bit_order(bit_order)?;
         let mut bit_vectors = Vec::with_capacity(sequences.len());
 
         for sequence in sequences {
-            let index = self.adess.index_for_sequence(&sequence);
-
-            // convert index to numpy array
-            let len = self.adess.num_bits() as usize;
-            let mut bits_vec = vec![0; len];
-            let mut mask = Integer::from(1);
-            let zero = Integer::from(0);
-            for i in (0..len).rev() {
-                let masked_index: Integer = (&mask & &index).into();
-                if masked_index != zero {
-                    bits_vec[i] = 1;
-                }
-                mask <<= 1;
-            }
-            bit_vectors.push(bits_vec);
+            let index = self
+                .adess
+                .try_index_for_sequence(&sequence)
+                .map_err(|err| PyValueError::new_err(err.message()))?;
+            bit_vectors.push(self.index_to_bit_vec(&index, bit_order));
         }
         Ok(PyArray::from_vec2(py, &bit_vectors).unwrap())
     }
 
+    /// Returns the amplitude sequences for frames of bit strings as a 3D numpy array
+    ///
+    /// The blocks of all frames are encoded in parallel, the result has the dimension
+    /// [frames, blocks, `n_max`]. The values in `frames` should be either `1` or `0`.
+    ///
+    /// Raises an exception if any index bit string in `frames` is invalid.
+    ///
+    /// - `frames` - 3D numpy array of dimension [frames, blocks, `num_data_bits()`]
+    /// - `bit_order` - `"msb"` (most significant bit first) or `"lsb"`
+    #[args(bit_order = "\"msb\"")]
+    pub fn multi_encode_frames<'py>(
+        &self,
+        py: Python<'py>,
+        frames: PyReadonlyArray3<u8>,
+        bit_order: &str,
+    ) -> PyResult<&'py PyArray3<usize>> {
+        let bit_order = parse_bit_order(bit_order)?;
+        let frames = frames.as_array();
+        let (num_frames, num_blocks, num_bits) = frames.dim();
+        if num_bits != self.adess.num_bits() as usize {
+            return Err(PyValueError::new_err(
+                "The last dimension of `frames` must be `num_data_bits()`",
+            ));
+        }
+        let n_max = self.adess.trellis.n_max;
+        let mut sequences = Array3::<usize>::zeros((num_frames, num_blocks, n_max));
+
+        let frames = frames.as_standard_layout();
+        let bits = frames.as_slice().expect("standard layout is contiguous");
+        let output = sequences
+            .as_slice_mut()
+            .expect("newly allocated array is contiguous");
+        py.allow_threads(|| {
+            for_each_block_parallel(
+                num_frames * num_blocks,
+                (bits, num_bits),
+                (output, n_max),
+                |index_bits, sequence| {
+                    let index = utils::bits_to_integer_with_order(index_bits, bit_order);
+                    sequence.copy_from_slice(&self.adess.try_sequence_for_index(&index)?);
+                    Ok(())
+                },
+            )
+        })
+        .map_err(|err| PyValueError::new_err(err.message()))?;
+        Ok(sequences.into_pyarray(py))
+    }
+
+    /// Returns the bit strings for frames of amplitude sequences as a 3D numpy array
+    ///
+    /// The blocks of all frames are decoded in parallel, the result has the dimension
+    /// [frames, blocks, `num_data_bits()`].
+    ///
+    /// Raises an exception if any amplitude sequence in `frames` is invalid.
+    ///
+    /// - `frames` - 3D numpy array of dimension [frames, blocks, `n_max`]
+    /// - `bit_order` - `"msb"` (most significant bit first) or `"lsb"`
+    #[args(bit_order = "\"msb\"")]
+    pub fn multi_decode_frames<'py>(
+        &self,
+        py: Python<'py>,
+        frames: PyReadonlyArray3<usize>,
+        bit_order: &str,
+    ) -> PyResult<&'py PyArray3<u8>> {
+        let bit_order = parse_bit_order(bit_order)?;
+        let frames = frames.as_array();
+        let (num_frames, num_blocks, n_max) = frames.dim();
+        if n_max != self.adess.trellis.n_max {
+            return Err(PyValueError::new_err(
+                "The last dimension of `frames` must be `n_max`",
+            ));
+        }
+        let num_bits = self.adess.num_bits() as usize;
+        let mut bits = Array3::<u8>::zeros((num_frames, num_blocks, num_bits));
+
+        let frames = frames.as_standard_layout();
+        let sequences = frames.as_slice().expect("standard layout is contiguous");
+        let output = bits
+            .as_slice_mut()
+            .expect("newly allocated array is contiguous");
+        py.allow_threads(|| {
+            for_each_block_parallel(
+                num_frames * num_blocks,
+                (sequences, n_max),
+                (output, num_bits),
+                |sequence, index_bits| {
+                    let index = self.adess.try_index_for_sequence(sequence)?;
+                    utils::integer_to_bits_into(&index, bit_order, index_bits);
+                    Ok(())
+                },
+            )
+        })
+        .map_err(|err| PyValueError::new_err(err.message()))?;
+        Ok(bits.into_pyarray(py))
+    }
+
+    /// Measures the encode and decode throughput for `duration` seconds each
+    ///
+    /// Returns a dict with the number of processed blocks, blocks/s and Mbit/s for encoding
+    /// and decoding.
+    pub fn benchmark<'py>(&self, py: Python<'py>, duration: f64) -> PyResult<&'py PyDict> {
+        if !(duration >= 0.0 && duration.is_finite()) {
+            return Err(PyValueError::new_err(
+                "`duration` must be a non-negative number",
+            ));
+        }
+        let result = py.allow_threads(|| {
+            self.adess
+                .benchmark(std::time::Duration::from_secs_f64(duration))
+        });
+        let dict = PyDict::new(py);
+        dict.set_item("num_encoded_blocks", result.num_encoded_blocks)?;
+        dict.set_item("num_decoded_blocks", result.num_decoded_blocks)?;
+        dict.set_item("encode_blocks_per_second", result.encode_blocks_per_second)?;
+        dict.set_item("decode_blocks_per_second", result.decode_blocks_per_second)?;
+        dict.set_item("encode_mbit_per_second", result.encode_mbit_per_second)?;
+        dict.set_item("decode_mbit_per_second", result.decode_mbit_per_second)?;
+        Ok(dict)
+    }
+    /// Returns a `Session` for repeated encoding / decoding into preallocated numpy arrays
+    ///
+    /// - `bit_order` - `"msb"` (most significant bit first) or `"lsb"`
+    #[args(bit_order = "\"msb\"")]
+    pub fn session(&self, bit_order: &str) -> PyResult<Session> {
+        let mut workspace = Workspace::new(Arc::clone(&self.adess));
+        workspace.set_bit_order(parse_bit_order(bit_order)?);
+        Ok(Session { workspace })
+    }
+
     /// Returns the number of bits encoded per amplitude sequence
     pub fn num_data_bits(&self) -> PyResult<u32> {
         Ok(self.adess.num_bits())
@@ -266,12 +445,254 @@ impl AdEss {
     pub fn average_energy(&self) -> PyResult<f32> {
         Ok(self.adess.average_energy())
     }
-    /// Returns the maximum number of possible amplitude sequences as a string
+    /// Returns the number of amplitude sequences held by the trellis as a Python `int`
+    pub fn num_sequences_total(&self, py: Python) -> PyResult<PyObject> {
+        integer_to_py(py, &self.adess.num_sequences())
+    }
+    /// Returns the number of amplitude sequences used for encoding ($2^{num\_data\_bits}$) as
+    /// a Python `int`
+    pub fn num_sequences_used(&self, py: Python) -> PyResult<PyObject> {
+        integer_to_py(py, &self.adess.num_sequences_used())
+    }
+    /// Returns the fraction of the amplitude sequences used for encoding
+    pub fn utilization(&self) -> PyResult<f64> {
+        Ok(self.adess.utilization())
+    }
+}
+
+impl AdEss {
+    /// Returns the `num_data_bits()` bits of `index` in `bit_order`
+    fn index_to_bit_vec(&self, index: &Integer, bit_order: BitOrder) -> Vec<u32> {
+        utils::integer_to_bits_with_order(index, self.adess.num_bits() as usize, bit_order)
+            .into_iter()
+            .map(u32::from)
+            .collect()
+    }
+}
+
+/// Reusable workspace of an `AdEss` for real-time loops, see `AdEss.session()`
+///
+/// The scratch memory is allocated once, the results are written into user-provided numpy
+/// arrays, so repeated calls do not allocate.
+#[pyclass]
+pub struct Session {
+    workspace: Workspace,
+}
+
+#[pymethods]
+impl Session {
+    /// Writes the amplitude sequence for the given bits into `out`
+    ///
+    /// Raises an exception if `index_bits` or `out` is invalid.
+    ///
+    /// - `index_bits` - contiguous `uint8` numpy array of length `num_data_bits()`
+    /// - `out` - contiguous `uint64` numpy array of length `n_max`
+    pub fn encode_into(
+        &mut self,
+        index_bits: PyReadonlyArray1<u8>,
+        out: &PyArray1<usize>,
+    ) -> PyResult<()> {
+        let index_bits = index_bits
+            .as_slice()
+            .map_err(|_| PyValueError::new_err("`index_bits` must be contiguous"))?;
+        let mut out = out.readwrite();
+        let sequence = out
+            .as_slice_mut()
+            .map_err(|_| PyValueError::new_err("`out` must be contiguous"))?;
+        self.workspace
+            .encode_bits_into(index_bits, sequence)
+            .map_err(|err| PyValueError::new_err(err.message()))
+    }
+    /// Writes the bits of the index corresponding to the amplitude sequence into `out`
+    ///
+    /// Raises an exception if `sequence` or `out` is invalid.
+    ///
+    /// - `sequence` - contiguous `uint64` numpy array of length `n_max`
+    /// - `out` - contiguous `uint8` numpy array of length `num_data_bits()`
+    pub fn decode_into(
+        &mut self,
+        sequence: PyReadonlyArray1<usize>,
+        out: &PyArray1<u8>,
+    ) -> PyResult<()> {
+        let sequence = sequence
+            .as_slice()
+            .map_err(|_| PyValueError::new_err("`sequence` must be contiguous"))?;
+        let mut out = out.readwrite();
+        let index_bits = out
+            .as_slice_mut()
+            .map_err(|_| PyValueError::new_err("`out` must be contiguous"))?;
+        self.workspace
+            .decode_bits_into(sequence, index_bits)
+            .map_err(|err| PyValueError::new_err(err.message()))
+    }
+}
+
+/// Reverse trellis shaping encoder/decoder with energy based ordering of the sequences
+///
+/// - `num_bits`: Minimum number of data bits that can be encoded
+/// - `n_max`: Number of symbols/amplitudes
+/// - `weights`: Array of weights, `weights[0]` is the weight for $a=1$, `weights[1]` for
+/// $a=3$, ...
+#[pyclass]
+pub struct RTS {
+    rts: Rust_RTS,
+}
+
+#[pymethods]
+impl RTS {
+    /// Reverse trellis shaping encoder/decoder with energy based ordering of the sequences
+    ///
+    /// - `num_bits`: Minimum number of data bits that can be encoded
+    /// - `n_max`: Number of symbols/amplitudes
+    /// - `weights`: Array of weights, `weights[0]` is the weight for $a=1$, `weights[1]` for
+    /// $a=3$, ...
+    #[new]
+    pub fn new(num_bits: usize, n_max: usize, weights: Vec<usize>) -> PyResult<Self> {
+        match Rust_RTS::try_new(num_bits, n_max, &weights) {
+            Ok(rts) => Ok(RTS { rts }),
+            Err(err) => Err(PyValueError::new_err(err.message())),
+        }
+    }
+
+    /// Returns the amplitude sequence for the given bits as a numpy array
+    ///
+    /// Raises an exception if `index_bits` is invalid.
     ///
-    /// WARNING: Effect of limiting the used indexes to a power of two is not regarded!!!
-    pub fn num_sequences_possible(&self) -> PyResult<String> {
-        Ok(self.adess.num_sequences().to_string_radix(10))
+    /// - `index_bits` - numpy array or list of length `num_data_bits()`
+    /// - `bit_order` - `"msb"` (most significant bit first) or `"lsb"`
+    #[args(bit_order = "\"msb\"")]
+    pub fn encode<'py>(
+        &self,
+        py: Python<'py>,
+        index_bits: Vec<u8>,
+        bit_order: &str,
+    ) -> PyResult<&'py PyArray1<usize>> {
+        let index = utils::bits_to_integer_with_order(&index_bits, parse_bit_order(bit_order)?);
+        let sequence = self
+            .rts
+            .try_sequence_for_index(&index)
+            .map_err(|err| PyValueError::new_err(err.message()))?;
+        Ok(sequence.into_pyarray(py))
     }
+
+    /// Returns the index corresponding to the provided amplitude sequence as a numpy
+    /// array of `1`s and `0`s
+    ///
+    /// Raises an exception if `sequence` is invalid.
+    ///
+    /// - `sequence` - numpy array or list of length `n_max` (as passed to constructor)
+    /// - `bit_order` - `"msb"` (most significant bit first) or `"lsb"`
+    #[args(bit_order = "\"msb\"")]
+    pub fn decode<'py>(
+        &self,
+        py: Python<'py>,
+        sequence: Vec<usize>,
+        bit_order: &str,
+    ) -> PyResult<&'py PyArray1<u8>> {
+        let index = self
+            .rts
+            .try_index_for_sequence(&sequence)
+            .map_err(|err| PyValueError::new_err(err.message()))?;
+        let bits = utils::integer_to_bits_with_order(
+            &index,
+            self.rts.num_bits() as usize,
+            parse_bit_order(bit_order)?,
+        );
+        Ok(bits.into_pyarray(py))
+    }
+
+    /// Returns the number of bits encoded per amplitude sequence
+    pub fn num_data_bits(&self) -> PyResult<u32> {
+        Ok(self.rts.num_bits())
+    }
+    /// Returns the weights used by the internal trellis
+    pub fn get_weights<'py>(&self, py: Python<'py>) -> &'py PyArray1<usize> {
+        self.rts.get_weights().into_pyarray(py)
+    }
+    /// Returns the probabilities of the amplitude values
+    ///
+    /// The probabilities are returned as an array with the lowest index corresponding to the
+    /// lowest amplitude.
+    pub fn amplitude_distribution<'py>(&self, py: Python<'py>) -> PyResult<&'py PyArray1<f32>> {
+        Ok(self.rts.amplitude_distribution().into_pyarray(py))
+    }
+    /// Returns the average energy of amplitude sequences
+    pub fn average_energy(&self) -> PyResult<f32> {
+        Ok(DistributionMatcher::average_energy(&self.rts))
+    }
+    /// Returns the number of amplitude sequences held by the trellis as a Python `int`
+    pub fn num_sequences_total(&self, py: Python) -> PyResult<PyObject> {
+        integer_to_py(py, &self.rts.num_sequences())
+    }
+    /// Returns the number of amplitude sequences used for encoding ($2^{num\_data\_bits}$) as
+    /// a Python `int`
+    pub fn num_sequences_used(&self, py: Python) -> PyResult<PyObject> {
+        integer_to_py(py, &self.rts.num_sequences_used())
+    }
+    /// Returns the fraction of the amplitude sequences used for encoding
+    pub fn utilization(&self) -> PyResult<f64> {
+        Ok(self.rts.utilization())
+    }
+}
+
+/// Converts an [Integer] of arbitrary size into a Python `int`
+fn integer_to_py(py: Python, integer: &Integer) -> PyResult<PyObject> {
+    let int = py
+        .get_type::<PyLong>()
+        .call1((integer.to_string_radix(10),))?;
+    Ok(int.into_py(py))
+}
+
+/// Parses the `bit_order` argument, `"msb"` (most significant bit first) or `"lsb"`
+fn parse_bit_order(bit_order: &str) -> PyResult<BitOrder> {
+    match bit_order {
+        "msb" => Ok(BitOrder::MsbFirst),
+        "lsb" => Ok(BitOrder::LsbFirst),
+        _ => Err(PyValueError::new_err(
+            "`bit_order` must be \"msb\" or \"lsb\"",
+        )),
+    }
+}
+
+/// Applies `process` to each of the `num_blocks` blocks of `input_len` inputs and `output_len`
+/// outputs
+///
+/// The blocks are split into one contiguous chunk per available CPU. A chunk stops at its first
+/// error, the error of the first failed chunk is returned once all threads are joined.
+fn for_each_block_parallel<I: Sync, O: Send, E: Send>(
+    num_blocks: usize,
+    (input, input_len): (&[I], usize),
+    (output, output_len): (&mut [O], usize),
+    process: impl Fn(&[I], &mut [O]) -> Result<(), E> + Sync,
+) -> Result<(), E> {
+    let num_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let blocks_per_thread = num_blocks.div_ceil(num_threads).max(1);
+    let process = &process;
+    let chunk_results: Vec<Result<(), E>> = std::thread::scope(|scope| {
+        let mut output = output;
+        let mut handles = Vec::with_capacity(num_threads);
+        for first_block in (0..num_blocks).step_by(blocks_per_thread) {
+            let num_chunk_blocks = blocks_per_thread.min(num_blocks - first_block);
+            let (output_chunk, rest) =
+                std::mem::take(&mut output).split_at_mut(num_chunk_blocks * output_len);
+            output = rest;
+            let input_chunk = &input[first_block * input_len..][..num_chunk_blocks * input_len];
+            handles.push(scope.spawn(move || {
+                for block in 0..num_chunk_blocks {
+                    process(
+                        &input_chunk[block * input_len..][..input_len],
+                        &mut output_chunk[block * output_len..][..output_len],
+                    )?;
+                }
+                Ok(())
+            }));
+        }
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("processing a block does not panic"))
+            .collect()
+    });
+    chunk_results.into_iter().collect()
 }
 
 /// Python distribution matcher module implemented in Rust.
@@ -286,5 +707,7 @@ impl AdEss {
 #[pymodule]
 fn pyadess(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<AdEss>()?;
+    m.add_class::<RTS>()?;
+    m.add_class::<Session>()?;
     Ok(())
 }
@@ -1,10 +1,24 @@
-use rug::Complete;
-use rug::Integer;
-use rug::Rational;
+use std::hint::black_box;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::trellis::Trellis;
+use crate::bigint::{Complete, Integer, Order, RandState, Rational};
+use crate::cancel::CancellationToken;
+use crate::complexity::{self, ThresholdOrBits};
+use crate::error::AdEssError;
+use crate::report::ShapingReport;
+use crate::rts::RTS;
+use crate::trellis::{max_used_weight, Trellis, EXCLUDED_WEIGHT};
 use crate::trellis_utils;
-use crate::utils::{cumsum, entropy, kl_divergence};
+use crate::trellis_value::TrellisValue;
+use crate::utils::{self, cumsum, entropy, kl_divergence, BitOrder};
+
+/// Resolution factors searched by [AdEss::new_for_budget()] and [AdEss::new_for_average_energy()],
+/// see [AdEss::calc_weights()]
+const RES_FACTORS: [f32; 5] = [1.0, 2.0, 4.0, 8.0, 16.0];
 
 /// Arbitrary-Distribution ESS (AD-ESS)
 ///
@@ -12,22 +26,112 @@ use crate::utils::{cumsum, entropy, kl_divergence};
 /// [decode](AdEss::index_for_sequence) using AD-ESS.
 /// Additional methods calculate the [amplitude distribution](AdEss::amplitude_distribution),
 /// [average energy](AdEss::average_energy) or other metrics.
+///
+/// # Thread safety
+///
+/// [AdEss] is `Send + Sync`: encoding and decoding only read the trellis, so one instance can be
+/// used from many threads (e.g., a rayon thread pool) without a mutex. The trellis is shared
+/// between clones, so cloning an instance for each worker does not copy the node values.
+/// Modifications via [AdEss::trellis_mut()] copy a shared trellis first.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AdEss {
-    pub trellis: Trellis,
+    pub trellis: Arc<Trellis>,
 }
 
 impl AdEss {
     /// Returns a new [AdEss] instance given weights
     ///
     /// The trellis is calculated with `n_max` stages using the weights `weights` and holds
-    /// sequences with a sum weight up to `threshold`.
+    /// sequences with a sum weight up to `threshold`. See [AutoAdEss](crate::compact::AutoAdEss)
+    /// for fixed width node values if the number of sequences is small.
     pub fn new(threshold: usize, n_max: usize, weights: &[usize]) -> AdEss {
         let trellis = Trellis::new(threshold, n_max, weights);
-        let mut instance = AdEss { trellis };
+        let mut instance = AdEss {
+            trellis: Arc::new(trellis),
+        };
         instance.calc_forward_trellis();
         instance
     }
 
+    /// Returns a new [AdEss] instance like [AdEss::new()] or an error if `token` is cancelled
+    ///
+    /// The token is checked once per trellis stage.
+    pub fn new_cancellable(
+        threshold: usize,
+        n_max: usize,
+        weights: &[usize],
+        token: &CancellationToken,
+    ) -> Result<AdEss, AdEssError> {
+        let trellis = Trellis::new(threshold, n_max, weights);
+        let mut instance = AdEss {
+            trellis: Arc::new(trellis),
+        };
+        instance.fill_forward_trellis(|_, _| true, token, None)?;
+        Ok(instance)
+    }
+
+    /// Returns a new [AdEss] instance like [AdEss::new()] or an error if more than `memory_limit`
+    /// bytes would be needed for the trellis values
+    ///
+    /// The memory of the nodes is checked before the trellis is allocated, the memory of the node
+    /// values is tracked stage by stage during the construction, see [Trellis::memory_bytes()].
+    pub fn new_with_memory_limit(
+        threshold: usize,
+        n_max: usize,
+        weights: &[usize],
+        memory_limit: usize,
+    ) -> Result<AdEss, AdEssError> {
+        let num_weight_levels = Trellis::new_structure_only(threshold, n_max, weights)
+            .get_weight_levels()
+            .len();
+        if (n_max + 1) * num_weight_levels * std::mem::size_of::<Integer>() > memory_limit {
+            return Err(AdEssError::MemoryLimitExceeded);
+        }
+        let mut instance = AdEss {
+            trellis: Arc::new(Trellis::new(threshold, n_max, weights)),
+        };
+        instance.fill_forward_trellis(
+            |_, _| true,
+            &CancellationToken::new(),
+            Some(memory_limit),
+        )?;
+        Ok(instance)
+    }
+
+    /// Returns a new [AdEss] instance given arbitrary weights or an error for invalid parameters
+    ///
+    /// Unlike [AdEss::new()] the smallest weight does not need to be 0. The smallest weight is
+    /// subtracted from all weights and `n_max` times the smallest weight from `threshold`, i.e.,
+    /// [AdEss::get_weights()] returns the shifted weights. An error is returned if `weights` is
+    /// empty, `n_max` times the smallest weight overflows or `threshold` is below the smallest
+    /// reachable sum weight.
+    pub fn try_new(threshold: usize, n_max: usize, weights: &[usize]) -> Result<AdEss, AdEssError> {
+        let min_weight = *weights
+            .iter()
+            .min()
+            .ok_or(AdEssError::InvalidWeights("`weights` must not be empty"))?;
+        let min_sum_weight = n_max
+            .checked_mul(min_weight)
+            .ok_or(AdEssError::InvalidParameter(
+                "The smallest reachable sum weight overflows `usize`",
+            ))?;
+        let threshold =
+            threshold
+                .checked_sub(min_sum_weight)
+                .ok_or(AdEssError::InvalidParameter(
+                    "`threshold` is below the smallest reachable sum weight",
+                ))?;
+        let weights: Vec<usize> = weights
+            .iter()
+            .map(|&w| match w {
+                EXCLUDED_WEIGHT => EXCLUDED_WEIGHT,
+                w => w - min_weight,
+            })
+            .collect();
+        Ok(AdEss::new(threshold, n_max, &weights))
+    }
+
     /// Returns a new [AdEss] instance given a distribution
     ///
     /// The trellis is calculated with `n_max` stages using weights computed via
@@ -41,7 +145,7 @@ impl AdEss {
         n_max: usize,
         distribution: &[f32],
         res_factor: f32,
-    ) -> Result<(AdEss, Vec<f32>), &'static str> {
+    ) -> Result<(AdEss, Vec<f32>), AdEssError> {
         let weights = AdEss::calc_weights(distribution, res_factor)?;
         let adess = AdEss::new(threshold, n_max, &weights);
         let p_goal = adess.get_distribution(res_factor);
@@ -63,20 +167,82 @@ impl AdEss {
         n_max: usize,
         distribution: &[f32],
         res_factor: f32,
-    ) -> Result<(AdEss, Vec<f32>), &'static str> {
+    ) -> Result<(AdEss, Vec<f32>), AdEssError> {
+        AdEss::new_for_distribution_num_bits_cancellable(
+            num_bits,
+            n_max,
+            distribution,
+            res_factor,
+            &CancellationToken::new(),
+        )
+    }
+
+    /// Returns an [AdEss] instance like [AdEss::new_for_distribution_num_bits()] or an error if
+    /// `token` is cancelled during the threshold search or the trellis construction
+    pub fn new_for_distribution_num_bits_cancellable(
+        num_bits: usize,
+        n_max: usize,
+        distribution: &[f32],
+        res_factor: f32,
+        token: &CancellationToken,
+    ) -> Result<(AdEss, Vec<f32>), AdEssError> {
         let weights = AdEss::calc_weights(distribution, res_factor)?;
 
         let num_sequences = Integer::u_pow_u(2, num_bits as u32).complete();
-        let reverse_trellis =
-            trellis_utils::reverse_trellis_upto_num_sequences(num_sequences, n_max, &weights)?;
+        let reverse_trellis = trellis_utils::reverse_trellis_upto_num_sequences_cancellable(
+            num_sequences,
+            n_max,
+            &weights,
+            token,
+        )?;
         let threshold = reverse_trellis.threshold;
+        // release the reverse trellis before the forward trellis is allocated
+        drop(reverse_trellis);
 
-        let adess = AdEss::new(threshold, n_max, &weights);
+        let adess = AdEss::new_cancellable(threshold, n_max, &weights, token)?;
         let p_goal = adess.get_distribution(res_factor);
 
         Ok((adess, p_goal))
     }
 
+    /// Returns a new [AdEss] instance given floating point weights, e.g., `-log2(p)`
+    ///
+    /// The weights are quantized with the step size `quantization_step` via
+    /// [AdEss::quantize_weights()]. The trellis holds sequences whose sum of
+    /// `weights - min(weights)` is at most `threshold`, after `threshold` is quantized the same way.
+    pub fn new_for_float_weights(
+        threshold: f64,
+        n_max: usize,
+        weights: &[f64],
+        quantization_step: f64,
+    ) -> Result<AdEss, AdEssError> {
+        let weights = AdEss::quantize_weights(weights, quantization_step)?;
+        if !(threshold >= 0.0 && threshold.is_finite()) {
+            return Err(AdEssError::InvalidParameter(
+                "`threshold` must be finite and non-negative",
+            ));
+        }
+        let threshold = (threshold / quantization_step).round() as usize;
+        Ok(AdEss::new(threshold, n_max, &weights))
+    }
+
+    /// Returns an [AdEss] instance given floating point weights which encodes at least `num_bits` bits
+    ///
+    /// The weights are quantized via [AdEss::quantize_weights()], the threshold is chosen as in
+    /// [AdEss::new_for_distribution_num_bits()].
+    pub fn new_for_float_weights_num_bits(
+        num_bits: usize,
+        n_max: usize,
+        weights: &[f64],
+        quantization_step: f64,
+    ) -> Result<AdEss, AdEssError> {
+        let weights = AdEss::quantize_weights(weights, quantization_step)?;
+        let num_sequences = Integer::u_pow_u(2, num_bits as u32).complete();
+        let reverse_trellis =
+            trellis_utils::reverse_trellis_upto_num_sequences(num_sequences, n_max, &weights)?;
+        Ok(AdEss::new(reverse_trellis.threshold, n_max, &weights))
+    }
+
     /// Returns an [AdEss] whith a threshold chosen to maximizes the lower bound on mutual information
     ///
     /// According to formulas (13) and (14) in <https://doi.org/10.1109/LWC.2018.2890595>
@@ -85,23 +251,14 @@ impl AdEss {
     /// [AdEss::calc_weights()], `distribution` and `res_factor` are passed to [AdEss::calc_weights()].
     ///
     /// - `search_width`: number of weight levels to check below and above the initial estimated
-    ///   optimal threshold
-    /// - `rev_trellis_calculation_fraction`: the fraction of the reverse trellis that should be
-    ///   calculated. If the calculated fraction is to small, the optimal threshold can not be found.
+    ///   optimal threshold, see [AdEss::optimal_threshold()]
     pub fn new_for_distribution_optimal_threshold(
         n_max: usize,
-        distribution: &Vec<f32>,
+        distribution: &[f32],
         res_factor: f32,
         search_width: usize,
-        rev_trellis_calculation_fraction: f32,
-    ) -> Result<(AdEss, Vec<f32>), &'static str> {
-        let threshold = AdEss::optimal_threshold(
-            n_max,
-            distribution,
-            res_factor,
-            search_width,
-            rev_trellis_calculation_fraction,
-        )?;
+    ) -> Result<(AdEss, Vec<f32>), AdEssError> {
+        let threshold = AdEss::optimal_threshold(n_max, distribution, res_factor, search_width)?;
 
         let result =
             AdEss::new_for_distribution_threshold(threshold, n_max, distribution, res_factor)?;
@@ -109,42 +266,479 @@ impl AdEss {
         Ok(result)
     }
 
+    /// Returns the [AdEss] with maximum rate whose average energy does not exceed `average_energy`
+    ///
+    /// The weights are computed via [AdEss::calc_weights()] from the Maxwell-Boltzmann
+    /// distribution on `num_amplitudes` amplitudes `1, 3, 5, ...` with the average energy
+    /// `average_energy`, see [utils::maxwell_boltzmann_for_energy()], for the resolution factors
+    /// `1, 2, 4, 8, 16`. For each resolution factor the largest threshold that meets the energy
+    /// target is found by bisection over the weight levels, assuming the average energy grows with
+    /// the threshold. As in [AdEss::threshold_sweep()], only a single reverse trellis is
+    /// calculated per resolution factor and the amplitude distributions are looked up from its
+    /// cumulative sums. The candidate with the most bits is selected, the smaller resolution
+    /// factor on ties.
+    ///
+    /// A new [AdEss] instance and the target distribution [AdEss::get_distribution()] are returned.
+    pub fn new_for_average_energy(
+        n_max: usize,
+        num_amplitudes: usize,
+        average_energy: f32,
+    ) -> Result<(AdEss, Vec<f32>), AdEssError> {
+        let levels = utils::uniform_levels(num_amplitudes);
+        let distribution = utils::maxwell_boltzmann_for_energy(&levels, average_energy)
+            .map_err(AdEssError::InvalidParameter)?;
+
+        // (num_bits, threshold, weights, res_factor) of the best candidate
+        let mut best: Option<(u32, usize, Vec<usize>, f32)> = None;
+        for res_factor in RES_FACTORS {
+            let weights = AdEss::calc_weights(&distribution, res_factor)?;
+            let max_threshold = max_used_weight(&weights).unwrap() * n_max;
+            let rev_trellis = AdEss::calc_reverse_trellis(max_threshold, n_max, &weights);
+            let cumulative_sums: Vec<Vec<Integer>> = (0..n_max + 1)
+                .map(|n| rev_trellis.stage_cumulative_sums(n))
+                .collect();
+            let weight_levels = rev_trellis.weight_levels_slice();
+            let bits_and_energy = |wl_idx: usize| {
+                let (num_bits, amplitude_distribution) =
+                    AdEss::amplitude_distribution_from_reverse_trellis_sums(
+                        &rev_trellis,
+                        &cumulative_sums,
+                        weight_levels[wl_idx],
+                    );
+                (
+                    num_bits,
+                    utils::average_energy(&amplitude_distribution, &levels),
+                )
+            };
+
+            // the threshold 0 only holds sequences of the lowest energy amplitude
+            let mut wl_idx_low = 0;
+            let mut wl_idx_high = weight_levels.len() - 1;
+            if bits_and_energy(wl_idx_high).1 <= average_energy {
+                wl_idx_low = wl_idx_high;
+            }
+            while wl_idx_high - wl_idx_low > 1 {
+                let wl_idx = (wl_idx_low + wl_idx_high) / 2;
+                if bits_and_energy(wl_idx).1 <= average_energy {
+                    wl_idx_low = wl_idx;
+                } else {
+                    wl_idx_high = wl_idx;
+                }
+            }
+
+            let num_bits = bits_and_energy(wl_idx_low).0;
+            if best.as_ref().is_none_or(|best| num_bits > best.0) {
+                best = Some((num_bits, weight_levels[wl_idx_low], weights, res_factor));
+            }
+        }
+
+        let (_, threshold, weights, res_factor) = best.expect("at least one resolution factor");
+        let adess = AdEss::new(threshold, n_max, &weights);
+        let p_goal = adess.get_distribution(res_factor);
+        Ok((adess, p_goal))
+    }
+
+    /// Returns the [AdEss] with at least `target_rate` bit/amplitude and the smallest mutual
+    /// information loss bound whose trellis needs at most `max_storage_bytes` bytes
+    ///
+    /// The candidates combine the resolution factors `1, 2, 4, 8, 16` (see [AdEss::calc_weights()])
+    /// with `n_max = 4, 8, 16, ...`, each with the smallest threshold that encodes
+    /// `ceil(target_rate * n_max)` bits as in [AdEss::new_for_distribution_num_bits()]. The memory
+    /// footprint of a candidate is predicted via [complexity::estimate_complexity()], so `n_max`
+    /// is only increased until the budget is exceeded and only the trellises within the budget
+    /// are calculated. A candidate which can not be constructed ends the search for its resolution
+    /// factor. The memory of the selected trellis is [Trellis::memory_bytes()].
+    ///
+    /// A new [AdEss] instance and the target distribution [AdEss::get_distribution()] are
+    /// returned, an error if no candidate meets the target rate within the budget.
+    pub fn new_for_budget(
+        target_rate: f32,
+        distribution: &[f32],
+        max_storage_bytes: usize,
+    ) -> Result<(AdEss, Vec<f32>), AdEssError> {
+        const MAX_N_MAX: usize = 1 << 12;
+        if !(target_rate > 0.0 && target_rate.is_finite()) {
+            return Err(AdEssError::InvalidParameter(
+                "`target_rate` must be finite and positive",
+            ));
+        }
+        AdEss::calc_weights(distribution, 1.0)?;
+
+        let mut best: Option<(f32, AdEss, Vec<f32>)> = None;
+        for res_factor in RES_FACTORS {
+            let mut n_max = 4;
+            while n_max <= MAX_N_MAX {
+                let num_bits = (target_rate as f64 * n_max as f64).ceil() as usize;
+                let estimate = match complexity::estimate_complexity(
+                    distribution,
+                    res_factor,
+                    n_max,
+                    ThresholdOrBits::NumBits(num_bits),
+                ) {
+                    Ok(estimate) => estimate,
+                    // the target rate is not reachable with this resolution factor
+                    Err(_) => break,
+                };
+                if estimate.memory_bytes > max_storage_bytes {
+                    break;
+                }
+                let (adess, p_goal) = match AdEss::new_for_distribution_num_bits(
+                    num_bits,
+                    n_max,
+                    distribution,
+                    res_factor,
+                ) {
+                    Ok(candidate) => candidate,
+                    // skip the remaining `n_max` of this resolution factor, not the whole search
+                    Err(err) => {
+                        log::debug!("res_factor {res_factor}, n_max {n_max}: {err}");
+                        break;
+                    }
+                };
+                let mi_loss_bound = adess.mi_loss_bound(distribution);
+                log::debug!(
+                    "res_factor {res_factor}, n_max {n_max}, threshold {}: mutual information loss bound {mi_loss_bound}",
+                    adess.trellis.threshold
+                );
+                let is_better = match &best {
+                    Some((best_loss, _, _)) => mi_loss_bound < *best_loss,
+                    None => true,
+                };
+                if adess.trellis.memory_bytes() <= max_storage_bytes && is_better {
+                    best = Some((mi_loss_bound, adess, p_goal));
+                }
+                n_max *= 2;
+            }
+        }
+        best.map(|(_, adess, p_goal)| (adess, p_goal))
+            .ok_or(AdEssError::NoSolution(
+                "No configuration meets the target rate within the storage budget",
+            ))
+    }
+
+    /// Returns a new [AdEss] instance with the same weights and threshold as `rts`
+    ///
+    /// The weight levels of the [RTS] trellis are reused, only the forward trellis values are
+    /// calculated.
+    pub fn from_rts(rts: &RTS) -> AdEss {
+        let mut instance = AdEss {
+            trellis: Arc::new(rts.trellis.structure_like()),
+        };
+        instance.calc_forward_trellis();
+        instance
+    }
+
+    /// Returns an [AdEss] instance with the forward trellis read from the file at `path`
+    ///
+    /// The file is written by [Trellis::save()], e.g., `adess.trellis.save(path)`, so
+    /// deployments can ship precomputed trellises instead of calculating them on startup.
+    pub fn from_trellis_file(path: impl AsRef<Path>) -> io::Result<AdEss> {
+        AdEss::from_trellis(Trellis::load(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+    /// Returns an [AdEss] instance for a precomputed forward `trellis`
+    ///
+    /// The node values are not recalculated, e.g., for deserialized trellises or trellises
+    /// generated by external tools. A shared trellis, e.g., of a
+    /// [TrellisRegistry](crate::registry::TrellisRegistry), is not copied. An error is returned if
+    /// not all nodes up to the threshold are stored or the trellis holds no sequences.
+    pub fn from_trellis(trellis: impl Into<Arc<Trellis>>) -> Result<AdEss, AdEssError> {
+        let trellis = trellis.into();
+        trellis.check_storage()?;
+        if *trellis.get_ref(0, 0) <= 0 {
+            return Err(AdEssError::InvalidParameter(
+                "The forward trellis must hold at least one sequence",
+            ));
+        }
+        Ok(AdEss { trellis })
+    }
+
+    /// Returns a new [AdEss] instance with a larger `threshold` and / or `n_max`
+    ///
+    /// The weights of `self` are used. The forward trellis value of a node is the number of
+    /// sequences with `n_max - stage` amplitudes and a sum weight up to `threshold - wl`, so all
+    /// nodes whose value is already stored in `self` are copied instead of being recalculated.
+    /// This speeds up design loops which increase the threshold until a rate is met.
+    pub fn extend(&self, threshold: usize, n_max: usize) -> Result<AdEss, AdEssError> {
+        let old_threshold = self.trellis.threshold;
+        let old_n_max = self.trellis.n_max;
+        if threshold < old_threshold || n_max < old_n_max {
+            return Err(AdEssError::InvalidParameter(
+                "`threshold` and `n_max` must not be smaller than the existing ones",
+            ));
+        }
+        let threshold_increase = threshold - old_threshold;
+        let stage_offset = n_max - old_n_max;
+
+        let mut trellis = Trellis::new(threshold, n_max, &self.trellis.get_weights());
+        for n in (0..n_max + 1).rev() {
+            for wl in trellis.get_weight_levels() {
+                let is_stored = n >= stage_offset
+                    && wl >= threshold_increase
+                    && self.trellis.is_weight_level(wl - threshold_increase);
+                if is_stored {
+                    let value = self.trellis.get(n - stage_offset, wl - threshold_increase);
+                    trellis.set(n, wl, value);
+                } else if n == n_max {
+                    trellis.set(n, wl, Integer::from(1));
+                } else {
+                    for (_, next_wl) in trellis.get_successors(wl) {
+                        trellis.add(n, wl, trellis.get(n + 1, next_wl));
+                    }
+                }
+            }
+        }
+        Ok(AdEss {
+            trellis: Arc::new(trellis),
+        })
+    }
+
+    /// Returns (threshold, num_bits, average energy, rate loss) for each threshold in `thresholds`
+    ///
+    /// Only a single reverse trellis for the largest threshold is calculated. The number of
+    /// sequences with `L` amplitudes and a sum weight up to `r` is a cumulative sum over stage `L`
+    /// of this reverse trellis, all forward trellis values are looked up from these sums.
+    ///
+    /// The rate loss is `H(P_A) - num_bits / n_max`.
+    pub fn threshold_sweep(
+        weights: &[usize],
+        n_max: usize,
+        thresholds: &[usize],
+    ) -> Vec<(usize, u32, f32, f32)> {
+        let max_threshold = match thresholds.iter().max() {
+            Some(&threshold) => threshold,
+            None => return vec![],
+        };
+        let rev_trellis = AdEss::calc_reverse_trellis(max_threshold, n_max, weights);
+        // cumulative[l][wl_idx]: number of sequences with `l` amplitudes and a sum weight up to
+        // `weight_levels[wl_idx]`
+        let cumulative: Vec<Vec<Integer>> = (0..n_max + 1)
+            .map(|l| rev_trellis.stage_cumulative_sums(l))
+            .collect();
+        let levels = utils::uniform_levels(weights.len());
+
+        thresholds
+            .iter()
+            .map(|&threshold| {
+                let (num_bits, amplitude_distribution) =
+                    AdEss::amplitude_distribution_from_reverse_trellis_sums(
+                        &rev_trellis,
+                        &cumulative,
+                        threshold,
+                    );
+                let rate = num_bits as f32 / n_max as f32;
+                (
+                    threshold,
+                    num_bits,
+                    utils::average_energy(&amplitude_distribution, &levels),
+                    entropy(&amplitude_distribution) - rate,
+                )
+            })
+            .collect()
+    }
+
     /// Compute weights from a probability distribution
     ///
     /// `distribution` is a slice/vec of (amplitude) probabilities, i.e., `sum(distribution) == 1`
     ///
+    /// Amplitudes with probability 0 get the weight [EXCLUDED_WEIGHT] and are never used, the
+    /// weight indexes (amplitude labels) of the other amplitudes are unchanged.
+    ///
     /// The `res_factor` controls a trade off between trellis size and distribution quantisation.
     /// High `res_factor` leads to fine quantisation but a potentially large trellis.
-    pub fn calc_weights(distribution: &[f32], res_factor: f32) -> Result<Vec<usize>, &'static str> {
+    pub fn calc_weights(distribution: &[f32], res_factor: f32) -> Result<Vec<usize>, AdEssError> {
+        Ok(
+            AdEss::calc_weights_with_rounding(distribution, res_factor, RoundingMode::Round)?
+                .weights,
+        )
+    }
+
+    /// Compute weights from a probability distribution with the given rounding mode
+    ///
+    /// Like [AdEss::calc_weights()], which uses [RoundingMode::Round], but the rounding of the
+    /// exact weights `-log2(p) * res_factor` is selected by `rounding_mode`. The resulting
+    /// quantization error is reported as well, see [WeightQuantization].
+    pub fn calc_weights_with_rounding(
+        distribution: &[f32],
+        res_factor: f32,
+        rounding_mode: RoundingMode,
+    ) -> Result<WeightQuantization, AdEssError> {
+        if distribution.is_empty() {
+            return Err(AdEssError::InvalidDistribution(
+                "`distribution` must not be empty",
+            ));
+        }
         let weights: Vec<f32> = distribution
             .iter()
             .map(|p| -p.log2() * res_factor)
             .collect();
         let min_weight = weights.iter().fold(f32::INFINITY, |a, &b| a.min(b));
-        let weights = weights.iter().map(|weight| weight - min_weight);
-        // + 0.5 to convert floor to integer rounding
-        let weights: Vec<usize> = weights.map(|weight| (weight + 0.5) as usize).collect();
-        Ok(weights)
+        if min_weight.is_infinite() {
+            return Err(AdEssError::InvalidDistribution(
+                "`distribution` must contain a positive probability",
+            ));
+        }
+        let exact_weights: Vec<f32> = weights.iter().map(|weight| weight - min_weight).collect();
+
+        let mut diffused_error = 0f32;
+        let weights: Vec<usize> = exact_weights
+            .iter()
+            .map(|&weight| match rounding_mode {
+                // amplitudes with probability 0 are never used
+                _ if weight.is_infinite() => EXCLUDED_WEIGHT,
+                RoundingMode::Floor => weight as usize,
+                RoundingMode::Ceil => weight.ceil() as usize,
+                // + 0.5 to convert floor to integer rounding
+                RoundingMode::Round => (weight + 0.5) as usize,
+                RoundingMode::ErrorDiffusion => {
+                    // the rounding error is carried over to the next weight
+                    let target = weight + diffused_error;
+                    let rounded = (target + 0.5).max(0.0) as usize;
+                    diffused_error = target - rounded as f32;
+                    rounded
+                }
+            })
+            .collect();
+        // error diffusion may round up the smallest weight
+        let min_weight = *weights
+            .iter()
+            .min()
+            .expect("checked for empty distribution above");
+        let weights: Vec<usize> = weights
+            .iter()
+            .map(|&w| match w {
+                EXCLUDED_WEIGHT => EXCLUDED_WEIGHT,
+                w => w - min_weight,
+            })
+            .collect();
+
+        let quantized_distribution = utils::distribution_from_weights(&weights, res_factor);
+        Ok(WeightQuantization {
+            errors: weights
+                .iter()
+                .zip(exact_weights)
+                .map(|(&w, exact)| match w {
+                    EXCLUDED_WEIGHT => 0.0,
+                    w => w as f32 - exact,
+                })
+                .collect(),
+            kl_divergence: kl_divergence(&quantized_distribution, &distribution.to_vec()),
+            weights,
+        })
+    }
+
+    /// Quantizes floating point weights to integer weights
+    ///
+    /// The smallest weight is subtracted and the result is rounded to multiples of
+    /// `quantization_step`, i.e., [AdEss::calc_weights()] equals quantizing `-log2(p)` with the
+    /// step `1 / res_factor`.
+    pub fn quantize_weights(
+        weights: &[f64],
+        quantization_step: f64,
+    ) -> Result<Vec<usize>, AdEssError> {
+        if !(quantization_step > 0.0 && quantization_step.is_finite()) {
+            return Err(AdEssError::InvalidParameter(
+                "`quantization_step` must be finite and positive",
+            ));
+        }
+        if weights.is_empty() || weights.iter().any(|w| !w.is_finite()) {
+            return Err(AdEssError::InvalidWeights(
+                "`weights` must be non empty and finite",
+            ));
+        }
+        let min_weight = weights.iter().fold(f64::INFINITY, |a, &b| a.min(b));
+        Ok(weights
+            .iter()
+            .map(|w| ((w - min_weight) / quantization_step).round() as usize)
+            .collect())
+    }
+
+    /// Returns a new [AdEss] instance holding only sequences with sum weight `shell_weight`
+    ///
+    /// Unlike [AdEss::new()], which holds all sequences inside a sphere (sum weight up to the
+    /// threshold), only sequences on the sphere surface are used. If the weights are the amplitude
+    /// energies, all sequences have the same energy, which gives a constant-composition-like
+    /// codebook with trellis based indexing.
+    ///
+    /// Note: [AdEss::reverse_trellis()] does not account for the single shell.
+    pub fn new_single_shell(
+        shell_weight: usize,
+        n_max: usize,
+        weights: &[usize],
+    ) -> Result<AdEss, AdEssError> {
+        let trellis = Trellis::new(shell_weight, n_max, weights);
+        let mut instance = AdEss {
+            trellis: Arc::new(trellis),
+        };
+        instance
+            .fill_forward_trellis(
+                |n, wl| n < n_max || wl == shell_weight,
+                &CancellationToken::new(),
+                None,
+            )
+            .expect("token is never cancelled");
+        if instance.num_sequences() == 0 {
+            return Err(AdEssError::InvalidParameter(
+                "`shell_weight` is not reachable with `n_max` weights",
+            ));
+        }
+        Ok(instance)
     }
 
     /// Fill `self.trellis` with values
     fn calc_forward_trellis(&mut self) {
-        for n in (0..self.trellis.n_max + 1).rev() {
-            for wl in self.trellis.get_weight_levels() {
-                if n == self.trellis.n_max {
+        self.fill_forward_trellis(|_, _| true, &CancellationToken::new(), None)
+            .expect("token is never cancelled");
+    }
+    /// Fill `self.trellis` with values, sequences may only pass nodes with
+    /// `is_valid_node(stage, wl) == true`, the values of all other nodes are 0
+    ///
+    /// `token` is checked once per stage.
+    pub(crate) fn fill_forward_trellis(
+        &mut self,
+        is_valid_node: impl Fn(usize, usize) -> bool,
+        token: &CancellationToken,
+        memory_limit: Option<usize>,
+    ) -> Result<(), AdEssError> {
+        let trellis =
+            Arc::get_mut(&mut self.trellis).expect("The trellis is not shared before it is filled");
+        let (num_stages, num_weight_levels) = trellis.get_storage_dimensions();
+        let mut memory_bytes = num_stages * num_weight_levels * std::mem::size_of::<Integer>();
+        for n in (0..trellis.n_max + 1).rev() {
+            token.check()?;
+            if let Some(memory_limit) = memory_limit {
+                if n < trellis.n_max {
+                    memory_bytes += trellis.stage_memory_bytes(n + 1)
+                        - num_weight_levels * std::mem::size_of::<Integer>();
+                }
+                if memory_bytes > memory_limit {
+                    return Err(AdEssError::MemoryLimitExceeded);
+                }
+            }
+            for wl in trellis.get_weight_levels() {
+                if !is_valid_node(n, wl) {
+                    continue;
+                }
+                if n == trellis.n_max {
                     // number of possible sequences for end nodes is 1
-                    self.trellis.set(n, wl, Integer::from(1));
+                    trellis.set(n, wl, Integer::from(1));
                 } else {
                     // number of possible paths for a node is the sum of the number
                     // of possible sequences of all successor nodes
-                    for (_, next_wl) in self.trellis.get_successors(wl) {
-                        self.trellis.add(n, wl, self.trellis.get(n + 1, next_wl));
+                    for (_, next_wl) in trellis.get_successors(wl) {
+                        trellis.add(n, wl, trellis.get(n + 1, next_wl));
                     }
                 }
                 // debugging output
-                //println!("n: {}, wl: {}, value: {}", n, wl, self.trellis.get(n, wl));
+                //println!("n: {}, wl: {}, value: {}", n, wl, trellis.get(n, wl));
+            }
+        }
+        if let Some(memory_limit) = memory_limit {
+            if trellis.memory_bytes() > memory_limit {
+                return Err(AdEssError::MemoryLimitExceeded);
             }
         }
+        Ok(())
     }
     /// Returns the amplitude value for a given weight index
     fn weight_idx_to_amplitude(weight_index: usize) -> usize {
@@ -165,6 +759,11 @@ impl AdEss {
     /// sequences with a sum weight up to `threshold`.
     pub fn calc_reverse_trellis(threshold: usize, n_max: usize, weights: &[usize]) -> Trellis {
         let mut rev_trellis = Trellis::new(threshold, n_max, weights);
+        AdEss::fill_reverse_trellis(&mut rev_trellis);
+        rev_trellis
+    }
+    /// Fill the zero initialized `rev_trellis` with reverse trellis values
+    pub(crate) fn fill_reverse_trellis(rev_trellis: &mut Trellis) {
         rev_trellis.set(0, 0, Integer::from(1));
 
         for n in 0..rev_trellis.n_max {
@@ -175,7 +774,6 @@ impl AdEss {
                 }
             }
         }
-        rev_trellis
     }
     /// Calculates the threshold that maximizes the lower bound on mutual-information
     ///
@@ -187,90 +785,334 @@ impl AdEss {
     ///   [AdEss::calc_weights()]
     /// - `search_width`: number of weight levels to check below and above the initial estimated
     ///   optimal threshold
-    /// - `rev_trellis_calculation_fraction`: the fraction of the reverse trellis that should be
-    ///   calculated. If the calculated fraction is to small, the optimal threshold can not be found.
+    ///
+    /// The initial estimate is the smallest threshold with at least `2^ceil(n_max * H(P))`
+    /// sequences. A single reverse trellis is expanded incrementally up to `search_width` weight
+    /// levels beyond this estimate, see [trellis_utils::reverse_trellis_beyond_num_sequences()].
+    /// The amplitude distribution of every candidate threshold is read from the running sums of
+    /// its stages, no forward trellis is calculated.
+    ///
+    /// See [AdEss::optimal_threshold_with_diagnostics()] for the intermediate results.
     pub fn optimal_threshold(
         n_max: usize,
-        distribution: &Vec<f32>,
+        distribution: &[f32],
         res_factor: f32,
         search_width: usize,
-        rev_trellis_calculation_fraction: f32,
-    ) -> Result<usize, &'static str> {
-        // this code could be significantly improved using
-        // `trellis_utils::reverse_trellis_upto_num_sequences` the function
-        // argument `rev_trellis_calculation_fraction` would no longer be necessary
-        println!("WARNING: Code has not been checked with non-unique weights!");
+    ) -> Result<usize, AdEssError> {
+        AdEss::optimal_threshold_with_diagnostics(n_max, distribution, res_factor, search_width)
+            .map(|diagnostics| diagnostics.threshold)
+    }
+    /// Like [AdEss::optimal_threshold()] but returns all tested thresholds, their mutual
+    /// information loss bounds and warnings, see [ThresholdDiagnostics]
+    ///
+    /// The warnings are also emitted with [log::warn!], the tested thresholds with [log::debug!].
+    pub fn optimal_threshold_with_diagnostics(
+        n_max: usize,
+        distribution: &[f32],
+        res_factor: f32,
+        search_width: usize,
+    ) -> Result<ThresholdDiagnostics, AdEssError> {
+        AdEss::search_optimal_threshold(n_max, distribution, res_factor, search_width, false)
+    }
+    /// Like [AdEss::optimal_threshold_with_diagnostics()] but without a fixed `search_width`
+    ///
+    /// The mutual information loss bound is not unimodal in the threshold, it has local minima
+    /// every few weight levels. The search window therefore starts with one weight level per
+    /// amplitude below and above the initial estimate and is extended in blocks of doubling
+    /// width. A side is extended as long as its last block contains the smallest loss found so
+    /// far, i.e., the search stops once the loss increases on both sides of the optimum (or the
+    /// smallest / largest threshold is reached).
+    pub fn optimal_threshold_adaptive(
+        n_max: usize,
+        distribution: &[f32],
+        res_factor: f32,
+    ) -> Result<ThresholdDiagnostics, AdEssError> {
+        let search_width = distribution.len().max(2);
+        AdEss::search_optimal_threshold(n_max, distribution, res_factor, search_width, true)
+    }
+    /// Searches the weight levels around the estimated optimal threshold, see
+    /// [AdEss::optimal_threshold_with_diagnostics()] and [AdEss::optimal_threshold_adaptive()]
+    fn search_optimal_threshold(
+        n_max: usize,
+        distribution: &[f32],
+        res_factor: f32,
+        search_width: usize,
+        adaptive: bool,
+    ) -> Result<ThresholdDiagnostics, AdEssError> {
         let weights = AdEss::calc_weights(distribution, res_factor)?;
+        let mut warnings = vec![];
+        let mut sorted_weights = weights.clone();
+        sorted_weights.sort_unstable();
+        if sorted_weights.windows(2).any(|pair| pair[0] == pair[1]) {
+            warnings.push("Non-unique weights, amplitudes with equal weights are equiprobable");
+        }
 
-        let max_possible_wl = (weights.iter().max().unwrap() * n_max) as f32;
-        let rev_trellis_threshold = (max_possible_wl * rev_trellis_calculation_fraction) as usize;
-        let rev_trellis = AdEss::calc_reverse_trellis(rev_trellis_threshold, n_max, &weights);
+        let estimated_num_bits = (n_max as f64 * entropy(distribution) as f64).ceil() as u32;
+        let estimated_optimal_size = Integer::from(1) << estimated_num_bits;
+        let mut rev_trellis = trellis_utils::reverse_trellis_beyond_num_sequences(
+            &estimated_optimal_size,
+            search_width,
+            n_max,
+            &weights,
+        );
+        let mut cumulative_sums: Vec<Vec<Integer>> = (0..n_max + 1)
+            .map(|n| rev_trellis.stage_cumulative_sums(n))
+            .collect();
+        let num_weight_levels = rev_trellis.weight_levels_slice().len();
 
-        let code_sizes = rev_trellis
-            .get_stage(n_max)
+        // all weight levels are used if the estimated size exceeds the number of sequences
+        let estimated_optimal_wl_idx = cumulative_sums[n_max]
             .iter()
-            .fold(vec![], |mut total, wl_val| {
-                if total.is_empty() {
-                    total.push(wl_val.clone());
-                } else {
-                    total.push(Integer::from(wl_val + &total[total.len() - 1]));
-                }
-                total
-            });
-        let weight_levels = rev_trellis.get_weight_levels();
-
-        let estimated_optimal_size =
-            Integer::from_f64(2.0_f64.powf(n_max as f64 * entropy(distribution) as f64)).unwrap();
-        let estimated_optimal_wl_idx = code_sizes.iter().position(|x| x >= &estimated_optimal_size);
-        let estimated_optimal_wl_idx = match estimated_optimal_wl_idx {
-            Some(wl_idx) => wl_idx,
-            None => return Err("The calculated fraction of the reverse trellis is to small!"),
-        };
+            .position(|x| x >= &estimated_optimal_size)
+            .unwrap_or(cumulative_sums[n_max].len() - 1);
 
-        let mut max_mi_losses = vec![];
-        let mut tested_wl_idxs = vec![];
-        let search_start_wl_idx = estimated_optimal_wl_idx - search_width;
-        let search_end_wl_idx = estimated_optimal_wl_idx + search_width;
-        for (wl_idx, &threshold) in weight_levels
-            .iter()
-            .enumerate()
-            .take(search_end_wl_idx)
-            .skip(search_start_wl_idx)
-        {
-            let (adess, _) =
-                AdEss::new_for_distribution_threshold(threshold, n_max, distribution, res_factor)?;
+        // mutual information loss bound of all tested weight level indexes
+        let mut mi_loss_bounds: Vec<Option<f32>> = vec![];
+        let mut search_start_wl_idx = estimated_optimal_wl_idx.saturating_sub(search_width);
+        let mut search_end_wl_idx = estimated_optimal_wl_idx + search_width + 1;
+        // weight level indexes added to the window in the last extension on either side
+        let mut lower_block_end_wl_idx = search_end_wl_idx;
+        let mut upper_block_start_wl_idx = search_start_wl_idx;
+        let mut width = search_width.max(1);
+        loop {
+            let num_stored_weight_levels = rev_trellis.get_num_weight_levels();
+            if search_end_wl_idx > num_stored_weight_levels {
+                trellis_utils::expand_reverse_trellis_by(
+                    &mut rev_trellis,
+                    search_end_wl_idx - num_stored_weight_levels,
+                );
+                // continue the running sums with the added weight levels
+                let added_wls = &rev_trellis.weight_levels_slice()
+                    [num_stored_weight_levels..rev_trellis.get_num_weight_levels()];
+                for (n, sums) in cumulative_sums.iter_mut().enumerate() {
+                    for &wl in added_wls {
+                        let mut sum = sums.last().expect("weight level 0 is stored").clone();
+                        sum += rev_trellis.get_ref(n, wl);
+                        sums.push(sum);
+                    }
+                }
+            }
+            search_end_wl_idx = search_end_wl_idx.min(rev_trellis.get_num_weight_levels());
+            if mi_loss_bounds.len() < search_end_wl_idx {
+                mi_loss_bounds.resize(search_end_wl_idx, None);
+            }
+            for (wl_idx, loss) in mi_loss_bounds
+                .iter_mut()
+                .enumerate()
+                .take(search_end_wl_idx)
+                .skip(search_start_wl_idx)
+                .filter(|(_, loss)| loss.is_none())
+            {
+                let threshold = rev_trellis.weight_levels_slice()[wl_idx];
+                let (num_bits, amplitude_distribution) =
+                    AdEss::amplitude_distribution_from_reverse_trellis_sums(
+                        &rev_trellis,
+                        &cumulative_sums,
+                        threshold,
+                    );
+                let rate = num_bits as f32 / n_max as f32;
+                let mi_loss_bound =
+                    utils::mi_loss_bound(&amplitude_distribution, distribution, rate);
+                log::debug!("threshold {threshold}: mutual information loss bound {mi_loss_bound}");
+                *loss = Some(mi_loss_bound);
+            }
 
-            let amp_distr = adess.amplitude_distribution();
-            let n = n_max as f32;
-            let log2_code_size = (adess.num_sequences().significant_bits() - 1) as f32;
-            let amplitude_kl = kl_divergence(&amp_distr, distribution);
+            let window = &mi_loss_bounds[search_start_wl_idx..search_end_wl_idx];
+            let min_mi_loss_bound = window
+                .iter()
+                .flatten()
+                .fold(f32::INFINITY, |a, &b| a.min(b));
+            let min_wl_idx = match window
+                .iter()
+                .position(|&loss| loss == Some(min_mi_loss_bound))
+            {
+                Some(idx) => search_start_wl_idx + idx,
+                None => {
+                    return Err(AdEssError::NoSolution(
+                        "Failed finding minimum mutual information loss!",
+                    ))
+                }
+            };
 
-            // upper bound on reduction in mutual information
-            let max_mi_loss = entropy(&amp_distr) - log2_code_size / n + amplitude_kl;
-            max_mi_losses.push(max_mi_loss);
-            tested_wl_idxs.push(wl_idx);
+            if !adaptive {
+                let at_lower_edge = min_wl_idx == search_start_wl_idx && search_start_wl_idx > 0;
+                let at_upper_edge =
+                    min_wl_idx + 1 == search_end_wl_idx && search_end_wl_idx < num_weight_levels;
+                if at_lower_edge || at_upper_edge {
+                    warnings.push(
+                        "The optimum is at the edge of the search window, increase `search_width`",
+                    );
+                }
+                break;
+            }
+            // a side is extended as long as its last extension contains the minimum
+            let extend_lower = min_wl_idx < lower_block_end_wl_idx && search_start_wl_idx > 0;
+            let extend_upper =
+                min_wl_idx >= upper_block_start_wl_idx && search_end_wl_idx < num_weight_levels;
+            if !(extend_lower || extend_upper) {
+                break;
+            }
+            lower_block_end_wl_idx = search_start_wl_idx;
+            upper_block_start_wl_idx = search_end_wl_idx;
+            if extend_lower {
+                search_start_wl_idx = search_start_wl_idx.saturating_sub(width);
+            }
+            if extend_upper {
+                search_end_wl_idx += width;
+            }
+            width *= 2;
+        }
+        for warning in &warnings {
+            log::warn!("{warning}");
         }
 
-        let min_max_mi_loss = max_mi_losses.iter().fold(f32::INFINITY, |a, &b| a.min(b));
-        let min_loss_idx = max_mi_losses
+        let weight_levels = rev_trellis.weight_levels_slice();
+        let (tested_thresholds, mi_loss_bounds): (Vec<usize>, Vec<f32>) = mi_loss_bounds
             .iter()
-            .position(|&loss| loss == min_max_mi_loss);
-        let min_loss_idx = match min_loss_idx {
-            Some(idx) => idx,
-            None => return Err("Failed finding minimum mutual information loss!"),
+            .enumerate()
+            .filter_map(|(wl_idx, loss)| loss.map(|loss| (weight_levels[wl_idx], loss)))
+            .unzip();
+        let min_mi_loss_bound = mi_loss_bounds.iter().fold(f32::INFINITY, |a, &b| a.min(b));
+        let min_loss_idx = mi_loss_bounds
+            .iter()
+            .position(|&loss| loss == min_mi_loss_bound)
+            .expect("at least one threshold is tested");
+
+        Ok(ThresholdDiagnostics {
+            estimated_threshold: weight_levels[estimated_optimal_wl_idx],
+            threshold: tested_thresholds[min_loss_idx],
+            tested_thresholds,
+            mi_loss_bounds,
+            warnings,
+        })
+    }
+    /// Returns the number of bits and the amplitude distribution of the [AdEss] with `threshold`
+    /// without calculating its trellis
+    ///
+    /// The forward trellis value of the node at `stage` and `wl` is the number of sequences with
+    /// `n_max - stage` amplitudes and a sum weight up to `threshold - wl`, i.e., a running sum of
+    /// the reverse trellis stage `n_max - stage`. `cumulative_sums[n]` holds
+    /// [Trellis::stage_cumulative_sums()] of stage `n`, `rev_trellis` must store all weight levels
+    /// up to `threshold`.
+    ///
+    /// Only the trellis path of the first abandoned sequence is visited: the sequences below it
+    /// are split into the completions of the nodes left of the path. The completions of a node
+    /// are symmetric in their positions, so each position holds the amplitude `a` as often as
+    /// the successor node of `a` has completions.
+    fn amplitude_distribution_from_reverse_trellis_sums(
+        rev_trellis: &Trellis,
+        cumulative_sums: &[Vec<Integer>],
+        threshold: usize,
+    ) -> (u32, Vec<f32>) {
+        let n_max = rev_trellis.n_max;
+        let weights = rev_trellis.get_weights();
+        let stored_wls = &rev_trellis.weight_levels_slice()[..rev_trellis.get_num_weight_levels()];
+        let forward_value = |stage: usize, wl: usize| -> Option<&Integer> {
+            let max_wl = threshold.checked_sub(wl)?;
+            // index of the largest stored weight level up to `threshold - wl`, the weight level 0
+            // is always present
+            let wl_idx = stored_wls.partition_point(|&stored_wl| stored_wl <= max_wl) - 1;
+            Some(&cumulative_sums[n_max - stage][wl_idx])
         };
-        let optimal_wl_idx = tested_wl_idxs[min_loss_idx];
-        let optimal_threshold = weight_levels[optimal_wl_idx];
+        // adds the occurences of each amplitude in all completions of the node at `stage` and `wl`
+        let add_completions = |counts: &mut [Integer], stage: usize, wl: usize| {
+            if stage == n_max {
+                return;
+            }
+            for (count, &weight) in counts.iter_mut().zip(&weights) {
+                let successor_value = wl
+                    .checked_add(weight)
+                    .and_then(|next_wl| forward_value(stage + 1, next_wl));
+                if let Some(value) = successor_value {
+                    *count += Integer::from(value * (n_max - stage));
+                }
+            }
+        };
+
+        let num_sequences = forward_value(0, 0).expect("weight level 0 is stored");
+        let num_bits = num_sequences.significant_bits().saturating_sub(1);
+        let num_sequences_used = Integer::u_pow_u(2, num_bits).complete();
+        let mut counts = vec![Integer::new(); weights.len()];
+        if n_max == 0 {
+            // sequences without amplitudes
+            return (num_bits, vec![0f32; weights.len()]);
+        }
+        if num_sequences_used == *num_sequences {
+            add_completions(&mut counts, 0, 0);
+        } else {
+            // walk along the first abandoned sequence, i.e., the sequence with index
+            // `num_sequences_used`
+            let mut remaining_index = num_sequences_used.clone();
+            let mut prefix_counts = vec![0usize; weights.len()];
+            let mut wl = 0usize;
+            for stage in 0..n_max {
+                let mut num_skipped = Integer::new();
+                for &(w_idx, weight) in rev_trellis.get_sorted_weights() {
+                    let Some(next_wl) = wl.checked_add(weight).filter(|&next| next <= threshold)
+                    else {
+                        break;
+                    };
+                    let value = forward_value(stage + 1, next_wl).expect("`next_wl` <= threshold");
+                    if remaining_index < *value {
+                        wl = next_wl;
+                        // the prefix is shared by all skipped sequences of this stage
+                        for (count, &prefix_count) in counts.iter_mut().zip(&prefix_counts) {
+                            *count += Integer::from(&num_skipped * prefix_count);
+                        }
+                        prefix_counts[w_idx] += 1;
+                        break;
+                    }
+                    remaining_index -= value;
+                    num_skipped += value;
+                    counts[w_idx] += value;
+                    add_completions(&mut counts, stage + 1, next_wl);
+                }
+            }
+        }
 
-        Ok(optimal_threshold)
+        let num_amplitudes = Integer::from(&num_sequences_used * n_max);
+        let amplitude_distribution = counts
+            .into_iter()
+            .map(|count| Rational::from((count, &num_amplitudes)).to_f32())
+            .collect();
+        (num_bits, amplitude_distribution)
+    }
+    /// Returns the trellis for modification, it is copied first if it is shared with other
+    /// instances
+    pub fn trellis_mut(&mut self) -> &mut Trellis {
+        Arc::make_mut(&mut self.trellis)
     }
     /// Returns the number of sequences that can be encoded / decoded
     pub fn num_sequences(&self) -> Integer {
         self.trellis.get(0, 0)
     }
+    /// Returns the total and the used number of sequences
+    pub fn sequence_counts(&self) -> utils::SequenceCounts {
+        utils::SequenceCounts::for_total(self.num_sequences())
+    }
+    /// Returns the number of sequences used for encoding (`2^num_bits`)
+    pub fn num_sequences_used(&self) -> Integer {
+        self.sequence_counts().used
+    }
+    /// Returns the fraction of sequences used for encoding, see [AdEss::sequence_counts()]
+    pub fn utilization(&self) -> f64 {
+        self.sequence_counts().utilization()
+    }
     /// Returns the number of bits that can be encoded / decoded
     pub fn num_bits(&self) -> u32 {
-        self.num_sequences().significant_bits() - 1
+        self.num_sequences().significant_bits().saturating_sub(1)
+    }
+    /// Returns `log2(num_sequences)`, i.e., the number of bits including the fractional bit
+    /// which is lost by using only `2^num_bits` sequences
+    pub fn num_bits_fractional(&self) -> f64 {
+        utils::integer_log2(&self.num_sequences())
+    }
+    /// Returns the rate `log2(num_sequences) / n_max` in bit/amplitude if all sequences are used
+    ///
+    /// The rate of encoding `num_bits` bits per sequence is
+    /// [DistributionMatcher::rate()](crate::matcher::DistributionMatcher::rate).
+    pub fn rate_bits_per_amplitude(&self) -> f64 {
+        self.num_bits_fractional() / self.trellis.n_max as f64
     }
     /// Returns the weights used by the internal trellis
     pub fn get_weights(&self) -> Vec<usize> {
@@ -288,6 +1130,19 @@ impl AdEss {
 
         p_goal
     }
+    /// Returns the upper bound on the reduction in mutual information when using this [AdEss]
+    ///
+    /// According to formulas (13) and (14) in <https://doi.org/10.1109/LWC.2018.2890595>, see
+    /// [utils::mi_loss_bound()]. `distribution` is the target (amplitude) distribution.
+    pub fn mi_loss_bound(&self, distribution: &[f32]) -> f32 {
+        let rate = self.num_bits() as f32 / self.trellis.n_max as f32;
+        utils::mi_loss_bound(&self.amplitude_distribution(), distribution, rate)
+    }
+    /// Returns the rate loss, energy and complexity figures of this [AdEss] for the target
+    /// `distribution`, see [ShapingReport]
+    pub fn analyze(&self, distribution: &[f32]) -> ShapingReport {
+        ShapingReport::new(self, &self.trellis, distribution)
+    }
     /// Returns the reverse trellis for this [AdEss]
     pub fn reverse_trellis(&self) -> Trellis {
         AdEss::calc_reverse_trellis(
@@ -299,72 +1154,353 @@ impl AdEss {
     /// Returns the amplitude sequence for a given `index` (encode)
     ///
     /// Calculations based on algorithm 1 in section III-C of <https://doi.org/10.1109/TWC.2019.2951139>.
+    ///
+    /// Panics if `index` is out of range, see [AdEss::try_sequence_for_index()].
     pub fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
-        assert!(index < &self.num_sequences(), "Index out of range!");
-
-        let mut amplitude_sequence = Vec::new();
-
-        let mut current_wl = 0;
-        let mut num_sequences_left_below = Integer::from(0);
-        for n in 0..self.trellis.n_max {
-            for (w_idx, next_wl) in self.trellis.get_successors(current_wl) {
-                let next_wl_value = self.trellis.get(n + 1, next_wl);
-
-                // it is impossible to leave all sequences possible with `next_wl` below
-                // when using `next_wl` as the next weight level
-                let just_unreachable_index =
-                    Integer::from(&num_sequences_left_below + &next_wl_value);
-
-                if index < &just_unreachable_index {
-                    // we can reach the target index via next_wl
-                    amplitude_sequence.push(AdEss::weight_idx_to_amplitude(w_idx));
-                    current_wl = next_wl;
-                    break;
-                } else {
-                    // target index is not reachable if we use `next_wl` as next weight level
-                    // -> to reach the target index, we have to use a higher next weight level
-                    // thus we leave below all sequences possible with the current `next_wl`
-                    num_sequences_left_below += next_wl_value
-                }
-            }
+        self.try_sequence_for_index(index)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+    /// Returns the amplitude sequence for a given `index` (encode) or an error if `index` is
+    /// negative or not smaller than [AdEss::num_sequences()]
+    pub fn try_sequence_for_index(&self, index: &Integer) -> Result<Vec<usize>, AdEssError> {
+        let mut amplitude_sequence = Vec::with_capacity(self.trellis.n_max);
+        self.walk_for_index(index, |w_idx| {
+            amplitude_sequence.push(AdEss::weight_idx_to_amplitude(w_idx))
+        })?;
+        Ok(amplitude_sequence)
+    }
+    /// Returns the amplitude sequence for an index given as `u64` limbs, see
+    /// [AdEss::sequence_for_index()]
+    ///
+    /// The least significant limb comes first.
+    pub fn sequence_for_index_from_limbs(&self, limbs: &[u64]) -> Vec<usize> {
+        self.sequence_for_index(&Integer::from_digits(limbs, Order::Lsf))
+    }
+    /// Returns the amplitude sequence for an index given as bytes (encode)
+    ///
+    /// `bytes` holds the [AdEss::num_bits()] index bits in `bit_order`, i.e., `ceil(num_bits / 8)`
+    /// bytes whose unused padding bits are 0, see [utils::bytes_to_integer()]. An error is
+    /// returned for a different number of bytes or if the index has more than `num_bits` bits.
+    pub fn sequence_for_index_bytes(
+        &self,
+        bytes: &[u8],
+        bit_order: BitOrder,
+    ) -> Result<Vec<usize>, AdEssError> {
+        self.try_sequence_for_index(&utils::index_from_bytes(bytes, self.num_bits(), bit_order)?)
+    }
+    /// Writes the amplitude sequence for a given `index` into `out` (encode)
+    ///
+    /// Unlike [AdEss::sequence_for_index()] no sequence is allocated and the remaining index is
+    /// updated in place, trellis values are compared by reference. An error is returned if `out`
+    /// does not have `n_max` entries or `index` is out of range. For encoding without any
+    /// allocation see [crate::workspace::Workspace].
+    pub fn sequence_for_index_into(
+        &self,
+        index: &Integer,
+        out: &mut [usize],
+    ) -> Result<(), AdEssError> {
+        if out.len() != self.trellis.n_max {
+            return Err(AdEssError::InvalidParameter(
+                "`out` must have `n_max` entries",
+            ));
         }
-        amplitude_sequence
+        if *index < 0 || *index >= *self.trellis.get_ref(0, 0) {
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        self.encode_remaining_index_into(&mut index.clone(), out);
+        Ok(())
+    }
+    /// Writes the amplitude sequence for `remaining_index` into `out`, the index is consumed
+    ///
+    /// `remaining_index` must be a valid index and `out` must have `n_max` entries.
+    pub(crate) fn encode_remaining_index_into(
+        &self,
+        remaining_index: &mut Integer,
+        out: &mut [usize],
+    ) {
+        let mut amplitudes = out.iter_mut();
+        self.walk_for_remaining_index(remaining_index, |w_idx| {
+            *amplitudes.next().expect("`out` has `n_max` entries") =
+                AdEss::weight_idx_to_amplitude(w_idx)
+        });
+    }
+    /// Returns the energy `sum(a * a)` of the amplitude sequence for a given `index`
+    ///
+    /// The energy is accumulated during the encoding walk, the sequence is not materialized. An
+    /// error is returned if `index` is negative or not smaller than [AdEss::num_sequences()].
+    pub fn energy_of_index(&self, index: &Integer) -> Result<usize, AdEssError> {
+        let mut energy = 0;
+        self.walk_for_index(index, |w_idx| {
+            let amplitude = AdEss::weight_idx_to_amplitude(w_idx);
+            energy += amplitude * amplitude;
+        })?;
+        Ok(energy)
+    }
+    /// Returns the amplitude sequence for a given `index` together with its trellis path
+    ///
+    /// The traversed weight levels, the sum weight and the energy are collected during the
+    /// encoding walk. An error is returned if `index` is negative or not smaller than
+    /// [AdEss::num_sequences()].
+    pub fn sequence_for_index_with_info(
+        &self,
+        index: &Integer,
+    ) -> Result<EncodingInfo, AdEssError> {
+        let mut sequence = Vec::with_capacity(self.trellis.n_max);
+        let mut weight_levels = Vec::with_capacity(self.trellis.n_max + 1);
+        weight_levels.push(0);
+        let mut energy = 0;
+        self.walk_for_index(index, |w_idx| {
+            let amplitude = AdEss::weight_idx_to_amplitude(w_idx);
+            sequence.push(amplitude);
+            energy += amplitude * amplitude;
+            weight_levels
+                .push(weight_levels[weight_levels.len() - 1] + self.trellis.get_weight(w_idx));
+        })?;
+        Ok(EncodingInfo {
+            sequence,
+            weight: weight_levels[weight_levels.len() - 1],
+            weight_levels,
+            energy,
+        })
+    }
+    /// Returns the amplitude sequences for all `indices` (batch encode) or an error if an index
+    /// is out of range, see [AdEss::try_sequence_for_index()]
+    pub fn sequences_for_indices(
+        &self,
+        indices: &[Integer],
+    ) -> Result<Vec<Vec<usize>>, AdEssError> {
+        indices
+            .iter()
+            .map(|index| self.try_sequence_for_index(index))
+            .collect()
+    }
+    /// Returns the indices for all `sequences` (batch decode) or an error if a sequence is not
+    /// in the trellis, see [AdEss::try_index_for_sequence()]
+    pub fn indices_for_sequences(
+        &self,
+        sequences: &[Vec<usize>],
+    ) -> Result<Vec<Integer>, AdEssError> {
+        sequences
+            .iter()
+            .map(|sequence| self.try_index_for_sequence(sequence))
+            .collect()
+    }
+    /// Returns the amplitude sequences for all `indices` encoded on `num_threads` threads
+    ///
+    /// See [AdEss::sequences_for_indices()], with `num_threads == 0` rayon chooses the number of
+    /// threads.
+    #[cfg(feature = "rayon")]
+    pub fn par_sequences_for_indices(
+        &self,
+        indices: &[Integer],
+        num_threads: usize,
+    ) -> Result<Vec<Vec<usize>>, AdEssError> {
+        utils::par_map(indices, num_threads, |index| {
+            self.try_sequence_for_index(index)
+        })
+        .into_iter()
+        .collect()
+    }
+    /// Returns the indices for all `sequences` decoded on `num_threads` threads, see
+    /// [AdEss::par_sequences_for_indices()]
+    #[cfg(feature = "rayon")]
+    pub fn par_indices_for_sequences(
+        &self,
+        sequences: &[Vec<usize>],
+        num_threads: usize,
+    ) -> Result<Vec<Integer>, AdEssError> {
+        utils::par_map(sequences, num_threads, |sequence| {
+            self.try_index_for_sequence(sequence)
+        })
+        .into_iter()
+        .collect()
+    }
+    /// Returns the amplitude sequences for all `indices` or an error if `token` is cancelled or
+    /// an index is out of range
+    ///
+    /// The token is checked before each sequence is encoded.
+    pub fn sequences_for_indices_cancellable(
+        &self,
+        indices: &[Integer],
+        token: &CancellationToken,
+    ) -> Result<Vec<Vec<usize>>, AdEssError> {
+        indices
+            .iter()
+            .map(|index| {
+                token.check()?;
+                self.try_sequence_for_index(index)
+            })
+            .collect()
+    }
+    /// Returns the sequence energies for all `indexes` or an error if an index is out of range,
+    /// see [AdEss::energy_of_index()]
+    pub fn energies_of_indexes(&self, indexes: &[Integer]) -> Result<Vec<usize>, AdEssError> {
+        indexes
+            .iter()
+            .map(|index| self.energy_of_index(index))
+            .collect()
+    }
+    /// Walks the trellis path of `index` and calls `visit` with the weight index of each stage
+    ///
+    /// An error is returned before the walk if `index` is out of range.
+    fn walk_for_index(&self, index: &Integer, visit: impl FnMut(usize)) -> Result<(), AdEssError> {
+        if *index < 0 || *index >= *self.trellis.get_ref(0, 0) {
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        self.walk_for_remaining_index(&mut index.clone(), visit);
+        Ok(())
+    }
+    /// Walks the trellis path of `remaining_index`, see [walk_for_remaining_index()]
+    fn walk_for_remaining_index(&self, remaining_index: &mut Integer, visit: impl FnMut(usize)) {
+        walk_for_remaining_index(&self.trellis, remaining_index, visit);
+    }
+    /// Returns the index for a given `amplitude_sequence` (decode) or an error if the sequence
+    /// is not in the trellis
+    ///
+    /// Sequences with a wrong length, unknown amplitudes or a sum weight above the threshold,
+    /// e.g., due to channel errors, are rejected, see [AdEss::check_sequence()].
+    pub fn try_index_for_sequence(
+        &self,
+        amplitude_sequence: &[usize],
+    ) -> Result<Integer, AdEssError> {
+        match self.check_sequence(amplitude_sequence) {
+            SequenceReport::Used(index) | SequenceReport::Unused(index) => Ok(index),
+            SequenceReport::Invalid { reason, .. } => Err(AdEssError::InvalidSequence(reason)),
+        }
+    }
+    /// Returns `true` if `amplitude_sequence` is in the trellis, see
+    /// [AdEss::try_index_for_sequence()]
+    pub fn is_valid_sequence(&self, amplitude_sequence: &[usize]) -> bool {
+        !matches!(
+            self.check_sequence(amplitude_sequence),
+            SequenceReport::Invalid { .. }
+        )
     }
     /// Returns the index for a given `amplitude_sequence` (decode)
     ///
     /// Calculations based on algorithm 2 in section III-C of <https://doi.org/10.1109/TWC.2019.2951139>.
+    ///
+    /// The sequence is not validated, the index of an invalid sequence is meaningless. See
+    /// [AdEss::try_index_for_sequence()] for received sequences.
     pub fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
         let weight_idx_seq = AdEss::amplitude_seq_to_weight_idx_seq(amplitude_sequence);
+        index_for_weight_idx_sequence(&self.trellis, &weight_idx_seq)
+    }
+    /// Writes the index for a given `amplitude_sequence` as bytes into `out` (decode), see
+    /// [AdEss::sequence_for_index_bytes()]
+    ///
+    /// An error is returned if `out` does not have `ceil(num_bits / 8)` entries or the sequence
+    /// is not used for encoding, see [AdEss::check_sequence()].
+    pub fn index_for_sequence_bytes(
+        &self,
+        amplitude_sequence: &[usize],
+        bit_order: BitOrder,
+        out: &mut [u8],
+    ) -> Result<(), AdEssError> {
+        let index = self.try_index_for_sequence(amplitude_sequence)?;
+        utils::index_to_bytes(&index, self.num_bits(), bit_order, out)
+    }
+    /// Returns the index for a given `amplitude_sequence` as `u64` limbs, see
+    /// [AdEss::index_for_sequence()]
+    ///
+    /// The least significant limb comes first. At least `ceil(num_bits / 64)` limbs are returned.
+    pub fn index_for_sequence_to_limbs(&self, amplitude_sequence: &[usize]) -> Vec<u64> {
+        let mut limbs = self
+            .index_for_sequence(amplitude_sequence)
+            .to_digits::<u64>(Order::Lsf);
+        let num_limbs = (self.num_bits() as usize).div_ceil(64);
+        if limbs.len() < num_limbs {
+            limbs.resize(num_limbs, 0);
+        }
+        limbs
+    }
+    /// Returns the index range of all used sequences starting with the amplitudes in `prefix`
+    ///
+    /// As the sequences are ordered lexicographically by the trellis, the indexes of all
+    /// sequences with a common prefix are contiguous. Only indexes with [AdEss::num_bits()] bits
+    /// are used, so the range may be empty. An error is returned if `prefix` is longer than
+    /// `n_max` or no sequence starts with it.
+    pub fn index_range_for_prefix(&self, prefix: &[usize]) -> Result<Range<Integer>, AdEssError> {
+        if prefix.len() > self.trellis.n_max {
+            return Err(AdEssError::InvalidSequence(
+                "`prefix` must not be longer than `n_max`",
+            ));
+        }
         let weights = self.trellis.get_weights();
+        let mut prefix_weight = 0;
+        for &amplitude in prefix {
+            if amplitude % 2 == 0 || (amplitude - 1) / 2 >= weights.len() {
+                return Err(AdEssError::InvalidSequence("Invalid amplitude in `prefix`"));
+            }
+            prefix_weight += weights[(amplitude - 1) / 2];
+        }
+        if prefix_weight > self.trellis.threshold {
+            return Err(AdEssError::InvalidSequence(
+                "No sequence starts with `prefix`",
+            ));
+        }
 
-        // the index of the sequence, before the number of lower sequences is added
-        let mut index = Integer::from(0);
-
-        // compute the sequence of traversed weight levels
-        let wl_seq = weight_idx_seq.iter().fold(vec![0], |mut acc, w_idx| {
-            acc.push(weights[*w_idx] + acc[acc.len() - 1]);
-            acc
-        });
-
-        // add number of lower sequences to the index
-        for n in 0..self.trellis.n_max {
-            // sum number of possible sequences where the next weight would have lower order than
-            // the real next weight
-            for (w_idx, next_wl) in self.trellis.get_successors(wl_seq[n]) {
-                if next_wl <= wl_seq[n + 1] && w_idx != weight_idx_seq[n] {
-                    index += self.trellis.get(n + 1, next_wl);
-                } else {
+        let num_sequences_used = Integer::u_pow_u(2, self.num_bits()).complete();
+        let start = self.index_for_sequence(prefix);
+        let end = self.trellis.get(prefix.len(), prefix_weight) + &start;
+        Ok(start.min(num_sequences_used.clone())..end.min(num_sequences_used))
+    }
+    /// Checks whether `amplitude_sequence` is a used, an unused or an invalid sequence
+    ///
+    /// Helps to detect a receiver configured with a different threshold or different weights
+    /// than the transmitter, see [SequenceReport].
+    pub fn check_sequence(&self, amplitude_sequence: &[usize]) -> SequenceReport {
+        let n_max = self.trellis.n_max;
+        let num_weights = self.trellis.get_weights().len();
+        let mut current_wl = 0;
+        let mut index = Integer::new();
+        for (n, &amplitude) in amplitude_sequence.iter().take(n_max).enumerate() {
+            let invalid = |reason| SequenceReport::Invalid {
+                position: n,
+                reason,
+            };
+            if amplitude % 2 == 0 || (amplitude - 1) / 2 >= num_weights {
+                return invalid("Amplitude without weight");
+            }
+            let target_w_idx = (amplitude - 1) / 2;
+            let mut next = None;
+            for &(w_idx, next_wl) in self.trellis.successors_slice(current_wl) {
+                let next_wl_value = self.trellis.get_or_0(n + 1, next_wl);
+                if w_idx == target_w_idx {
+                    next = Some((next_wl, next_wl_value));
                     break;
                 }
+                index += next_wl_value;
+            }
+            match next {
+                None => return invalid("Threshold exceeded"),
+                Some((_, value)) if value == 0 => return invalid("Amplitude leaves the trellis"),
+                Some((next_wl, _)) => current_wl = next_wl,
             }
         }
-        index
+        if amplitude_sequence.len() != n_max {
+            return SequenceReport::Invalid {
+                position: amplitude_sequence.len().min(n_max),
+                reason: "Sequence length differs from `n_max`",
+            };
+        }
+
+        if index < self.num_sequences_used() {
+            SequenceReport::Used(index)
+        } else {
+            SequenceReport::Unused(index)
+        }
     }
     /// Counts the occurences of the amplitude associated to `weight_idx` in stage `stage`
-    fn count_weight_in_stage(&self, weight_idx: usize, stage: usize) -> Integer {
-        let num_bits = self.num_bits();
-        let num_sequences_used = Integer::u_pow_u(2, num_bits).complete();
-        let first_abandoned_sequence = self.sequence_for_index(&num_sequences_used); // Short: FAS
+    ///
+    /// Only the sequences with a smaller index than `first_abandoned_sequence` (short: FAS) are
+    /// counted.
+    fn count_weight_in_stage(
+        &self,
+        weight_idx: usize,
+        stage: usize,
+        first_abandoned_sequence: &[usize],
+    ) -> Integer {
         let weights = self.trellis.get_weights();
         let fas_weight_idxs: Vec<usize> = first_abandoned_sequence // FAS weight indexes
             .iter()
@@ -383,7 +1519,7 @@ impl AdEss {
             (0..stage)
                 .map(|n| {
                     self.trellis
-                        .get_successors(fas_wls[n])
+                        .successors_slice(fas_wls[n])
                         .iter()
                         .take_while(|(w_idx, _)| w_idx != &fas_weight_idxs[n])
                         .map(|(_, wl)| self.trellis.get_or_0(n + 2, *wl + weights[weight_idx]))
@@ -411,7 +1547,7 @@ impl AdEss {
             (stage + 1..n_max)
                 .map(|n| {
                     self.trellis
-                        .get_successors(fas_wls[n])
+                        .successors_slice(fas_wls[n])
                         .iter()
                         .take_while(|(w_idx, _)| w_idx != &fas_weight_idxs[n])
                         .map(|(_, wl)| self.trellis.get_or_0(n + 1, *wl))
@@ -424,6 +1560,34 @@ impl AdEss {
 
         from_earlier_splits + from_split_at_stage + from_later_splits
     }
+    /// Returns the number of occurences of each amplitude in the sequences with the indexes
+    /// `0..num_indexes`
+    ///
+    /// Panics if `num_indexes` exceeds the number of sequences.
+    pub fn amplitude_counts_below(&self, num_indexes: &Integer) -> Vec<Integer> {
+        let n_max = self.trellis.n_max;
+        let num_weights = self.trellis.get_weights().len();
+        if *num_indexes == self.num_sequences() {
+            // the set of all sequences is symmetric, count the first position only
+            let mut counts = vec![Integer::new(); num_weights];
+            if n_max > 0 {
+                for (w_idx, wl) in self.trellis.get_successors(0) {
+                    counts[w_idx] = self.trellis.get(1, wl) * n_max;
+                }
+            }
+            return counts;
+        }
+        let first_abandoned_sequence = self.sequence_for_index(num_indexes);
+        (0..num_weights)
+            .map(|weight_idx| {
+                (0..n_max)
+                    .map(|stage| {
+                        self.count_weight_in_stage(weight_idx, stage, &first_abandoned_sequence)
+                    })
+                    .sum::<Integer>() // sum occurences over all stages
+            })
+            .collect()
+    }
     /// Returns the amplitude distribution as a [Vec]
     ///
     /// The amplitude distribution is valid if only sequences with indexes
@@ -436,21 +1600,144 @@ impl AdEss {
             return self.amplitude_distribution_full_utilization();
         }
 
+        self.amplitude_distribution_for_num_indices(&num_sequences_used)
+            .expect("2^num_bits sequences exist")
+    }
+    /// Returns the amplitude distribution if the sequences with the indexes `0..num_indices` are
+    /// used equiprobably
+    ///
+    /// Unlike [AdEss::amplitude_distribution()] the number of used indexes is not restricted to a
+    /// power of two, e.g., when framing overhead leaves part of the index space unused. An error
+    /// is returned if `num_indices` is not in `1..=num_sequences`.
+    pub fn amplitude_distribution_for_num_indices(
+        &self,
+        num_indices: &Integer,
+    ) -> Result<Vec<f32>, AdEssError> {
+        if *num_indices <= 0 || *num_indices > self.num_sequences() {
+            return Err(AdEssError::InvalidParameter(
+                "`num_indices` must be between 1 and the number of sequences",
+            ));
+        }
         let n_max = self.trellis.n_max;
+        Ok(self
+            .amplitude_counts_below(num_indices)
+            .into_iter()
+            .map(|count| {
+                // convert number of occurences to relative frequency
+                Rational::from((count, num_indices * n_max)).to_f32()
+            })
+            .collect())
+    }
+    /// Returns the amplitude distribution for a non-uniform distribution of the indexes
+    ///
+    /// `prior` holds (index, probability) pairs for indexes with a known probability, e.g., very
+    /// frequent control messages. The remaining probability is distributed uniformly over all
+    /// other indexes representable with [self.num_bits] bits.
+    pub fn amplitude_distribution_with_prior(
+        &self,
+        prior: &[(Integer, f64)],
+    ) -> Result<Vec<f32>, AdEssError> {
+        let num_sequences_used = Integer::u_pow_u(2, self.num_bits()).complete();
+        let mut prior_indexes: Vec<&Integer> = prior.iter().map(|(index, _)| index).collect();
+        prior_indexes.sort();
+        prior_indexes.dedup();
+        if prior_indexes.len() != prior.len() {
+            return Err(AdEssError::InvalidParameter(
+                "Indexes in `prior` must be unique",
+            ));
+        }
+        if prior.iter().any(|(index, _)| index >= &num_sequences_used) {
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        let prior_probability: f64 = prior.iter().map(|(_, p)| p).sum();
+        if prior.iter().any(|(_, p)| *p < 0.0) || prior_probability > 1.0 + 1e-9 {
+            return Err(AdEssError::InvalidDistribution(
+                "Probabilities in `prior` must be non-negative and sum up to at most 1",
+            ));
+        }
 
-        let weight_frequencies: Vec<f32> = (0..self.trellis.get_weights().len())
-            .map(|weight_idx| {
-                (0..n_max)
-                    .map(|stage| self.count_weight_in_stage(weight_idx, stage))
-                    .sum::<Integer>() // sum occurences over all stages
+        let n_max = self.trellis.n_max as f64;
+        let num_weights = self.trellis.get_weights().len();
+        // relative frequency of each amplitude in each prior sequence
+        let prior_frequencies: Vec<Vec<f64>> = prior
+            .iter()
+            .map(|(index, _)| {
+                let mut frequencies = vec![0f64; num_weights];
+                for a in self.sequence_for_index(index) {
+                    frequencies[(a - 1) / 2] += 1.0 / n_max;
+                }
+                frequencies
             })
-            .map(|weight_occurences| {
-                // convert number of occurences to relative frequency
-                Rational::from((weight_occurences, &num_sequences_used * n_max)).to_f32()
+            .collect();
+
+        let num_sequences_used = num_sequences_used.to_f64();
+        let num_other_sequences = num_sequences_used - prior.len() as f64;
+        let distribution = self
+            .amplitude_distribution()
+            .iter()
+            .enumerate()
+            .map(|(w_idx, &p_uniform)| {
+                let from_prior: f64 = prior
+                    .iter()
+                    .zip(prior_frequencies.iter())
+                    .map(|((_, p), frequencies)| p * frequencies[w_idx])
+                    .sum();
+                let prior_frequency_sum: f64 = prior_frequencies
+                    .iter()
+                    .map(|frequencies| frequencies[w_idx])
+                    .sum();
+                let from_others = if num_other_sequences > 0.0 {
+                    (1.0 - prior_probability)
+                        * (num_sequences_used * p_uniform as f64 - prior_frequency_sum)
+                        / num_other_sequences
+                } else {
+                    0.0
+                };
+                (from_prior + from_others) as f32
             })
             .collect();
+        Ok(distribution)
+    }
+    /// Returns the amplitude distribution for each position of the sequences
+    ///
+    /// `distribution[n][w_idx]` is the probability of the amplitude with weight index `w_idx` at
+    /// position `n`. Unlike the block average [AdEss::amplitude_distribution()] this shows the
+    /// edge effects caused by using only indexes representable with [self.num_bits] bits.
+    pub fn amplitude_distribution_per_position(&self) -> Vec<Vec<f32>> {
+        let num_sequences_used = Integer::u_pow_u(2, self.num_bits()).complete();
+        let n_max = self.trellis.n_max;
 
-        weight_frequencies
+        if num_sequences_used == self.num_sequences() {
+            // all sequences are used, the distribution is the same for all positions
+            return vec![self.amplitude_distribution_full_utilization(); n_max];
+        }
+
+        let first_abandoned_sequence = self.sequence_for_index(&num_sequences_used);
+        (0..n_max)
+            .map(|stage| {
+                (0..self.trellis.get_weights().len())
+                    .map(|weight_idx| {
+                        let weight_occurences = self.count_weight_in_stage(
+                            weight_idx,
+                            stage,
+                            &first_abandoned_sequence,
+                        );
+                        Rational::from((weight_occurences, &num_sequences_used)).to_f32()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    /// Returns the KL divergence between the amplitude distribution of each position and `distribution`
+    ///
+    /// Shows in which positions of the sequences the shaping deviates most from the target, see
+    /// [AdEss::amplitude_distribution_per_position()].
+    pub fn kl_divergence_per_position(&self, distribution: &[f32]) -> Vec<f32> {
+        let distribution = distribution.to_vec();
+        self.amplitude_distribution_per_position()
+            .iter()
+            .map(|position_distribution| kl_divergence(position_distribution, &distribution))
+            .collect()
     }
     /// Returns the amplitude distribution as a [Vec]
     ///
@@ -459,6 +1746,10 @@ impl AdEss {
     pub fn amplitude_distribution_full_utilization(&self) -> Vec<f32> {
         let num_sequences = self.num_sequences();
         let mut distribution = vec![0f32; self.trellis.get_weights().len()];
+        if self.trellis.n_max == 0 {
+            // sequences without amplitudes
+            return distribution;
+        }
 
         for (w_idx, wl) in self.trellis.get_successors(0) {
             distribution[w_idx] =
@@ -467,6 +1758,249 @@ impl AdEss {
         distribution
     }
 
+    /// Returns an iterator over (index, amplitude sequence) pairs for all indexes in `range`
+    ///
+    /// The sequences are generated in index order. Only the trellis path after the last changed
+    /// stage is recalculated for each step, i.e., the sequences are not encoded one by one.
+    pub fn iter_sequences(&self, range: Range<Integer>) -> SequenceIter<'_> {
+        assert!(
+            range.end <= self.num_sequences(),
+            "Index range out of range!"
+        );
+        let path = if range.start < range.end {
+            self.path_for_index(&range.start)
+        } else {
+            vec![]
+        };
+        SequenceIter {
+            adess: self,
+            index: range.start,
+            end: range.end,
+            path,
+        }
+    }
+    /// Returns the successors and the position of the chosen successor for each stage of the
+    /// trellis path of `index`
+    fn path_for_index(&self, index: &Integer) -> Vec<(Vec<(usize, usize)>, usize)> {
+        let mut path = Vec::with_capacity(self.trellis.n_max);
+        let mut current_wl = 0;
+        let mut num_sequences_left_below = Integer::from(0);
+        for n in 0..self.trellis.n_max {
+            let successors = self.trellis.get_successors(current_wl);
+            for (position, &(_, next_wl)) in successors.iter().enumerate() {
+                num_sequences_left_below += self.trellis.get(n + 1, next_wl);
+                if index < &num_sequences_left_below {
+                    num_sequences_left_below -= self.trellis.get(n + 1, next_wl);
+                    current_wl = next_wl;
+                    path.push((successors, position));
+                    break;
+                }
+            }
+        }
+        path
+    }
+
+    /// Returns `num_sequences` amplitude sequences for uniformly random indexes
+    ///
+    /// Only indexes representable with [AdEss::num_bits()] bits are drawn. The same `seed` always
+    /// returns the same sequences.
+    pub fn sample_sequences(&self, num_sequences: usize, seed: u64) -> Vec<Vec<usize>> {
+        let mut rand = RandState::new();
+        rand.seed(&Integer::from(seed));
+        let num_sequences_used = Integer::u_pow_u(2, self.num_bits()).complete();
+
+        (0..num_sequences)
+            .map(|_| {
+                let index = Integer::from(num_sequences_used.random_below_ref(&mut rand));
+                self.sequence_for_index(&index)
+            })
+            .collect()
+    }
+
+    /// Measures the encode and decode throughput on the current machine
+    ///
+    /// Uniformly random indexes are encoded for `duration`, afterwards the resulting sequences
+    /// are decoded for `duration`. At least one block is processed in each phase.
+    pub fn benchmark(&self, duration: Duration) -> BenchmarkResult {
+        let mut rand = RandState::new();
+        let num_sequences_used = self.num_sequences_used();
+        let indexes: Vec<Integer> = (0..256)
+            .map(|_| Integer::from(num_sequences_used.random_below_ref(&mut rand)))
+            .collect();
+        let sequences: Vec<Vec<usize>> = indexes
+            .iter()
+            .map(|index| self.sequence_for_index(index))
+            .collect();
+
+        let start = Instant::now();
+        let mut num_encoded_blocks = 0;
+        while num_encoded_blocks == 0 || start.elapsed() < duration {
+            black_box(self.sequence_for_index(&indexes[num_encoded_blocks % indexes.len()]));
+            num_encoded_blocks += 1;
+        }
+        let encode_time = start.elapsed().as_secs_f64();
+
+        let start = Instant::now();
+        let mut num_decoded_blocks = 0;
+        while num_decoded_blocks == 0 || start.elapsed() < duration {
+            black_box(self.index_for_sequence(&sequences[num_decoded_blocks % sequences.len()]));
+            num_decoded_blocks += 1;
+        }
+        let decode_time = start.elapsed().as_secs_f64();
+
+        let encode_blocks_per_second = num_encoded_blocks as f64 / encode_time;
+        let decode_blocks_per_second = num_decoded_blocks as f64 / decode_time;
+        let mbit_per_block = self.num_bits() as f64 / 1e6;
+        BenchmarkResult {
+            num_encoded_blocks,
+            num_decoded_blocks,
+            encode_blocks_per_second,
+            decode_blocks_per_second,
+            encode_mbit_per_second: encode_blocks_per_second * mbit_per_block,
+            decode_mbit_per_second: decode_blocks_per_second * mbit_per_block,
+        }
+    }
+
+    /// Returns the number of used sequences for each sequence weight as (weight, count) pairs
+    ///
+    /// Only indexes representable with [AdEss::num_bits()] bits are counted. The counts are
+    /// calculated from the trellis, see [AdEss::energy_distribution()].
+    pub fn weight_distribution(&self) -> Vec<(usize, Integer)> {
+        let weights = self.trellis.get_weights();
+        self.sequence_value_distribution(|w_idx| weights[w_idx])
+    }
+    /// Returns the number of used sequences for each sequence energy as (energy, count) pairs
+    ///
+    /// Only indexes representable with [AdEss::num_bits()] bits are counted. The used sequences
+    /// are split into trellis nodes branching off the path of the first abandoned sequence, the
+    /// energy distribution of all sequences starting at these nodes is calculated with a
+    /// backward recursion. No sequences are enumerated.
+    pub fn energy_distribution(&self) -> Vec<(usize, Integer)> {
+        self.sequence_value_distribution(|w_idx| {
+            let amplitude = AdEss::weight_idx_to_amplitude(w_idx);
+            amplitude * amplitude
+        })
+    }
+    /// Returns the complementary cumulative distribution `P(E >= e)` of the sequence energy as
+    /// (energy, probability) pairs
+    ///
+    /// Assumes only indexes representable with [AdEss::num_bits()] bits are used equiprobably.
+    pub fn energy_ccdf(&self) -> Vec<(usize, f32)> {
+        let num_sequences_used = Integer::u_pow_u(2, self.num_bits()).complete();
+        let mut num_sequences_above = num_sequences_used.clone();
+        self.energy_distribution()
+            .into_iter()
+            .map(|(energy, count)| {
+                let probability =
+                    Rational::from((num_sequences_above.clone(), &num_sequences_used)).to_f32();
+                num_sequences_above -= count;
+                (energy, probability)
+            })
+            .collect()
+    }
+    /// Returns the raw moments `E[E^j]` for `j = 1..=k` of the sequence energy `E = sum(a * a)`
+    ///
+    /// Assumes only indexes representable with [AdEss::num_bits()] bits are used equiprobably.
+    /// The moments are calculated exactly from [AdEss::energy_distribution()].
+    pub fn energy_moments(&self, k: u32) -> Vec<f64> {
+        let num_sequences_used = Integer::u_pow_u(2, self.num_bits()).complete();
+        let energy_distribution = self.energy_distribution();
+        (1..k + 1)
+            .map(|j| {
+                let sum: Integer = energy_distribution
+                    .iter()
+                    .map(|(energy, count)| Integer::u_pow_u(*energy as u32, j).complete() * count)
+                    .sum();
+                Rational::from((sum, &num_sequences_used)).to_f64()
+            })
+            .collect()
+    }
+    /// Returns the variance of the sequence energy, see [AdEss::energy_moments()]
+    pub fn energy_variance(&self) -> f64 {
+        let moments = self.energy_moments(2);
+        moments[1] - moments[0] * moments[0]
+    }
+    /// Returns the distribution of `sum(value_of(w_idx))` over all used sequences
+    fn sequence_value_distribution(
+        &self,
+        value_of: impl Fn(usize) -> usize,
+    ) -> Vec<(usize, Integer)> {
+        let n_max = self.trellis.n_max;
+        let weight_levels = self.trellis.get_weight_levels();
+        let num_sequences_used = Integer::u_pow_u(2, self.num_bits()).complete();
+
+        // (weight level, value offset) of all nodes whose sequences are all used, per stage
+        let mut used_nodes = vec![vec![]; n_max + 1];
+        if num_sequences_used == self.num_sequences() {
+            used_nodes[0].push((0, 0));
+        } else {
+            let first_abandoned_sequence = self.sequence_for_index(&num_sequences_used);
+            let mut current_wl = 0;
+            let mut offset = 0;
+            for (n, a) in first_abandoned_sequence.iter().enumerate() {
+                let fas_w_idx = (a - 1) / 2;
+                for (w_idx, next_wl) in self.trellis.get_successors(current_wl) {
+                    if w_idx == fas_w_idx {
+                        current_wl = next_wl;
+                        offset += value_of(w_idx);
+                        break;
+                    }
+                    used_nodes[n + 1].push((next_wl, offset + value_of(w_idx)));
+                }
+            }
+        }
+
+        // counts[wl_idx][v]: number of sequences from node (n, wl) to the end with value v
+        let mut counts: Vec<Vec<Integer>> = weight_levels
+            .iter()
+            .map(|&wl| {
+                if self.trellis.get(n_max, wl) == 0 {
+                    vec![]
+                } else {
+                    vec![Integer::from(1)]
+                }
+            })
+            .collect();
+        let mut distribution: Vec<Integer> = vec![];
+        for n in (0..n_max + 1).rev() {
+            for &(wl, offset) in used_nodes[n].iter() {
+                let node_counts = &counts[self.trellis.get_weight_level_index(wl)];
+                if distribution.len() < offset + node_counts.len() {
+                    distribution.resize(offset + node_counts.len(), Integer::new());
+                }
+                for (v, count) in node_counts.iter().enumerate() {
+                    distribution[offset + v] += count;
+                }
+            }
+            if n == 0 {
+                break;
+            }
+            counts = weight_levels
+                .iter()
+                .map(|&wl| {
+                    let mut node_counts: Vec<Integer> = vec![];
+                    for (w_idx, next_wl) in self.trellis.get_successors(wl) {
+                        let value = value_of(w_idx);
+                        let next_counts = &counts[self.trellis.get_weight_level_index(next_wl)];
+                        if node_counts.len() < value + next_counts.len() {
+                            node_counts.resize(value + next_counts.len(), Integer::new());
+                        }
+                        for (v, count) in next_counts.iter().enumerate() {
+                            node_counts[value + v] += count;
+                        }
+                    }
+                    node_counts
+                })
+                .collect();
+        }
+
+        distribution
+            .into_iter()
+            .enumerate()
+            .filter(|(_, count)| *count != 0)
+            .collect()
+    }
+
     /// Returns the average energy
     ///
     /// Assumes only indexes representable with [self.num_bits] bits are used.
@@ -480,3 +2014,239 @@ impl AdEss {
             .sum::<f32>()
     }
 }
+
+/// Rounding of the exact weights in [AdEss::calc_weights_with_rounding()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    Floor,
+    /// Round to the nearest integer (used by [AdEss::calc_weights()])
+    Round,
+    Ceil,
+    /// Round to the nearest integer and carry the rounding error over to the next weight
+    ErrorDiffusion,
+}
+
+/// Intermediate results of [AdEss::optimal_threshold_with_diagnostics()]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThresholdDiagnostics {
+    /// Threshold with the smallest mutual information loss bound
+    pub threshold: usize,
+    /// Smallest threshold with at least `2^ceil(n_max * H(P))` sequences, the center of the
+    /// search
+    pub estimated_threshold: usize,
+    /// Tested thresholds in increasing order
+    pub tested_thresholds: Vec<usize>,
+    /// Mutual information loss bound of each tested threshold, see [AdEss::mi_loss_bound()]
+    pub mi_loss_bounds: Vec<f32>,
+    /// Warnings about the search, e.g., an optimum at the edge of the search window
+    pub warnings: Vec<&'static str>,
+}
+
+/// Integer weights and their quantization error, see [AdEss::calc_weights_with_rounding()]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightQuantization {
+    pub weights: Vec<usize>,
+    /// Difference between each integer weight and the exact weight `-log2(p) * res_factor`
+    /// (both shifted such that the smallest weight is 0)
+    pub errors: Vec<f32>,
+    /// KL divergence between the distribution of the integer weights, see
+    /// [utils::distribution_from_weights()], and the target distribution
+    pub kl_divergence: f32,
+}
+
+/// Amplitude sequence and trellis path of an index, see [AdEss::sequence_for_index_with_info()]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodingInfo {
+    pub sequence: Vec<usize>,
+    /// Traversed weight levels, starting with 0 and ending with the sum weight
+    pub weight_levels: Vec<usize>,
+    /// Sum weight of the sequence
+    pub weight: usize,
+    /// Energy `sum(a * a)` of the sequence
+    pub energy: usize,
+}
+
+/// Membership of an amplitude sequence, see [AdEss::check_sequence()]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequenceReport {
+    /// The sequence is used for encoding and has the contained index
+    Used(Integer),
+    /// The sequence is in the trellis but its index has more than [AdEss::num_bits()] bits
+    Unused(Integer),
+    /// The sequence is not in the trellis, `position` is the first offending amplitude
+    Invalid {
+        position: usize,
+        reason: &'static str,
+    },
+}
+
+/// Encode and decode throughput of an [AdEss], see [AdEss::benchmark()]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchmarkResult {
+    pub num_encoded_blocks: usize,
+    pub num_decoded_blocks: usize,
+    pub encode_blocks_per_second: f64,
+    pub decode_blocks_per_second: f64,
+    /// Encoded data bits in Mbit/s
+    pub encode_mbit_per_second: f64,
+    /// Decoded data bits in Mbit/s
+    pub decode_mbit_per_second: f64,
+}
+
+/// Iterator over the amplitude sequences of an [AdEss], see [AdEss::iter_sequences()]
+pub struct SequenceIter<'a> {
+    adess: &'a AdEss,
+    index: Integer,
+    end: Integer,
+    path: Vec<(Vec<(usize, usize)>, usize)>,
+}
+
+impl SequenceIter<'_> {
+    /// Returns the position of the first successor at or after `start` with a non-zero value
+    fn next_used_successor(
+        &self,
+        stage: usize,
+        successors: &[(usize, usize)],
+        start: usize,
+    ) -> Option<usize> {
+        (start..successors.len()).find(|&position| {
+            self.adess
+                .trellis
+                .get(stage + 1, successors[position].1)
+                .cmp0()
+                .is_gt()
+        })
+    }
+    /// Moves the path to the sequence with the next higher index
+    fn advance_path(&mut self) {
+        let n_max = self.adess.trellis.n_max;
+        // the last stage which can move to a higher successor
+        let changed_stage = (0..n_max).rev().find_map(|n| {
+            let (successors, position) = &self.path[n];
+            self.next_used_successor(n, successors, position + 1)
+                .map(|next_position| (n, next_position))
+        });
+        let (changed_stage, next_position) = match changed_stage {
+            Some(change) => change,
+            None => return,
+        };
+        self.path[changed_stage].1 = next_position;
+
+        // all later stages use the lowest possible successor
+        for n in changed_stage + 1..n_max {
+            let (successors, position) = &self.path[n - 1];
+            let current_wl = successors[*position].1;
+            let successors = self.adess.trellis.get_successors(current_wl);
+            let position = self
+                .next_used_successor(n, &successors, 0)
+                .expect("every used node has a used successor");
+            self.path[n] = (successors, position);
+        }
+    }
+}
+
+impl Iterator for SequenceIter<'_> {
+    type Item = (Integer, Vec<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.end {
+            return None;
+        }
+        let amplitude_sequence = self
+            .path
+            .iter()
+            .map(|(successors, position)| AdEss::weight_idx_to_amplitude(successors[*position].0))
+            .collect();
+        let item = (self.index.clone(), amplitude_sequence);
+
+        self.index += 1;
+        if self.index < self.end {
+            self.advance_path();
+        }
+        Some(item)
+    }
+}
+
+/// Walks the trellis path of `remaining_index` in a forward trellis and calls `visit` with the
+/// weight index of each stage, the index is consumed
+///
+/// If the successor sums of the trellis are built (see [Trellis::build_successor_sums()]),
+/// the branch of each stage is found by binary search instead of a linear scan.
+pub(crate) fn walk_for_remaining_index<T: TrellisValue>(
+    trellis: &Trellis<T>,
+    remaining_index: &mut T,
+    mut visit: impl FnMut(usize),
+) {
+    let sorted_weights = trellis.get_sorted_weights();
+    let mut current_wl = 0;
+    for n in 0..trellis.n_max {
+        if let Some(sums) = trellis.successor_sums(n, current_wl) {
+            // the sums are non-decreasing, the branch is the first one whose sum exceeds
+            // the remaining index, all sequences of the previous branches are left below
+            let k = sums.partition_point(|sum| sum <= remaining_index);
+            if k > 0 {
+                *remaining_index -= &sums[k - 1];
+            }
+            let (w_idx, weight) = sorted_weights[k];
+            visit(w_idx);
+            current_wl += weight;
+            continue;
+        }
+        for &(w_idx, weight) in sorted_weights {
+            let next_wl = current_wl + weight;
+            let next_wl_value = trellis.get_ref(n + 1, next_wl);
+            if *remaining_index < *next_wl_value {
+                // we can reach the target index via next_wl
+                visit(w_idx);
+                current_wl = next_wl;
+                break;
+            }
+            // target index is not reachable if we use `next_wl` as next weight level
+            // -> to reach the target index, we have to use a higher next weight level
+            // thus we leave below all sequences possible with the current `next_wl`
+            *remaining_index -= next_wl_value;
+        }
+    }
+}
+
+/// Returns the index of the sequence of weight indexes `weight_idx_seq` in a forward trellis
+///
+/// Calculations based on algorithm 2 in section III-C of <https://doi.org/10.1109/TWC.2019.2951139>.
+pub(crate) fn index_for_weight_idx_sequence<T: TrellisValue>(
+    trellis: &Trellis<T>,
+    weight_idx_seq: &[usize],
+) -> T {
+    let weights = trellis.weights_slice();
+
+    // the index of the sequence, before the number of lower sequences is added
+    let mut index = T::zero();
+
+    // compute the sequence of traversed weight levels
+    let wl_seq = weight_idx_seq.iter().fold(vec![0], |mut acc, w_idx| {
+        acc.push(weights[*w_idx] + acc[acc.len() - 1]);
+        acc
+    });
+
+    // add number of lower sequences to the index, for a prefix of a sequence this is the
+    // index of the first sequence starting with the prefix
+    for n in 0..weight_idx_seq.len() {
+        // sum number of possible sequences where the next weight would have lower order than
+        // the real next weight
+        // a prefix above the threshold is not a cached weight level
+        let uncached_successors;
+        let successors = if trellis.is_weight_level(wl_seq[n]) {
+            trellis.successors_slice(wl_seq[n])
+        } else {
+            uncached_successors = trellis.get_successors(wl_seq[n]);
+            &uncached_successors
+        };
+        for &(w_idx, next_wl) in successors {
+            if next_wl <= wl_seq[n + 1] && w_idx != weight_idx_seq[n] {
+                index += trellis.get_ref(n + 1, next_wl);
+            } else {
+                break;
+            }
+        }
+    }
+    index
+}
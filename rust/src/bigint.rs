@@ -0,0 +1,14 @@
+// The default feature `rug` uses GMP. Without it, the feature `num-bigint` selects a pure Rust
+// backend with the subset of the rug API used by this crate, e.g., for targets without a C
+// toolchain.
+
+#[cfg(feature = "rug")]
+pub use rug::{integer::Order, ops::Pow, rand::RandState, Assign, Complete, Integer, Rational};
+
+#[cfg(all(feature = "num-bigint", not(feature = "rug")))]
+mod num_backend;
+#[cfg(all(feature = "num-bigint", not(feature = "rug")))]
+pub use num_backend::{Assign, Complete, Integer, Order, Pow, RandState, Rational};
+
+#[cfg(not(any(feature = "rug", feature = "num-bigint")))]
+compile_error!("Either the `rug` or the `num-bigint` feature must be enabled");
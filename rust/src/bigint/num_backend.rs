@@ -0,0 +1,511 @@
+use num_bigint::{BigInt, BigUint, RandBigInt, Sign};
+use num_integer::Integer as _;
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{
+    Add, AddAssign, BitAnd, BitAndAssign, BitXor, BitXorAssign, Div, DivAssign, Mul, MulAssign,
+    Neg, Rem, RemAssign, Shl, ShlAssign, Shr, ShrAssign, Sub, SubAssign,
+};
+
+/// Completes an operation with borrowed operands, see `rug::Complete`
+pub trait Complete {
+    type Completed;
+    fn complete(self) -> Self::Completed;
+}
+
+/// Assigns a value without reallocation, see `rug::Assign`
+pub trait Assign<Src = Self> {
+    fn assign(&mut self, src: Src);
+}
+
+/// Power with an unsigned exponent, see `rug::ops::Pow`
+pub trait Pow<Rhs> {
+    type Output;
+    fn pow(self, rhs: Rhs) -> Self::Output;
+}
+
+/// Order of the digits, see `rug::integer::Order`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Order {
+    /// Least significant digit first
+    Lsf,
+    /// Most significant digit first
+    Msf,
+}
+
+/// Digit types of [Integer::to_digits()] and [Integer::from_digits()]
+pub trait Digit: Copy {
+    const BITS: u32;
+    fn to_u128(self) -> u128;
+    fn from_u128(value: u128) -> Self;
+}
+
+impl Digit for u8 {
+    const BITS: u32 = u8::BITS;
+    fn to_u128(self) -> u128 {
+        self as u128
+    }
+    fn from_u128(value: u128) -> Self {
+        value as u8
+    }
+}
+
+impl Digit for u64 {
+    const BITS: u32 = u64::BITS;
+    fn to_u128(self) -> u128 {
+        self as u128
+    }
+    fn from_u128(value: u128) -> Self {
+        value as u64
+    }
+}
+
+/// Arbitrary precision integer backed by [BigInt], see `rug::Integer`
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Integer(BigInt);
+
+impl Integer {
+    pub fn new() -> Integer {
+        Integer(BigInt::zero())
+    }
+    /// The capacity is ignored, [BigInt] allocates on demand
+    pub fn with_capacity(_bits: usize) -> Integer {
+        Integer::new()
+    }
+    pub fn u_pow_u(base: u32, exponent: u32) -> Incomplete {
+        Incomplete(Integer(BigInt::from(base).pow(exponent)))
+    }
+    pub fn significant_bits(&self) -> u32 {
+        self.0.bits() as u32
+    }
+    /// Returns the number of ones of a non-negative value
+    pub fn count_ones(&self) -> Option<u32> {
+        match self.0.sign() {
+            Sign::Minus => None,
+            _ => Some(self.0.magnitude().count_ones() as u32),
+        }
+    }
+    pub fn get_bit(&self, index: u32) -> bool {
+        self.0.bit(index as u64)
+    }
+    pub fn is_negative(&self) -> bool {
+        self.0.is_negative()
+    }
+    pub fn cmp0(&self) -> Ordering {
+        self.0.sign().cmp(&Sign::NoSign)
+    }
+    pub fn to_f64(&self) -> f64 {
+        self.0.to_f64().unwrap_or(f64::INFINITY)
+    }
+    pub fn to_f32(&self) -> f32 {
+        self.0.to_f32().unwrap_or(f32::INFINITY)
+    }
+    pub fn from_f64(value: f64) -> Option<Integer> {
+        BigInt::from_f64(value.trunc()).map(Integer)
+    }
+    pub fn to_usize(&self) -> Option<usize> {
+        self.0.to_usize()
+    }
+    pub fn to_u32(&self) -> Option<u32> {
+        self.0.to_u32()
+    }
+    pub fn to_u64(&self) -> Option<u64> {
+        self.0.to_u64()
+    }
+    pub fn to_u128(&self) -> Option<u128> {
+        self.0.to_u128()
+    }
+    pub fn to_i64(&self) -> Option<i64> {
+        self.0.to_i64()
+    }
+    pub fn from_str_radix(src: &str, radix: i32) -> Result<Integer, ParseIntegerError> {
+        BigInt::parse_bytes(src.as_bytes(), radix as u32)
+            .map(Integer)
+            .ok_or(ParseIntegerError)
+    }
+    /// Returns the truncated quotient and the remainder
+    pub fn div_rem_ref(&self, divisor: &Integer) -> (Integer, Integer) {
+        let (quotient, remainder) = self.0.div_rem(&divisor.0);
+        (Integer(quotient), Integer(remainder))
+    }
+    /// Returns the floored quotient and the remainder
+    pub fn div_rem_floor(self, divisor: Integer) -> (Integer, Integer) {
+        let (quotient, remainder) = self.0.div_mod_floor(&divisor.0);
+        (Integer(quotient), Integer(remainder))
+    }
+    /// Returns the digits of the absolute value
+    pub fn to_digits<T: Digit>(&self, order: Order) -> Vec<T> {
+        let mask = u128::MAX >> (128 - T::BITS);
+        let mut digits: Vec<T> = self
+            .0
+            .magnitude()
+            .iter_u64_digits()
+            .flat_map(|limb| {
+                (0..u64::BITS / T::BITS)
+                    .map(move |i| T::from_u128((limb as u128 >> (i * T::BITS)) & mask))
+            })
+            .collect();
+        // the most significant limb may be padded with zero digits
+        while digits.last().is_some_and(|digit| digit.to_u128() == 0) {
+            digits.pop();
+        }
+        if order == Order::Msf {
+            digits.reverse();
+        }
+        digits
+    }
+    pub fn from_digits<T: Digit>(digits: &[T], order: Order) -> Integer {
+        let mut value = BigUint::zero();
+        let mut push = |digit: &T| value = (&value << T::BITS) + BigUint::from(digit.to_u128());
+        match order {
+            Order::Lsf => digits.iter().rev().for_each(&mut push),
+            Order::Msf => digits.iter().for_each(&mut push),
+        }
+        Integer(BigInt::from(value))
+    }
+    /// Returns a uniformly distributed random value in `0..self`
+    pub fn random_below_ref(&self, rng: &mut RandState) -> Incomplete {
+        Incomplete(Integer(rng.rng.gen_bigint_range(&BigInt::zero(), &self.0)))
+    }
+    /// Returns a uniformly distributed random value with up to `bits` bits
+    pub fn random_bits(bits: u32, rng: &mut RandState) -> Incomplete {
+        Incomplete(Integer(BigInt::from(rng.rng.gen_biguint(bits as u64))))
+    }
+}
+
+/// Result of an operation with borrowed operands, see the `*Incomplete` types of rug
+///
+/// Unlike rug, the operation is evaluated immediately.
+pub struct Incomplete(Integer);
+
+impl Complete for Incomplete {
+    type Completed = Integer;
+    fn complete(self) -> Integer {
+        self.0
+    }
+}
+
+impl From<Incomplete> for Integer {
+    fn from(value: Incomplete) -> Integer {
+        value.0
+    }
+}
+
+impl AddAssign<Incomplete> for Integer {
+    fn add_assign(&mut self, rhs: Incomplete) {
+        self.0 += rhs.0 .0;
+    }
+}
+
+impl SubAssign<Incomplete> for Integer {
+    fn sub_assign(&mut self, rhs: Incomplete) {
+        self.0 -= rhs.0 .0;
+    }
+}
+
+impl Complete for Integer {
+    type Completed = Integer;
+    fn complete(self) -> Integer {
+        self
+    }
+}
+
+impl Complete for (Integer, Integer) {
+    type Completed = (Integer, Integer);
+    fn complete(self) -> (Integer, Integer) {
+        self
+    }
+}
+
+impl Assign<Integer> for Integer {
+    fn assign(&mut self, src: Integer) {
+        *self = src;
+    }
+}
+
+impl Assign<&Integer> for Integer {
+    fn assign(&mut self, src: &Integer) {
+        self.clone_from(src);
+    }
+}
+
+impl From<&Integer> for Integer {
+    fn from(value: &Integer) -> Integer {
+        value.clone()
+    }
+}
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Debug for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// Error of [Integer::from_str_radix()]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseIntegerError;
+
+impl fmt::Display for ParseIntegerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid digit found in string")
+    }
+}
+
+impl std::error::Error for ParseIntegerError {}
+
+impl Neg for Integer {
+    type Output = Integer;
+    fn neg(self) -> Integer {
+        Integer(-self.0)
+    }
+}
+
+impl Sum<Integer> for Integer {
+    fn sum<I: Iterator<Item = Integer>>(iter: I) -> Integer {
+        Integer(iter.map(|value| value.0).sum())
+    }
+}
+
+impl<'a> Sum<&'a Integer> for Integer {
+    fn sum<I: Iterator<Item = &'a Integer>>(iter: I) -> Integer {
+        Integer(iter.map(|value| &value.0).sum())
+    }
+}
+
+impl Pow<u32> for Integer {
+    type Output = Integer;
+    fn pow(self, exponent: u32) -> Integer {
+        Integer(self.0.pow(exponent))
+    }
+}
+
+impl Pow<u32> for &Integer {
+    type Output = Incomplete;
+    fn pow(self, exponent: u32) -> Incomplete {
+        Incomplete(Integer(self.0.pow(exponent)))
+    }
+}
+
+/// Implements a binary operator for all combinations of owned and borrowed [Integer]s
+macro_rules! impl_integer_op {
+    ($op:ident, $method:ident, $op_assign:ident, $method_assign:ident) => {
+        impl $op<Integer> for Integer {
+            type Output = Integer;
+            fn $method(self, rhs: Integer) -> Integer {
+                Integer(self.0.$method(rhs.0))
+            }
+        }
+        impl $op<&Integer> for Integer {
+            type Output = Integer;
+            fn $method(self, rhs: &Integer) -> Integer {
+                Integer(self.0.$method(&rhs.0))
+            }
+        }
+        impl $op<Integer> for &Integer {
+            type Output = Integer;
+            fn $method(self, rhs: Integer) -> Integer {
+                Integer((&self.0).$method(rhs.0))
+            }
+        }
+        impl $op<&Integer> for &Integer {
+            type Output = Incomplete;
+            fn $method(self, rhs: &Integer) -> Incomplete {
+                Incomplete(Integer((&self.0).$method(&rhs.0)))
+            }
+        }
+        impl $op_assign<Integer> for Integer {
+            fn $method_assign(&mut self, rhs: Integer) {
+                self.0.$method_assign(rhs.0);
+            }
+        }
+        impl $op_assign<&Integer> for Integer {
+            fn $method_assign(&mut self, rhs: &Integer) {
+                self.0.$method_assign(&rhs.0);
+            }
+        }
+    };
+}
+
+impl_integer_op!(Add, add, AddAssign, add_assign);
+impl_integer_op!(Sub, sub, SubAssign, sub_assign);
+impl_integer_op!(Mul, mul, MulAssign, mul_assign);
+impl_integer_op!(Div, div, DivAssign, div_assign);
+impl_integer_op!(Rem, rem, RemAssign, rem_assign);
+impl_integer_op!(BitAnd, bitand, BitAndAssign, bitand_assign);
+impl_integer_op!(BitXor, bitxor, BitXorAssign, bitxor_assign);
+
+/// Implements conversions, comparisons and arithmetic with a primitive integer type
+macro_rules! impl_primitive {
+    ($($primitive:ty),*) => {$(
+        impl From<$primitive> for Integer {
+            fn from(value: $primitive) -> Integer {
+                Integer(BigInt::from(value))
+            }
+        }
+        impl Assign<$primitive> for Integer {
+            fn assign(&mut self, src: $primitive) {
+                self.0 = BigInt::from(src);
+            }
+        }
+        impl PartialEq<$primitive> for Integer {
+            fn eq(&self, other: &$primitive) -> bool {
+                self.0 == BigInt::from(*other)
+            }
+        }
+        impl PartialEq<Integer> for $primitive {
+            fn eq(&self, other: &Integer) -> bool {
+                BigInt::from(*self) == other.0
+            }
+        }
+        impl PartialOrd<$primitive> for Integer {
+            fn partial_cmp(&self, other: &$primitive) -> Option<Ordering> {
+                self.0.partial_cmp(&BigInt::from(*other))
+            }
+        }
+        impl PartialOrd<Integer> for $primitive {
+            fn partial_cmp(&self, other: &Integer) -> Option<Ordering> {
+                BigInt::from(*self).partial_cmp(&other.0)
+            }
+        }
+        impl_primitive_op!($primitive, Add, add, AddAssign, add_assign);
+        impl_primitive_op!($primitive, Sub, sub, SubAssign, sub_assign);
+        impl_primitive_op!($primitive, Mul, mul, MulAssign, mul_assign);
+        impl_primitive_op!($primitive, Div, div, DivAssign, div_assign);
+    )*};
+}
+
+/// Implements a binary operator of [Integer] and a primitive integer type
+macro_rules! impl_primitive_op {
+    ($primitive:ty, $op:ident, $method:ident, $op_assign:ident, $method_assign:ident) => {
+        impl $op<$primitive> for Integer {
+            type Output = Integer;
+            fn $method(self, rhs: $primitive) -> Integer {
+                Integer(self.0.$method(BigInt::from(rhs)))
+            }
+        }
+        impl $op<$primitive> for &Integer {
+            type Output = Incomplete;
+            fn $method(self, rhs: $primitive) -> Incomplete {
+                Incomplete(Integer((&self.0).$method(BigInt::from(rhs))))
+            }
+        }
+        impl $op_assign<$primitive> for Integer {
+            fn $method_assign(&mut self, rhs: $primitive) {
+                self.0.$method_assign(BigInt::from(rhs));
+            }
+        }
+        impl $op<Integer> for $primitive {
+            type Output = Integer;
+            fn $method(self, rhs: Integer) -> Integer {
+                Integer(BigInt::from(self).$method(rhs.0))
+            }
+        }
+        impl $op<&Integer> for $primitive {
+            type Output = Incomplete;
+            fn $method(self, rhs: &Integer) -> Incomplete {
+                Incomplete(Integer(BigInt::from(self).$method(&rhs.0)))
+            }
+        }
+    };
+}
+
+impl_primitive!(i32, u8, u32, i64, u64, u128, usize);
+
+/// Implements a shift by a `u32` number of bits
+macro_rules! impl_shift {
+    ($op:ident, $method:ident, $op_assign:ident, $method_assign:ident) => {
+        impl $op<u32> for Integer {
+            type Output = Integer;
+            fn $method(self, bits: u32) -> Integer {
+                Integer(self.0.$method(bits))
+            }
+        }
+        impl $op<u32> for &Integer {
+            type Output = Incomplete;
+            fn $method(self, bits: u32) -> Incomplete {
+                Incomplete(Integer((&self.0).$method(bits)))
+            }
+        }
+        impl $op_assign<u32> for Integer {
+            fn $method_assign(&mut self, bits: u32) {
+                self.0.$method_assign(bits);
+            }
+        }
+    };
+}
+
+impl_shift!(Shl, shl, ShlAssign, shl_assign);
+impl_shift!(Shr, shr, ShrAssign, shr_assign);
+
+/// Quotient of two [Integer]s, see `rug::Rational`
+///
+/// Only the conversion to floating point numbers is supported.
+pub struct Rational(BigInt, BigInt);
+
+impl<N, D> From<(N, D)> for Rational
+where
+    Integer: From<N> + From<D>,
+{
+    fn from((numerator, denominator): (N, D)) -> Rational {
+        let denominator = Integer::from(denominator).0;
+        assert!(!denominator.is_zero(), "division by zero");
+        Rational(Integer::from(numerator).0, denominator)
+    }
+}
+
+impl Rational {
+    pub fn to_f64(&self) -> f64 {
+        // the quotient is scaled to 64 significant bits to keep the precision of large values
+        let shift = self.0.bits() as i64 - self.1.bits() as i64 - 64;
+        let quotient = if shift > 0 {
+            &self.0 / (&self.1 << shift as u64)
+        } else {
+            (&self.0 << shift.unsigned_abs()) / &self.1
+        };
+        quotient.to_f64().unwrap_or(f64::NAN) * 2f64.powi(shift as i32)
+    }
+    pub fn to_f32(&self) -> f32 {
+        self.to_f64() as f32
+    }
+}
+
+/// Random number generator, see `rug::rand::RandState`
+pub struct RandState {
+    rng: StdRng,
+}
+
+impl RandState {
+    pub fn new() -> RandState {
+        RandState {
+            rng: StdRng::seed_from_u64(0),
+        }
+    }
+    /// Seeds the generator with the least significant 64 bits of `seed`
+    pub fn seed(&mut self, seed: &Integer) {
+        let limb = seed.0.magnitude().iter_u64_digits().next().unwrap_or(0);
+        self.rng = StdRng::seed_from_u64(limb);
+    }
+    /// Returns a random number with `bits` bits, `bits` must not exceed 32
+    pub fn bits(&mut self, bits: u32) -> u32 {
+        self.rng
+            .gen::<u32>()
+            .checked_shr(u32::BITS - bits)
+            .unwrap_or(0)
+    }
+}
+
+impl Default for RandState {
+    fn default() -> Self {
+        RandState::new()
+    }
+}
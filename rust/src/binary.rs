@@ -0,0 +1,109 @@
+use crate::ad_ess::AdEss;
+use crate::bigint::Integer;
+use crate::error::AdEssError;
+use crate::utils::{bits_to_integer, integer_to_bits};
+
+/// Distribution matcher with a binary output alphabet
+///
+/// The two output symbols `0` and `1` take the place of the amplitudes `1` and `3` of [AdEss],
+/// i.e., `weights[0]` is the weight of the output bit `0` and `weights[1]` the weight of the
+/// output bit `1`. Shaping the bits, e.g., to few ones, is useful for sparse signaling or to
+/// reduce the wear of flash memory cells.
+pub struct BinaryDm {
+    adess: AdEss,
+}
+
+impl BinaryDm {
+    /// Returns a new [BinaryDm] holding the bit sequences with a sum weight up to `threshold`
+    ///
+    /// Unlike [AdEss::new()] the smallest weight does not need to be 0, see [AdEss::try_new()].
+    pub fn new(
+        threshold: usize,
+        n_max: usize,
+        weights: [usize; 2],
+    ) -> Result<BinaryDm, AdEssError> {
+        Ok(BinaryDm {
+            adess: AdEss::try_new(threshold, n_max, &weights)?,
+        })
+    }
+    /// Returns a new [BinaryDm] which encodes at least `num_bits` bits with ones of probability
+    /// `p_one`
+    ///
+    /// See [AdEss::new_for_distribution_num_bits()] for `res_factor`.
+    pub fn new_for_ones_probability(
+        p_one: f32,
+        num_bits: usize,
+        n_max: usize,
+        res_factor: f32,
+    ) -> Result<BinaryDm, AdEssError> {
+        if !(p_one > 0.0 && p_one < 1.0) {
+            return Err(AdEssError::InvalidParameter(
+                "`p_one` must be between 0 and 1 (exclusive)",
+            ));
+        }
+        let (adess, _) = AdEss::new_for_distribution_num_bits(
+            num_bits,
+            n_max,
+            &[1.0 - p_one, p_one],
+            res_factor,
+        )?;
+        Ok(BinaryDm { adess })
+    }
+    /// Returns the internal [AdEss] instance (output bits `b` map to amplitudes `2 * b + 1`)
+    pub fn adess(&self) -> &AdEss {
+        &self.adess
+    }
+    /// Returns the weights of the output bits `0` and `1`
+    pub fn get_weights(&self) -> Vec<usize> {
+        self.adess.get_weights()
+    }
+    /// Returns the number of output bits per block
+    pub fn n_max(&self) -> usize {
+        self.adess.trellis.n_max
+    }
+    /// Returns the number of data bits per block
+    pub fn num_bits(&self) -> u32 {
+        self.adess.num_bits()
+    }
+    /// Returns the rate in data bits per output bit
+    pub fn rate(&self) -> f32 {
+        self.num_bits() as f32 / self.n_max() as f32
+    }
+    /// Returns the output bits for a given `index` (encode)
+    pub fn bits_for_index(&self, index: &Integer) -> Vec<u8> {
+        self.adess
+            .sequence_for_index(index)
+            .iter()
+            .map(|a| ((a - 1) / 2) as u8)
+            .collect()
+    }
+    /// Returns the index for given `output_bits` (decode)
+    pub fn index_for_bits(&self, output_bits: &[u8]) -> Result<Integer, AdEssError> {
+        if output_bits.len() != self.n_max() || output_bits.iter().any(|&b| b > 1) {
+            return Err(AdEssError::InvalidSequence(
+                "`output_bits` must be `n_max` values of 0 or 1",
+            ));
+        }
+        let amplitudes: Vec<usize> = output_bits.iter().map(|&b| 2 * b as usize + 1).collect();
+        Ok(self.adess.index_for_sequence(&amplitudes))
+    }
+    /// Returns the output bits for [BinaryDm::num_bits()] data bits (most significant bit first)
+    pub fn encode(&self, data_bits: &[u8]) -> Result<Vec<u8>, AdEssError> {
+        if data_bits.len() != self.num_bits() as usize {
+            return Err(AdEssError::InvalidParameter(
+                "`data_bits` must hold `num_bits` bits",
+            ));
+        }
+        Ok(self.bits_for_index(&bits_to_integer(data_bits)))
+    }
+    /// Returns the data bits (most significant bit first) for the `output_bits` of a block
+    pub fn decode(&self, output_bits: &[u8]) -> Result<Vec<u8>, AdEssError> {
+        let index = self.index_for_bits(output_bits)?;
+        Ok(integer_to_bits(&index, self.num_bits() as usize))
+    }
+    /// Returns the probability of a one in the output bits if all data bits are used
+    /// equiprobably
+    pub fn ones_probability(&self) -> f32 {
+        self.adess.amplitude_distribution()[1]
+    }
+}
@@ -0,0 +1,466 @@
+use crate::bigint::{Complete, Integer, Order, Rational};
+use crate::error::AdEssError;
+use crate::matcher::DistributionMatcher;
+use crate::trellis::Trellis;
+
+/// Trellis node value with a bounded number of significant bits
+///
+/// The represented value is `mantissa * 2^exponent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BoundedValue {
+    pub mantissa: u64,
+    pub exponent: u32,
+}
+
+impl BoundedValue {
+    /// Returns the represented value as a [Integer](crate::bigint::Integer)
+    pub fn to_integer(&self) -> Integer {
+        Integer::from(self.mantissa) << self.exponent
+    }
+
+    /// Rounds `value * 2^exponent` down to a value with at most `precision` significant bits
+    ///
+    /// Rounding down guarantees that a node never holds more sequences than its
+    /// successors can provide, this keeps the enumeration invertible.
+    fn round_down(value: u128, exponent: u32, precision: u32) -> BoundedValue {
+        let significant_bits = 128 - value.leading_zeros();
+        let shift = significant_bits.saturating_sub(precision);
+        BoundedValue {
+            mantissa: (value >> shift) as u64,
+            exponent: exponent + shift,
+        }
+    }
+
+    /// Returns the sum of `values` rounded down to `precision` significant bits
+    ///
+    /// All summands are aligned to a common exponent first. Bits below this exponent
+    /// are discarded which can only decrease the result.
+    fn sum_round_down(values: &[BoundedValue], precision: u32) -> BoundedValue {
+        let max_exponent = match values.iter().map(|v| v.exponent).max() {
+            Some(exponent) => exponent,
+            None => return BoundedValue::default(),
+        };
+        // leave enough headroom in the u128 accumulator for all summands and carries
+        let headroom = 128 - precision - (usize::BITS - values.len().leading_zeros());
+        let min_exponent = values.iter().map(|v| v.exponent).min().unwrap_or(0);
+        let common_exponent = min_exponent.max(max_exponent.saturating_sub(headroom));
+
+        let aligned_sum: u128 = values
+            .iter()
+            .map(|v| {
+                if v.exponent >= common_exponent {
+                    (v.mantissa as u128) << (v.exponent - common_exponent)
+                } else {
+                    v.mantissa
+                        .checked_shr(common_exponent - v.exponent)
+                        .unwrap_or(0) as u128
+                }
+            })
+            .sum();
+
+        BoundedValue::round_down(aligned_sum, common_exponent, precision)
+    }
+}
+
+/// Index as `u64` limbs, the least significant limb first
+///
+/// A [BoundedValue] spans at most two limbs, so it is compared with, added to and subtracted
+/// from the index with fixed width arithmetic on these limbs and the carries above them. No
+/// [Integer] is allocated per trellis node.
+struct IndexLimbs(Vec<u64>);
+
+impl IndexLimbs {
+    fn zero(num_limbs: usize) -> IndexLimbs {
+        IndexLimbs(vec![0; num_limbs])
+    }
+    /// Returns `None` if `index` is negative or does not fit into `num_limbs` limbs
+    fn from_integer(index: &Integer, num_limbs: usize) -> Option<IndexLimbs> {
+        if *index < 0 {
+            return None;
+        }
+        let mut limbs = index.to_digits::<u64>(Order::Lsf);
+        if limbs.len() > num_limbs {
+            return None;
+        }
+        limbs.resize(num_limbs, 0);
+        Some(IndexLimbs(limbs))
+    }
+    fn to_integer(&self) -> Integer {
+        Integer::from_digits(&self.0, Order::Lsf)
+    }
+    /// Returns the position of the lower limb of `value` and its two limbs
+    fn split(value: BoundedValue) -> (usize, u128) {
+        let limb_idx = (value.exponent / u64::BITS) as usize;
+        (
+            limb_idx,
+            (value.mantissa as u128) << (value.exponent % u64::BITS),
+        )
+    }
+    /// Returns the limbs `limb_idx` and `limb_idx + 1`
+    fn window(&self, limb_idx: usize) -> u128 {
+        let high = self.0.get(limb_idx + 1).copied().unwrap_or(0);
+        ((high as u128) << u64::BITS) | self.0[limb_idx] as u128
+    }
+    fn set_window(&mut self, limb_idx: usize, window: u128) {
+        self.0[limb_idx] = window as u64;
+        if let Some(high) = self.0.get_mut(limb_idx + 1) {
+            *high = (window >> u64::BITS) as u64;
+        }
+    }
+    fn is_below(&self, value: BoundedValue) -> bool {
+        let (limb_idx, value) = IndexLimbs::split(value);
+        self.0.iter().skip(limb_idx + 2).all(|&limb| limb == 0) && self.window(limb_idx) < value
+    }
+    fn add(&mut self, value: BoundedValue) {
+        let (limb_idx, value) = IndexLimbs::split(value);
+        let (sum, mut carry) = self.window(limb_idx).overflowing_add(value);
+        self.set_window(limb_idx, sum);
+        for limb in self.0.iter_mut().skip(limb_idx + 2) {
+            if !carry {
+                break;
+            }
+            (*limb, carry) = limb.overflowing_add(1);
+        }
+    }
+    /// Subtracts `value`, which must not exceed the index
+    fn sub(&mut self, value: BoundedValue) {
+        let (limb_idx, value) = IndexLimbs::split(value);
+        let (difference, mut borrow) = self.window(limb_idx).overflowing_sub(value);
+        self.set_window(limb_idx, difference);
+        for limb in self.0.iter_mut().skip(limb_idx + 2) {
+            if !borrow {
+                break;
+            }
+            (*limb, borrow) = limb.overflowing_sub(1);
+        }
+    }
+}
+
+/// Bounded-precision AD-ESS
+///
+/// Works like [AdEss](crate::ad_ess::AdEss) but every trellis node value is rounded down to
+/// `precision` significant bits, see
+/// Y. C. Gültekin, F. M. J. Willems, W. J. van Houtum and S. Şerbetli, "Approximate enumerative
+/// sphere shaping," in Proc. IEEE ISIT, 2018, doi: 10.1109/ISIT.2018.8437345.
+///
+/// Trellis nodes are stored as [BoundedValue]s, so no arbitrary-precision arithmetic is needed to
+/// build the trellis and the storage per node is constant. The price is a slightly smaller number
+/// of sequences compared to the exact trellis. Indexes are encoded and decoded with fixed width
+/// arithmetic on their `u64` limbs, they are only converted from and to [Integer] at the
+/// interface.
+pub struct BoundedAdEss {
+    pub precision: u32,
+    structure: Trellis,
+    data: Vec<Vec<BoundedValue>>,
+    /// Number of limbs of an index, including room for the carries of invalid sequences
+    num_limbs: usize,
+}
+
+impl BoundedAdEss {
+    /// Returns a new [BoundedAdEss] instance given weights
+    ///
+    /// The trellis is calculated with `n_max` stages using the weights `weights` and holds
+    /// sequences with a sum weight up to `threshold`. Node values keep at most `precision`
+    /// significant bits (1 to 64).
+    ///
+    /// An error is returned if `precision` is out of range, `weights` is empty or the smallest
+    /// weight is not 0.
+    pub fn new(
+        threshold: usize,
+        n_max: usize,
+        weights: &[usize],
+        precision: u32,
+    ) -> Result<BoundedAdEss, AdEssError> {
+        if !(1..=64).contains(&precision) {
+            return Err(AdEssError::InvalidParameter(
+                "`precision` must be between 1 and 64 bits",
+            ));
+        }
+        if weights.iter().min() != Some(&0) {
+            return Err(AdEssError::InvalidWeights(
+                "`weights` must not be empty and the smallest weight must be 0",
+            ));
+        }
+        let structure = Trellis::new_structure_only(threshold, n_max, weights);
+        let num_weight_levels = structure.get_weight_levels().len();
+        let data = vec![vec![BoundedValue::default(); num_weight_levels]; n_max + 1];
+
+        let mut instance = BoundedAdEss {
+            precision,
+            structure,
+            data,
+            num_limbs: 0,
+        };
+        instance.calc_forward_trellis();
+        // the sum of the at most `n_max * num_weights` values skipped while decoding fits into
+        // one more limb than the largest value
+        let max_exponent = instance
+            .data
+            .iter()
+            .flatten()
+            .map(|value| value.exponent)
+            .max()
+            .unwrap_or(0);
+        instance.num_limbs = (max_exponent / u64::BITS) as usize + 3;
+        Ok(instance)
+    }
+
+    /// Fill the trellis with bounded-precision values
+    fn calc_forward_trellis(&mut self) {
+        let n_max = self.structure.n_max;
+        let weight_levels = self.structure.get_weight_levels();
+        for (wl_idx, _) in weight_levels.iter().enumerate() {
+            self.data[n_max][wl_idx] = BoundedValue {
+                mantissa: 1,
+                exponent: 0,
+            };
+        }
+        for n in (0..n_max).rev() {
+            for (wl_idx, &wl) in weight_levels.iter().enumerate() {
+                let successor_values: Vec<BoundedValue> = self
+                    .structure
+                    .get_successors(wl)
+                    .into_iter()
+                    .map(|(_, next_wl)| self.get(n + 1, next_wl))
+                    .collect();
+                self.data[n][wl_idx] =
+                    BoundedValue::sum_round_down(&successor_values, self.precision);
+            }
+        }
+    }
+    /// Returns the amplitude value for a given weight index
+    fn weight_idx_to_amplitude(weight_index: usize) -> usize {
+        weight_index * 2 + 1
+    }
+}
+
+impl BoundedAdEss {
+    /// Get function for trellis values
+    pub fn get(&self, stage: usize, weight_level: usize) -> BoundedValue {
+        self.data[stage][self.structure.get_weight_level_index(weight_level)]
+    }
+    /// Returns the number of sequences that can be encoded / decoded
+    pub fn num_sequences(&self) -> Integer {
+        self.get(0, 0).to_integer()
+    }
+    /// Returns the number of bits that can be encoded / decoded
+    pub fn num_bits(&self) -> u32 {
+        self.num_sequences().significant_bits().saturating_sub(1)
+    }
+    /// Returns the weights used by the internal trellis
+    pub fn get_weights(&self) -> Vec<usize> {
+        self.structure.get_weights()
+    }
+    pub fn get_storage_dimensions(&self) -> (usize, usize) {
+        (self.data.len(), self.data[0].len())
+    }
+    /// Returns the number of bytes used to store the trellis node values
+    pub fn storage_bytes(&self) -> usize {
+        let (num_stages, num_weight_levels) = self.get_storage_dimensions();
+        num_stages * num_weight_levels * std::mem::size_of::<BoundedValue>()
+    }
+    /// Returns the amplitude sequence for a given `index` (encode)
+    ///
+    /// Panics if `index` is out of range, see [BoundedAdEss::try_sequence_for_index()].
+    pub fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        self.try_sequence_for_index(index)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+    /// Returns the amplitude sequence for a given `index` (encode) or an error if `index` is
+    /// negative or not smaller than [BoundedAdEss::num_sequences()]
+    pub fn try_sequence_for_index(&self, index: &Integer) -> Result<Vec<usize>, AdEssError> {
+        let mut remaining_index = IndexLimbs::from_integer(index, self.num_limbs)
+            .filter(|index| index.is_below(self.get(0, 0)))
+            .ok_or(AdEssError::IndexOutOfRange)?;
+
+        let mut amplitude_sequence = Vec::with_capacity(self.structure.n_max);
+        let mut current_wl = 0;
+        for n in 0..self.structure.n_max {
+            // the node value does not exceed the sum of its successors, a successor is always found
+            for &(w_idx, next_wl) in self.structure.successors_slice(current_wl) {
+                let next_wl_value = self.get(n + 1, next_wl);
+                if remaining_index.is_below(next_wl_value) {
+                    amplitude_sequence.push(BoundedAdEss::weight_idx_to_amplitude(w_idx));
+                    current_wl = next_wl;
+                    break;
+                }
+                remaining_index.sub(next_wl_value);
+            }
+        }
+        Ok(amplitude_sequence)
+    }
+    /// Returns the index for a given `amplitude_sequence` (decode)
+    ///
+    /// Panics if the sequence is not used by the trellis, see
+    /// [BoundedAdEss::try_index_for_sequence()].
+    pub fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        self.try_index_for_sequence(amplitude_sequence)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+    /// Returns the index for a given `amplitude_sequence` (decode) or an error if the sequence
+    /// is not used by the trellis
+    ///
+    /// Rounding down the node values drops the last sequences of a node, i.e., a sequence
+    /// below the threshold is only used if its index within every node of its path is smaller
+    /// than the node value. The skipped successor values are therefore added from the last
+    /// stage to the first, the partial sums are the indexes within the nodes.
+    pub fn try_index_for_sequence(
+        &self,
+        amplitude_sequence: &[usize],
+    ) -> Result<Integer, AdEssError> {
+        if amplitude_sequence.len() != self.structure.n_max {
+            return Err(AdEssError::InvalidSequence(
+                "The amplitude sequence must have `n_max` amplitudes",
+            ));
+        }
+        // weight level and position of the chosen successor for each stage
+        let mut path = Vec::with_capacity(self.structure.n_max);
+        let mut current_wl = 0;
+        for &amplitude in amplitude_sequence {
+            if amplitude.is_multiple_of(2) {
+                return Err(AdEssError::InvalidSequence("Amplitudes must be odd"));
+            }
+            let weight_idx = (amplitude - 1) / 2;
+            let successors = self.structure.successors_slice(current_wl);
+            let position = successors
+                .iter()
+                .position(|&(w_idx, _)| w_idx == weight_idx)
+                .ok_or(AdEssError::InvalidSequence(
+                    "The amplitude sequence exceeds the threshold",
+                ))?;
+            path.push((current_wl, position));
+            current_wl = successors[position].1;
+        }
+
+        let mut index = IndexLimbs::zero(self.num_limbs);
+        for (n, &(wl, position)) in path.iter().enumerate().rev() {
+            for &(_, next_wl) in &self.structure.successors_slice(wl)[..position] {
+                index.add(self.get(n + 1, next_wl));
+            }
+            if !index.is_below(self.get(n, wl)) {
+                return Err(AdEssError::InvalidSequence(
+                    "The amplitude sequence is dropped by the rounded node values",
+                ));
+            }
+        }
+        Ok(index.to_integer())
+    }
+    /// Returns the number of occurences of each amplitude in the sequences with the indexes
+    /// `0..num_indexes`
+    ///
+    /// The sequences of a node are not symmetric in their positions as for [AdEss], so the
+    /// occurences in all sequences of every node are counted first, from the last stage to the
+    /// first.
+    ///
+    /// [AdEss]: crate::ad_ess::AdEss
+    pub fn amplitude_counts_below(&self, num_indexes: &Integer) -> Vec<Integer> {
+        let n_max = self.structure.n_max;
+        let weight_levels = self.structure.get_weight_levels();
+        // node_counts[n][wl_idx]: occurences in all sequences of the node
+        let mut node_counts: Vec<Vec<Vec<Integer>>> = vec![vec![]; n_max + 1];
+        node_counts[n_max] = vec![vec![Integer::new(); self.num_weights()]; weight_levels.len()];
+        for n in (0..n_max).rev() {
+            node_counts[n] = weight_levels
+                .iter()
+                .map(|&wl| {
+                    let num_sequences = self.get(n, wl).to_integer();
+                    self.amplitude_counts_in_node(n, wl, &num_sequences, &node_counts)
+                })
+                .collect();
+        }
+        self.amplitude_counts_in_node(0, 0, num_indexes, &node_counts)
+    }
+    /// Returns the number of occurences of each amplitude in the sequences with the indexes
+    /// `0..num_indexes` within the node at `stage` and `wl`
+    ///
+    /// `node_counts` must hold the occurences in all sequences of the nodes after `stage`.
+    fn amplitude_counts_in_node(
+        &self,
+        stage: usize,
+        wl: usize,
+        num_indexes: &Integer,
+        node_counts: &[Vec<Vec<Integer>>],
+    ) -> Vec<Integer> {
+        let mut counts = vec![Integer::new(); self.num_weights()];
+        // amplitudes of the path from the node to the current node
+        let mut prefix_counts = vec![0usize; self.num_weights()];
+        let mut remaining_index = num_indexes.clone();
+        let mut current_wl = wl;
+        for n in stage..self.structure.n_max {
+            if remaining_index == 0 {
+                break;
+            }
+            let mut num_skipped = Integer::new();
+            let mut next = None;
+            for &(w_idx, next_wl) in self.structure.successors_slice(current_wl) {
+                let next_wl_value = self.get(n + 1, next_wl).to_integer();
+                if remaining_index < next_wl_value {
+                    next = Some((w_idx, next_wl));
+                    break;
+                }
+                remaining_index -= &next_wl_value;
+                counts[w_idx] += &next_wl_value;
+                let next_wl_idx = self.structure.get_weight_level_index(next_wl);
+                for (count, next_count) in counts.iter_mut().zip(&node_counts[n + 1][next_wl_idx]) {
+                    *count += next_count;
+                }
+                num_skipped += next_wl_value;
+            }
+            // the path is shared by all sequences skipped at this stage
+            for (count, &prefix_count) in counts.iter_mut().zip(&prefix_counts) {
+                *count += Integer::from(&num_skipped * prefix_count);
+            }
+            match next {
+                Some((w_idx, next_wl)) => {
+                    prefix_counts[w_idx] += 1;
+                    current_wl = next_wl;
+                }
+                None => break,
+            }
+        }
+        counts
+    }
+    /// Returns the amplitude distribution if indexes with [BoundedAdEss::num_bits()] bits are
+    /// used equiprobably
+    pub fn amplitude_distribution(&self) -> Vec<f32> {
+        let n_max = self.structure.n_max;
+        if n_max == 0 {
+            // sequences without amplitudes
+            return vec![0f32; self.num_weights()];
+        }
+        let num_sequences_used = Integer::u_pow_u(2, self.num_bits()).complete();
+        let num_amplitudes = Integer::from(&num_sequences_used * n_max);
+        self.amplitude_counts_below(&num_sequences_used)
+            .into_iter()
+            .map(|count| Rational::from((count, &num_amplitudes)).to_f32())
+            .collect()
+    }
+    fn num_weights(&self) -> usize {
+        self.structure.get_weights().len()
+    }
+}
+
+impl DistributionMatcher for BoundedAdEss {
+    fn n_max(&self) -> usize {
+        self.structure.n_max
+    }
+    fn num_bits(&self) -> u32 {
+        BoundedAdEss::num_bits(self)
+    }
+    fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        BoundedAdEss::sequence_for_index(self, index)
+    }
+    fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        BoundedAdEss::index_for_sequence(self, amplitude_sequence)
+    }
+    fn try_index_for_sequence(&self, amplitude_sequence: &[usize]) -> Result<Integer, AdEssError> {
+        BoundedAdEss::try_index_for_sequence(self, amplitude_sequence)
+    }
+    fn amplitude_distribution(&self) -> Vec<f32> {
+        BoundedAdEss::amplitude_distribution(self)
+    }
+    fn amplitude_counts_below(&self, num_indexes: &Integer) -> Option<Vec<Integer>> {
+        (*num_indexes >= 0 && *num_indexes <= self.num_sequences())
+            .then(|| BoundedAdEss::amplitude_counts_below(self, num_indexes))
+    }
+}
@@ -0,0 +1,37 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::error::AdEssError;
+
+/// Token to abort long-running operations from another thread
+///
+/// Clones share the same state, cancelling one clone cancels all of them. Cancellable
+/// operations check the token periodically and return an error once it is cancelled, the
+/// memory of partial results is released.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Returns a new token which is not cancelled
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+    /// Requests the cancellation of all operations using this token
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+    /// Returns true if the cancellation was requested
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+    /// Returns an error if the cancellation was requested
+    pub fn check(&self) -> Result<(), AdEssError> {
+        if self.is_cancelled() {
+            Err(AdEssError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
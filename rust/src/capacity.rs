@@ -0,0 +1,131 @@
+use crate::error::AdEssError;
+use crate::utils;
+
+/// Returns the mutual information `I(X;Y)` in bit/symbol of PAS symbols on a real AWGN channel
+///
+/// A symbol `x = s * levels[w_idx]` consists of a uniformly distributed sign `s` and an amplitude
+/// with probability `amplitude_distribution[w_idx]`, the noise has the variance `noise_variance`.
+/// `I(X;Y) = H(X) - H(X|Y)` is calculated by numerical integration as in
+/// [bicm_gmi()](crate::labeling::bicm_gmi), i.e., it is the rate achievable with a symbol-metric
+/// decoder.
+pub fn mutual_information(
+    amplitude_distribution: &[f32],
+    levels: &[f32],
+    noise_variance: f32,
+) -> f32 {
+    assert_eq!(
+        levels.len(),
+        amplitude_distribution.len(),
+        "One level per amplitude required!"
+    );
+    let symbols: Vec<(f64, f64)> = [1.0, -1.0]
+        .iter()
+        .flat_map(|&sign| {
+            levels
+                .iter()
+                .zip(amplitude_distribution)
+                .map(move |(&level, &p)| (sign * level as f64, p as f64 / 2.0))
+        })
+        .filter(|(_, p)| *p > 0.0)
+        .collect();
+
+    let noise_variance = noise_variance as f64;
+    let sigma = noise_variance.sqrt();
+    let y_max = levels.iter().fold(0f64, |a, &b| a.max(b as f64)) + 10.0 * sigma;
+    let step = sigma / 16.0;
+    let num_steps = (2.0 * y_max / step).ceil() as usize;
+    let normalization = step / (2.0 * std::f64::consts::PI * noise_variance).sqrt();
+
+    let mut conditional_entropy = 0f64;
+    for k in 0..=num_steps {
+        let y = -y_max + k as f64 * step;
+        let likelihoods: Vec<f64> = symbols
+            .iter()
+            .map(|(x, p)| p * (-(y - x).powi(2) / (2.0 * noise_variance)).exp())
+            .collect();
+        let total: f64 = likelihoods.iter().sum();
+        // `likelihood * normalization` equals `p(x) * p(y|x) * dy`
+        for &likelihood in likelihoods.iter().filter(|&&likelihood| likelihood > 0.0) {
+            conditional_entropy -= likelihood * normalization * (likelihood / total).log2();
+        }
+    }
+
+    let symbol_entropy = utils::entropy(amplitude_distribution) as f64 + 1.0;
+    (symbol_entropy - conditional_entropy) as f32
+}
+
+/// Returns the Maxwell-Boltzmann amplitude distribution maximizing the mutual information at the
+/// signal-to-noise ratio `snr_db`
+///
+/// The amplitudes `1, 3, 5, ...` are transmitted with PAS, see [mutual_information()], and the
+/// noise variance follows from `snr_db` and the average energy of the distribution. The optimum
+/// within the Maxwell-Boltzmann family is close to the capacity-achieving distribution of the
+/// constellation, see F. R. Kschischang and S. Pasupathy, "Optimal nonuniform signaling for
+/// Gaussian channels," IEEE Trans. Inf. Theory, vol. 39, no. 3, pp. 913-929, 1993.
+///
+/// The average energy of the distribution is searched on a grid between the smallest and the
+/// uniform average energy and refined by golden section search. The result can be passed to,
+/// e.g., [AdEss::new_for_distribution_num_bits()](crate::ad_ess::AdEss::new_for_distribution_num_bits).
+pub fn optimal_distribution_for_snr(
+    num_amplitudes: usize,
+    snr_db: f32,
+) -> Result<Vec<f32>, AdEssError> {
+    if num_amplitudes == 0 {
+        return Err(AdEssError::InvalidParameter(
+            "At least one amplitude required",
+        ));
+    }
+    if !snr_db.is_finite() {
+        return Err(AdEssError::InvalidParameter("The SNR must be finite"));
+    }
+    if num_amplitudes == 1 {
+        return Ok(vec![1.0]);
+    }
+    let levels = utils::uniform_levels(num_amplitudes);
+    let uniform_energy = utils::average_energy(&utils::mb(num_amplitudes, 0.0), &levels);
+    let snr = 10f32.powf(snr_db / 10.0);
+
+    // distribution and mutual information for the energy fraction `t` in `(0, 1]`
+    let candidate = |t: f32| -> Result<(Vec<f32>, f32), AdEssError> {
+        let energy = 1.0 + (uniform_energy - 1.0) * t;
+        let distribution = utils::maxwell_boltzmann_for_energy(&levels, energy)
+            .map_err(AdEssError::InvalidParameter)?;
+        let average_energy = utils::average_energy(&distribution, &levels);
+        let mi = mutual_information(&distribution, &levels, average_energy / snr);
+        Ok((distribution, mi))
+    };
+
+    const NUM_GRID_POINTS: usize = 32;
+    let mut best_grid_point = NUM_GRID_POINTS;
+    let mut best = candidate(1.0)?;
+    for grid_point in 1..NUM_GRID_POINTS {
+        let current = candidate(grid_point as f32 / NUM_GRID_POINTS as f32)?;
+        if current.1 > best.1 {
+            best_grid_point = grid_point;
+            best = current;
+        }
+    }
+
+    let inv_phi = (5f32.sqrt() - 1.0) / 2.0;
+    let mut low = (best_grid_point - 1).max(1) as f32 / NUM_GRID_POINTS as f32;
+    let mut high = (best_grid_point + 1).min(NUM_GRID_POINTS) as f32 / NUM_GRID_POINTS as f32;
+    let mut left = candidate(high - inv_phi * (high - low))?;
+    let mut right = candidate(low + inv_phi * (high - low))?;
+    for _ in 0..24 {
+        if left.1 > right.1 {
+            high = low + inv_phi * (high - low);
+            right = left;
+            left = candidate(high - inv_phi * (high - low))?;
+        } else {
+            low = high - inv_phi * (high - low);
+            left = right;
+            right = candidate(low + inv_phi * (high - low))?;
+        }
+    }
+    for current in [left, right] {
+        if current.1 > best.1 {
+            best = current;
+        }
+    }
+    Ok(best.0)
+}
@@ -0,0 +1,249 @@
+use crate::bigint::Integer;
+use crate::error::AdEssError;
+use crate::matcher::DistributionMatcher;
+
+/// Constant composition distribution matcher (CCDM)
+///
+/// All output sequences have the same composition, i.e., the amplitude `2 * w_idx + 1` occurs
+/// exactly `composition[w_idx]` times. The index is mapped with arithmetic coding, see
+/// P. Schulte and G. Böcherer, "Constant composition distribution matching," IEEE Trans. Inf.
+/// Theory, vol. 62, no. 1, pp. 430-434, 2016, doi: 10.1109/TIT.2015.2499181.
+///
+/// The arithmetic code models the amplitudes with the probabilities of the remaining composition,
+/// so each of the `M` sequences of the composition has the probability `1/M` and the interval
+/// `[rank/M, (rank+1)/M)` with its lexicographic rank. With infinite precision, the index `i` is
+/// mapped to the sequence whose interval contains `i/2^num_bits`, i.e., the sequence with rank
+/// `floor(i * M / 2^num_bits)`. The intervals are computed exactly with [Integer]s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CCDM {
+    composition: Vec<usize>,
+    n_max: usize,
+    num_bits: u32,
+    num_sequences: Integer,
+}
+
+impl CCDM {
+    /// Returns a new [CCDM] instance for the given `composition`
+    ///
+    /// `num_bits` must not exceed `floor(log2(M))`, where `M` is the number of sequences with
+    /// the given composition, see [CCDM::max_num_bits()].
+    pub fn new(composition: &[usize], num_bits: u32) -> Result<CCDM, AdEssError> {
+        let n_max: usize = composition.iter().sum();
+        if n_max == 0 {
+            return Err(AdEssError::InvalidDistribution(
+                "`composition` must contain at least one amplitude",
+            ));
+        }
+        let num_sequences = CCDM::num_sequences_for_composition(composition);
+        if num_bits >= num_sequences.significant_bits() {
+            return Err(AdEssError::InvalidParameter(
+                "`num_bits` exceeds the number of sequences with the composition",
+            ));
+        }
+        Ok(CCDM {
+            composition: composition.to_vec(),
+            n_max,
+            num_bits,
+            num_sequences,
+        })
+    }
+
+    /// Returns a new [CCDM] instance with `n_max` amplitudes approximating `distribution`
+    ///
+    /// The composition is `n_max * distribution` rounded with the largest remainder method.
+    pub fn for_distribution(
+        distribution: &[f32],
+        n_max: usize,
+        num_bits: u32,
+    ) -> Result<CCDM, AdEssError> {
+        CCDM::new(
+            &CCDM::composition_for_distribution(distribution, n_max)?,
+            num_bits,
+        )
+    }
+
+    /// Returns the composition of `n_max` amplitudes approximating `distribution`, see
+    /// [CCDM::for_distribution()]
+    pub fn composition_for_distribution(
+        distribution: &[f32],
+        n_max: usize,
+    ) -> Result<Vec<usize>, AdEssError> {
+        if distribution.is_empty() || distribution.iter().any(|&p| !p.is_finite() || p < 0.0) {
+            return Err(AdEssError::InvalidDistribution(
+                "`distribution` must contain non-negative probabilities",
+            ));
+        }
+        let sum: f64 = distribution.iter().map(|&p| p as f64).sum();
+        if sum <= 0.0 {
+            return Err(AdEssError::InvalidDistribution(
+                "`distribution` must contain non-negative probabilities",
+            ));
+        }
+        let targets: Vec<f64> = distribution
+            .iter()
+            .map(|&p| p as f64 / sum * n_max as f64)
+            .collect();
+        let mut composition: Vec<usize> = targets.iter().map(|t| t.floor() as usize).collect();
+        let mut by_remainder: Vec<usize> = (0..distribution.len()).collect();
+        by_remainder.sort_by(|&a, &b| {
+            let remainder = |w_idx: usize| targets[w_idx] - composition[w_idx] as f64;
+            remainder(b).total_cmp(&remainder(a))
+        });
+        let missing = n_max - composition.iter().sum::<usize>();
+        for &w_idx in by_remainder.iter().cycle().take(missing) {
+            composition[w_idx] += 1;
+        }
+        Ok(composition)
+    }
+
+    /// Returns the multinomial coefficient `n! / prod(composition[w_idx]!)`
+    fn num_sequences_for_composition(composition: &[usize]) -> Integer {
+        let mut num_sequences = Integer::from(1);
+        let mut n = 0;
+        for &count in composition {
+            // binomial(n + count, count) built up factor by factor, each division is exact
+            for k in 1..=count {
+                n += 1;
+                num_sequences *= n;
+                num_sequences /= k;
+            }
+        }
+        num_sequences
+    }
+
+    /// Returns the amplitude value for a given weight index
+    fn weight_idx_to_amplitude(weight_index: usize) -> usize {
+        weight_index * 2 + 1
+    }
+}
+
+impl CCDM {
+    /// Returns the number of amplitudes per sequence
+    pub fn n_max(&self) -> usize {
+        self.n_max
+    }
+    /// Returns the number of bits that can be encoded / decoded
+    pub fn num_bits(&self) -> u32 {
+        self.num_bits
+    }
+    /// Returns the largest possible `num_bits` for the composition
+    pub fn max_num_bits(&self) -> u32 {
+        self.num_sequences.significant_bits() - 1
+    }
+    /// Returns the composition of all sequences
+    pub fn get_composition(&self) -> Vec<usize> {
+        self.composition.clone()
+    }
+    /// Returns the number of sequences with the composition
+    pub fn num_sequences(&self) -> Integer {
+        self.num_sequences.clone()
+    }
+    /// Returns the number of bits needed to store the composition
+    pub fn storage_bits(&self) -> usize {
+        let bits_per_count = (usize::BITS - self.n_max.leading_zeros()) as usize;
+        self.composition.len() * bits_per_count
+    }
+    /// Returns the amplitude sequence for a given `index` (encode)
+    pub fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        assert!(
+            *index >= 0 && index.significant_bits() <= self.num_bits,
+            "Index out of range!"
+        );
+        let mut rank = Integer::from(index * &self.num_sequences) >> self.num_bits;
+
+        let mut remaining = self.composition.clone();
+        let mut num_sequences = self.num_sequences.clone();
+        let mut amplitude_sequence = Vec::with_capacity(self.n_max);
+        for position in 0..self.n_max {
+            let num_remaining = self.n_max - position;
+            for (w_idx, count) in remaining.iter_mut().enumerate() {
+                if *count == 0 {
+                    continue;
+                }
+                let num_sequences_w_idx = Integer::from(&num_sequences * *count) / num_remaining;
+                if rank < num_sequences_w_idx {
+                    *count -= 1;
+                    num_sequences = num_sequences_w_idx;
+                    amplitude_sequence.push(CCDM::weight_idx_to_amplitude(w_idx));
+                    break;
+                }
+                rank -= num_sequences_w_idx;
+            }
+        }
+        amplitude_sequence
+    }
+    /// Returns the index for a given `amplitude_sequence` (decode)
+    ///
+    /// Panics if `amplitude_sequence` does not have the composition, see
+    /// [CCDM::try_index_for_sequence()].
+    pub fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        self.try_index_for_sequence(amplitude_sequence).unwrap()
+    }
+    /// Returns the index for a given `amplitude_sequence` (decode) or an error if the sequence
+    /// does not have the composition or is not used for encoding
+    pub fn try_index_for_sequence(
+        &self,
+        amplitude_sequence: &[usize],
+    ) -> Result<Integer, AdEssError> {
+        if amplitude_sequence.len() != self.n_max {
+            return Err(AdEssError::InvalidSequence(
+                "The amplitude sequence must have `n_max` amplitudes",
+            ));
+        }
+        let mut rank = Integer::new();
+        let mut remaining = self.composition.clone();
+        let mut num_sequences = self.num_sequences.clone();
+        for (position, &amplitude) in amplitude_sequence.iter().enumerate() {
+            let num_remaining = self.n_max - position;
+            let w_idx = amplitude / 2;
+            if amplitude.is_multiple_of(2) || w_idx >= remaining.len() || remaining[w_idx] == 0 {
+                return Err(AdEssError::InvalidSequence(
+                    "The amplitude sequence does not have the composition",
+                ));
+            }
+            for &count in &remaining[..w_idx] {
+                rank += Integer::from(&num_sequences * count) / num_remaining;
+            }
+            num_sequences = Integer::from(&num_sequences * remaining[w_idx]) / num_remaining;
+            remaining[w_idx] -= 1;
+        }
+
+        // smallest index whose interval start `index / 2^num_bits` lies in `[rank/M, (rank+1)/M)`
+        let index = ((Integer::from(&rank) << self.num_bits) + &self.num_sequences - 1u32)
+            / &self.num_sequences;
+        if Integer::from(&index * &self.num_sequences) >> self.num_bits != rank
+            || index.significant_bits() > self.num_bits
+        {
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        Ok(index)
+    }
+    /// Returns the amplitude distribution, i.e., the normalized composition
+    pub fn amplitude_distribution(&self) -> Vec<f32> {
+        self.composition
+            .iter()
+            .map(|&count| count as f32 / self.n_max as f32)
+            .collect()
+    }
+}
+
+impl DistributionMatcher for CCDM {
+    fn n_max(&self) -> usize {
+        self.n_max
+    }
+    fn num_bits(&self) -> u32 {
+        self.num_bits
+    }
+    fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        CCDM::sequence_for_index(self, index)
+    }
+    fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        CCDM::index_for_sequence(self, amplitude_sequence)
+    }
+    fn try_index_for_sequence(&self, amplitude_sequence: &[usize]) -> Result<Integer, AdEssError> {
+        CCDM::try_index_for_sequence(self, amplitude_sequence)
+    }
+    fn amplitude_distribution(&self) -> Vec<f32> {
+        CCDM::amplitude_distribution(self)
+    }
+}
@@ -0,0 +1,151 @@
+use crate::ad_ess::{AdEss, SequenceReport};
+use crate::bigint::Integer;
+use crate::error::AdEssError;
+use crate::streaming::StreamingMatcher;
+use crate::utils::{bits_to_integer, integer_to_bits};
+
+/// Joint shaping of a group of [AdEss] blocks
+///
+/// A single block only uses `2^num_bits` of its `num_sequences` indexes. The group index is
+/// instead split into digits in base `num_sequences`: each block encodes the index modulo
+/// `num_sequences` and carries the residual (the quotient) into the next block. Only the
+/// residual of the last block is truncated to a power of two, so the group encodes
+/// `floor(num_blocks * log2(num_sequences))` instead of `num_blocks * num_bits` bits.
+///
+/// This is the chunk of a [StreamingMatcher] whose residual digits take all index bits of a
+/// block (`residual_bits = num_bits`, `chunk_length = num_blocks`). Unlike the streaming matcher
+/// a group is encoded and decoded at once, so received sequences are validated and bitstreams of
+/// arbitrary length can be framed.
+pub struct ChainedAdEss {
+    matcher: StreamingMatcher,
+}
+
+impl ChainedAdEss {
+    /// Returns a [ChainedAdEss] shaping groups of `num_blocks` blocks with `adess`
+    pub fn new(adess: AdEss, num_blocks: usize) -> Result<ChainedAdEss, AdEssError> {
+        if num_blocks == 0 {
+            return Err(AdEssError::InvalidParameter(
+                "`num_blocks` must be at least 1",
+            ));
+        }
+        let residual_bits = adess.num_bits();
+        Ok(ChainedAdEss {
+            matcher: StreamingMatcher::new(adess, residual_bits, num_blocks)?,
+        })
+    }
+    /// Returns the [AdEss] of a single block
+    pub fn adess(&self) -> &AdEss {
+        self.matcher.adess()
+    }
+    /// Returns the number of blocks per group
+    pub fn num_blocks(&self) -> usize {
+        self.matcher.chunk_length()
+    }
+    /// Returns the number of bits encoded per group
+    pub fn num_bits(&self) -> u32 {
+        self.matcher.bits_per_chunk() as u32
+    }
+    /// Returns the additional bits per group compared to independently shaped blocks
+    pub fn num_bits_gain(&self) -> u32 {
+        self.num_bits() - self.num_blocks() as u32 * self.adess().num_bits()
+    }
+    /// Returns the amplitude sequences of all blocks for a given group `index` (encode)
+    pub fn sequences_for_index(&self, index: &Integer) -> Result<Vec<Vec<usize>>, AdEssError> {
+        if *index < 0 || index.significant_bits() > self.num_bits() {
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        // the block indexes are the residual digits in base `num_sequences`
+        Ok(self
+            .matcher
+            .residual_digits(index.clone())
+            .iter()
+            .map(|block_index| self.adess().sequence_for_index(block_index))
+            .collect())
+    }
+    /// Returns the group index for the amplitude `sequences` of all blocks (decode)
+    ///
+    /// An error is returned if the number of sequences differs from the number of blocks or a
+    /// sequence is not in the trellis, see [AdEss::check_sequence()].
+    pub fn index_for_sequences(&self, sequences: &[Vec<usize>]) -> Result<Integer, AdEssError> {
+        if sequences.len() != self.num_blocks() {
+            return Err(AdEssError::InvalidParameter(
+                "The number of sequences must equal `num_blocks`",
+            ));
+        }
+        let mut block_indexes = Vec::with_capacity(sequences.len());
+        for sequence in sequences {
+            block_indexes.push(match self.adess().check_sequence(sequence) {
+                SequenceReport::Used(block_index) | SequenceReport::Unused(block_index) => {
+                    block_index
+                }
+                SequenceReport::Invalid { reason, .. } => {
+                    return Err(AdEssError::InvalidSequence(reason))
+                }
+            });
+        }
+        let index = self.matcher.residual_from_digits(block_indexes.into_iter());
+        if index.significant_bits() > self.num_bits() {
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        Ok(index)
+    }
+    /// Returns the amplitude sequences of all blocks for `num_bits()` data bits
+    pub fn encode(&self, data_bits: &[u8]) -> Result<Vec<Vec<usize>>, AdEssError> {
+        if data_bits.len() != self.num_bits() as usize {
+            return Err(AdEssError::InvalidParameter(
+                "`data_bits` must have `num_bits()` bits",
+            ));
+        }
+        self.sequences_for_index(&bits_to_integer(data_bits))
+    }
+    /// Returns the data bits for the amplitude `sequences` of all blocks
+    pub fn decode(&self, sequences: &[Vec<usize>]) -> Result<Vec<u8>, AdEssError> {
+        let index = self.index_for_sequences(sequences)?;
+        Ok(integer_to_bits(&index, self.num_bits() as usize))
+    }
+    /// Returns the number of blocks needed to frame `num_data_bits` bits, see
+    /// [ChainedAdEss::encode_bitstream()]
+    pub fn num_blocks_for_bits(&self, num_data_bits: usize) -> usize {
+        num_data_bits.div_ceil(self.num_bits().max(1) as usize) * self.num_blocks()
+    }
+    /// Returns the amplitude sequences for a bitstream of arbitrary length (framing)
+    ///
+    /// The bitstream is split into groups of `num_bits()` bits, the last group is padded with
+    /// zeros. Each group is encoded into `num_blocks` sequences, see [ChainedAdEss::encode()], so
+    /// the fractional bits of the individual blocks are recovered except for the padding.
+    pub fn encode_bitstream(&self, data_bits: &[u8]) -> Result<Vec<Vec<usize>>, AdEssError> {
+        if self.num_bits() == 0 {
+            return Err(AdEssError::InvalidParameter(
+                "A group must encode at least one bit",
+            ));
+        }
+        let mut sequences = Vec::with_capacity(self.num_blocks_for_bits(data_bits.len()));
+        let num_bits = self.num_bits() as usize;
+        for group_bits in data_bits.chunks(num_bits) {
+            let mut group_bits = group_bits.to_vec();
+            group_bits.resize(num_bits, 0);
+            sequences.extend(self.encode(&group_bits)?);
+        }
+        Ok(sequences)
+    }
+    /// Returns the first `num_data_bits` bits of the bitstream for the amplitude `sequences` of
+    /// consecutive groups (deframing), see [ChainedAdEss::encode_bitstream()]
+    pub fn decode_bitstream(
+        &self,
+        sequences: &[Vec<usize>],
+        num_data_bits: usize,
+    ) -> Result<Vec<u8>, AdEssError> {
+        if sequences.len() != self.num_blocks_for_bits(num_data_bits) {
+            return Err(AdEssError::InvalidParameter(
+                "The number of sequences does not match `num_data_bits`",
+            ));
+        }
+        let mut data_bits =
+            Vec::with_capacity(sequences.len() / self.num_blocks() * self.num_bits() as usize);
+        for group_sequences in sequences.chunks(self.num_blocks()) {
+            data_bits.extend(self.decode(group_sequences)?);
+        }
+        data_bits.truncate(num_data_bits);
+        Ok(data_bits)
+    }
+}
@@ -0,0 +1,222 @@
+use crate::ad_ess::{index_for_weight_idx_sequence, walk_for_remaining_index, AdEss};
+use crate::bigint::Integer;
+use crate::error::AdEssError;
+use crate::trellis::Trellis;
+use crate::trellis_utils;
+use crate::trellis_value::TrellisValue;
+
+/// [AdEss] encoder / decoder with fixed width node values, see [AutoAdEss]
+///
+/// Encoding and decoding run without allocations for the node values, the indexes are
+/// converted from and to [Integer] at the interface.
+pub struct CompactAdEss<T: TrellisValue> {
+    trellis: Trellis<T>,
+}
+
+impl<T: TrellisValue> CompactAdEss<T> {
+    /// Returns a new [CompactAdEss] for the parameters of [AdEss::new()], `None` if a node value
+    /// does not fit into `T`
+    ///
+    /// The number of sequences is counted before the trellis is allocated, see
+    /// [trellis_utils::count_sequences()], so no [Integer] trellis is built.
+    pub fn new(threshold: usize, n_max: usize, weights: &[usize]) -> Option<CompactAdEss<T>> {
+        T::from_integer(&trellis_utils::count_sequences(threshold, n_max, weights))?;
+        Some(CompactAdEss::new_unchecked(threshold, n_max, weights))
+    }
+    /// Returns a new [CompactAdEss], the number of sequences must fit into `T`
+    fn new_unchecked(threshold: usize, n_max: usize, weights: &[usize]) -> CompactAdEss<T> {
+        let mut trellis = Trellis::new_with_backend::<T>(threshold, n_max, weights);
+        let one = T::from_integer(&Integer::from(1)).expect("1 fits into every backend");
+        for n in (0..n_max + 1).rev() {
+            for wl in trellis.get_weight_levels() {
+                if n == n_max {
+                    // number of possible sequences for end nodes is 1
+                    trellis.set(n, wl, one.clone());
+                } else {
+                    // the node value is the sum of the values of its successors, which is
+                    // bounded by the value of the node (0, 0)
+                    for (_, next_wl) in trellis.get_successors(wl) {
+                        trellis.add(n, wl, trellis.get(n + 1, next_wl));
+                    }
+                }
+            }
+        }
+        CompactAdEss { trellis }
+    }
+    /// Returns a [CompactAdEss] with the trellis of `adess`, `None` if a node value does not
+    /// fit into `T`
+    pub fn from_adess(adess: &AdEss) -> Option<CompactAdEss<T>> {
+        Some(CompactAdEss {
+            trellis: adess.trellis.to_backend()?,
+        })
+    }
+    /// Returns the trellis with the node values of type `T`
+    pub fn trellis(&self) -> &Trellis<T> {
+        &self.trellis
+    }
+    /// Returns the number of sequences, see [AdEss::num_sequences()]
+    pub fn num_sequences(&self) -> Integer {
+        self.trellis.get_ref(0, 0).to_integer()
+    }
+    /// Returns the number of bits per block, see [AdEss::num_bits()]
+    pub fn num_bits(&self) -> u32 {
+        self.trellis
+            .get_ref(0, 0)
+            .significant_bits()
+            .saturating_sub(1)
+    }
+    /// Returns the amplitude sequence for a given `index` (encode)
+    ///
+    /// Panics if `index` is out of range, see [CompactAdEss::try_sequence_for_index()].
+    pub fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        self.try_sequence_for_index(index)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+    /// Returns the amplitude sequence for a given `index` (encode) or an error if `index` is
+    /// negative or not smaller than [CompactAdEss::num_sequences()]
+    pub fn try_sequence_for_index(&self, index: &Integer) -> Result<Vec<usize>, AdEssError> {
+        let mut remaining_index = T::from_integer(index)
+            .filter(|index| index < self.trellis.get_ref(0, 0))
+            .ok_or(AdEssError::IndexOutOfRange)?;
+        let mut amplitude_sequence = Vec::with_capacity(self.trellis.n_max);
+        walk_for_remaining_index(&self.trellis, &mut remaining_index, |w_idx| {
+            amplitude_sequence.push(2 * w_idx + 1)
+        });
+        Ok(amplitude_sequence)
+    }
+    /// Returns the index for a given `amplitude_sequence` (decode) or an error if the sequence is
+    /// not in the trellis
+    ///
+    /// The sequence must have `n_max` odd amplitudes whose sum weight does not exceed the
+    /// threshold, see [AdEss::try_index_for_sequence()].
+    pub fn try_index_for_sequence(
+        &self,
+        amplitude_sequence: &[usize],
+    ) -> Result<Integer, AdEssError> {
+        if amplitude_sequence.len() != self.trellis.n_max {
+            return Err(AdEssError::InvalidSequence(
+                "Sequence length differs from `n_max`",
+            ));
+        }
+        let weights = self.trellis.weights_slice();
+        let mut sum_weight = 0;
+        for &amplitude in amplitude_sequence {
+            if amplitude.is_multiple_of(2) || (amplitude - 1) / 2 >= weights.len() {
+                return Err(AdEssError::InvalidSequence("Amplitude without weight"));
+            }
+            sum_weight += weights[(amplitude - 1) / 2];
+            // excluded amplitudes have a weight above the threshold
+            if sum_weight > self.trellis.threshold {
+                return Err(AdEssError::InvalidSequence("Threshold exceeded"));
+            }
+        }
+        Ok(self.index_for_sequence(amplitude_sequence))
+    }
+    /// Returns the index for a given `amplitude_sequence` (decode), see
+    /// [AdEss::index_for_sequence()]
+    ///
+    /// The sequence is not validated, see [CompactAdEss::try_index_for_sequence()].
+    pub fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        let weight_idx_seq: Vec<usize> = amplitude_sequence.iter().map(|a| (a - 1) / 2).collect();
+        index_for_weight_idx_sequence(&self.trellis, &weight_idx_seq).to_integer()
+    }
+}
+
+/// [AdEss] with the smallest node value type which cannot overflow
+///
+/// All node values of a forward trellis are bounded by the number of sequences, as the
+/// weight 0 allows to extend every shorter sequence. If the number of sequences fits into a
+/// `u64` or `u128`, a [CompactAdEss] is used.
+pub enum AutoAdEss {
+    U64(CompactAdEss<u64>),
+    U128(CompactAdEss<u128>),
+    Integer(AdEss),
+}
+
+impl AutoAdEss {
+    /// Returns a new [AutoAdEss] for the parameters of [AdEss::new()]
+    ///
+    /// The number of sequences is counted before any trellis is allocated, see
+    /// [trellis_utils::count_sequences()], and only the trellis of the selected node value type
+    /// is built.
+    pub fn new(threshold: usize, n_max: usize, weights: &[usize]) -> AutoAdEss {
+        let num_sequences = trellis_utils::count_sequences(threshold, n_max, weights);
+        if u64::from_integer(&num_sequences).is_some() {
+            AutoAdEss::U64(CompactAdEss::new_unchecked(threshold, n_max, weights))
+        } else if u128::from_integer(&num_sequences).is_some() {
+            AutoAdEss::U128(CompactAdEss::new_unchecked(threshold, n_max, weights))
+        } else {
+            AutoAdEss::Integer(AdEss::new(threshold, n_max, weights))
+        }
+    }
+    /// Returns an [AutoAdEss] with the smallest node value type for the trellis of `adess`
+    pub fn from_adess(adess: AdEss) -> AutoAdEss {
+        if let Some(compact) = CompactAdEss::from_adess(&adess) {
+            AutoAdEss::U64(compact)
+        } else if let Some(compact) = CompactAdEss::from_adess(&adess) {
+            AutoAdEss::U128(compact)
+        } else {
+            AutoAdEss::Integer(adess)
+        }
+    }
+    /// Returns the number of sequences, see [AdEss::num_sequences()]
+    pub fn num_sequences(&self) -> Integer {
+        match self {
+            AutoAdEss::U64(compact) => compact.num_sequences(),
+            AutoAdEss::U128(compact) => compact.num_sequences(),
+            AutoAdEss::Integer(adess) => adess.num_sequences(),
+        }
+    }
+    /// Returns the number of bits per block, see [AdEss::num_bits()]
+    pub fn num_bits(&self) -> u32 {
+        match self {
+            AutoAdEss::U64(compact) => compact.num_bits(),
+            AutoAdEss::U128(compact) => compact.num_bits(),
+            AutoAdEss::Integer(adess) => adess.num_bits(),
+        }
+    }
+    /// Returns the memory footprint of the node values in bytes, see [Trellis::memory_bytes()]
+    pub fn memory_bytes(&self) -> usize {
+        match self {
+            AutoAdEss::U64(compact) => compact.trellis().memory_bytes(),
+            AutoAdEss::U128(compact) => compact.trellis().memory_bytes(),
+            AutoAdEss::Integer(adess) => adess.trellis.memory_bytes(),
+        }
+    }
+    /// Returns the amplitude sequence for a given `index` (encode), see
+    /// [AdEss::sequence_for_index()]
+    pub fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        self.try_sequence_for_index(index)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+    /// Returns the amplitude sequence for a given `index` (encode) or an error if `index` is out
+    /// of range, see [AdEss::try_sequence_for_index()]
+    pub fn try_sequence_for_index(&self, index: &Integer) -> Result<Vec<usize>, AdEssError> {
+        match self {
+            AutoAdEss::U64(compact) => compact.try_sequence_for_index(index),
+            AutoAdEss::U128(compact) => compact.try_sequence_for_index(index),
+            AutoAdEss::Integer(adess) => adess.try_sequence_for_index(index),
+        }
+    }
+    /// Returns the index for a given `amplitude_sequence` (decode) or an error if the sequence is
+    /// not in the trellis, see [AdEss::try_index_for_sequence()]
+    pub fn try_index_for_sequence(
+        &self,
+        amplitude_sequence: &[usize],
+    ) -> Result<Integer, AdEssError> {
+        match self {
+            AutoAdEss::U64(compact) => compact.try_index_for_sequence(amplitude_sequence),
+            AutoAdEss::U128(compact) => compact.try_index_for_sequence(amplitude_sequence),
+            AutoAdEss::Integer(adess) => adess.try_index_for_sequence(amplitude_sequence),
+        }
+    }
+    /// Returns the index for a given `amplitude_sequence` (decode), see
+    /// [AdEss::index_for_sequence()]
+    pub fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        match self {
+            AutoAdEss::U64(compact) => compact.index_for_sequence(amplitude_sequence),
+            AutoAdEss::U128(compact) => compact.index_for_sequence(amplitude_sequence),
+            AutoAdEss::Integer(adess) => adess.index_for_sequence(amplitude_sequence),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::fmt;
+
+use crate::ad_ess::AdEss;
+use crate::ccdm::CCDM;
+use crate::error::AdEssError;
+use crate::matcher::DistributionMatcher;
+use crate::pcdm::PCDM;
+use crate::rts::RTS;
+use crate::shell_mapping::ShellMapper;
+use crate::trellis::Trellis;
+use crate::utils::{entropy, kl_divergence};
+
+/// Performance figures of a single distribution matcher
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatcherComparison {
+    /// Name of the matcher
+    pub name: String,
+    /// Number of amplitudes per sequence
+    pub n_max: usize,
+    /// Number of bits per sequence
+    pub num_bits: u32,
+    /// Rate in bit/amplitude
+    pub rate: f32,
+    /// Rate loss `H(P_A) - rate` in bit/amplitude
+    pub rate_loss: f32,
+    /// Average energy per amplitude
+    pub average_energy: f32,
+    /// KL divergence `D(P_A||P_target)` between the amplitude and the target distribution
+    pub kl_divergence: f32,
+    /// Number of bits to store the trellis / code tables
+    pub storage_bits: usize,
+    /// Worst case number of trellis values or table entries accessed to encode a sequence
+    pub max_operations: usize,
+}
+
+impl MatcherComparison {
+    /// Returns the performance figures of `matcher`
+    ///
+    /// - `target_distribution`: distribution the KL divergence is calculated for
+    /// - `storage_bits` and `max_operations`: complexity estimates of the matcher
+    pub fn new(
+        name: &str,
+        matcher: &dyn DistributionMatcher,
+        target_distribution: &[f32],
+        storage_bits: usize,
+        max_operations: usize,
+    ) -> MatcherComparison {
+        let amplitude_distribution = matcher.amplitude_distribution();
+        let rate = matcher.rate();
+        MatcherComparison {
+            name: name.to_string(),
+            n_max: matcher.n_max(),
+            num_bits: matcher.num_bits(),
+            rate,
+            rate_loss: entropy(&amplitude_distribution) - rate,
+            average_energy: matcher.average_energy(),
+            kl_divergence: kl_divergence(&amplitude_distribution, &target_distribution.to_vec()),
+            storage_bits,
+            max_operations,
+        }
+    }
+}
+
+/// Configures all available matchers and returns their performance figures
+///
+/// Every matcher uses `n_max` amplitudes and encodes at least `floor(rate * n_max)` bits.
+/// [AdEss], [RTS] and [ShellMapper] use weights computed via [AdEss::calc_weights()] with
+/// `res_factor`, [PCDM] uses the dyadic approximation of `distribution` and [CCDM] the
+/// composition closest to `n_max * distribution`. Matchers which can not be configured for the
+/// requested rate are left out.
+pub fn compare(
+    distribution: &[f32],
+    rate: f32,
+    n_max: usize,
+    res_factor: f32,
+) -> Result<Vec<MatcherComparison>, AdEssError> {
+    let num_bits = (rate * n_max as f32).floor() as usize;
+    let weights = AdEss::calc_weights(distribution, res_factor)?;
+    let num_weights = weights.len();
+
+    let mut comparisons = vec![];
+
+    let (adess, _) =
+        AdEss::new_for_distribution_num_bits(num_bits, n_max, distribution, res_factor)?;
+    comparisons.push(MatcherComparison::new(
+        "AD-ESS",
+        &adess,
+        distribution,
+        adess.trellis.storage_bits(),
+        n_max * num_weights,
+    ));
+
+    let rts = RTS::new(num_bits, n_max, &weights);
+    comparisons.push(MatcherComparison::new(
+        "RTS",
+        &rts,
+        distribution,
+        rts.trellis.storage_bits(),
+        rts.trellis.get_num_weight_levels() + n_max * num_weights,
+    ));
+
+    let shell_mapper = ShellMapper::from_rts(&rts);
+    comparisons.push(MatcherComparison::new(
+        "Shell",
+        &shell_mapper,
+        distribution,
+        shell_mapper.trellis.storage_bits(),
+        (2 * n_max - 1) * shell_mapper.trellis.get_num_weight_levels(),
+    ));
+
+    if let Ok(pcdm) = PCDM::new(distribution, n_max, num_bits as u32) {
+        comparisons.push(MatcherComparison::new(
+            "PCDM",
+            &pcdm,
+            distribution,
+            pcdm.storage_bits(),
+            n_max,
+        ));
+    }
+
+    if let Ok(ccdm) = CCDM::for_distribution(distribution, n_max, num_bits as u32) {
+        comparisons.push(MatcherComparison::new(
+            "CCDM",
+            &ccdm,
+            distribution,
+            ccdm.storage_bits(),
+            n_max * distribution.len(),
+        ));
+    }
+
+    Ok(comparisons)
+}
+
+/// Summary of a single matcher in [compare_adess_rts()]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatcherSummary {
+    /// Rate in bit/amplitude
+    pub rate: f32,
+    /// Rate loss `H(P_A) - rate` in bit/amplitude
+    pub rate_loss: f32,
+    /// Induced amplitude distribution `P_A`
+    pub amplitude_distribution: Vec<f32>,
+    pub average_energy: f32,
+    /// Number of stages and weight levels of the trellis
+    pub storage_dimensions: (usize, usize),
+    /// Number of bits to store the trellis
+    pub storage_bits: usize,
+}
+
+impl MatcherSummary {
+    fn new(matcher: &dyn DistributionMatcher, trellis: &Trellis) -> MatcherSummary {
+        let amplitude_distribution = matcher.amplitude_distribution();
+        let rate = matcher.rate();
+        MatcherSummary {
+            rate,
+            rate_loss: entropy(&amplitude_distribution) - rate,
+            average_energy: matcher.average_energy(),
+            amplitude_distribution,
+            storage_dimensions: trellis.get_storage_dimensions(),
+            storage_bits: trellis.storage_bits(),
+        }
+    }
+}
+
+/// Side-by-side comparison of [AdEss] and [RTS]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdEssRtsComparison {
+    pub adess: MatcherSummary,
+    pub rts: MatcherSummary,
+}
+
+/// Returns a side-by-side comparison of [AdEss] and [RTS] with the same weights and rate
+///
+/// Both matchers use the smallest trellis that encodes at least `num_bits` bits with `n_max`
+/// amplitudes, i.e., they hold the same set of sequences but use a different ordering.
+pub fn compare_adess_rts(weights: &[usize], n_max: usize, num_bits: usize) -> AdEssRtsComparison {
+    let rts = RTS::new(num_bits, n_max, weights);
+    let adess = AdEss::from_rts(&rts);
+    AdEssRtsComparison {
+        adess: MatcherSummary::new(&adess, &adess.trellis),
+        rts: MatcherSummary::new(&rts, &rts.trellis),
+    }
+}
+
+/// Table of [MatcherComparison]s, [fmt::Display] formats it as plain text
+pub struct ComparisonTable<'a>(pub &'a [MatcherComparison]);
+
+impl fmt::Display for ComparisonTable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<8} {:>6} {:>8} {:>8} {:>10} {:>10} {:>10} {:>12} {:>10}",
+            "matcher",
+            "n_max",
+            "bits",
+            "rate",
+            "rate loss",
+            "energy",
+            "KL div.",
+            "storage/bit",
+            "max. ops"
+        )?;
+        for row in self.0 {
+            writeln!(
+                f,
+                "{:<8} {:>6} {:>8} {:>8.4} {:>10.4} {:>10.4} {:>10.4} {:>12} {:>10}",
+                row.name,
+                row.n_max,
+                row.num_bits,
+                row.rate,
+                row.rate_loss,
+                row.average_energy,
+                row.kl_divergence,
+                row.storage_bits,
+                row.max_operations
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use crate::ad_ess::AdEss;
+use crate::error::AdEssError;
+use crate::trellis::max_used_weight;
+
+/// Rough time of a single limb addition, used for [ComplexityEstimate::construction_time]
+const NANOS_PER_LIMB_ADDITION: f64 = 1.0;
+/// Size of a [Integer](crate::bigint::Integer) without its limbs
+const INTEGER_BYTES: usize = 16;
+const LIMB_BITS: f64 = 64.0;
+
+/// Either the threshold or the number of bits of an [AdEss] configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThresholdOrBits {
+    Threshold(usize),
+    NumBits(usize),
+}
+
+/// Predicted size and construction effort of an [AdEss] trellis, see [estimate_complexity()]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComplexityEstimate {
+    /// Weights computed via [AdEss::calc_weights()]
+    pub weights: Vec<usize>,
+    /// Threshold (estimated if the number of bits is given)
+    pub threshold: usize,
+    /// Estimated number of bits that can be encoded / decoded
+    pub num_bits: u32,
+    pub num_weight_levels: usize,
+    /// Number of trellis nodes (`(n_max + 1) * num_weight_levels`)
+    pub num_nodes: usize,
+    /// Estimated memory footprint of the trellis node values in bytes
+    pub memory_bytes: usize,
+    /// Number of big integer additions of the trellis construction
+    pub num_additions: usize,
+    /// Rough construction time assuming 1 ns per limb addition
+    pub construction_time: Duration,
+}
+
+/// Predicted storage of an [AdEss] trellis, see [estimate_storage()]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageEstimate {
+    pub num_weight_levels: usize,
+    /// Number of trellis nodes (`(n_max + 1) * num_weight_levels`)
+    pub num_nodes: usize,
+    /// Average number of significant bits of the node values
+    pub bits_per_node: f64,
+    /// Number of significant bits of all node values, see
+    /// [Trellis::storage_bits()](crate::trellis::Trellis::storage_bits)
+    pub storage_bits: usize,
+    /// Estimated memory footprint of the node values in bytes, see
+    /// [Trellis::memory_bytes()](crate::trellis::Trellis::memory_bytes)
+    pub memory_bytes: usize,
+}
+
+/// Returns `log2(2^a + 2^b + ...)` of the values `log2_values`
+fn log2_sum(log2_values: impl Iterator<Item = f64> + Clone) -> f64 {
+    let max = log2_values.clone().fold(f64::NEG_INFINITY, f64::max);
+    if max == f64::NEG_INFINITY {
+        return max;
+    }
+    max + log2_values
+        .map(|value| (value - max).exp2())
+        .sum::<f64>()
+        .log2()
+}
+
+/// Returns `log2` of the number of sequences with `num_stages` amplitudes for each sum weight
+/// up to `max_weight_level`, all stages from 0 to `num_stages` are passed to `visit_stage`
+fn log2_num_sequences(
+    weights: &[usize],
+    num_stages: usize,
+    max_weight_level: usize,
+    mut visit_stage: impl FnMut(usize, &[f64]),
+) -> Vec<f64> {
+    let mut counts = vec![f64::NEG_INFINITY; max_weight_level + 1];
+    counts[0] = 0.0;
+    visit_stage(0, &counts);
+    for stage in 1..=num_stages {
+        counts = (0..=max_weight_level)
+            .map(|wl| {
+                log2_sum(
+                    weights
+                        .iter()
+                        .filter(|&&w| w <= wl)
+                        .map(|&w| counts[wl - w]),
+                )
+            })
+            .collect();
+        visit_stage(stage, &counts);
+    }
+    counts
+}
+
+/// Returns the running `log2` sums of `log2_counts`
+fn log2_cumsum(log2_counts: &[f64]) -> Vec<f64> {
+    let mut total = f64::NEG_INFINITY;
+    log2_counts
+        .iter()
+        .map(|&count| {
+            total = log2_sum([total, count].into_iter());
+            total
+        })
+        .collect()
+}
+
+/// Returns all sums of `trellis_weights` up to `threshold`, i.e., the weight levels of the trellis
+fn weight_levels_upto(trellis_weights: &[usize], threshold: usize) -> Vec<usize> {
+    let mut is_weight_level = vec![false; threshold + 1];
+    is_weight_level[0] = true;
+    for wl in 1..=threshold {
+        is_weight_level[wl] = trellis_weights
+            .iter()
+            .any(|&w| w > 0 && w <= wl && is_weight_level[wl - w]);
+    }
+    (0..=threshold).filter(|&wl| is_weight_level[wl]).collect()
+}
+
+/// Storage and construction effort of a forward trellis, see [estimate_forward_trellis()]
+struct ForwardTrellisEstimate {
+    storage: StorageEstimate,
+    /// Number of bits that can be encoded / decoded
+    num_bits: u32,
+    /// Number of successors of all weight levels
+    num_successors: usize,
+    /// Number of limb additions of the trellis construction
+    limb_additions: f64,
+}
+
+/// Predicts the storage of the forward trellis of `AdEss::new(threshold, n_max, weights)`
+///
+/// The node values are counted in the log domain as in [estimate_complexity()], so huge
+/// configurations can be pruned without allocating the trellis. The number of weight levels and
+/// nodes are exact, the number of bits is exact up to floating point rounding and the memory
+/// footprint is a rough estimate.
+pub fn estimate_storage(threshold: usize, n_max: usize, weights: &[usize]) -> StorageEstimate {
+    estimate_forward_trellis(threshold, n_max, weights).storage
+}
+
+/// Counts the node values of the forward trellis in the log domain, shared by
+/// [estimate_storage()] and [estimate_complexity()]
+fn estimate_forward_trellis(
+    threshold: usize,
+    n_max: usize,
+    weights: &[usize],
+) -> ForwardTrellisEstimate {
+    // excluded amplitudes are replaced by a weight above the threshold as in the trellis
+    let trellis_weights: Vec<usize> = weights.iter().map(|&w| w.min(threshold + 1)).collect();
+    let weight_levels = weight_levels_upto(&trellis_weights, threshold);
+    let successor_counts: Vec<usize> = weight_levels
+        .iter()
+        .map(|wl| {
+            trellis_weights
+                .iter()
+                .filter(|&&w| wl + w <= threshold)
+                .count()
+        })
+        .collect();
+
+    // the forward trellis node (stage, wl) counts the sequences with `n_max - stage` amplitudes
+    // and a sum weight up to `threshold - wl`
+    let mut storage_bits = 0f64;
+    let mut num_limbs = 0f64;
+    let mut limb_additions = 0f64;
+    let counts = log2_num_sequences(&trellis_weights, n_max, threshold, |num_stages, counts| {
+        let cumulative_counts = log2_cumsum(counts);
+        for (&wl, &successors) in weight_levels.iter().zip(&successor_counts) {
+            let log2_value = cumulative_counts[threshold - wl];
+            // a node value of zero has no significant bits
+            if log2_value > f64::NEG_INFINITY {
+                storage_bits += (log2_value + 1e-9).floor() + 1.0;
+            }
+            let limbs = (log2_value.max(0.0) / LIMB_BITS).floor() + 1.0;
+            num_limbs += limbs;
+            if num_stages > 0 {
+                limb_additions += limbs * successors as f64;
+            }
+        }
+    });
+
+    let num_nodes = (n_max + 1) * weight_levels.len();
+    ForwardTrellisEstimate {
+        storage: StorageEstimate {
+            num_weight_levels: weight_levels.len(),
+            num_nodes,
+            bits_per_node: storage_bits / num_nodes as f64,
+            storage_bits: storage_bits as usize,
+            memory_bytes: num_nodes * INTEGER_BYTES + num_limbs as usize * (LIMB_BITS as usize / 8),
+        },
+        num_bits: (log2_cumsum(&counts)[threshold] + 1e-9).floor() as u32,
+        num_successors: successor_counts.iter().sum(),
+        limb_additions,
+    }
+}
+
+/// Predicts the size of an [AdEss] trellis before it is allocated
+///
+/// The weights are computed via [AdEss::calc_weights()] with `distribution` and `res_factor`.
+/// If the number of bits is given, the threshold is estimated as for
+/// [AdEss::new_for_distribution_num_bits()]. All sequence counts are calculated in the log
+/// domain with floating point numbers, so the trellis node values are never allocated.
+///
+/// The memory footprint and construction time are rough estimates of the forward trellis of
+/// [AdEss], the number of weight levels and nodes are exact.
+pub fn estimate_complexity(
+    distribution: &[f32],
+    res_factor: f32,
+    n_max: usize,
+    threshold_or_bits: ThresholdOrBits,
+) -> Result<ComplexityEstimate, AdEssError> {
+    let weights = AdEss::calc_weights(distribution, res_factor)?;
+    let max_weight = max_used_weight(&weights).ok_or(AdEssError::InvalidDistribution(
+        "`distribution` must not be empty",
+    ))?;
+
+    let threshold = match threshold_or_bits {
+        ThresholdOrBits::Threshold(threshold) => threshold,
+        ThresholdOrBits::NumBits(num_bits) => {
+            let counts = log2_num_sequences(&weights, n_max, n_max * max_weight, |_, _| {});
+            log2_cumsum(&counts)
+                .iter()
+                .position(|&log2_num_sequences| log2_num_sequences >= num_bits as f64 - 1e-9)
+                .ok_or(AdEssError::TrellisTooSmall("`num_bits` is to large"))?
+        }
+    };
+
+    let estimate = estimate_forward_trellis(threshold, n_max, &weights);
+    Ok(ComplexityEstimate {
+        weights,
+        threshold,
+        num_bits: estimate.num_bits,
+        num_weight_levels: estimate.storage.num_weight_levels,
+        num_nodes: estimate.storage.num_nodes,
+        memory_bytes: estimate.storage.memory_bytes,
+        num_additions: n_max * estimate.num_successors,
+        construction_time: Duration::from_nanos(
+            (estimate.limb_additions * NANOS_PER_LIMB_ADDITION) as u64,
+        ),
+    })
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+/// Returns the codeword lengths of the dyadic distribution closest to `distribution`
+///
+/// Uses geometric Huffman coding (GHC) which minimizes the KL-divergence `D(Q||P)` between the
+/// dyadic distribution `Q` and the target distribution `P`, see
+/// G. Böcherer and R. Mathar, "Matching dyadic distributions to channels," in Proc. Data
+/// Compression Conf., 2011, doi: 10.1109/DCC.2011.10.
+///
+/// Amplitudes which are not used by the dyadic distribution get the length `None`.
+pub fn geometric_huffman_lengths(distribution: &[f32]) -> Vec<Option<u32>> {
+    let mut lengths: Vec<Option<u32>> = distribution
+        .iter()
+        .map(|&p| if p > 0.0 { Some(0) } else { None })
+        .collect();
+
+    let mut nodes: Vec<(f64, Vec<usize>)> = distribution
+        .iter()
+        .enumerate()
+        .filter(|(_, &p)| p > 0.0)
+        .map(|(idx, &p)| (p as f64, vec![idx]))
+        .collect();
+
+    while nodes.len() > 1 {
+        // sort in descending order, the two smallest nodes are at the end
+        nodes.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let (p_small, leaves_small) = nodes.pop().expect("at least two nodes");
+        let (p_large, mut leaves_large) = nodes.pop().expect("at least two nodes");
+
+        if p_large >= 4.0 * p_small {
+            // the smaller node is discarded
+            for leaf in leaves_small {
+                lengths[leaf] = None;
+            }
+            nodes.push((p_large, leaves_large));
+        } else {
+            leaves_large.extend(leaves_small);
+            for &leaf in leaves_large.iter() {
+                lengths[leaf] = lengths[leaf].map(|length| length + 1);
+            }
+            nodes.push((2.0 * (p_large * p_small).sqrt(), leaves_large));
+        }
+    }
+
+    lengths
+}
+
+/// Returns the dyadic distribution closest to `distribution`
+///
+/// See [geometric_huffman_lengths()] for details.
+pub fn dyadic_approximation(distribution: &[f32]) -> Vec<f32> {
+    geometric_huffman_lengths(distribution)
+        .iter()
+        .map(|length| match length {
+            Some(length) => (-(*length as f32)).exp2(),
+            None => 0.0,
+        })
+        .collect()
+}
+
+/// Simple distribution matcher for dyadic distributions
+///
+/// Each amplitude is represented by a codeword of a complete prefix-free code. Parsing
+/// uniformly distributed bits with this code yields amplitudes distributed according to
+/// the dyadic distribution [DyadicMatcher::get_distribution()].
+pub struct DyadicMatcher {
+    codewords: Vec<Option<Vec<u8>>>,
+    codeword_lookup: HashMap<Vec<u8>, usize>,
+}
+
+impl DyadicMatcher {
+    /// Returns a [DyadicMatcher] for the dyadic approximation of `distribution`
+    pub fn new(distribution: &[f32]) -> Result<DyadicMatcher, &'static str> {
+        DyadicMatcher::from_code_lengths(&geometric_huffman_lengths(distribution))
+    }
+
+    /// Returns a [DyadicMatcher] using a canonical prefix-free code with the given codeword lengths
+    ///
+    /// The lengths must fulfill the Kraft inequality with equality and at least two amplitudes
+    /// must be used.
+    pub fn from_code_lengths(lengths: &[Option<u32>]) -> Result<DyadicMatcher, &'static str> {
+        let mut used: Vec<(usize, u32)> = lengths
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, length)| length.map(|length| (idx, length)))
+            .collect();
+        if used.len() < 2 {
+            return Err("At least two amplitudes must be used");
+        }
+        let max_length = used.iter().map(|(_, length)| *length).max().unwrap_or(0);
+        if max_length >= u64::BITS {
+            return Err("Codeword lengths are too large");
+        }
+        let kraft_sum: u64 = used
+            .iter()
+            .map(|(_, length)| 1u64 << (max_length - length))
+            .sum();
+        if kraft_sum != 1u64 << max_length {
+            return Err("Codeword lengths do not describe a complete prefix-free code");
+        }
+
+        // canonical code construction
+        used.sort_by_key(|&(idx, length)| (length, idx));
+        let mut codewords = vec![None; lengths.len()];
+        let mut codeword_lookup = HashMap::new();
+        let mut code = 0u64;
+        let mut previous_length = used[0].1;
+        for (idx, length) in used {
+            code <<= length - previous_length;
+            previous_length = length;
+            let codeword: Vec<u8> = (0..length)
+                .rev()
+                .map(|bit| ((code >> bit) & 1) as u8)
+                .collect();
+            codeword_lookup.insert(codeword.clone(), idx);
+            codewords[idx] = Some(codeword);
+            code += 1;
+        }
+
+        Ok(DyadicMatcher {
+            codewords,
+            codeword_lookup,
+        })
+    }
+
+    /// Returns the amplitude value for a given weight index
+    fn weight_idx_to_amplitude(weight_index: usize) -> usize {
+        weight_index * 2 + 1
+    }
+}
+
+impl DyadicMatcher {
+    /// Returns the codeword for each amplitude, `None` for unused amplitudes
+    pub fn get_codewords(&self) -> Vec<Option<Vec<u8>>> {
+        self.codewords.clone()
+    }
+    /// Returns the (dyadic) distribution of the amplitudes
+    pub fn get_distribution(&self) -> Vec<f32> {
+        self.codewords
+            .iter()
+            .map(|codeword| match codeword {
+                Some(codeword) => (-(codeword.len() as f32)).exp2(),
+                None => 0.0,
+            })
+            .collect()
+    }
+    /// Returns the average number of bits per amplitude
+    pub fn rate(&self) -> f32 {
+        self.codewords
+            .iter()
+            .flatten()
+            .map(|codeword| codeword.len() as f32 * (-(codeword.len() as f32)).exp2())
+            .sum()
+    }
+    /// Returns the amplitude for the codeword at the start of `bits`
+    ///
+    /// The amplitude and the codeword length are returned, `None` if `bits` ends before a
+    /// codeword is complete.
+    pub fn encode_one(&self, bits: &[u8]) -> Option<(usize, usize)> {
+        (1..=bits.len()).find_map(|length| {
+            self.codeword_lookup
+                .get(&bits[..length])
+                .map(|&weight_idx| (DyadicMatcher::weight_idx_to_amplitude(weight_idx), length))
+        })
+    }
+    /// Returns the amplitudes for the given bits (encode)
+    ///
+    /// Bits are parsed until no complete codeword is left. The amplitudes and the number of
+    /// consumed bits are returned.
+    pub fn encode(&self, bits: &[u8]) -> (Vec<usize>, usize) {
+        let mut amplitudes = vec![];
+        let mut consumed = 0;
+        while let Some((amplitude, length)) = self.encode_one(&bits[consumed..]) {
+            amplitudes.push(amplitude);
+            consumed += length;
+        }
+        (amplitudes, consumed)
+    }
+    /// Returns the bits for the given amplitudes (decode)
+    pub fn decode(&self, amplitudes: &[usize]) -> Vec<u8> {
+        amplitudes
+            .iter()
+            .flat_map(|a| {
+                self.codewords[(a - 1) / 2]
+                    .as_ref()
+                    .expect("amplitude is not used by this matcher")
+                    .iter()
+                    .copied()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+
+/// Errors of the distribution matchers in this crate
+///
+/// Variants with a message describe the violated condition in more detail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdEssError {
+    /// A parameter is outside of its valid range
+    InvalidParameter(&'static str),
+    /// The weights are empty, not finite or their smallest weight is not 0
+    InvalidWeights(&'static str),
+    /// The (amplitude) distribution is empty or has no positive probability
+    InvalidDistribution(&'static str),
+    /// The trellis cannot hold the required weight levels or sequences
+    TrellisTooSmall(&'static str),
+    /// The index is negative or not smaller than the number of sequences
+    IndexOutOfRange,
+    /// The amplitude sequence is not in the trellis
+    InvalidSequence(&'static str),
+    /// No configuration satisfies the requirements
+    NoSolution(&'static str),
+    /// The trellis would exceed the memory limit
+    MemoryLimitExceeded,
+    /// The operation was cancelled, see [crate::cancel::CancellationToken]
+    Cancelled,
+}
+
+impl AdEssError {
+    /// Returns the description of the error
+    pub fn message(&self) -> &'static str {
+        match self {
+            AdEssError::InvalidParameter(message)
+            | AdEssError::InvalidWeights(message)
+            | AdEssError::InvalidDistribution(message)
+            | AdEssError::TrellisTooSmall(message)
+            | AdEssError::InvalidSequence(message)
+            | AdEssError::NoSolution(message) => message,
+            AdEssError::IndexOutOfRange => "Index out of range",
+            AdEssError::MemoryLimitExceeded => "Memory limit exceeded",
+            AdEssError::Cancelled => "Operation cancelled",
+        }
+    }
+}
+
+impl fmt::Display for AdEssError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for AdEssError {}
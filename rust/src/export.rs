@@ -0,0 +1,205 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::compare::MatcherComparison;
+use crate::sweep::BlocklengthSweepPoint;
+
+/// Single value of an exported row
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportValue {
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+/// Structured row (configuration parameters and metrics) that can be exported
+///
+/// All rows of one export must return the same columns in the same order.
+pub trait ExportRow {
+    /// Returns the column names
+    fn column_names(&self) -> Vec<&'static str>;
+    /// Returns the values in the order of [ExportRow::column_names()]
+    fn values(&self) -> Vec<ExportValue>;
+}
+
+impl ExportRow for MatcherComparison {
+    fn column_names(&self) -> Vec<&'static str> {
+        vec![
+            "matcher",
+            "n_max",
+            "num_bits",
+            "rate",
+            "rate_loss",
+            "average_energy",
+            "kl_divergence",
+            "storage_bits",
+            "max_operations",
+        ]
+    }
+    fn values(&self) -> Vec<ExportValue> {
+        vec![
+            ExportValue::Text(self.name.clone()),
+            ExportValue::Int(self.n_max as i64),
+            ExportValue::Int(self.num_bits as i64),
+            ExportValue::Float(self.rate as f64),
+            ExportValue::Float(self.rate_loss as f64),
+            ExportValue::Float(self.average_energy as f64),
+            ExportValue::Float(self.kl_divergence as f64),
+            ExportValue::Int(self.storage_bits as i64),
+            ExportValue::Int(self.max_operations as i64),
+        ]
+    }
+}
+
+impl ExportRow for BlocklengthSweepPoint {
+    fn column_names(&self) -> Vec<&'static str> {
+        vec![
+            "n_max",
+            "threshold",
+            "num_bits",
+            "rate",
+            "rate_loss",
+            "average_energy",
+        ]
+    }
+    fn values(&self) -> Vec<ExportValue> {
+        vec![
+            ExportValue::Int(self.n_max as i64),
+            ExportValue::Int(self.threshold as i64),
+            ExportValue::Int(self.num_bits as i64),
+            ExportValue::Float(self.rate as f64),
+            ExportValue::Float(self.rate_loss as f64),
+            ExportValue::Float(self.average_energy as f64),
+        ]
+    }
+}
+
+/// Returns `text` quoted for CSV if necessary
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Writes `rows` with a header line as CSV to `writer`
+///
+/// The output can be read with `pandas.read_csv()`.
+pub fn write_csv<R: ExportRow>(mut writer: impl Write, rows: &[R]) -> io::Result<()> {
+    let header = match rows.first() {
+        Some(row) => row.column_names(),
+        None => return Ok(()),
+    };
+    writeln!(writer, "{}", header.join(","))?;
+    for row in rows {
+        let fields: Vec<String> = row
+            .values()
+            .iter()
+            .map(|value| match value {
+                ExportValue::Int(value) => value.to_string(),
+                ExportValue::Float(value) => value.to_string(),
+                ExportValue::Text(value) => csv_field(value),
+            })
+            .collect();
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+/// Writes `rows` as CSV to the file at `path`, see [write_csv()]
+pub fn write_csv_file<R: ExportRow>(path: impl AsRef<Path>, rows: &[R]) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(File::create(path)?);
+    write_csv(&mut writer, rows)?;
+    writer.flush()
+}
+
+/// Writes `rows` as a single row group Parquet file to `path`
+///
+/// The column types are taken from the first row. The file can be read with
+/// `pandas.read_parquet()`.
+#[cfg(feature = "parquet")]
+pub fn write_parquet_file<R: ExportRow>(
+    path: impl AsRef<Path>,
+    rows: &[R],
+) -> Result<(), parquet::errors::ParquetError> {
+    use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+    use parquet::errors::ParquetError;
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use std::sync::Arc;
+
+    let first_row = rows
+        .first()
+        .ok_or_else(|| ParquetError::General("At least one row is required".to_string()))?;
+    let column_fields: Vec<String> = first_row
+        .column_names()
+        .iter()
+        .zip(first_row.values())
+        .map(|(name, value)| match value {
+            ExportValue::Int(_) => format!("REQUIRED INT64 {};", name),
+            ExportValue::Float(_) => format!("REQUIRED DOUBLE {};", name),
+            ExportValue::Text(_) => format!("REQUIRED BYTE_ARRAY {} (UTF8);", name),
+        })
+        .collect();
+    let schema = parse_message_type(&format!(
+        "message results {{ {} }}",
+        column_fields.join(" ")
+    ))?;
+
+    let values: Vec<Vec<ExportValue>> = rows.iter().map(|row| row.values()).collect();
+    let mut writer = SerializedFileWriter::new(
+        File::create(path)?,
+        Arc::new(schema),
+        Arc::new(WriterProperties::builder().build()),
+    )?;
+    let mut row_group = writer.next_row_group()?;
+    let mut column_idx = 0;
+    while let Some(mut column_writer) = row_group.next_column()? {
+        let column = values.iter().map(|row_values| &row_values[column_idx]);
+        let type_mismatch =
+            || ParquetError::General("Column types differ between rows".to_string());
+        match first_row.values()[column_idx] {
+            ExportValue::Int(_) => {
+                let column = column
+                    .map(|value| match value {
+                        ExportValue::Int(value) => Ok(*value),
+                        _ => Err(type_mismatch()),
+                    })
+                    .collect::<Result<Vec<i64>, _>>()?;
+                column_writer
+                    .typed::<Int64Type>()
+                    .write_batch(&column, None, None)?;
+            }
+            ExportValue::Float(_) => {
+                let column = column
+                    .map(|value| match value {
+                        ExportValue::Float(value) => Ok(*value),
+                        _ => Err(type_mismatch()),
+                    })
+                    .collect::<Result<Vec<f64>, _>>()?;
+                column_writer
+                    .typed::<DoubleType>()
+                    .write_batch(&column, None, None)?;
+            }
+            ExportValue::Text(_) => {
+                let column = column
+                    .map(|value| match value {
+                        ExportValue::Text(value) => Ok(ByteArray::from(value.as_str())),
+                        _ => Err(type_mismatch()),
+                    })
+                    .collect::<Result<Vec<ByteArray>, _>>()?;
+                column_writer
+                    .typed::<ByteArrayType>()
+                    .write_batch(&column, None, None)?;
+            }
+        }
+        column_writer.close()?;
+        column_idx += 1;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use crate::bigint::Integer;
+use crate::error::AdEssError;
+use crate::matcher::DistributionMatcher;
+use crate::utils::{bits_to_integer, integer_to_bits};
+
+/// Hierarchical distribution matching (Hi-DM) with an outer and an inner matcher
+///
+/// The indexes `0..2^inner.num_bits()` of the inner matcher are split into groups of `2^k`
+/// consecutive indexes, one group per amplitude of the outer matcher. The first
+/// `outer.num_bits()` bits of an index are encoded by the outer matcher into `L = outer.n_max()`
+/// amplitudes, the outer amplitude `2 * w + 1` at position `l` selects group `w` for the `l`-th
+/// inner block. The remaining bits are split into `L` chunks of `k` bits, each selecting the
+/// inner index within its group, i.e., the inner index is `w * 2^k + i`. The `L` inner sequences
+/// are concatenated into `L * inner.n_max()` amplitudes.
+///
+/// When decoding, the group of each decoded inner index is the outer amplitude, so the inner
+/// indexes feed the outer matcher. Long effective block lengths are reached with the storage of
+/// two small matchers. With [RTS](crate::rts::RTS) as inner matcher, groups with a small `w` hold
+/// the sequences with the smallest energy, i.e., the outer matcher shapes the energy of the inner
+/// blocks.
+pub struct HiDm<O: DistributionMatcher, I: DistributionMatcher> {
+    outer: O,
+    inner: I,
+    group_bits: u32,
+    block_bits: u32,
+    group_distributions: Vec<Vec<f64>>,
+}
+
+impl<O: DistributionMatcher, I: DistributionMatcher> HiDm<O, I> {
+    /// Returns a new [HiDm] instance
+    ///
+    /// `k = inner.num_bits() - ceil(log2(W))` bits are encoded per inner block, where `W` is the
+    /// number of amplitudes of `outer`. An error is returned if no bits are left (`k == 0`) or
+    /// `inner` can not count the amplitudes of index ranges, see
+    /// [DistributionMatcher::amplitude_counts_below()].
+    pub fn new(outer: O, inner: I) -> Result<HiDm<O, I>, AdEssError> {
+        let num_groups = outer.amplitude_distribution().len();
+        let group_bits = num_groups.next_power_of_two().trailing_zeros();
+        if inner.num_bits() <= group_bits {
+            return Err(AdEssError::InvalidParameter(
+                "The inner matcher must encode more bits than the outer amplitude",
+            ));
+        }
+        let block_bits = inner.num_bits() - group_bits;
+
+        let counts_below = (0..=num_groups)
+            .map(|w| inner.amplitude_counts_below(&(Integer::from(w) << block_bits)))
+            .collect::<Option<Vec<Vec<Integer>>>>()
+            .ok_or(AdEssError::InvalidParameter(
+                "The inner matcher must count the amplitudes of index ranges",
+            ))?;
+        let num_group_amplitudes = (Integer::from(inner.n_max()) << block_bits).to_f64();
+        let group_distributions = counts_below
+            .windows(2)
+            .map(|counts| {
+                counts[1]
+                    .iter()
+                    .zip(&counts[0])
+                    .map(|(upper, lower)| Integer::from(upper - lower).to_f64())
+                    .map(|count| count / num_group_amplitudes)
+                    .collect()
+            })
+            .collect();
+
+        Ok(HiDm {
+            outer,
+            inner,
+            group_bits,
+            block_bits,
+            group_distributions,
+        })
+    }
+}
+
+impl<O: DistributionMatcher, I: DistributionMatcher> HiDm<O, I> {
+    /// Returns the outer matcher
+    pub fn outer(&self) -> &O {
+        &self.outer
+    }
+    /// Returns the inner matcher
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+    /// Returns the number of bits encoded per inner block (`k`)
+    pub fn block_bits(&self) -> u32 {
+        self.block_bits
+    }
+    /// Returns the amplitude distribution of the inner blocks in group `w`
+    pub fn group_distribution(&self, w: usize) -> Vec<f32> {
+        self.group_distributions[w]
+            .iter()
+            .map(|&p| p as f32)
+            .collect()
+    }
+    /// Returns the index for a given `amplitude_sequence` (decode) or an error if an inner
+    /// block or the outer sequence can not be decoded
+    pub fn try_index_for_sequence(
+        &self,
+        amplitude_sequence: &[usize],
+    ) -> Result<Integer, AdEssError> {
+        if amplitude_sequence.len() != DistributionMatcher::n_max(self) {
+            return Err(AdEssError::InvalidSequence(
+                "The amplitude sequence must have `n_max` amplitudes",
+            ));
+        }
+        let inner_bits = (self.group_bits + self.block_bits) as usize;
+        let mut outer_sequence = Vec::with_capacity(self.outer.n_max());
+        let mut block_bits = Vec::with_capacity((self.block_bits as usize) * self.outer.n_max());
+        for block in amplitude_sequence.chunks(self.inner.n_max()) {
+            let inner_index = self.inner.try_index_for_sequence(block)?;
+            let w = (Integer::from(&inner_index) >> self.block_bits).to_usize();
+            match w {
+                Some(w) if w < self.group_distributions.len() => {
+                    outer_sequence.push(2 * w + 1);
+                }
+                _ => {
+                    return Err(AdEssError::InvalidSequence(
+                        "The inner index is not in a group",
+                    ))
+                }
+            }
+            let bits = integer_to_bits(&inner_index, inner_bits);
+            block_bits.extend_from_slice(&bits[self.group_bits as usize..]);
+        }
+        let outer_index = self.outer.try_index_for_sequence(&outer_sequence)?;
+        if outer_index.significant_bits() > self.outer.num_bits() {
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        let mut bits = integer_to_bits(&outer_index, self.outer.num_bits() as usize);
+        bits.append(&mut block_bits);
+        Ok(bits_to_integer(&bits))
+    }
+}
+
+impl<O: DistributionMatcher, I: DistributionMatcher> DistributionMatcher for HiDm<O, I> {
+    fn n_max(&self) -> usize {
+        self.outer.n_max() * self.inner.n_max()
+    }
+    fn num_bits(&self) -> u32 {
+        self.outer.num_bits() + self.outer.n_max() as u32 * self.block_bits
+    }
+    fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        let bits = integer_to_bits(index, DistributionMatcher::num_bits(self) as usize);
+        let (outer_bits, block_bits) = bits.split_at(self.outer.num_bits() as usize);
+        let outer_sequence = self.outer.sequence_for_index(&bits_to_integer(outer_bits));
+
+        let mut sequence = Vec::with_capacity(DistributionMatcher::n_max(self));
+        for (amplitude, bits) in outer_sequence
+            .iter()
+            .zip(block_bits.chunks(self.block_bits as usize))
+        {
+            let w = (amplitude - 1) / 2;
+            let inner_index = (Integer::from(w) << self.block_bits) + bits_to_integer(bits);
+            sequence.extend(self.inner.sequence_for_index(&inner_index));
+        }
+        sequence
+    }
+    /// Panics if the sequence can not be decoded, see [HiDm::try_index_for_sequence()]
+    fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        HiDm::try_index_for_sequence(self, amplitude_sequence).unwrap()
+    }
+    fn try_index_for_sequence(&self, amplitude_sequence: &[usize]) -> Result<Integer, AdEssError> {
+        HiDm::try_index_for_sequence(self, amplitude_sequence)
+    }
+    /// Mixture of the group distributions weighted with the outer amplitude distribution
+    fn amplitude_distribution(&self) -> Vec<f32> {
+        let outer_distribution = self.outer.amplitude_distribution();
+        let mut distribution = vec![0f64; self.group_distributions[0].len()];
+        for (&p_outer, group_distribution) in
+            outer_distribution.iter().zip(&self.group_distributions)
+        {
+            for (p, &p_group) in distribution.iter_mut().zip(group_distribution) {
+                *p += p_outer as f64 * p_group;
+            }
+        }
+        distribution.iter().map(|&p| p as f32).collect()
+    }
+}
@@ -0,0 +1,119 @@
+use crate::ad_ess::AdEss;
+use crate::error::AdEssError;
+
+/// Configuration estimated from observed amplitude sequences, see [infer_configuration()]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferredConfiguration {
+    /// Number of amplitudes, i.e., the largest observed weight index plus one
+    pub num_amplitudes: usize,
+    pub n_max: usize,
+    /// Relative frequency of each amplitude
+    pub distribution: Vec<f32>,
+    pub weights: Vec<usize>,
+    /// Largest sum weight of the observed sequences, i.e., the smallest consistent threshold
+    pub threshold: usize,
+    /// Number of bits of the [AdEss] with `threshold`, `n_max` and `weights`
+    pub num_bits: u32,
+    /// Number of bits of the largest index of the observed sequences
+    ///
+    /// This is a lower bound of the number of bits used by the source. A value above `num_bits`
+    /// indicates that the observed sequences are not the lexicographically first ones of the
+    /// trellis, i.e., that `weights` differ from those of the source.
+    pub min_num_bits: u32,
+}
+
+/// Estimates the [AdEss] configuration which produced the amplitude `sequences`
+///
+/// The weights are computed via [AdEss::calc_weights()] from the empirical amplitude
+/// distribution with `res_factor`, see [infer_configuration_for_weights()] for known weights.
+pub fn infer_configuration(
+    sequences: &[Vec<usize>],
+    res_factor: f32,
+) -> Result<InferredConfiguration, AdEssError> {
+    let distribution = empirical_distribution(sequences)?;
+    let weights = AdEss::calc_weights(&distribution, res_factor)?;
+    infer_configuration_for_weights(sequences, &weights)
+}
+
+/// Estimates the threshold and number of bits of the [AdEss] with `weights` which produced the
+/// amplitude `sequences`
+///
+/// Each sequence must fit below the threshold, so the largest observed sum weight is the
+/// smallest consistent threshold. The indexes of the observed sequences in this trellis bound
+/// the number of bits used by the source from below.
+pub fn infer_configuration_for_weights(
+    sequences: &[Vec<usize>],
+    weights: &[usize],
+) -> Result<InferredConfiguration, AdEssError> {
+    let distribution = empirical_distribution(sequences)?;
+    if distribution.len() > weights.len() {
+        return Err(AdEssError::InvalidWeights(
+            "`sequences` contain amplitudes without weight",
+        ));
+    }
+    let n_max = sequences[0].len();
+    let threshold = sequences
+        .iter()
+        .map(|sequence| sequence.iter().map(|a| weights[(a - 1) / 2]).sum::<usize>())
+        .max()
+        .expect("checked for empty sequences above");
+
+    let adess = AdEss::new(threshold, n_max, weights);
+    let max_index = sequences
+        .iter()
+        .map(|sequence| adess.index_for_sequence(sequence))
+        .max()
+        .expect("checked for empty sequences above");
+
+    Ok(InferredConfiguration {
+        num_amplitudes: distribution.len(),
+        n_max,
+        distribution,
+        weights: weights.to_vec(),
+        threshold,
+        num_bits: adess.num_bits(),
+        min_num_bits: max_index.significant_bits(),
+    })
+}
+
+/// Returns the relative frequency of each amplitude in `sequences`
+///
+/// An error is returned for an empty corpus, sequences of different lengths or even amplitudes.
+fn empirical_distribution(sequences: &[Vec<usize>]) -> Result<Vec<f32>, AdEssError> {
+    let n_max = sequences
+        .first()
+        .ok_or(AdEssError::InvalidParameter(
+            "`sequences` must not be empty",
+        ))?
+        .len();
+    if n_max == 0 {
+        return Err(AdEssError::InvalidParameter(
+            "`sequences` must contain amplitudes",
+        ));
+    }
+    if sequences.iter().any(|sequence| sequence.len() != n_max) {
+        return Err(AdEssError::InvalidParameter(
+            "All sequences must have the same length",
+        ));
+    }
+    if sequences.iter().flatten().any(|a| a.is_multiple_of(2)) {
+        return Err(AdEssError::InvalidSequence(
+            "Amplitudes must be positive odd numbers",
+        ));
+    }
+
+    let num_amplitudes = sequences
+        .iter()
+        .flatten()
+        .max()
+        .map_or(0, |a| (a - 1) / 2 + 1);
+    let mut counts = vec![0usize; num_amplitudes];
+    for &a in sequences.iter().flatten() {
+        counts[(a - 1) / 2] += 1;
+    }
+    let num_observed = (sequences.len() * n_max) as f32;
+    Ok(counts
+        .iter()
+        .map(|&count| count as f32 / num_observed)
+        .collect())
+}
@@ -0,0 +1,460 @@
+use crate::ad_ess::AdEss;
+use crate::error::AdEssError;
+use crate::utils;
+
+/// Returns the binary reflected Gray labeling of `num_amplitudes` amplitudes
+///
+/// `labeling[w_idx]` is the label of the amplitude with weight index `w_idx`.
+pub fn gray_labeling(num_amplitudes: usize) -> Vec<usize> {
+    (0..num_amplitudes)
+        .map(|w_idx| w_idx ^ (w_idx >> 1))
+        .collect()
+}
+
+/// Returns the natural labeling of `num_amplitudes` amplitudes, i.e., `labeling[w_idx] = w_idx`
+pub fn natural_labeling(num_amplitudes: usize) -> Vec<usize> {
+    (0..num_amplitudes).collect()
+}
+
+/// Returns the number of bits per label or an error if `labeling` is not a permutation of
+/// `0..2^m`
+pub(crate) fn num_label_bits(labeling: &[usize]) -> Result<usize, AdEssError> {
+    if !labeling.len().is_power_of_two() {
+        return Err(AdEssError::InvalidParameter(
+            "The number of labels must be a power of two",
+        ));
+    }
+    let mut is_used = vec![false; labeling.len()];
+    for &label in labeling {
+        if label >= labeling.len() || std::mem::replace(&mut is_used[label], true) {
+            return Err(AdEssError::InvalidParameter(
+                "`labeling` must be a permutation of `0..labeling.len()`",
+            ));
+        }
+    }
+    Ok(labeling.len().trailing_zeros() as usize)
+}
+
+/// Returns the label bits (MSB first) of all `amplitudes`
+///
+/// These are the amplitude bits fed into the systematic FEC encoder in probabilistic amplitude
+/// shaping (PAS). `labeling[w_idx]` is the label of the amplitude `2 * w_idx + 1`, see
+/// [natural_labeling()] and [gray_labeling()].
+pub fn amplitudes_to_label_bits(
+    amplitudes: &[usize],
+    labeling: &[usize],
+) -> Result<Vec<u8>, AdEssError> {
+    let num_bits = num_label_bits(labeling)?;
+    let mut bits = Vec::with_capacity(amplitudes.len() * num_bits);
+    for &amplitude in amplitudes {
+        if amplitude.is_multiple_of(2) || (amplitude - 1) / 2 >= labeling.len() {
+            return Err(AdEssError::InvalidSequence("Amplitude without label"));
+        }
+        let label = labeling[(amplitude - 1) / 2];
+        bits.extend((0..num_bits).rev().map(|bit| ((label >> bit) & 1) as u8));
+    }
+    Ok(bits)
+}
+
+/// Returns the amplitudes for label bits (MSB first), see [amplitudes_to_label_bits()]
+pub fn label_bits_to_amplitudes(bits: &[u8], labeling: &[usize]) -> Result<Vec<usize>, AdEssError> {
+    let num_bits = num_label_bits(labeling)?;
+    if num_bits == 0 || !bits.len().is_multiple_of(num_bits) {
+        return Err(AdEssError::InvalidParameter(
+            "The number of bits must be a multiple of the label length",
+        ));
+    }
+    let mut w_idx_for_label = vec![0; labeling.len()];
+    for (w_idx, &label) in labeling.iter().enumerate() {
+        w_idx_for_label[label] = w_idx;
+    }
+    bits.chunks(num_bits)
+        .map(|label_bits| {
+            let label = label_bits.iter().try_fold(0, |label, &bit| match bit {
+                0 | 1 => Ok((label << 1) | bit as usize),
+                _ => Err(AdEssError::InvalidParameter("Bits must be 0 or 1")),
+            })?;
+            Ok(2 * w_idx_for_label[label] + 1)
+        })
+        .collect()
+}
+
+/// Returns (symbol, probability, bits) of all PAS symbols with non-zero probability
+///
+/// The sign is the first bit (1 for negative symbols), followed by the label bits (MSB first).
+fn pas_symbols(
+    amplitude_distribution: &[f32],
+    levels: &[f32],
+    labeling: &[usize],
+) -> Vec<(f64, f64, Vec<usize>)> {
+    let num_label_bits = amplitude_distribution.len().trailing_zeros() as usize;
+    [1.0, -1.0]
+        .iter()
+        .flat_map(|&sign| {
+            (0..amplitude_distribution.len()).map(move |w_idx| {
+                let bits = std::iter::once((sign < 0.0) as usize)
+                    .chain(
+                        (0..num_label_bits)
+                            .rev()
+                            .map(|bit| (labeling[w_idx] >> bit) & 1),
+                    )
+                    .collect();
+                (
+                    sign * levels[w_idx] as f64,
+                    amplitude_distribution[w_idx] as f64 / 2.0,
+                    bits,
+                )
+            })
+        })
+        .filter(|(_, p, _)| *p > 0.0)
+        .collect()
+}
+
+/// Returns an estimate of the BICM generalized mutual information (GMI) in bit/symbol
+///
+/// Probabilistic amplitude shaping (PAS) with an ASK constellation on a real AWGN channel with
+/// noise variance `noise_variance` is assumed. A symbol `x = s * levels[w_idx]` consists of a
+/// uniformly distributed sign `s` (first bit) and an amplitude with probability
+/// `amplitude_distribution[w_idx]` and label `labeling[w_idx]` (remaining bits).
+///
+/// The GMI `H(X) - sum_i H(B_i|Y)` of a bit-metric decoder is calculated by numerical integration.
+pub fn bicm_gmi(
+    amplitude_distribution: &[f32],
+    levels: &[f32],
+    labeling: &[usize],
+    noise_variance: f32,
+) -> f32 {
+    let num_amplitudes = amplitude_distribution.len();
+    assert!(
+        num_amplitudes.is_power_of_two() && levels.len() == num_amplitudes,
+        "Number of amplitudes must be a power of two with one level per amplitude!"
+    );
+    assert_eq!(
+        labeling.len(),
+        num_amplitudes,
+        "One label per amplitude required!"
+    );
+    let num_label_bits = num_amplitudes.trailing_zeros() as usize;
+    let symbols = pas_symbols(amplitude_distribution, levels, labeling);
+
+    let sigma = (noise_variance as f64).sqrt();
+    let y_max = levels.iter().fold(0f64, |a, &b| a.max(b as f64)) + 10.0 * sigma;
+    let step = sigma / 16.0;
+    let num_steps = (2.0 * y_max / step).ceil() as usize;
+    let normalization = step / (2.0 * std::f64::consts::PI * noise_variance as f64).sqrt();
+
+    let mut sum_bit_entropies = 0f64;
+    for k in 0..=num_steps {
+        let y = -y_max + k as f64 * step;
+        let likelihoods: Vec<f64> = symbols
+            .iter()
+            .map(|(x, p, _)| p * (-(y - x).powi(2) / (2.0 * noise_variance as f64)).exp())
+            .collect();
+        let total: f64 = likelihoods.iter().sum();
+        if total == 0.0 {
+            continue;
+        }
+        let mut bit_likelihoods = vec![[0f64; 2]; num_label_bits + 1];
+        for ((_, _, bits), likelihood) in symbols.iter().zip(&likelihoods) {
+            for (bit_idx, &bit) in bits.iter().enumerate() {
+                bit_likelihoods[bit_idx][bit] += likelihood;
+            }
+        }
+        // `likelihood * normalization` equals `p(x) * p(y|x) * dy`
+        for ((_, _, bits), &likelihood) in symbols.iter().zip(&likelihoods) {
+            if likelihood == 0.0 {
+                continue;
+            }
+            let log_bit_posteriors: f64 = bits
+                .iter()
+                .enumerate()
+                .map(|(bit_idx, &bit)| (bit_likelihoods[bit_idx][bit] / total).log2())
+                .sum();
+            sum_bit_entropies -= likelihood * normalization * log_bit_posteriors;
+        }
+    }
+
+    let symbol_entropy = utils::entropy(amplitude_distribution) + 1.0;
+    (symbol_entropy as f64 - sum_bit_entropies) as f32
+}
+
+/// Returns an error if `amplitude_distribution`, `levels` and `labeling` do not describe a PAS
+/// constellation as in [bicm_gmi()]
+fn check_constellation(
+    amplitude_distribution: &[f32],
+    levels: &[f32],
+    labeling: &[usize],
+) -> Result<(), AdEssError> {
+    let num_amplitudes = amplitude_distribution.len();
+    if !num_amplitudes.is_power_of_two()
+        || levels.len() != num_amplitudes
+        || labeling.len() != num_amplitudes
+    {
+        return Err(AdEssError::InvalidParameter(
+            "Number of amplitudes must be a power of two with one level and label each",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the LLRs `log(P(b=0|y) / P(b=1|y))` of all `num_bits` bits of the [pas_symbols()]
+/// for the received sample `y`
+fn symbol_llrs(
+    symbols: &[(f64, f64, Vec<usize>)],
+    num_bits: usize,
+    noise_variance: f64,
+    y: f64,
+) -> Vec<f64> {
+    // log-domain metrics to avoid underflow far from the symbols
+    let metrics: Vec<f64> = symbols
+        .iter()
+        .map(|(x, p, _)| p.ln() - (y - x).powi(2) / (2.0 * noise_variance))
+        .collect();
+    (0..num_bits)
+        .map(|bit_idx| {
+            let log_likelihood = |bit: usize| {
+                let bit_metrics = symbols
+                    .iter()
+                    .zip(&metrics)
+                    .filter(|((_, _, bits), _)| bits[bit_idx] == bit)
+                    .map(|(_, &metric)| metric);
+                let max = bit_metrics.clone().fold(f64::NEG_INFINITY, f64::max);
+                max + bit_metrics.map(|m| (m - max).exp()).sum::<f64>().ln()
+            };
+            log_likelihood(0) - log_likelihood(1)
+        })
+        .collect()
+}
+
+/// Returns the per-bit LLRs `log(P(b=0|y) / P(b=1|y))` of received PAM `samples`
+///
+/// The symbols and the channel are modeled as in [bicm_gmi()], i.e., the non-uniform prior
+/// `amplitude_distribution` (e.g., [AdEss::amplitude_distribution()]) is taken into account. The
+/// LLRs of a sample are consecutive in the returned vector, the sign bit comes first followed by
+/// the label bits (MSB first). Bits which cannot be 0 or 1 due to amplitudes with probability 0
+/// have infinite LLRs.
+pub fn llrs_for_samples(
+    amplitude_distribution: &[f32],
+    levels: &[f32],
+    labeling: &[usize],
+    noise_variance: f32,
+    samples: &[f32],
+) -> Result<Vec<f32>, AdEssError> {
+    check_constellation(amplitude_distribution, levels, labeling)?;
+    if noise_variance <= 0.0 {
+        return Err(AdEssError::InvalidParameter(
+            "The noise variance must be positive",
+        ));
+    }
+    let num_bits = amplitude_distribution.len().trailing_zeros() as usize + 1;
+    let symbols = pas_symbols(amplitude_distribution, levels, labeling);
+    Ok(samples
+        .iter()
+        .flat_map(|&y| symbol_llrs(&symbols, num_bits, noise_variance as f64, y as f64))
+        .map(|llr| llr as f32)
+        .collect())
+}
+
+/// Quantized per-bit LLR lookup table for fixed-point bit-metric decoders, see [llr_table()]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlrTable {
+    /// Sample value of the first table entry
+    pub y_min: f32,
+    /// Distance of the sample values of neighboring table entries
+    pub y_step: f32,
+    /// LLR value of one quantization step
+    pub llr_step: f32,
+    /// `llrs[entry][bit]` with the bit order of [bicm_gmi()]
+    pub llrs: Vec<Vec<i32>>,
+}
+
+impl LlrTable {
+    /// Returns the index of the table entry closest to the received `sample`
+    pub fn entry_for_sample(&self, sample: f32) -> usize {
+        let entry = ((sample - self.y_min) / self.y_step).round().max(0.0) as usize;
+        entry.min(self.llrs.len() - 1)
+    }
+    /// Returns the quantized LLRs of all bits for the received `sample`
+    pub fn llrs_for_sample(&self, sample: f32) -> &[i32] {
+        &self.llrs[self.entry_for_sample(sample)]
+    }
+}
+
+/// Returns a quantized LLR lookup table for PAS with a bit-metric decoder
+///
+/// The symbols and the channel are modeled as in [bicm_gmi()], the noise variance follows from
+/// `snr_db` and the average energy of `amplitude_distribution`. The LLR `log(P(b=0|y) / P(b=1|y))`
+/// of each bit is tabulated for `num_entries` equally spaced samples `y` covering all levels plus
+/// four noise standard deviations. The LLRs are quantized to signed `llr_bits` bit integers,
+/// the quantization step is chosen such that the largest magnitude in the table is representable.
+pub fn llr_table(
+    amplitude_distribution: &[f32],
+    levels: &[f32],
+    labeling: &[usize],
+    snr_db: f32,
+    num_entries: usize,
+    llr_bits: u32,
+) -> Result<LlrTable, AdEssError> {
+    check_constellation(amplitude_distribution, levels, labeling)?;
+    if num_entries < 2 || !(2..=32).contains(&llr_bits) {
+        return Err(AdEssError::InvalidParameter(
+            "At least two entries and 2 to 32 LLR bits are required",
+        ));
+    }
+    let num_bits = amplitude_distribution.len().trailing_zeros() as usize + 1;
+    let average_energy = utils::average_energy(amplitude_distribution, levels) as f64;
+    let noise_variance = average_energy / 10f64.powf(snr_db as f64 / 10.0);
+    let symbols = pas_symbols(amplitude_distribution, levels, labeling);
+
+    let y_max = levels.iter().fold(0f64, |a, &b| a.max(b as f64)) + 4.0 * noise_variance.sqrt();
+    let y_step = 2.0 * y_max / (num_entries - 1) as f64;
+    let llrs: Vec<Vec<f64>> = (0..num_entries)
+        .map(|entry| {
+            let y = -y_max + entry as f64 * y_step;
+            symbol_llrs(&symbols, num_bits, noise_variance, y)
+        })
+        .collect();
+
+    let max_quantized = ((1i64 << (llr_bits - 1)) - 1) as f64;
+    let max_llr = llrs
+        .iter()
+        .flatten()
+        .filter(|llr| llr.is_finite())
+        .fold(0f64, |a, &b| a.max(b.abs()));
+    let llr_step = if max_llr > 0.0 {
+        max_llr / max_quantized
+    } else {
+        1.0
+    };
+    Ok(LlrTable {
+        y_min: -y_max as f32,
+        y_step: y_step as f32,
+        llr_step: llr_step as f32,
+        llrs: llrs
+            .iter()
+            .map(|entry_llrs| {
+                entry_llrs
+                    .iter()
+                    .map(|llr| {
+                        (llr / llr_step)
+                            .round()
+                            .clamp(-max_quantized, max_quantized) as i32
+                    })
+                    .collect()
+            })
+            .collect(),
+    })
+}
+
+/// Returns the labeling that maximizes [bicm_gmi()] and the corresponding GMI
+///
+/// Starting from the Gray labeling, pairs of labels are swapped as long as the GMI increases.
+pub fn optimize_labeling(
+    amplitude_distribution: &[f32],
+    levels: &[f32],
+    noise_variance: f32,
+) -> (Vec<usize>, f32) {
+    let num_amplitudes = amplitude_distribution.len();
+    let mut labeling = gray_labeling(num_amplitudes);
+    let mut gmi = bicm_gmi(amplitude_distribution, levels, &labeling, noise_variance);
+
+    loop {
+        let mut best_swap = None;
+        let mut best_gmi = gmi;
+        for i in 0..num_amplitudes {
+            for j in i + 1..num_amplitudes {
+                labeling.swap(i, j);
+                let swapped_gmi =
+                    bicm_gmi(amplitude_distribution, levels, &labeling, noise_variance);
+                labeling.swap(i, j);
+                if swapped_gmi > best_gmi + 1e-6 {
+                    best_gmi = swapped_gmi;
+                    best_swap = Some((i, j));
+                }
+            }
+        }
+        match best_swap {
+            Some((i, j)) => {
+                labeling.swap(i, j);
+                gmi = best_gmi;
+            }
+            None => return (labeling, gmi),
+        }
+    }
+}
+
+/// Result of [optimize_shaping_and_labeling()]
+pub struct ShapingAndLabeling {
+    /// [AdEss] instance with the best GMI
+    pub adess: AdEss,
+    /// Target distribution of `adess`, see [AdEss::get_distribution()]
+    pub distribution: Vec<f32>,
+    /// Label of each amplitude, see [bicm_gmi()]
+    pub labeling: Vec<usize>,
+    /// GMI in bit/symbol
+    pub gmi: f32,
+}
+
+/// Jointly optimizes the shaped distribution and the amplitude labeling for PAS
+///
+/// `num_candidates` Maxwell-Boltzmann distributions on the amplitudes `1, 3, 5, ...` with
+/// average energies between the smallest and the uniform average energy are tested. For each
+/// candidate an [AdEss] encoding `num_bits` bits with `n_max` amplitudes is configured, see
+/// [AdEss::new_for_distribution_num_bits()], and the labeling is optimized for the resulting
+/// amplitude distribution with [optimize_labeling()]. The noise variance follows from `snr_db`
+/// and the average energy of each candidate.
+///
+/// The candidate with the largest GMI is returned.
+pub fn optimize_shaping_and_labeling(
+    n_max: usize,
+    num_amplitudes: usize,
+    num_bits: usize,
+    snr_db: f32,
+    res_factor: f32,
+    num_candidates: usize,
+) -> Result<ShapingAndLabeling, AdEssError> {
+    if !num_amplitudes.is_power_of_two() || num_amplitudes < 2 {
+        return Err(AdEssError::InvalidParameter(
+            "The number of amplitudes must be a power of two",
+        ));
+    }
+    let levels = utils::uniform_levels(num_amplitudes);
+    let uniform = vec![1.0 / num_amplitudes as f32; num_amplitudes];
+    let uniform_energy = utils::average_energy(&uniform, &levels);
+    let rate = num_bits as f32 / n_max as f32;
+
+    let mut best: Option<ShapingAndLabeling> = None;
+    for candidate in 1..=num_candidates {
+        let energy = 1.0 + (uniform_energy - 1.0) * candidate as f32 / num_candidates as f32;
+        let mb = utils::maxwell_boltzmann_for_energy(&levels, energy)
+            .map_err(AdEssError::InvalidParameter)?;
+        if utils::entropy(&mb) <= rate {
+            continue;
+        }
+        let (adess, distribution) =
+            match AdEss::new_for_distribution_num_bits(num_bits, n_max, &mb, res_factor) {
+                Ok(result) => result,
+                Err(_) => continue,
+            };
+        let amplitude_distribution = adess.amplitude_distribution();
+        let average_energy = utils::average_energy(&amplitude_distribution, &levels);
+        let noise_variance = average_energy / 10f32.powf(snr_db / 10.0);
+        let (labeling, gmi) = optimize_labeling(&amplitude_distribution, &levels, noise_variance);
+
+        let is_better = match &best {
+            Some(best) => gmi > best.gmi,
+            None => true,
+        };
+        if is_better {
+            best = Some(ShapingAndLabeling {
+                adess,
+                distribution,
+                labeling,
+                gmi,
+            });
+        }
+    }
+    best.ok_or(AdEssError::NoSolution(
+        "No candidate distribution supports the requested rate",
+    ))
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use crate::ad_ess::AdEss;
+use crate::bigint::Integer;
+
+/// Lattices whose points can be listed by [lattice_points()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lattice {
+    /// Cubic lattice with odd coordinates, i.e., `(2Z + 1)^dim` like the PAM amplitudes
+    Cubic,
+    /// Checkerboard lattice `D_dim`, i.e., integer points with even coordinate sum
+    Checkerboard,
+}
+
+/// Returns all points of `lattice` in `dim` dimensions with a squared norm up to `max_squared_norm`
+///
+/// The points are sorted by their squared norm, points with equal norm lexicographically.
+pub fn lattice_points(lattice: Lattice, dim: usize, max_squared_norm: u64) -> Vec<Vec<i32>> {
+    let radius = (max_squared_norm as f64).sqrt().floor() as i32;
+    let is_member = |point: &[i32]| match lattice {
+        Lattice::Cubic => point.iter().all(|x| x % 2 != 0),
+        Lattice::Checkerboard => point.iter().sum::<i32>() % 2 == 0,
+    };
+
+    let mut points = vec![];
+    let mut point = vec![-radius; dim];
+    loop {
+        if is_member(&point) && squared_norm(&point) <= max_squared_norm {
+            points.push(point.clone());
+        }
+        // next point of the cube [-radius, radius]^dim
+        match point.iter().rposition(|&x| x < radius) {
+            Some(pos) => {
+                point[pos] += 1;
+                point[pos + 1..].fill(-radius);
+            }
+            None => break,
+        }
+    }
+    points.sort_by_key(|point| (squared_norm(point), point.clone()));
+    points
+}
+
+/// Returns the squared euclidean norm of `point`
+fn squared_norm(point: &[i32]) -> u64 {
+    point.iter().map(|&x| (x as i64 * x as i64) as u64).sum()
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Shell enumeration of sequences of multidimensional lattice points
+///
+/// Each trellis stage chooses one point of a finite constituent constellation (e.g., the
+/// points of a lattice or the coset representatives of a sublattice). The weight of a point is
+/// its squared norm, shifted by the smallest squared norm and divided by the greatest common
+/// divisor of the differences. The sum weight is thus an exact measure of the energy and the
+/// trellis of [AdEss] holds all point sequences with an energy up to `max_energy`, i.e., the
+/// lattice points within a sphere of the `n_max * dim` dimensional space.
+pub struct LatticeShellShaper {
+    points: Vec<Vec<i32>>,
+    point_lookup: HashMap<Vec<i32>, usize>,
+    adess: AdEss,
+}
+
+impl LatticeShellShaper {
+    /// Returns a new [LatticeShellShaper] for the constituent constellation `points`
+    ///
+    /// All points must be distinct and have the same dimension, `max_energy` bounds the sum of the
+    /// squared norms of the `n_max` points of a sequence.
+    pub fn new(
+        points: &[Vec<i32>],
+        max_energy: u64,
+        n_max: usize,
+    ) -> Result<LatticeShellShaper, &'static str> {
+        let dim = points.first().ok_or("`points` must not be empty")?.len();
+        if points.iter().any(|point| point.len() != dim) {
+            return Err("All points must have the same dimension");
+        }
+        let point_lookup: HashMap<Vec<i32>, usize> = points
+            .iter()
+            .enumerate()
+            .map(|(idx, point)| (point.clone(), idx))
+            .collect();
+        if point_lookup.len() != points.len() {
+            return Err("`points` must be distinct");
+        }
+
+        let norms: Vec<u64> = points.iter().map(|point| squared_norm(point)).collect();
+        let min_norm = *norms.iter().min().expect("checked for empty points above");
+        let step = norms
+            .iter()
+            .fold(0, |acc, norm| gcd(acc, norm - min_norm))
+            .max(1);
+        let weights: Vec<usize> = norms
+            .iter()
+            .map(|norm| ((norm - min_norm) / step) as usize)
+            .collect();
+        let threshold = max_energy
+            .checked_sub(n_max as u64 * min_norm)
+            .ok_or("`max_energy` is below the smallest energy of a sequence")?
+            / step;
+
+        Ok(LatticeShellShaper {
+            points: points.to_vec(),
+            point_lookup,
+            adess: AdEss::new(threshold as usize, n_max, &weights),
+        })
+    }
+    /// Returns a new [LatticeShellShaper] for the points of `lattice` up to `max_squared_norm`
+    ///
+    /// See [lattice_points()] and [LatticeShellShaper::new()].
+    pub fn for_lattice(
+        lattice: Lattice,
+        dim: usize,
+        max_squared_norm: u64,
+        max_energy: u64,
+        n_max: usize,
+    ) -> Result<LatticeShellShaper, &'static str> {
+        LatticeShellShaper::new(
+            &lattice_points(lattice, dim, max_squared_norm),
+            max_energy,
+            n_max,
+        )
+    }
+    /// Returns the points of the constituent constellation
+    pub fn get_points(&self) -> Vec<Vec<i32>> {
+        self.points.clone()
+    }
+    /// Returns the number of dimensions of each point
+    pub fn dim(&self) -> usize {
+        self.points[0].len()
+    }
+    /// Returns the number of points per sequence
+    pub fn n_max(&self) -> usize {
+        self.adess.trellis.n_max
+    }
+    /// Returns the weight of each point used by the internal trellis
+    pub fn get_weights(&self) -> Vec<usize> {
+        self.adess.get_weights()
+    }
+    /// Returns the number of point sequences within the sphere
+    pub fn num_sequences(&self) -> Integer {
+        self.adess.num_sequences()
+    }
+    /// Returns the number of bits that can be encoded / decoded
+    pub fn num_bits(&self) -> u32 {
+        self.adess.num_bits()
+    }
+    /// Returns the point sequence for a given `index` (encode)
+    pub fn points_for_index(&self, index: &Integer) -> Vec<Vec<i32>> {
+        self.adess
+            .sequence_for_index(index)
+            .iter()
+            .map(|a| self.points[(a - 1) / 2].clone())
+            .collect()
+    }
+    /// Returns the index for a given `point_sequence` (decode)
+    ///
+    /// An error is returned if a point is not part of the constituent constellation.
+    pub fn index_for_points(&self, point_sequence: &[Vec<i32>]) -> Result<Integer, &'static str> {
+        let amplitude_sequence = point_sequence
+            .iter()
+            .map(|point| {
+                self.point_lookup
+                    .get(point)
+                    .map(|idx| 2 * idx + 1)
+                    .ok_or("Point is not part of the constellation")
+            })
+            .collect::<Result<Vec<usize>, &'static str>>()?;
+        Ok(self.adess.index_for_sequence(&amplitude_sequence))
+    }
+    /// Returns the probability of each point if indexes with [LatticeShellShaper::num_bits()]
+    /// bits are used equiprobably
+    pub fn point_distribution(&self) -> Vec<f32> {
+        self.adess.amplitude_distribution()
+    }
+    /// Returns the average energy (squared norm) per point
+    pub fn average_energy(&self) -> f32 {
+        self.point_distribution()
+            .iter()
+            .zip(self.points.iter())
+            .map(|(p, point)| p * squared_norm(point) as f32)
+            .sum()
+    }
+}
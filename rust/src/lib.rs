@@ -11,13 +11,101 @@
 
 /// Arbitrary-Distribution ESS
 pub mod ad_ess;
+/// Arbitrary precision integers and rationals of the `rug` or `num-bigint` backend
+pub mod bigint;
+/// Distribution matching with a binary output alphabet
+pub mod binary;
+/// AD-ESS with bounded-precision trellis values
+pub mod bounded_ess;
+/// Cancellation of long-running operations
+pub mod cancel;
+/// Mutual information and capacity-achieving amplitude distributions of the AWGN channel
+pub mod capacity;
+/// Constant composition distribution matching (CCDM) with arithmetic coding
+pub mod ccdm;
+/// Joint shaping of block groups carrying residual index information between blocks
+pub mod chained;
+/// [ad_ess::AdEss] with `u64` or `u128` node values
+pub mod compact;
+/// Comparison of rate loss, energy and complexity of all distribution matchers
+pub mod compare;
+/// Trellis size and construction effort estimation
+pub mod complexity;
+/// Dyadic distribution approximation and matching
+pub mod dyadic;
+/// Error type of the distribution matchers
+pub mod error;
+/// Export of metric rows to CSV (and Parquet)
+pub mod export;
+/// Hierarchical distribution matching (Hi-DM) composing an outer and an inner matcher
+pub mod hidm;
+/// Estimation of configurations from observed amplitude sequences
+pub mod inference;
+/// Amplitude labeling and joint shaping / labeling optimization for PAS
+pub mod labeling;
+/// Shell enumeration of multidimensional lattice constellations
+pub mod lattice;
+/// Common interface of all distribution matchers
+pub mod matcher;
+/// Import of NumPy `.npy` and `.npz` arrays
+pub mod npy;
+/// Probabilistic amplitude shaping (PAS) framing of payload bits into PAM / QAM symbols
+pub mod pas;
+/// Prefix-free code distribution matching
+pub mod pcdm;
+/// Multi-threaded encoding / decoding pipelines with bounded queues
+pub mod pipeline;
 /// Implementation of a trellis used in [ad_ess::AdEss] and [rts::RTS]
 pub mod trellis;
 pub mod trellis_utils;
+/// Node value types of a [trellis::Trellis], e.g., `u64` for small trellises
+pub mod trellis_value;
+/// Newtypes for amplitudes, weight indexes and weight levels
+///
+/// The encoding and decoding methods of [ad_ess::AdEss] and [trellis::Trellis] keep raw `usize`
+/// values, the newtypes are used by the typed accessors [trellis::Trellis::value()],
+/// [trellis::Trellis::successors()] and [trellis::Trellis::predecessors()] and by
+/// [matcher::DistributionMatcher::amplitudes_for_index()].
+pub mod types;
 pub mod utils;
 
+/// Ready-made configurations for common constellations, rates and blocklengths
+pub mod presets;
+/// AdEss configurations for multiple shaping rates
+pub mod rate_ladder;
+/// Cache of constructed trellises shared between matcher instances
+pub mod registry;
+/// Rate loss and shaping gain reports of trellis based distribution matchers
+pub mod report;
 /// Reverse Trellis Shaping
 pub mod rts;
+/// Stage by stage encoding and decoding like hardware implementations
+pub mod sequential;
+/// Shell mapping of the reverse trellis sequences with Laroia's ordering
+pub mod shell_mapping;
+/// End-to-end AWGN simulation of probabilistic amplitude shaping
+#[cfg(feature = "sim")]
+pub mod sim;
+/// Hard-decision slicing of received samples to amplitudes
+pub mod slicer;
+/// Statistics of sampled amplitude streams
+pub mod statistics;
+/// Streaming distribution matching across consecutive blocks
+pub mod streaming;
+/// Rate loss and energy sweeps over system parameters
+pub mod sweep;
+/// AD-ESS with per-stage thresholds (wedge-shaped trellis)
+pub mod wedge;
+/// Allocation-free encoding and decoding with reusable scratch memory
+pub mod workspace;
+
+// The encoders are used from thread pools, see [ad_ess::AdEss#thread-safety]
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ad_ess::AdEss>();
+    assert_send_sync::<rts::RTS>();
+    assert_send_sync::<trellis::Trellis>();
+};
 
 #[cfg(test)]
 mod tests;
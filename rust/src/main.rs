@@ -1,89 +1,123 @@
-use rug::Integer;
+use std::time::Duration;
 
 use ad_ess::ad_ess::AdEss;
-use ad_ess::trellis_utils;
-use ad_ess::utils::{entropy, information, kl_divergence};
+use ad_ess::presets;
+#[cfg(feature = "sim")]
+use ad_ess::sim;
+use ad_ess::utils::information;
 
 fn main() {
-    let reverse_trellis =
-        trellis_utils::reverse_trellis_upto_num_sequences(Integer::from(65), 4, &[0, 1, 3, 6])
-            .unwrap();
-    trellis_utils::pprint_trellis(&reverse_trellis);
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("presets") => {
+            for preset in presets::presets() {
+                println!("{}", preset.name);
+            }
+            return;
+        }
+        Some("preset") => {
+            let Some(name) = args.get(2) else {
+                eprintln!("Usage: {} preset <name>", args[0]);
+                std::process::exit(1);
+            };
+            let preset = match presets::preset_by_name(name) {
+                Ok(preset) => preset,
+                Err(msg) => {
+                    eprintln!("{msg}: {name}");
+                    std::process::exit(1);
+                }
+            };
+            match preset.build() {
+                Ok(adess) => profile_adess(&adess, &preset.distribution(), preset.res_factor),
+                Err(err) => {
+                    eprintln!("Failed to build preset {name}: {err}");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some("benchmark") => {
+            let Some(name) = args.get(2) else {
+                eprintln!("Usage: {} benchmark <preset> [seconds]", args[0]);
+                std::process::exit(1);
+            };
+            let seconds = args.get(3).map_or(Ok(1.0), |s| s.parse::<f64>());
+            let (Ok(adess), Ok(seconds)) = (presets::adess_for_preset(name), seconds) else {
+                eprintln!("Invalid preset or duration");
+                std::process::exit(1);
+            };
+            let result = adess.benchmark(Duration::from_secs_f64(seconds));
+            println!(
+                "encode: {:.0} blocks/s, {:.2} Mbit/s",
+                result.encode_blocks_per_second, result.encode_mbit_per_second
+            );
+            println!(
+                "decode: {:.0} blocks/s, {:.2} Mbit/s",
+                result.decode_blocks_per_second, result.decode_mbit_per_second
+            );
+            return;
+        }
+        #[cfg(feature = "sim")]
+        Some("simulate") => {
+            let Some(name) = args.get(2) else {
+                eprintln!(
+                    "Usage: {} simulate <preset> [snr_db] [blocks] [hard|soft]",
+                    args[0]
+                );
+                std::process::exit(1);
+            };
+            let snr_db = args.get(3).map_or(Ok(10.0), |s| s.parse::<f32>());
+            let num_blocks = args.get(4).map_or(Ok(1000), |s| s.parse::<usize>());
+            let demapping = match args.get(5).map(String::as_str) {
+                None | Some("hard") => Ok(sim::Demapping::Hard),
+                Some("soft") => Ok(sim::Demapping::Soft),
+                Some(_) => Err(()),
+            };
+            let (Ok(adess), Ok(snr_db), Ok(num_blocks), Ok(demapping)) = (
+                presets::adess_for_preset(name),
+                snr_db,
+                num_blocks,
+                demapping,
+            ) else {
+                eprintln!("Invalid preset or simulation parameters");
+                std::process::exit(1);
+            };
+            match sim::simulate_awgn(&adess, snr_db, num_blocks, demapping, 0) {
+                Ok(result) => println!("{result:#?}"),
+                Err(msg) => {
+                    eprintln!("{msg}");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        _ => {}
+    }
 
-    let original_distribution = vec![0.28, 0.3, 0.2, 0.22];
-    println!("{original_distribution:?}");
-    let n_max = 95;
-    let factor = 10.0;
-
-    let (adess, _) =
-        AdEss::new_for_distribution_num_bits(40, n_max, &original_distribution, factor).unwrap();
-    println!("Num bits: {}", adess.num_bits());
-
-    let optimal_threshold =
-        AdEss::optimal_threshold(n_max, &original_distribution, factor, 10, 0.5).unwrap();
-    println!("optimal threshold: {optimal_threshold}");
-
-    profile_adess(optimal_threshold, n_max, &original_distribution, factor);
+    eprintln!("Usage: {} <command> [args]", args[0]);
+    eprintln!("Commands:");
+    eprintln!("  presets                        list the preset names");
+    eprintln!("  preset <name>                  print metrics of a preset");
+    eprintln!("  benchmark <preset> [seconds]   measure the encoding and decoding throughput");
+    #[cfg(feature = "sim")]
+    eprintln!("  simulate <preset> [snr_db] [blocks] [hard|soft]   simulate PAS over AWGN");
+    std::process::exit(1);
 }
 
-fn profile_adess(threshold: usize, n_max: usize, original_distribution: &Vec<f32>, factor: f32) {
+fn profile_adess(adess: &AdEss, original_distribution: &[f32], factor: f32) {
     println!();
     println!("##########################################");
     println!("Profile AD_ESS");
     println!("##########################################");
     println!();
-    println!("Threshold: {threshold}");
+    println!("Threshold: {}", adess.trellis.threshold);
     println!();
 
-    let (adess, distribution) =
-        AdEss::new_for_distribution_threshold(threshold, n_max, original_distribution, factor)
-            .unwrap();
+    let distribution = adess.get_distribution(factor);
 
     println!("Goal distribution: {distribution:?}");
     println!("  Information: {:?} bit", information(&distribution));
     println!("  Trellis weights: {:?}", adess.trellis.get_weights());
-    println!("  Entropy: {} bit", entropy(&distribution));
-    println!();
-
-    println!(
-        "Amplitude distribution: {:?}",
-        adess.amplitude_distribution()
-    );
-    println!(
-        "  Information: {:?} bit",
-        information(&adess.amplitude_distribution())
-    );
-    println!(
-        "  Entropy: {} bit",
-        entropy(&adess.amplitude_distribution())
-    );
-    println!();
-    println!(
-        "KL-divergence (goal to final):     {}",
-        kl_divergence(&distribution, &adess.amplitude_distribution())
-    );
-    println!(
-        "KL-divergence (original to final): {}",
-        kl_divergence(original_distribution, &adess.amplitude_distribution())
-    );
     println!();
-    println!(
-        "Storage complexity: {:?}",
-        adess.trellis.get_storage_dimensions()
-    );
-    println!(
-        "Num sequences: 2^{:?}",
-        adess.num_sequences().to_f64().log2()
-    );
-    println!(
-        "Shaping rate: {} bit/amplitude",
-        adess.num_bits() as f32 / n_max as f32
-    );
-    println!();
-
-    println!(
-        "Mutual information loss: {} bit/channel use",
-        entropy(original_distribution) - adess.num_bits() as f32 / n_max as f32
-            + kl_divergence(&adess.amplitude_distribution(), original_distribution)
-    );
+    print!("{}", adess.analyze(original_distribution));
 }
@@ -0,0 +1,159 @@
+use crate::ad_ess::AdEss;
+use crate::bigint::Integer;
+use crate::error::AdEssError;
+use crate::rts::RTS;
+use crate::types::{self, Amplitude};
+use crate::utils;
+use crate::wedge::WedgeAdEss;
+
+/// Common interface of all block based distribution matchers
+///
+/// A distribution matcher maps indexes with [DistributionMatcher::num_bits()] bits to
+/// amplitude sequences of length [DistributionMatcher::n_max()] and back.
+pub trait DistributionMatcher {
+    /// Returns the number of amplitudes per sequence
+    fn n_max(&self) -> usize;
+    /// Returns the number of bits that can be encoded / decoded
+    fn num_bits(&self) -> u32;
+    /// Returns the amplitude sequence for a given `index` (encode)
+    fn sequence_for_index(&self, index: &Integer) -> Vec<usize>;
+    /// Returns the index for a given `amplitude_sequence` (decode)
+    fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer;
+    /// Returns the amplitude distribution if indexes with [DistributionMatcher::num_bits()]
+    /// bits are used equiprobably
+    fn amplitude_distribution(&self) -> Vec<f32>;
+    /// Returns the number of occurences of each amplitude in the sequences with the indexes
+    /// `0..num_indexes` or `None` if the matcher can not count them
+    ///
+    /// Used for the exact amplitude distribution of index ranges, e.g., in
+    /// [HiDm](crate::hidm::HiDm).
+    fn amplitude_counts_below(&self, _num_indexes: &Integer) -> Option<Vec<Integer>> {
+        None
+    }
+
+    /// Returns the index for a received `amplitude_sequence` (decode) or an error if the
+    /// sequence is not in the trellis
+    ///
+    /// By default only the length is checked, matchers which validate sequences (e.g.,
+    /// [AdEss::try_index_for_sequence()]) override this method.
+    fn try_index_for_sequence(&self, amplitude_sequence: &[usize]) -> Result<Integer, AdEssError> {
+        if amplitude_sequence.len() != self.n_max() {
+            return Err(AdEssError::InvalidSequence(
+                "The amplitude sequence must have `n_max` amplitudes",
+            ));
+        }
+        Ok(self.index_for_sequence(amplitude_sequence))
+    }
+
+    /// Returns the typed amplitude sequence for a given `index` (encode)
+    fn amplitudes_for_index(&self, index: &Integer) -> Vec<Amplitude> {
+        self.sequence_for_index(index)
+            .into_iter()
+            .map(Amplitude)
+            .collect()
+    }
+    /// Returns the index for a given typed `amplitude_sequence` (decode)
+    ///
+    /// An error is returned if the sequence does not have [DistributionMatcher::n_max()]
+    /// amplitudes.
+    fn index_for_amplitudes(
+        &self,
+        amplitude_sequence: &[Amplitude],
+    ) -> Result<Integer, AdEssError> {
+        if amplitude_sequence.len() != self.n_max() {
+            return Err(AdEssError::InvalidSequence(
+                "The amplitude sequence must have `n_max` amplitudes",
+            ));
+        }
+        Ok(self.index_for_sequence(&types::amplitude_values(amplitude_sequence)))
+    }
+    /// Returns the shaping rate in bit/amplitude
+    fn rate(&self) -> f32 {
+        self.num_bits() as f32 / self.n_max() as f32
+    }
+    /// Returns the average energy per amplitude
+    fn average_energy(&self) -> f32 {
+        let amplitude_distribution = self.amplitude_distribution();
+        let levels = utils::uniform_levels(amplitude_distribution.len());
+        utils::average_energy(&amplitude_distribution, &levels)
+    }
+    /// Returns the average energy per amplitude for non-uniformly spaced amplitudes
+    ///
+    /// `levels[w_idx]` is the value of the amplitude with weight index `w_idx`, i.e., the
+    /// amplitude `2 * w_idx + 1` is transmitted with the level `levels[w_idx]`.
+    fn average_energy_for_levels(&self, levels: &[f32]) -> f32 {
+        utils::average_energy(&self.amplitude_distribution(), levels)
+    }
+    /// Returns the sequence of amplitude levels for a given `index` (encode)
+    ///
+    /// See [DistributionMatcher::average_energy_for_levels()] for the meaning of `levels`.
+    fn levels_for_index(&self, index: &Integer, levels: &[f32]) -> Vec<f32> {
+        utils::amplitude_seq_to_levels(&self.sequence_for_index(index), levels)
+    }
+}
+
+impl DistributionMatcher for AdEss {
+    fn n_max(&self) -> usize {
+        self.trellis.n_max
+    }
+    fn num_bits(&self) -> u32 {
+        AdEss::num_bits(self)
+    }
+    fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        AdEss::sequence_for_index(self, index)
+    }
+    fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        AdEss::index_for_sequence(self, amplitude_sequence)
+    }
+    fn try_index_for_sequence(&self, amplitude_sequence: &[usize]) -> Result<Integer, AdEssError> {
+        AdEss::try_index_for_sequence(self, amplitude_sequence)
+    }
+    fn amplitude_distribution(&self) -> Vec<f32> {
+        AdEss::amplitude_distribution(self)
+    }
+    fn amplitude_counts_below(&self, num_indexes: &Integer) -> Option<Vec<Integer>> {
+        Some(AdEss::amplitude_counts_below(self, num_indexes))
+    }
+}
+
+impl DistributionMatcher for RTS {
+    fn n_max(&self) -> usize {
+        self.trellis.n_max
+    }
+    fn num_bits(&self) -> u32 {
+        RTS::num_bits(self)
+    }
+    fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        RTS::sequence_for_index(self, index)
+    }
+    fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        RTS::index_for_sequence(self, amplitude_sequence)
+    }
+    fn try_index_for_sequence(&self, amplitude_sequence: &[usize]) -> Result<Integer, AdEssError> {
+        RTS::try_index_for_sequence(self, amplitude_sequence)
+    }
+    fn amplitude_distribution(&self) -> Vec<f32> {
+        RTS::amplitude_distribution(self)
+    }
+    fn amplitude_counts_below(&self, num_indexes: &Integer) -> Option<Vec<Integer>> {
+        Some(RTS::amplitude_counts_below(self, num_indexes))
+    }
+}
+
+impl DistributionMatcher for WedgeAdEss {
+    fn n_max(&self) -> usize {
+        WedgeAdEss::n_max(self)
+    }
+    fn num_bits(&self) -> u32 {
+        WedgeAdEss::num_bits(self)
+    }
+    fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        WedgeAdEss::sequence_for_index(self, index)
+    }
+    fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        WedgeAdEss::index_for_sequence(self, amplitude_sequence)
+    }
+    fn amplitude_distribution(&self) -> Vec<f32> {
+        WedgeAdEss::amplitude_distribution(self)
+    }
+}
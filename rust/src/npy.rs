@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::bigint::Integer;
+
+const NPY_MAGIC: &[u8; 6] = b"\x93NUMPY";
+const ZIP_LOCAL_HEADER: u32 = 0x04034b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x02014b50;
+const ZIP_END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+
+/// Integer array read from a NumPy `.npy` file
+///
+/// The values are stored in C order. Integer dtypes of up to 64 bits and unicode string arrays
+/// holding decimal numbers are supported, the latter allow values which exceed 64 bits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpyArray {
+    pub shape: Vec<usize>,
+    pub values: Vec<Integer>,
+}
+
+impl NpyArray {
+    /// Returns the rows of a two dimensional array
+    pub fn rows(&self) -> Result<Vec<Vec<Integer>>, &'static str> {
+        match self.shape[..] {
+            [_, num_cols] if num_cols > 0 => Ok(self
+                .values
+                .chunks(num_cols)
+                .map(|row| row.to_vec())
+                .collect()),
+            [num_rows, 0] => Ok(vec![vec![]; num_rows]),
+            _ => Err("Two dimensional array required"),
+        }
+    }
+    /// Returns the values as `usize`
+    pub fn to_usize(&self) -> Result<Vec<usize>, &'static str> {
+        self.values
+            .iter()
+            .map(|value| value.to_usize().ok_or("Value does not fit into usize"))
+            .collect()
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Returns the value of `key` in the Python dict literal `header`
+fn header_value<'a>(header: &'a str, key: &str) -> io::Result<&'a str> {
+    let key = format!("'{key}':");
+    let start = header
+        .find(&key)
+        .ok_or_else(|| invalid_data("Incomplete npy header"))?
+        + key.len();
+    let value = header[start..].trim_start();
+    let end = if value.starts_with('(') {
+        value.find(')').map(|end| end + 1)
+    } else {
+        value.find([',', '}'])
+    }
+    .ok_or_else(|| invalid_data("Invalid npy header"))?;
+    Ok(value[..end].trim())
+}
+
+/// Reads an array in the NumPy `.npy` format from `reader`
+pub fn read_npy(mut reader: impl Read) -> io::Result<NpyArray> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic[..6] != NPY_MAGIC {
+        return Err(invalid_data("No npy data"));
+    }
+    let header_len = if magic[6] == 1 {
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        u16::from_le_bytes(len) as usize
+    } else {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        u32::from_le_bytes(len) as usize
+    };
+    let mut header = vec![0u8; header_len];
+    reader.read_exact(&mut header)?;
+    let header = String::from_utf8_lossy(&header);
+
+    if header_value(&header, "fortran_order")? != "False" {
+        return Err(invalid_data("Fortran order is not supported"));
+    }
+    let shape = header_value(&header, "shape")?
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse().map_err(|_| invalid_data("Invalid shape")))
+        .collect::<io::Result<Vec<usize>>>()?;
+    let num_values: usize = shape.iter().product();
+
+    let descr = header_value(&header, "descr")?.trim_matches('\'');
+    if descr.len() < 3 || !descr.is_ascii() {
+        return Err(invalid_data("Invalid dtype"));
+    }
+    let (byte_order, kind, size) = (&descr[..1], &descr[1..2], &descr[2..]);
+    let size: usize = size.parse().map_err(|_| invalid_data("Invalid dtype"))?;
+    if byte_order == ">" && size > 1 {
+        return Err(invalid_data("Big endian data is not supported"));
+    }
+    let item_bytes = match kind {
+        "i" | "u" if size <= 8 => size,
+        "U" => 4 * size,
+        _ => return Err(invalid_data("Unsupported dtype")),
+    };
+
+    let mut data = vec![0u8; num_values * item_bytes];
+    reader.read_exact(&mut data)?;
+    let values = data
+        .chunks(item_bytes)
+        .map(|item| match kind {
+            "i" => {
+                // sign extension to 64 bits
+                let fill = if item[size - 1] & 0x80 != 0 { 0xff } else { 0 };
+                let mut bytes = [fill; 8];
+                bytes[..size].copy_from_slice(item);
+                Ok(Integer::from(i64::from_le_bytes(bytes)))
+            }
+            "u" => {
+                let mut bytes = [0u8; 8];
+                bytes[..size].copy_from_slice(item);
+                Ok(Integer::from(u64::from_le_bytes(bytes)))
+            }
+            _ => {
+                let string: String = item
+                    .chunks(4)
+                    .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                    .take_while(|&c| c != 0)
+                    .filter_map(char::from_u32)
+                    .collect();
+                Integer::from_str_radix(string.trim(), 10)
+                    .map_err(|_| invalid_data("String is no decimal number"))
+            }
+        })
+        .collect::<io::Result<Vec<Integer>>>()?;
+    Ok(NpyArray { shape, values })
+}
+
+/// Reads the array in the NumPy `.npy` file at `path`
+pub fn load_npy(path: impl AsRef<Path>) -> io::Result<NpyArray> {
+    read_npy(io::BufReader::new(fs::File::open(path)?))
+}
+
+/// Reads all arrays in the NumPy `.npz` file at `path`, keyed by their names
+///
+/// Only uncompressed archives as written by `numpy.savez()` are supported.
+pub fn load_npz(path: impl AsRef<Path>) -> io::Result<HashMap<String, NpyArray>> {
+    let archive = fs::read(path)?;
+    let u16_at = |pos: usize| -> io::Result<usize> {
+        archive
+            .get(pos..pos + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or_else(|| invalid_data("Truncated zip archive"))
+    };
+    let u32_at = |pos: usize| -> io::Result<u32> {
+        archive
+            .get(pos..pos + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| invalid_data("Truncated zip archive"))
+    };
+
+    let end_of_central_directory = (0..archive.len().saturating_sub(21))
+        .rev()
+        .find(|&pos| u32_at(pos).ok() == Some(ZIP_END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(|| invalid_data("No zip archive"))?;
+    let num_entries = u16_at(end_of_central_directory + 10)?;
+    let mut pos = u32_at(end_of_central_directory + 16)? as usize;
+
+    let mut arrays = HashMap::new();
+    for _ in 0..num_entries {
+        if u32_at(pos)? != ZIP_CENTRAL_HEADER {
+            return Err(invalid_data("Invalid zip central directory"));
+        }
+        if u16_at(pos + 10)? != 0 {
+            return Err(invalid_data("Compressed npz files are not supported"));
+        }
+        let size = u32_at(pos + 20)?;
+        let local_header = u32_at(pos + 42)?;
+        if size == u32::MAX || local_header == u32::MAX {
+            return Err(invalid_data("Zip64 archives are not supported"));
+        }
+        let name_len = u16_at(pos + 28)?;
+        let name = archive
+            .get(pos + 46..pos + 46 + name_len)
+            .ok_or_else(|| invalid_data("Truncated zip archive"))?;
+        let name = String::from_utf8_lossy(name);
+        pos += 46 + name_len + u16_at(pos + 30)? + u16_at(pos + 32)?;
+
+        let local_header = local_header as usize;
+        if u32_at(local_header)? != ZIP_LOCAL_HEADER {
+            return Err(invalid_data("Invalid zip local header"));
+        }
+        let start = local_header + 30 + u16_at(local_header + 26)? + u16_at(local_header + 28)?;
+        let data = archive
+            .get(start..start + size as usize)
+            .ok_or_else(|| invalid_data("Truncated zip archive"))?;
+        let name = name.strip_suffix(".npy").unwrap_or(&name).to_string();
+        arrays.insert(name, read_npy(data)?);
+    }
+    Ok(arrays)
+}
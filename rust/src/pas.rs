@@ -0,0 +1,278 @@
+use crate::error::AdEssError;
+use crate::labeling;
+use crate::matcher::DistributionMatcher;
+use crate::slicer::Slicer;
+use crate::utils;
+
+/// Returns the PAM symbols `(-1)^sign_bits[n] * levels[w_idx]` of the amplitudes `2 * w_idx + 1`
+///
+/// A sign bit 1 selects the negative symbol, as in [bicm_gmi()](crate::labeling::bicm_gmi).
+pub fn symbols_for_amplitudes(
+    amplitudes: &[usize],
+    sign_bits: &[u8],
+    levels: &[f32],
+) -> Result<Vec<f32>, AdEssError> {
+    if amplitudes.len() != sign_bits.len() {
+        return Err(AdEssError::InvalidParameter(
+            "One sign bit per amplitude required",
+        ));
+    }
+    amplitudes
+        .iter()
+        .zip(sign_bits)
+        .map(|(&amplitude, &sign_bit)| {
+            if amplitude.is_multiple_of(2) || (amplitude - 1) / 2 >= levels.len() {
+                return Err(AdEssError::InvalidSequence("Amplitude without level"));
+            }
+            let level = levels[(amplitude - 1) / 2];
+            match sign_bit {
+                0 => Ok(level),
+                1 => Ok(-level),
+                _ => Err(AdEssError::InvalidParameter("Bits must be 0 or 1")),
+            }
+        })
+        .collect()
+}
+
+/// Returns the amplitudes and sign bits of (noisy) PAM `symbols`, see [symbols_for_amplitudes()]
+///
+/// The amplitudes are decided by `slicer`, the sign bits by the sign of the symbols.
+pub fn amplitudes_for_symbols(symbols: &[f32], slicer: &Slicer) -> (Vec<usize>, Vec<u8>) {
+    let sign_bits = symbols
+        .iter()
+        .map(|symbol| symbol.is_sign_negative() as u8)
+        .collect();
+    (slicer.sequence_for_samples(symbols), sign_bits)
+}
+
+/// Probabilistic amplitude shaping (PAS) framing of a [DistributionMatcher]
+///
+/// A payload of [PasFramer::payload_bits()] bits consists of the `num_bits` shaping index bits
+/// (most significant bit first) followed by `n_max` uniformly distributed sign bits. The index is
+/// encoded into `n_max` shaped amplitudes which are combined with the signs into PAM symbols, see
+/// [symbols_for_amplitudes()]. For QAM, consecutive PAM symbols are the in-phase and quadrature
+/// components.
+///
+/// In a complete PAS system the sign bits are (partly) the parity bits of a systematic FEC code
+/// over the amplitude labels, see [crate::labeling].
+pub struct PasFramer<'a, M: DistributionMatcher> {
+    matcher: &'a M,
+    levels: Vec<f32>,
+    slicer: Slicer,
+}
+
+impl<'a, M: DistributionMatcher> PasFramer<'a, M> {
+    /// Returns a [PasFramer] with the uniformly spaced levels `1, 3, 5, ...`
+    pub fn new(matcher: &'a M) -> Result<PasFramer<'a, M>, AdEssError> {
+        let num_amplitudes = matcher.amplitude_distribution().len();
+        PasFramer::with_levels(matcher, &utils::uniform_levels(num_amplitudes))
+    }
+    /// Returns a [PasFramer] transmitting the amplitude `2 * w_idx + 1` with `levels[w_idx]`
+    ///
+    /// The levels must be strictly increasing and positive, the received symbols are decided
+    /// by a minimum distance [Slicer].
+    pub fn with_levels(matcher: &'a M, levels: &[f32]) -> Result<PasFramer<'a, M>, AdEssError> {
+        if levels.len() < matcher.amplitude_distribution().len() {
+            return Err(AdEssError::InvalidParameter(
+                "One level per amplitude required",
+            ));
+        }
+        Ok(PasFramer {
+            matcher,
+            levels: levels.to_vec(),
+            slicer: Slicer::for_levels(levels).map_err(AdEssError::InvalidParameter)?,
+        })
+    }
+}
+
+impl<M: DistributionMatcher> PasFramer<'_, M> {
+    /// Returns the number of payload bits per frame (`num_bits + n_max`)
+    pub fn payload_bits(&self) -> usize {
+        self.matcher.num_bits() as usize + self.matcher.n_max()
+    }
+    /// Returns the payload bits per PAM symbol
+    pub fn rate(&self) -> f32 {
+        self.payload_bits() as f32 / self.matcher.n_max() as f32
+    }
+    /// Splits `payload` into the shaping index bits and the sign bits
+    pub fn split_payload<'b>(&self, payload: &'b [u8]) -> Result<(&'b [u8], &'b [u8]), AdEssError> {
+        if payload.len() != self.payload_bits() {
+            return Err(AdEssError::InvalidParameter(
+                "`payload` must have `payload_bits` bits",
+            ));
+        }
+        if payload.iter().any(|&bit| bit > 1) {
+            return Err(AdEssError::InvalidParameter("Bits must be 0 or 1"));
+        }
+        Ok(payload.split_at(self.matcher.num_bits() as usize))
+    }
+    /// Returns the `n_max` PAM symbols for `payload` (encode)
+    pub fn symbols_for_payload(&self, payload: &[u8]) -> Result<Vec<f32>, AdEssError> {
+        let (index_bits, sign_bits) = self.split_payload(payload)?;
+        let amplitudes = self
+            .matcher
+            .sequence_for_index(&utils::bits_to_integer(index_bits));
+        symbols_for_amplitudes(&amplitudes, sign_bits, &self.levels)
+    }
+    /// Returns the payload for `n_max` (noisy) PAM symbols (decode)
+    ///
+    /// An error is returned if the decided amplitudes (e.g., due to channel errors) are rejected
+    /// by [DistributionMatcher::try_index_for_sequence()] or their index exceeds `num_bits` bits.
+    pub fn payload_for_symbols(&self, symbols: &[f32]) -> Result<Vec<u8>, AdEssError> {
+        if symbols.len() != self.matcher.n_max() {
+            return Err(AdEssError::InvalidParameter(
+                "`symbols` must have `n_max` entries",
+            ));
+        }
+        let (amplitudes, sign_bits) = amplitudes_for_symbols(symbols, &self.slicer);
+        self.payload_for_amplitudes(&amplitudes, &sign_bits)
+    }
+    /// Returns the payload for `n_max` decided amplitudes and sign bits (decode), see
+    /// [PasFramer::payload_for_symbols()]
+    pub fn payload_for_amplitudes(
+        &self,
+        amplitudes: &[usize],
+        sign_bits: &[u8],
+    ) -> Result<Vec<u8>, AdEssError> {
+        if sign_bits.len() != amplitudes.len() {
+            return Err(AdEssError::InvalidParameter(
+                "One sign bit per amplitude required",
+            ));
+        }
+        let num_bits = self.matcher.num_bits();
+        let index = self.matcher.try_index_for_sequence(amplitudes)?;
+        if index.significant_bits() > num_bits {
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        let mut payload = utils::integer_to_bits(&index, num_bits as usize);
+        payload.extend_from_slice(sign_bits);
+        Ok(payload)
+    }
+    /// Returns the PAM levels of the amplitudes
+    pub fn get_levels(&self) -> Vec<f32> {
+        self.levels.clone()
+    }
+}
+
+/// Complex QAM symbol with the in-phase component `re` and the quadrature component `im`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct QamSymbol {
+    /// In-phase component
+    pub re: f32,
+    /// Quadrature component
+    pub im: f32,
+}
+
+/// Square QAM mapper with a configurable bit labeling of the amplitudes
+///
+/// Each QAM symbol consists of two PAS PAM symbols, the in-phase and quadrature components are
+/// consecutive entries of the amplitude and sign sequences. The bits of a component are the
+/// sign bit (1 for negative values) followed by the amplitude label (MSB first) as in
+/// [bicm_gmi()](crate::labeling::bicm_gmi), the in-phase bits precede the quadrature bits. With
+/// [gray_labeling()](crate::labeling::gray_labeling) of the amplitudes, the components are Gray
+/// labeled PAM constellations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QamMapper {
+    labeling: Vec<usize>,
+    levels: Vec<f32>,
+    slicer: Slicer,
+}
+
+impl QamMapper {
+    /// Returns a Gray labeled [QamMapper] with the uniformly spaced levels `1, 3, 5, ...`
+    pub fn new(num_amplitudes: usize) -> Result<QamMapper, AdEssError> {
+        QamMapper::with_labeling(
+            &labeling::gray_labeling(num_amplitudes),
+            &utils::uniform_levels(num_amplitudes),
+        )
+    }
+    /// Returns a [QamMapper] transmitting the amplitude `2 * w_idx + 1` with `levels[w_idx]` and
+    /// the label `labeling[w_idx]`
+    ///
+    /// `labeling` must be a permutation of `0..2^m` with `m >= 1`, the levels must be strictly
+    /// increasing and positive.
+    pub fn with_labeling(labeling: &[usize], levels: &[f32]) -> Result<QamMapper, AdEssError> {
+        if labeling::num_label_bits(labeling)? == 0 {
+            return Err(AdEssError::InvalidParameter(
+                "At least two amplitudes required",
+            ));
+        }
+        if levels.len() != labeling.len() {
+            return Err(AdEssError::InvalidParameter("One level per label required"));
+        }
+        Ok(QamMapper {
+            labeling: labeling.to_vec(),
+            levels: levels.to_vec(),
+            slicer: Slicer::for_levels(levels).map_err(AdEssError::InvalidParameter)?,
+        })
+    }
+    /// Returns the label of each amplitude
+    pub fn get_labeling(&self) -> Vec<usize> {
+        self.labeling.clone()
+    }
+    /// Returns the number of bits per QAM symbol
+    pub fn bits_per_symbol(&self) -> usize {
+        2 * (1 + labeling::num_label_bits(&self.labeling).unwrap())
+    }
+    /// Returns the QAM symbols of pairs of consecutive amplitudes and sign bits
+    pub fn symbols_for_amplitudes(
+        &self,
+        amplitudes: &[usize],
+        sign_bits: &[u8],
+    ) -> Result<Vec<QamSymbol>, AdEssError> {
+        if !amplitudes.len().is_multiple_of(2) {
+            return Err(AdEssError::InvalidParameter(
+                "An even number of amplitudes is required",
+            ));
+        }
+        let values = symbols_for_amplitudes(amplitudes, sign_bits, &self.levels)?;
+        Ok(values
+            .chunks(2)
+            .map(|pair| QamSymbol {
+                re: pair[0],
+                im: pair[1],
+            })
+            .collect())
+    }
+    /// Returns the amplitudes and sign bits of (noisy) QAM `symbols` (hard decision), see
+    /// [QamMapper::symbols_for_amplitudes()]
+    pub fn amplitudes_for_symbols(&self, symbols: &[QamSymbol]) -> (Vec<usize>, Vec<u8>) {
+        let values: Vec<f32> = symbols
+            .iter()
+            .flat_map(|symbol| [symbol.re, symbol.im])
+            .collect();
+        amplitudes_for_symbols(&values, &self.slicer)
+    }
+    /// Returns the QAM symbols for `bits`, [QamMapper::bits_per_symbol()] bits per symbol
+    pub fn symbols_for_bits(&self, bits: &[u8]) -> Result<Vec<QamSymbol>, AdEssError> {
+        if !bits.len().is_multiple_of(self.bits_per_symbol()) {
+            return Err(AdEssError::InvalidParameter(
+                "The number of bits must be a multiple of `bits_per_symbol`",
+            ));
+        }
+        let bits_per_component = self.bits_per_symbol() / 2;
+        let sign_bits: Vec<u8> = bits.iter().step_by(bits_per_component).copied().collect();
+        let label_bits: Vec<u8> = bits
+            .chunks(bits_per_component)
+            .flat_map(|component_bits| &component_bits[1..])
+            .copied()
+            .collect();
+        let amplitudes = labeling::label_bits_to_amplitudes(&label_bits, &self.labeling)?;
+        self.symbols_for_amplitudes(&amplitudes, &sign_bits)
+    }
+    /// Returns the bits of (noisy) QAM `symbols` (hard decision), see
+    /// [QamMapper::symbols_for_bits()]
+    pub fn bits_for_symbols(&self, symbols: &[QamSymbol]) -> Vec<u8> {
+        let (amplitudes, sign_bits) = self.amplitudes_for_symbols(symbols);
+        let label_bits = labeling::amplitudes_to_label_bits(&amplitudes, &self.labeling)
+            .expect("The slicer only decides labeled amplitudes");
+        let num_label_bits = self.bits_per_symbol() / 2 - 1;
+        sign_bits
+            .iter()
+            .zip(label_bits.chunks(num_label_bits))
+            .flat_map(|(&sign_bit, component_label_bits)| {
+                std::iter::once(sign_bit).chain(component_label_bits.iter().copied())
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,275 @@
+use crate::bigint::Integer;
+use crate::dyadic::DyadicMatcher;
+use crate::matcher::DistributionMatcher;
+use crate::utils::{bits_to_integer, integer_to_bits};
+
+/// Prefix-free code distribution matcher (PCDM) with fixed-length framing
+///
+/// The bits of an index are parsed with the prefix-free code of a [DyadicMatcher]. As the number
+/// of parsed bits varies, every frame of `n_max` amplitudes ends with a termination phase, see
+/// G. Böcherer, F. Steiner and P. Schulte, "Bandwidth efficient and rate-matched low-density
+/// parity-check coded modulation," IEEE Trans. Commun., vol. 63, no. 12, pp. 4651-4665, 2015,
+/// doi: 10.1109/TCOMM.2015.2494016.
+///
+/// Before each amplitude, encoder and decoder compare the number of remaining bits `b` with the
+/// number of remaining amplitudes `r`. As long as `b <= l_min + (r - 1) * t` holds (`l_min` is the
+/// shortest codeword length), the prefix-free code is used and missing bits at the end of the
+/// index are filled with zeros. Otherwise the frame is terminated: each of the remaining
+/// amplitudes carries `t` bits using a uniform code over the `2^t` smallest used amplitudes.
+pub struct PCDM {
+    code: DyadicMatcher,
+    codewords: Vec<Option<Vec<u8>>>,
+    termination_amplitudes: Vec<usize>,
+    termination_bits: usize,
+    n_max: usize,
+    num_bits: u32,
+}
+
+impl PCDM {
+    /// Returns a new [PCDM] instance for the dyadic approximation of `distribution`
+    ///
+    /// - `n_max`: number of amplitudes per frame
+    /// - `num_bits`: number of bits per frame, at most `n_max * t` where `2^t` amplitudes can be
+    ///   used for termination
+    pub fn new(distribution: &[f32], n_max: usize, num_bits: u32) -> Result<PCDM, &'static str> {
+        PCDM::from_dyadic_matcher(DyadicMatcher::new(distribution)?, n_max, num_bits)
+    }
+
+    /// Returns a new [PCDM] instance using the prefix-free code of `code`
+    pub fn from_dyadic_matcher(
+        code: DyadicMatcher,
+        n_max: usize,
+        num_bits: u32,
+    ) -> Result<PCDM, &'static str> {
+        if n_max == 0 {
+            return Err("`n_max` must be at least 1");
+        }
+        let codewords = code.get_codewords();
+        let used_weight_idxs: Vec<usize> = codewords
+            .iter()
+            .enumerate()
+            .filter(|(_, codeword)| codeword.is_some())
+            .map(|(w_idx, _)| w_idx)
+            .collect();
+        let termination_bits = (usize::BITS - 1 - used_weight_idxs.len().leading_zeros()) as usize;
+        if num_bits as usize > n_max * termination_bits {
+            return Err("`num_bits` exceeds the capacity of the termination code");
+        }
+        let termination_amplitudes = used_weight_idxs[..1 << termination_bits]
+            .iter()
+            .map(|&w_idx| PCDM::weight_idx_to_amplitude(w_idx))
+            .collect();
+
+        Ok(PCDM {
+            code,
+            codewords,
+            termination_amplitudes,
+            termination_bits,
+            n_max,
+            num_bits,
+        })
+    }
+
+    /// Returns the amplitude value for a given weight index
+    fn weight_idx_to_amplitude(weight_index: usize) -> usize {
+        weight_index * 2 + 1
+    }
+
+    /// Returns the length of the shortest codeword
+    fn min_codeword_length(&self) -> usize {
+        self.codewords
+            .iter()
+            .flatten()
+            .map(Vec::len)
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Returns the length of the longest codeword
+    fn max_codeword_length(&self) -> usize {
+        self.codewords
+            .iter()
+            .flatten()
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns true if the prefix-free code is used for the next amplitude
+    ///
+    /// - `remaining_bits`: number of index bits not yet encoded
+    /// - `remaining_positions`: number of amplitudes left in the frame (at least 1)
+    fn use_prefix_code(&self, remaining_bits: usize, remaining_positions: usize) -> bool {
+        remaining_bits
+            <= self.min_codeword_length() + (remaining_positions - 1) * self.termination_bits
+    }
+}
+
+impl PCDM {
+    /// Returns the number of amplitudes per frame
+    pub fn n_max(&self) -> usize {
+        self.n_max
+    }
+    /// Returns the number of bits that can be encoded / decoded
+    pub fn num_bits(&self) -> u32 {
+        self.num_bits
+    }
+    /// Returns the amplitudes used by the termination code
+    pub fn get_termination_amplitudes(&self) -> Vec<usize> {
+        self.termination_amplitudes.clone()
+    }
+    /// Returns the number of bits needed to store the codeword table
+    pub fn storage_bits(&self) -> usize {
+        self.codewords.iter().flatten().map(Vec::len).sum()
+    }
+    /// Returns the amplitudes for the given bits (encode)
+    pub fn encode(&self, bits: &[u8]) -> Vec<usize> {
+        assert_eq!(bits.len(), self.num_bits as usize, "Wrong number of bits!");
+        let max_codeword_length = self.max_codeword_length();
+
+        let mut amplitude_sequence = Vec::with_capacity(self.n_max);
+        let mut position = 0;
+        let mut terminated = false;
+        for n in 0..self.n_max {
+            let remaining_bits = bits.len().saturating_sub(position);
+            terminated = terminated || !self.use_prefix_code(remaining_bits, self.n_max - n);
+
+            let window_length = if terminated {
+                self.termination_bits
+            } else {
+                max_codeword_length
+            };
+            let mut window = bits[position.min(bits.len())..]
+                .iter()
+                .copied()
+                .take(window_length)
+                .collect::<Vec<u8>>();
+            window.resize(window_length, 0);
+
+            if terminated {
+                let value = window
+                    .iter()
+                    .fold(0, |value, &bit| (value << 1) | bit as usize);
+                amplitude_sequence.push(self.termination_amplitudes[value]);
+                position += self.termination_bits;
+            } else {
+                let (amplitude, length) = self
+                    .code
+                    .encode_one(&window)
+                    .expect("the prefix-free code is complete");
+                amplitude_sequence.push(amplitude);
+                position += length;
+            }
+        }
+        amplitude_sequence
+    }
+    /// Returns the bits for the given amplitudes (decode)
+    pub fn decode(&self, amplitude_sequence: &[usize]) -> Vec<u8> {
+        assert_eq!(
+            amplitude_sequence.len(),
+            self.n_max,
+            "Wrong number of amplitudes!"
+        );
+
+        let mut bits = Vec::with_capacity(self.num_bits as usize + self.max_codeword_length());
+        let mut terminated = false;
+        for (n, &amplitude) in amplitude_sequence.iter().enumerate() {
+            let remaining_bits = (self.num_bits as usize).saturating_sub(bits.len());
+            terminated = terminated || !self.use_prefix_code(remaining_bits, self.n_max - n);
+
+            if terminated {
+                let value = self
+                    .termination_amplitudes
+                    .iter()
+                    .position(|&a| a == amplitude)
+                    .expect("amplitude is not used by the termination code");
+                bits.extend(
+                    (0..self.termination_bits)
+                        .rev()
+                        .map(|bit| ((value >> bit) & 1) as u8),
+                );
+            } else {
+                bits.extend(self.code.decode(&[amplitude]));
+            }
+        }
+        bits.truncate(self.num_bits as usize);
+        bits
+    }
+    /// Returns the amplitude distribution if indexes with [PCDM::num_bits()] bits are used
+    /// equiprobably
+    ///
+    /// The distribution is calculated exactly by tracking the probability of the number of
+    /// remaining bits and the framing mode over all positions of the frame.
+    pub fn amplitude_distribution(&self) -> Vec<f32> {
+        let num_bits = self.num_bits as usize;
+        let mut amplitude_probabilities = vec![0f64; self.codewords.len()];
+
+        // probability of (remaining bits, terminated) before the current position
+        let mut state = vec![[0f64; 2]; num_bits + 1];
+        state[num_bits][0] = 1.0;
+        for n in 0..self.n_max {
+            let mut next_state = vec![[0f64; 2]; num_bits + 1];
+            for (remaining_bits, probabilities) in state.iter().enumerate() {
+                for (was_terminated, &probability) in probabilities.iter().enumerate() {
+                    if probability == 0.0 {
+                        continue;
+                    }
+                    let terminated = was_terminated == 1
+                        || !self.use_prefix_code(remaining_bits, self.n_max - n);
+
+                    if terminated {
+                        // padded bits are zero, only some termination amplitudes are possible
+                        let known_bits = remaining_bits.min(self.termination_bits);
+                        let step = 1 << (self.termination_bits - known_bits);
+                        let p = probability * (-(known_bits as f64)).exp2();
+                        for &amplitude in self.termination_amplitudes.iter().step_by(step) {
+                            amplitude_probabilities[(amplitude - 1) / 2] += p;
+                        }
+                        next_state[remaining_bits.saturating_sub(self.termination_bits)][1] +=
+                            probability;
+                    } else {
+                        for (w_idx, codeword) in self.codewords.iter().enumerate() {
+                            let codeword = match codeword {
+                                Some(codeword) => codeword,
+                                None => continue,
+                            };
+                            let p = if codeword.len() <= remaining_bits {
+                                probability * (-(codeword.len() as f64)).exp2()
+                            } else if codeword[remaining_bits..].iter().all(|&bit| bit == 0) {
+                                probability * (-(remaining_bits as f64)).exp2()
+                            } else {
+                                continue;
+                            };
+                            amplitude_probabilities[w_idx] += p;
+                            next_state[remaining_bits.saturating_sub(codeword.len())][0] += p;
+                        }
+                    }
+                }
+            }
+            state = next_state;
+        }
+
+        amplitude_probabilities
+            .iter()
+            .map(|p| (p / self.n_max as f64) as f32)
+            .collect()
+    }
+}
+
+impl DistributionMatcher for PCDM {
+    fn n_max(&self) -> usize {
+        self.n_max
+    }
+    fn num_bits(&self) -> u32 {
+        self.num_bits
+    }
+    fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        self.encode(&integer_to_bits(index, self.num_bits as usize))
+    }
+    fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        bits_to_integer(&self.decode(amplitude_sequence))
+    }
+    fn amplitude_distribution(&self) -> Vec<f32> {
+        PCDM::amplitude_distribution(self)
+    }
+}
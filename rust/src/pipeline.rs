@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::ad_ess::AdEss;
+use crate::bigint::Integer;
+
+/// Configuration of an encoding / decoding pipeline, see [spawn_encoder()]
+#[derive(Clone)]
+pub struct PipelineConfig {
+    /// Matcher shared by all workers
+    pub adess: Arc<AdEss>,
+    /// Number of worker threads
+    pub num_workers: usize,
+    /// Capacity of the input, work and output queues
+    pub queue_capacity: usize,
+}
+
+impl PipelineConfig {
+    /// Returns a configuration with one worker per available CPU and a queue capacity of 64
+    pub fn new(adess: Arc<AdEss>) -> PipelineConfig {
+        PipelineConfig {
+            adess,
+            num_workers: thread::available_parallelism().map_or(1, |n| n.get()),
+            queue_capacity: 64,
+        }
+    }
+}
+
+/// Spawns a pipeline applying `process` with `num_workers` threads, the output keeps the input
+/// order
+///
+/// The pipeline stops at the first input for which `process` returns [None].
+fn spawn_pipeline<I, O>(
+    num_workers: usize,
+    queue_capacity: usize,
+    process: impl Fn(I) -> Option<O> + Send + Sync + 'static,
+) -> (SyncSender<I>, Receiver<O>)
+where
+    I: Send + 'static,
+    O: Send + 'static,
+{
+    let (input_sender, input_receiver) = mpsc::sync_channel::<I>(queue_capacity);
+    let (work_sender, work_receiver) = mpsc::sync_channel::<(usize, I)>(queue_capacity);
+    let (result_sender, result_receiver) = mpsc::sync_channel::<(usize, Option<O>)>(queue_capacity);
+    let (output_sender, output_receiver) = mpsc::sync_channel::<O>(queue_capacity);
+
+    // numbers the inputs to restore their order after the workers
+    thread::spawn(move || {
+        for job in input_receiver.into_iter().enumerate() {
+            if work_sender.send(job).is_err() {
+                break;
+            }
+        }
+    });
+
+    let work_receiver = Arc::new(Mutex::new(work_receiver));
+    let process = Arc::new(process);
+    for _ in 0..num_workers.max(1) {
+        let work_receiver = Arc::clone(&work_receiver);
+        let result_sender = result_sender.clone();
+        let process = Arc::clone(&process);
+        thread::spawn(move || loop {
+            let job = work_receiver.lock().expect("worker panicked").recv();
+            let Ok((job_idx, input)) = job else { break };
+            let output = process(input);
+            // failed inputs are forwarded to stop the output in order
+            let is_failed = output.is_none();
+            if result_sender.send((job_idx, output)).is_err() || is_failed {
+                break;
+            }
+        });
+    }
+    drop(result_sender);
+
+    thread::spawn(move || {
+        let mut pending = BTreeMap::new();
+        let mut next_idx = 0;
+        for (job_idx, output) in result_receiver {
+            pending.insert(job_idx, output);
+            while let Some(output) = pending.remove(&next_idx) {
+                let Some(output) = output else { return };
+                if output_sender.send(output).is_err() {
+                    return;
+                }
+                next_idx += 1;
+            }
+        }
+    });
+
+    (input_sender, output_receiver)
+}
+
+/// Spawns a pipeline encoding indexes to amplitude sequences
+///
+/// Indexes are fed through the returned sender, the amplitude sequences are received in the
+/// same order. All queues are bounded by [PipelineConfig::queue_capacity], so sending blocks
+/// while the output is not consumed. The pipeline shuts down once the sender is dropped and all
+/// sequences are received. Indexes with more than [AdEss::num_bits()] bits end the pipeline.
+pub fn spawn_encoder(config: PipelineConfig) -> (SyncSender<Integer>, Receiver<Vec<usize>>) {
+    let adess = config.adess;
+    let num_sequences_used = Integer::from(1) << adess.num_bits();
+    spawn_pipeline(config.num_workers, config.queue_capacity, move |index| {
+        if index < 0 || index >= num_sequences_used {
+            return None;
+        }
+        Some(adess.sequence_for_index(&index))
+    })
+}
+
+/// Spawns a pipeline decoding amplitude sequences to indexes, see [spawn_encoder()]
+///
+/// Sequences without [AdEss] `n_max` amplitudes end the pipeline.
+pub fn spawn_decoder(config: PipelineConfig) -> (SyncSender<Vec<usize>>, Receiver<Integer>) {
+    let adess = config.adess;
+    spawn_pipeline(
+        config.num_workers,
+        config.queue_capacity,
+        move |sequence: Vec<usize>| {
+            if sequence.len() != adess.trellis.n_max {
+                return None;
+            }
+            Some(adess.index_for_sequence(&sequence))
+        },
+    )
+}
@@ -0,0 +1,96 @@
+use crate::ad_ess::AdEss;
+use crate::error::AdEssError;
+use crate::utils;
+
+/// Ready-made [AdEss] configuration, see [presets()]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Preset {
+    pub name: &'static str,
+    /// Number of amplitudes, i.e., `sqrt(M) / 2` for M-QAM
+    pub num_amplitudes: usize,
+    /// Shaping rate in bit/amplitude
+    pub rate: f32,
+    pub n_max: usize,
+    /// Resolution factor of the weights, see [AdEss::calc_weights()]
+    pub res_factor: f32,
+}
+
+const fn preset(
+    name: &'static str,
+    num_amplitudes: usize,
+    rate: f32,
+    n_max: usize,
+    res_factor: f32,
+) -> Preset {
+    Preset {
+        name,
+        num_amplitudes,
+        rate,
+        n_max,
+        res_factor,
+    }
+}
+
+/// Coarser weights for longer blocks keep the trellis small
+const PRESETS: [Preset; 12] = [
+    preset("64qam-r1.25-n96", 4, 1.25, 96, 4.0),
+    preset("64qam-r1.25-n216", 4, 1.25, 216, 2.0),
+    preset("64qam-r1.25-n648", 4, 1.25, 648, 1.0),
+    preset("64qam-r1.5-n96", 4, 1.5, 96, 4.0),
+    preset("64qam-r1.5-n216", 4, 1.5, 216, 2.0),
+    preset("64qam-r1.5-n648", 4, 1.5, 648, 1.0),
+    preset("256qam-r2.25-n96", 8, 2.25, 96, 4.0),
+    preset("256qam-r2.25-n216", 8, 2.25, 216, 2.0),
+    preset("256qam-r2.25-n648", 8, 2.25, 648, 1.0),
+    preset("256qam-r2.5-n96", 8, 2.5, 96, 4.0),
+    preset("256qam-r2.5-n216", 8, 2.5, 216, 2.0),
+    preset("256qam-r2.5-n648", 8, 2.5, 648, 1.0),
+];
+
+/// Returns all presets
+///
+/// The names are `<M>qam-r<rate>-n<n_max>`, e.g., `64qam-r1.5-n96` for the 4 amplitudes of
+/// 64-QAM (8-ASK per dimension) with the shaping rate 1.5 bit/amplitude and `n_max = 96`.
+pub fn presets() -> &'static [Preset] {
+    &PRESETS
+}
+
+/// Returns the preset called `name`
+pub fn preset_by_name(name: &str) -> Result<Preset, AdEssError> {
+    PRESETS
+        .iter()
+        .find(|preset| preset.name == name)
+        .copied()
+        .ok_or(AdEssError::InvalidParameter("Unknown preset"))
+}
+
+/// Returns the [AdEss] of the preset called `name`, see [Preset::build()]
+pub fn adess_for_preset(name: &str) -> Result<AdEss, AdEssError> {
+    preset_by_name(name)?.build()
+}
+
+impl Preset {
+    /// Returns the Maxwell-Boltzmann target distribution whose entropy equals the rate
+    pub fn distribution(&self) -> Vec<f32> {
+        let levels = utils::uniform_levels(self.num_amplitudes);
+        utils::maxwell_boltzmann_for_entropy(&levels, self.rate)
+            .expect("the rates of all presets are below the uniform entropy")
+    }
+    /// Returns the number of bits per block, i.e., `rate * n_max`
+    pub fn num_bits(&self) -> usize {
+        (self.rate * self.n_max as f32) as usize
+    }
+    /// Returns the [AdEss] for the preset
+    ///
+    /// The trellis is constructed via [AdEss::new_for_distribution_num_bits()] with the target
+    /// [Preset::distribution()].
+    pub fn build(&self) -> Result<AdEss, AdEssError> {
+        let (adess, _) = AdEss::new_for_distribution_num_bits(
+            self.num_bits(),
+            self.n_max,
+            &self.distribution(),
+            self.res_factor,
+        )?;
+        Ok(adess)
+    }
+}
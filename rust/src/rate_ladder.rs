@@ -0,0 +1,107 @@
+use crate::ad_ess::AdEss;
+use crate::bigint::{Complete, Integer};
+use crate::error::AdEssError;
+use crate::trellis::Trellis;
+use crate::trellis_utils;
+
+/// A single AdEss configuration of a [RateLadder]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLadderRung {
+    /// Number of bits encoded per sequence
+    pub num_bits: usize,
+    /// Threshold of the smallest trellis encoding `num_bits` bits
+    pub threshold: usize,
+}
+
+/// Set of [AdEss] configurations with common weights covering several shaping rates
+///
+/// Reverse trellis values do not depend on the threshold, so the reverse trellis of every rung is
+/// a part of the [RateLadder::shared_reverse_trellis] computed for the largest rate.
+pub struct RateLadder {
+    pub n_max: usize,
+    pub weights: Vec<usize>,
+    pub rungs: Vec<RateLadderRung>,
+    shared_reverse_trellis: Trellis,
+}
+
+/// Returns a [RateLadder] covering the shaping `rates` (in bit/amplitude)
+///
+/// Each rung encodes at least `ceil(rate * n_max)` bits with the smallest possible threshold.
+/// All rungs use the weights computed via [AdEss::calc_weights()] from `distribution` and
+/// `res_factor`. Rates that result in the same configuration share a single rung, the rungs are
+/// sorted by increasing rate.
+///
+/// Only a single reverse trellis (for the largest rate) is calculated.
+pub fn generate_rate_ladder(
+    distribution: &[f32],
+    n_max: usize,
+    rates: &[f32],
+    res_factor: f32,
+) -> Result<RateLadder, AdEssError> {
+    let weights = AdEss::calc_weights(distribution, res_factor)?;
+    let mut bits_per_rung: Vec<usize> = rates
+        .iter()
+        .map(|rate| (rate * n_max as f32).ceil() as usize)
+        .collect();
+    bits_per_rung.sort_unstable();
+    bits_per_rung.dedup();
+    let max_num_bits = *bits_per_rung.last().ok_or(AdEssError::InvalidParameter(
+        "At least one rate is required",
+    ))?;
+
+    let shared_reverse_trellis = trellis_utils::reverse_trellis_upto_num_sequences(
+        Integer::u_pow_u(2, max_num_bits as u32).complete(),
+        n_max,
+        &weights,
+    )?;
+
+    // number of sequences with a sum weight up to each weight level
+    let weight_levels = shared_reverse_trellis.get_weight_levels();
+    let mut num_sequences = Integer::from(0);
+    let mut rungs = vec![];
+    let mut bits_iter = bits_per_rung.into_iter().peekable();
+    for (wl_idx, node_value) in shared_reverse_trellis.stage_slice(n_max).iter().enumerate() {
+        num_sequences += node_value;
+        while let Some(&num_bits) = bits_iter.peek() {
+            if num_sequences.significant_bits() as usize <= num_bits {
+                break;
+            }
+            rungs.push(RateLadderRung {
+                num_bits,
+                threshold: weight_levels[wl_idx],
+            });
+            bits_iter.next();
+        }
+    }
+    // rungs with equal thresholds are merged, the larger number of bits is kept
+    rungs.dedup_by(|later, earlier| {
+        if later.threshold == earlier.threshold {
+            earlier.num_bits = later.num_bits;
+            true
+        } else {
+            false
+        }
+    });
+
+    Ok(RateLadder {
+        n_max,
+        weights,
+        rungs,
+        shared_reverse_trellis,
+    })
+}
+
+impl RateLadder {
+    /// Returns the [AdEss] for the rung with index `rung_idx`
+    pub fn adess(&self, rung_idx: usize) -> AdEss {
+        AdEss::new(self.rungs[rung_idx].threshold, self.n_max, &self.weights)
+    }
+    /// Returns the reverse trellis holding the reverse trellises of all rungs
+    pub fn shared_reverse_trellis(&self) -> &Trellis {
+        &self.shared_reverse_trellis
+    }
+    /// Returns the storage dimensions of the shared reverse trellis
+    pub fn get_storage_dimensions(&self) -> (usize, usize) {
+        self.shared_reverse_trellis.get_storage_dimensions()
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::ad_ess::AdEss;
+use crate::trellis::Trellis;
+
+/// Key of a trellis configuration: (weights, n_max, threshold)
+type ConfigurationKey = (Vec<usize>, usize, usize);
+
+/// Cache of constructed [AdEss] forward trellises
+///
+/// Services which repeatedly instantiate the same configuration (e.g., per connection) share a
+/// single trellis instead of rebuilding it. A registry can be owned by the user or the
+/// process wide registry [TrellisRegistry::global()] can be used.
+#[derive(Debug, Default)]
+pub struct TrellisRegistry {
+    trellises: Mutex<HashMap<ConfigurationKey, Arc<Trellis>>>,
+}
+
+impl TrellisRegistry {
+    /// Returns a new empty registry
+    pub fn new() -> TrellisRegistry {
+        TrellisRegistry::default()
+    }
+    /// Returns the process wide registry
+    pub fn global() -> &'static TrellisRegistry {
+        static GLOBAL: OnceLock<TrellisRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(TrellisRegistry::new)
+    }
+    /// Returns the forward trellis of [AdEss::new()] for the given configuration
+    ///
+    /// The trellis is constructed on the first request only. The registry is not locked during
+    /// the construction, if two threads construct the same trellis, the first inserted is kept.
+    pub fn get(&self, weights: &[usize], n_max: usize, threshold: usize) -> Arc<Trellis> {
+        let key = (weights.to_vec(), n_max, threshold);
+        if let Some(trellis) = self.trellises.lock().unwrap().get(&key) {
+            return trellis.clone();
+        }
+        let trellis = AdEss::new(threshold, n_max, weights).trellis;
+        self.trellises
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert(trellis)
+            .clone()
+    }
+    /// Returns true if the trellis of the given configuration is stored
+    pub fn contains(&self, weights: &[usize], n_max: usize, threshold: usize) -> bool {
+        self.trellises
+            .lock()
+            .unwrap()
+            .contains_key(&(weights.to_vec(), n_max, threshold))
+    }
+    /// Returns the number of stored trellises
+    pub fn len(&self) -> usize {
+        self.trellises.lock().unwrap().len()
+    }
+    /// Returns true if no trellis is stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Removes all stored trellises, trellises still in use are not affected
+    pub fn clear(&self) {
+        self.trellises.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+
+use crate::matcher::DistributionMatcher;
+use crate::trellis::Trellis;
+use crate::utils::{self, kl_divergence};
+
+/// Rate loss, energy and complexity figures of a trellis based distribution matcher
+///
+/// Returned by [AdEss::analyze()](crate::ad_ess::AdEss::analyze) and
+/// [RTS::analyze()](crate::rts::RTS::analyze), [fmt::Display] formats it as plain text.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShapingReport {
+    /// Number of amplitudes per sequence
+    pub n_max: usize,
+    /// Number of bits per sequence
+    pub num_bits: u32,
+    /// Rate in bit/amplitude
+    pub rate: f32,
+    /// Target distribution `P`
+    pub target_distribution: Vec<f32>,
+    /// Entropy `H(P)` of the target distribution in bit
+    pub target_entropy: f32,
+    /// Induced amplitude distribution `P_A`
+    pub amplitude_distribution: Vec<f32>,
+    /// Entropy `H(P_A)` of the amplitude distribution in bit
+    pub entropy: f32,
+    /// Rate loss `H(P_A) - rate` in bit/amplitude
+    pub rate_loss: f32,
+    /// KL divergence `D(P_A||P)` between the amplitude and the target distribution
+    pub kl_divergence: f32,
+    /// KL divergence `D(P||P_A)` between the target and the amplitude distribution
+    pub reverse_kl_divergence: f32,
+    /// Upper bound on the mutual information loss, see [utils::mi_loss_bound()]
+    pub mi_loss_bound: f32,
+    /// Average energy per amplitude of the amplitudes `1, 3, 5, ...`
+    pub average_energy: f32,
+    /// Average energy per amplitude of uniformly distributed amplitudes with the same rate
+    pub uniform_average_energy: f32,
+    /// Shaping gain `10 * log10(uniform_average_energy / average_energy)` in dB
+    pub shaping_gain_db: f32,
+    /// Number of stages and weight levels of the trellis
+    pub storage_dimensions: (usize, usize),
+    /// Number of bits to store the trellis
+    pub storage_bits: usize,
+}
+
+impl ShapingReport {
+    /// Returns the report of `matcher` using `trellis` for the `target_distribution`
+    ///
+    /// The uniform reference has `2^rate` amplitudes, i.e., its average energy is
+    /// `(4^(rate + 1) - 1) / 3`, which is exact for integer rates.
+    pub(crate) fn new(
+        matcher: &dyn DistributionMatcher,
+        trellis: &Trellis,
+        target_distribution: &[f32],
+    ) -> ShapingReport {
+        let amplitude_distribution = matcher.amplitude_distribution();
+        let rate = matcher.rate();
+        let entropy = utils::entropy(&amplitude_distribution);
+        let average_energy = matcher.average_energy();
+        let uniform_average_energy = (4f32.powf(rate + 1.0) - 1.0) / 3.0;
+        ShapingReport {
+            n_max: matcher.n_max(),
+            num_bits: matcher.num_bits(),
+            rate,
+            target_distribution: target_distribution.to_vec(),
+            target_entropy: utils::entropy(target_distribution),
+            entropy,
+            rate_loss: entropy - rate,
+            kl_divergence: kl_divergence(&amplitude_distribution, &target_distribution.to_vec()),
+            reverse_kl_divergence: kl_divergence(target_distribution, &amplitude_distribution),
+            mi_loss_bound: utils::mi_loss_bound(&amplitude_distribution, target_distribution, rate),
+            amplitude_distribution,
+            average_energy,
+            uniform_average_energy,
+            shaping_gain_db: 10.0 * (uniform_average_energy / average_energy).log10(),
+            storage_dimensions: trellis.get_storage_dimensions(),
+            storage_bits: trellis.storage_bits(),
+        }
+    }
+}
+
+impl fmt::Display for ShapingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Target distribution: {:?}", self.target_distribution)?;
+        writeln!(f, "  Entropy: {} bit", self.target_entropy)?;
+        writeln!(
+            f,
+            "Amplitude distribution: {:?}",
+            self.amplitude_distribution
+        )?;
+        writeln!(f, "  Entropy: {} bit", self.entropy)?;
+        writeln!(
+            f,
+            "Rate: {} bit/amplitude ({} bits, {} amplitudes)",
+            self.rate, self.num_bits, self.n_max
+        )?;
+        writeln!(f, "Rate loss: {} bit/amplitude", self.rate_loss)?;
+        writeln!(f, "KL-divergence (final to target): {}", self.kl_divergence)?;
+        writeln!(
+            f,
+            "KL-divergence (target to final): {}",
+            self.reverse_kl_divergence
+        )?;
+        writeln!(
+            f,
+            "Mutual information loss: {} bit/channel use",
+            self.mi_loss_bound
+        )?;
+        writeln!(
+            f,
+            "Average energy: {} (uniform: {})",
+            self.average_energy, self.uniform_average_energy
+        )?;
+        writeln!(f, "Shaping gain: {} dB", self.shaping_gain_db)?;
+        writeln!(
+            f,
+            "Storage complexity: {:?} ({} bits)",
+            self.storage_dimensions, self.storage_bits
+        )
+    }
+}
@@ -1,13 +1,21 @@
-use rug::Complete;
-use rug::Integer;
-use rug::Rational;
+use std::sync::Arc;
 
+use crate::ad_ess::AdEss;
+use crate::bigint::{Complete, Integer, Rational};
+use crate::error::AdEssError;
+use crate::report::ShapingReport;
 use crate::trellis::Trellis;
 use crate::trellis_utils;
-use crate::utils;
+use crate::utils::{self, BitOrder};
 
+/// Reverse Trellis Shaping (RTS)
+///
+/// Like [AdEss], the instance is `Send + Sync` and the trellis is shared between clones, see
+/// [AdEss#thread-safety].
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RTS {
-    pub trellis: Trellis,
+    pub trellis: Arc<Trellis>,
 }
 
 impl RTS {
@@ -16,13 +24,55 @@ impl RTS {
     /// The smallest possible trellis that encodes `num_bits` bits is used, in
     /// some cases this trellis is capable of encoding more than `num_bits` bits.
     pub fn new(num_bits: usize, n_max: usize, weights: &[usize]) -> RTS {
+        RTS::try_new(num_bits, n_max, weights).unwrap()
+    }
+
+    /// Returns an [RTS] instance which encodes at least `num_bits` bits or an error
+    ///
+    /// An error is returned if `weights` is empty, the smallest weight is not 0 or `num_bits`
+    /// bits can not be encoded with `n_max` amplitudes.
+    pub fn try_new(num_bits: usize, n_max: usize, weights: &[usize]) -> Result<RTS, AdEssError> {
+        if weights.iter().min() != Some(&0) {
+            return Err(AdEssError::InvalidWeights(
+                "`weights` must not be empty and the smallest weight must be 0",
+            ));
+        }
         let trellis = trellis_utils::reverse_trellis_upto_num_sequences(
             Integer::u_pow_u(2, num_bits as u32).complete(),
             n_max,
             weights,
-        )
-        .unwrap();
-        RTS { trellis }
+        )?;
+        Ok(RTS {
+            trellis: Arc::new(trellis),
+        })
+    }
+
+    /// Returns a new [RTS] instance with the same weights and threshold as `adess`
+    ///
+    /// The weight levels of the [AdEss] trellis are reused, only the reverse trellis values are
+    /// calculated.
+    pub fn from_adess(adess: &AdEss) -> RTS {
+        let mut trellis = adess.trellis.structure_like();
+        AdEss::fill_reverse_trellis(&mut trellis);
+        RTS {
+            trellis: Arc::new(trellis),
+        }
+    }
+
+    /// Returns an [RTS] instance for a precomputed reverse `trellis`
+    ///
+    /// The node values are not recalculated. An error is returned if not all nodes up to the
+    /// threshold are stored or the trellis does not start with a single node of value 1 (see
+    /// [AdEss::calc_reverse_trellis()]).
+    pub fn from_trellis(trellis: impl Into<Arc<Trellis>>) -> Result<RTS, AdEssError> {
+        let trellis = trellis.into();
+        trellis.check_storage()?;
+        if *trellis.get_ref(0, 0) != 1 || trellis.stage_sum(0) != 1 {
+            return Err(AdEssError::InvalidParameter(
+                "The reverse trellis must start with a single node of value 1",
+            ));
+        }
+        Ok(RTS { trellis })
     }
 
     /// Returns the amplitude value for a given weight index
@@ -42,12 +92,35 @@ impl RTS {
 impl RTS {
     /// Returns the number of sequences that can be encoded / decoded
     pub fn num_sequences(&self) -> Integer {
-        let n_max = self.trellis.n_max;
-        self.trellis.get_stage(n_max).iter().sum()
+        self.trellis.stage_sum(self.trellis.n_max)
+    }
+    /// Returns the total and the used number of sequences
+    pub fn sequence_counts(&self) -> utils::SequenceCounts {
+        utils::SequenceCounts::for_total(self.num_sequences())
+    }
+    /// Returns the number of sequences used for encoding (`2^num_bits`)
+    pub fn num_sequences_used(&self) -> Integer {
+        self.sequence_counts().used
+    }
+    /// Returns the fraction of sequences used for encoding, see [RTS::sequence_counts()]
+    pub fn utilization(&self) -> f64 {
+        self.sequence_counts().utilization()
     }
     /// Returns the number of bits that can be encoded / decoded
     pub fn num_bits(&self) -> u32 {
-        self.num_sequences().significant_bits() - 1
+        self.num_sequences().significant_bits().saturating_sub(1)
+    }
+    /// Returns `log2(num_sequences)`, i.e., the number of bits including the fractional bit
+    /// which is lost by using only `2^num_bits` sequences
+    pub fn num_bits_fractional(&self) -> f64 {
+        utils::integer_log2(&self.num_sequences())
+    }
+    /// Returns the rate `log2(num_sequences) / n_max` in bit/amplitude if all sequences are used
+    ///
+    /// The rate of encoding `num_bits` bits per sequence is
+    /// [DistributionMatcher::rate()](crate::matcher::DistributionMatcher::rate).
+    pub fn rate_bits_per_amplitude(&self) -> f64 {
+        self.num_bits_fractional() / self.trellis.n_max as f64
     }
     /// Returns the weights used by the internal trellis
     pub fn get_weights(&self) -> Vec<usize> {
@@ -58,45 +131,182 @@ impl RTS {
         utils::distribution_from_weights(&self.get_weights(), res_factor)
     }
     /// Returns the amplitude sequence for a given index
+    ///
+    /// Panics if `index` is out of range, see [RTS::try_sequence_for_index()].
     pub fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
-        assert!(index < &self.num_sequences(), "Index out of range!");
-
+        self.try_sequence_for_index(index)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+    /// Returns the amplitude sequence for a given index or an error if `index` is negative or
+    /// not smaller than [RTS::num_sequences()]
+    pub fn try_sequence_for_index(&self, index: &Integer) -> Result<Vec<usize>, AdEssError> {
+        if *index < 0 || *index >= self.num_sequences() {
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        let mut sequence = vec![0usize; self.trellis.n_max];
+        self.encode_remaining_index_into(&mut index.clone(), &mut sequence);
+        Ok(sequence)
+    }
+    /// Returns the amplitude sequence for an index given as bytes, see
+    /// [AdEss::sequence_for_index_bytes()]
+    pub fn sequence_for_index_bytes(
+        &self,
+        bytes: &[u8],
+        bit_order: BitOrder,
+    ) -> Result<Vec<usize>, AdEssError> {
+        let mut index = utils::index_from_bytes(bytes, self.num_bits(), bit_order)?;
+        let mut sequence = vec![0usize; self.trellis.n_max];
+        self.encode_remaining_index_into(&mut index, &mut sequence);
+        Ok(sequence)
+    }
+    /// Writes the amplitude sequence for a given index into `out`
+    ///
+    /// Unlike [RTS::sequence_for_index()] no sequence is allocated and the remaining index is
+    /// updated in place. An error is returned if `out` does not have `n_max` entries or `index`
+    /// is out of range.
+    pub fn sequence_for_index_into(
+        &self,
+        index: &Integer,
+        out: &mut [usize],
+    ) -> Result<(), AdEssError> {
+        if out.len() != self.trellis.n_max {
+            return Err(AdEssError::InvalidParameter(
+                "`out` must have `n_max` entries",
+            ));
+        }
+        if *index < 0 || *index >= self.num_sequences() {
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        self.encode_remaining_index_into(&mut index.clone(), out);
+        Ok(())
+    }
+    /// Writes the amplitude sequence for `remaining_index` into `out`, the index is consumed
+    ///
+    /// The sequence is found backwards, starting with the weight level of the last stage.
+    fn encode_remaining_index_into(&self, remaining_index: &mut Integer, out: &mut [usize]) {
         let n_max = self.trellis.n_max;
-        let mut wl_path = vec![0usize; n_max + 1];
+        let weight_levels = self.trellis.weight_levels_slice();
 
-        let mut lower_nodes_sum = Integer::from(0);
-        for (wl_idx, node_value) in self.trellis.get_stage(n_max).iter().enumerate() {
-            lower_nodes_sum += node_value;
-            if &lower_nodes_sum > index {
-                wl_path[n_max] = self.trellis.get_weight_levels()[wl_idx];
-                lower_nodes_sum -= node_value;
+        let mut current_wl = 0;
+        for (wl_idx, node_value) in self.trellis.stage_slice(n_max).iter().enumerate() {
+            if *remaining_index < *node_value {
+                current_wl = weight_levels[wl_idx];
                 break;
             }
+            *remaining_index -= node_value;
         }
-        let mut local_index = index - lower_nodes_sum;
-        let mut weight_idx_seq = vec![0usize; n_max];
         for stage in (0..n_max).rev() {
-            lower_nodes_sum = Integer::from(0);
-            // caching predecessors may improve speed
-            for (w_idx, pred_wl) in self.trellis.get_predecessors(wl_path[stage + 1]) {
-                let node_value = self.trellis.get(stage, pred_wl);
-
-                lower_nodes_sum += &node_value;
-                if lower_nodes_sum > local_index {
-                    wl_path[stage] = pred_wl;
-                    weight_idx_seq[stage] = w_idx;
-                    lower_nodes_sum -= &node_value;
+            for &(w_idx, predecessor_wl) in self.trellis.predecessors_slice(current_wl) {
+                let node_value = self.trellis.get_ref(stage, predecessor_wl);
+                if *remaining_index < *node_value {
+                    out[stage] = RTS::weight_idx_to_amplitude(w_idx);
+                    current_wl = predecessor_wl;
                     break;
                 }
+                *remaining_index -= node_value;
             }
-            local_index -= lower_nodes_sum;
         }
-        weight_idx_seq
+    }
+    /// Returns the amplitude sequences for all `indices` (batch encode) or an error if an index
+    /// is out of range, see [RTS::try_sequence_for_index()]
+    pub fn sequences_for_indices(
+        &self,
+        indices: &[Integer],
+    ) -> Result<Vec<Vec<usize>>, AdEssError> {
+        indices
+            .iter()
+            .map(|index| self.try_sequence_for_index(index))
+            .collect()
+    }
+    /// Returns the indices for all `sequences` (batch decode) or an error if a sequence is not
+    /// in the trellis, see [RTS::try_index_for_sequence()]
+    pub fn indices_for_sequences(
+        &self,
+        sequences: &[Vec<usize>],
+    ) -> Result<Vec<Integer>, AdEssError> {
+        sequences
             .iter()
-            .map(|&weight_idx| RTS::weight_idx_to_amplitude(weight_idx))
+            .map(|sequence| self.try_index_for_sequence(sequence))
             .collect()
     }
+    /// Returns the amplitude sequences for all `indices` encoded on `num_threads` threads
+    ///
+    /// See [RTS::sequences_for_indices()], with `num_threads == 0` rayon chooses the number of
+    /// threads.
+    #[cfg(feature = "rayon")]
+    pub fn par_sequences_for_indices(
+        &self,
+        indices: &[Integer],
+        num_threads: usize,
+    ) -> Result<Vec<Vec<usize>>, AdEssError> {
+        utils::par_map(indices, num_threads, |index| {
+            self.try_sequence_for_index(index)
+        })
+        .into_iter()
+        .collect()
+    }
+    /// Returns the indices for all `sequences` decoded on `num_threads` threads, see
+    /// [RTS::par_sequences_for_indices()]
+    #[cfg(feature = "rayon")]
+    pub fn par_indices_for_sequences(
+        &self,
+        sequences: &[Vec<usize>],
+        num_threads: usize,
+    ) -> Result<Vec<Integer>, AdEssError> {
+        utils::par_map(sequences, num_threads, |sequence| {
+            self.try_index_for_sequence(sequence)
+        })
+        .into_iter()
+        .collect()
+    }
+    /// Writes the index for a given amplitude sequence as bytes into `out`, see
+    /// [AdEss::sequence_for_index_bytes()]
+    ///
+    /// An error is returned if `out` does not have `ceil(num_bits / 8)` entries, the sequence is
+    /// not in the trellis (see [RTS::try_index_for_sequence()]) or the index exceeds `num_bits`
+    /// bits.
+    pub fn index_for_sequence_bytes(
+        &self,
+        amplitude_sequence: &[usize],
+        bit_order: BitOrder,
+        out: &mut [u8],
+    ) -> Result<(), AdEssError> {
+        let index = self.try_index_for_sequence(amplitude_sequence)?;
+        utils::index_to_bytes(&index, self.num_bits(), bit_order, out)
+    }
+    /// Returns the index for a given amplitude sequence or an error if the sequence is not in the
+    /// trellis
+    ///
+    /// The sequence must have `n_max` odd amplitudes whose sum weight does not exceed the
+    /// threshold.
+    pub fn try_index_for_sequence(
+        &self,
+        amplitude_sequence: &[usize],
+    ) -> Result<Integer, AdEssError> {
+        if amplitude_sequence.len() != self.trellis.n_max {
+            return Err(AdEssError::InvalidSequence(
+                "The amplitude sequence must have `n_max` amplitudes",
+            ));
+        }
+        let weights = self.trellis.get_weights();
+        let mut sum_weight = 0;
+        for &amplitude in amplitude_sequence {
+            if amplitude.is_multiple_of(2)
+                || RTS::amplitude_to_weight_idx(amplitude) >= weights.len()
+            {
+                return Err(AdEssError::InvalidSequence("Amplitude without weight"));
+            }
+            sum_weight += weights[RTS::amplitude_to_weight_idx(amplitude)];
+            // excluded amplitudes have a weight above the threshold
+            if sum_weight > self.trellis.threshold {
+                return Err(AdEssError::InvalidSequence("Threshold exceeded"));
+            }
+        }
+        Ok(self.index_for_sequence(amplitude_sequence))
+    }
     /// Returns the index for a given amplitude sequence
+    ///
+    /// The sequence is not validated, see [RTS::try_index_for_sequence()].
     pub fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
         let n_max = self.trellis.n_max;
 
@@ -109,7 +319,7 @@ impl RTS {
 
         let mut index: Integer = self
             .trellis
-            .get_stage(self.trellis.n_max)
+            .stage_slice(self.trellis.n_max)
             .iter()
             .take(num_lower_end_nodes)
             .sum();
@@ -123,14 +333,14 @@ impl RTS {
             let stage = idx + 1;
             if let &[predecessor_wl, wl] = wl_transition {
                 self.trellis
-                    .get_predecessors(wl)
+                    .predecessors_slice(wl)
                     .iter()
                     .take_while(|(possible_weight_idx, possible_predecessor_wl)| {
                         *possible_predecessor_wl <= predecessor_wl
                             && *possible_weight_idx != weight_idx
                     })
                     .for_each(|(_, possible_predecessor_wl)| {
-                        index += self.trellis.get(stage - 1, *possible_predecessor_wl);
+                        index += self.trellis.get_ref(stage - 1, *possible_predecessor_wl);
                     });
             } else {
                 panic!("`window(2)` produced a window of length != 2");
@@ -177,8 +387,9 @@ impl RTS {
             .flat_map(|stage| {
                 let ref_fas_w_idxs = &fas_w_idxs;
                 self.trellis
-                    .get_predecessors(fas_wls[stage])
-                    .into_iter()
+                    .predecessors_slice(fas_wls[stage])
+                    .iter()
+                    .copied()
                     .take_while(move |(w_idx, _)| *w_idx != ref_fas_w_idxs[stage - 1])
                     // ensure `predecessor_wl - the_weight >= 0`
                     .skip_while(|(_, predecessor_wl)| *predecessor_wl < the_weight)
@@ -208,8 +419,9 @@ impl RTS {
                 .flat_map(|stage| {
                     let ref_fas_w_idxs = &fas_w_idxs;
                     self.trellis
-                        .get_predecessors(fas_wls[stage])
-                        .into_iter()
+                        .predecessors_slice(fas_wls[stage])
+                        .iter()
+                        .copied()
                         .take_while(move |(w_idx, _)| *w_idx != ref_fas_w_idxs[stage - 1])
                         .map(move |(_, predecessor_wl)| {
                             self.trellis.get_or_0(stage - 1, predecessor_wl)
@@ -220,6 +432,45 @@ impl RTS {
 
         amplitude_count
     }
+    /// Returns the number of occurences of each amplitude in the sequences with the indexes
+    /// `0..num_indexes`
+    ///
+    /// Panics if `num_indexes` exceeds the number of sequences.
+    pub fn amplitude_counts_below(&self, num_indexes: &Integer) -> Vec<Integer> {
+        let n_max = self.trellis.n_max;
+        let weights = self.trellis.get_weights();
+        if *num_indexes == self.num_sequences() {
+            // the set of all sequences is symmetric, count the last position only
+            let threshold = self.trellis.threshold;
+            return weights
+                .iter()
+                .map(|weight| {
+                    if n_max == 0 {
+                        return Integer::new();
+                    }
+                    let last_position_count: Integer = self
+                        .trellis
+                        .get_weight_levels()
+                        .iter()
+                        .take_while(|wl| *wl + *weight <= threshold)
+                        .map(|wl| self.trellis.get(n_max - 1, *wl))
+                        .sum();
+                    last_position_count * n_max
+                })
+                .collect();
+        }
+        let first_abandoned_seq = self.sequence_for_index(num_indexes);
+        (0..weights.len())
+            .map(RTS::weight_idx_to_amplitude)
+            .map(|amplitude| {
+                (0..n_max)
+                    .map(|stage| {
+                        self.count_amplitude_in_stage(amplitude, stage, &first_abandoned_seq)
+                    })
+                    .sum::<Integer>()
+            })
+            .collect()
+    }
     /// Returns the amplitude distribution as a [Vec]
     ///
     /// The amplitude distribution is valid if only sequences with indexes
@@ -230,21 +481,60 @@ impl RTS {
             return self.amplitude_distribution_full_utilization();
         }
 
-        let first_abandoned_seq = self.sequence_for_index(&num_sequences_used);
+        self.amplitude_distribution_for_num_indices(&num_sequences_used)
+            .expect("2^num_bits sequences exist")
+    }
+    /// Returns the amplitude distribution if the sequences with the indexes `0..num_indices` are
+    /// used equiprobably
+    ///
+    /// Unlike [RTS::amplitude_distribution()] the number of used indexes is not restricted to a
+    /// power of two, e.g., when framing overhead leaves part of the index space unused. An error
+    /// is returned if `num_indices` is not in `1..=num_sequences`.
+    pub fn amplitude_distribution_for_num_indices(
+        &self,
+        num_indices: &Integer,
+    ) -> Result<Vec<f32>, AdEssError> {
+        if *num_indices <= 0 || *num_indices > self.num_sequences() {
+            return Err(AdEssError::InvalidParameter(
+                "`num_indices` must be between 1 and the number of sequences",
+            ));
+        }
+        let n_max = self.trellis.n_max;
+        Ok(self
+            .amplitude_counts_below(num_indices)
+            .into_iter()
+            .map(|count| {
+                // convert number of occurences to relative frequency
+                Rational::from((count, num_indices * n_max)).to_f32()
+            })
+            .collect())
+    }
+    /// Returns the amplitude distribution for each position of the sequences
+    ///
+    /// `distribution[n][w_idx]` is the probability of the amplitude with weight index `w_idx` at
+    /// position `n`. Unlike the block average [RTS::amplitude_distribution()] this shows the
+    /// edge effects caused by using only indexes representable with [self.num_bits] bits.
+    pub fn amplitude_distribution_per_position(&self) -> Vec<Vec<f32>> {
+        let num_sequences_used = Integer::u_pow_u(2, self.num_bits()).complete();
         let n_max = self.trellis.n_max;
 
-        let num_weights = self.trellis.get_weights().len();
-        let amplitudes = (0..num_weights).map(RTS::weight_idx_to_amplitude);
-
-        let amplitude_counts = amplitudes.map(|amplitude| {
-            (0..n_max)
-                .map(|stage| self.count_amplitude_in_stage(amplitude, stage, &first_abandoned_seq))
-                .sum::<Integer>()
-        });
+        if num_sequences_used == self.num_sequences() {
+            // all sequences are used, the distribution is the same for all positions
+            return vec![self.amplitude_distribution_full_utilization(); n_max];
+        }
 
-        amplitude_counts
-            .map(|amplitude_count| {
-                Rational::from((&amplitude_count, &num_sequences_used * n_max)).to_f32()
+        let first_abandoned_seq = self.sequence_for_index(&num_sequences_used);
+        let num_weights = self.trellis.get_weights().len();
+        (0..n_max)
+            .map(|stage| {
+                (0..num_weights)
+                    .map(RTS::weight_idx_to_amplitude)
+                    .map(|amplitude| {
+                        let amplitude_count =
+                            self.count_amplitude_in_stage(amplitude, stage, &first_abandoned_seq);
+                        Rational::from((&amplitude_count, &num_sequences_used)).to_f32()
+                    })
+                    .collect()
             })
             .collect()
     }
@@ -257,6 +547,10 @@ impl RTS {
         let weight_levels = self.trellis.get_weight_levels();
         let threshold = self.trellis.threshold;
         let num_sequences = self.num_sequences();
+        if n_max == 0 {
+            // sequences without amplitudes
+            return vec![0f32; self.trellis.get_weights().len()];
+        }
 
         self.trellis
             .get_weights()
@@ -272,16 +566,21 @@ impl RTS {
             })
             .collect()
     }
-    // /// Returns the average energy
-    // ///
-    // /// Assumes only indexes representable with [self.num_bits] bits are used.
-    // pub fn average_energy(&self) -> f32 {
-    // let amplitude_distribution = self.amplitude_distribution();
-    // amplitude_distribution
-    // .iter()
-    // .enumerate()
-    // .map(|(w_idx, p)| (RTS::weight_idx_to_amplitude(w_idx) as f32, p))
-    // .map(|(a, p)| a * a * p) // expected value of energy == squared amplitude * probability
-    // .sum::<f32>()
-    // }
+    /// Returns the average energy
+    ///
+    /// Assumes only indexes representable with [self.num_bits] bits are used.
+    pub fn average_energy(&self) -> f32 {
+        let amplitude_distribution = self.amplitude_distribution();
+        amplitude_distribution
+            .iter()
+            .enumerate()
+            .map(|(w_idx, p)| (RTS::weight_idx_to_amplitude(w_idx) as f32, p))
+            .map(|(a, p)| a * a * p) // expected value of energy == squared amplitude * probability
+            .sum::<f32>()
+    }
+    /// Returns the rate loss, energy and complexity figures of this [RTS] for the target
+    /// `distribution`, see [ShapingReport]
+    pub fn analyze(&self, distribution: &[f32]) -> ShapingReport {
+        ShapingReport::new(self, &self.trellis, distribution)
+    }
 }
@@ -0,0 +1,139 @@
+use crate::ad_ess::AdEss;
+use crate::bigint::Integer;
+
+/// Encoder processing one trellis stage per [SequentialEncoder::step()]
+///
+/// Like an iterative hardware implementation, the encoder keeps the weight level of the current
+/// node and the index remaining after the already chosen amplitudes. Each step compares the
+/// remaining index to the values of the successor nodes, outputs one amplitude and subtracts
+/// the number of sequences left below.
+pub struct SequentialEncoder<'a> {
+    adess: &'a AdEss,
+    stage: usize,
+    weight_level: usize,
+    remaining_index: Integer,
+}
+
+impl<'a> SequentialEncoder<'a> {
+    /// Returns an encoder for the sequence of `index` or an error if `index` is out of range
+    pub fn new(adess: &'a AdEss, index: Integer) -> Result<SequentialEncoder<'a>, &'static str> {
+        if index < 0 || index >= adess.num_sequences() {
+            return Err("Index out of range");
+        }
+        Ok(SequentialEncoder {
+            adess,
+            stage: 0,
+            weight_level: 0,
+            remaining_index: index,
+        })
+    }
+    /// Returns the number of already processed stages
+    pub fn stage(&self) -> usize {
+        self.stage
+    }
+    /// Returns the weight level of the current trellis node
+    pub fn weight_level(&self) -> usize {
+        self.weight_level
+    }
+    /// Returns the index within the sequences starting at the current trellis node
+    pub fn remaining_index(&self) -> &Integer {
+        &self.remaining_index
+    }
+    /// Returns `true` if all `n_max` amplitudes are output
+    pub fn is_done(&self) -> bool {
+        self.stage == self.adess.trellis.n_max
+    }
+    /// Processes the next stage and returns its amplitude
+    ///
+    /// Panics if all amplitudes are already output, see [SequentialEncoder::is_done()].
+    pub fn step(&mut self) -> usize {
+        assert!(!self.is_done(), "All stages are processed");
+        let trellis = &self.adess.trellis;
+        for (w_idx, next_wl) in trellis.get_successors(self.weight_level) {
+            let next_wl_value = trellis.get(self.stage + 1, next_wl);
+            if self.remaining_index < next_wl_value {
+                self.stage += 1;
+                self.weight_level = next_wl;
+                return 2 * w_idx + 1;
+            }
+            self.remaining_index -= next_wl_value;
+        }
+        unreachable!("the remaining index is below the value of the current node")
+    }
+}
+
+/// Decoder processing one amplitude per [SequentialDecoder::step()]
+///
+/// The decoder keeps the weight level of the current node and the partial index, i.e., the
+/// number of sequences below the already received prefix.
+pub struct SequentialDecoder<'a> {
+    adess: &'a AdEss,
+    stage: usize,
+    weight_level: usize,
+    index: Integer,
+}
+
+impl<'a> SequentialDecoder<'a> {
+    /// Returns a decoder at the root of the trellis of `adess`
+    pub fn new(adess: &'a AdEss) -> SequentialDecoder<'a> {
+        SequentialDecoder {
+            adess,
+            stage: 0,
+            weight_level: 0,
+            index: Integer::new(),
+        }
+    }
+    /// Returns the number of already processed stages
+    pub fn stage(&self) -> usize {
+        self.stage
+    }
+    /// Returns the weight level of the current trellis node
+    pub fn weight_level(&self) -> usize {
+        self.weight_level
+    }
+    /// Returns the partial index of the already received amplitudes
+    ///
+    /// This is the index of the first sequence starting with the received prefix.
+    pub fn index(&self) -> &Integer {
+        &self.index
+    }
+    /// Returns `true` if all `n_max` amplitudes are received
+    pub fn is_done(&self) -> bool {
+        self.stage == self.adess.trellis.n_max
+    }
+    /// Processes the next `amplitude`
+    ///
+    /// An error is returned if all amplitudes are already received or the amplitude leaves the
+    /// trellis.
+    pub fn step(&mut self, amplitude: usize) -> Result<(), &'static str> {
+        if self.is_done() {
+            return Err("All stages are processed");
+        }
+        if amplitude.is_multiple_of(2) {
+            return Err("Amplitudes must be positive odd numbers");
+        }
+        let trellis = &self.adess.trellis;
+        let target_w_idx = (amplitude - 1) / 2;
+        let mut num_sequences_below = Integer::new();
+        for (w_idx, next_wl) in trellis.get_successors(self.weight_level) {
+            if w_idx == target_w_idx {
+                if trellis.get(self.stage + 1, next_wl) == 0 {
+                    break;
+                }
+                self.index += num_sequences_below;
+                self.stage += 1;
+                self.weight_level = next_wl;
+                return Ok(());
+            }
+            num_sequences_below += trellis.get(self.stage + 1, next_wl);
+        }
+        Err("Amplitude leaves the trellis")
+    }
+    /// Returns the index after all amplitudes are received
+    pub fn finish(self) -> Result<Integer, &'static str> {
+        if !self.is_done() {
+            return Err("Not all stages are processed");
+        }
+        Ok(self.index)
+    }
+}
@@ -0,0 +1,364 @@
+use std::sync::Arc;
+
+use crate::bigint::{Complete, Integer, Rational};
+use crate::error::AdEssError;
+use crate::matcher::DistributionMatcher;
+use crate::rts::RTS;
+use crate::trellis::Trellis;
+use crate::trellis_utils;
+
+/// Shell mapping with Laroia's generating-function approach
+///
+/// The amplitudes are the shells, their cost is the weight. The number of `n` amplitude tuples
+/// with sum weight `c` is the coefficient of `x^c` in `G(x)^n` with `G(x) = sum_w x^weights[w]`.
+/// These coefficients are the node values of a reverse trellis, so [ShellMapper] uses the same
+/// trellis as [RTS] with the same set of sequences. The ordering is the one of classical shell
+/// mapping, see R. Laroia, N. Farvardin and S. A. Tretter, "On optimal shaping of
+/// multidimensional constellations," IEEE Trans. Inf. Theory, vol. 40, no. 4, pp. 1044-1056,
+/// 1994:
+///
+/// - sequences are ordered by their sum weight,
+/// - within a sum weight, a block of `n` amplitudes is split into halves of `floor(n/2)` and
+///   `ceil(n/2)` amplitudes, ordered by the sum weight of the first half,
+/// - within a split, the index `i` is decomposed into `i = i_2 * g_1 + i_1`, where `g_1` is the
+///   number of first halves with their sum weight, and both halves are mapped recursively.
+///
+/// Unlike [RTS], the halves are encoded independently, which is how shell mapping is
+/// implemented with small tables in modems.
+#[derive(Clone)]
+pub struct ShellMapper {
+    pub trellis: Arc<Trellis>,
+}
+
+impl ShellMapper {
+    /// Returns a [ShellMapper] instance which encodes at least `num_bits` bits
+    ///
+    /// The smallest possible trellis that encodes `num_bits` bits is used, see [RTS::new()].
+    pub fn new(num_bits: usize, n_max: usize, weights: &[usize]) -> ShellMapper {
+        ShellMapper::try_new(num_bits, n_max, weights).unwrap()
+    }
+
+    /// Returns a [ShellMapper] instance which encodes at least `num_bits` bits or an error, see
+    /// [RTS::try_new()]
+    pub fn try_new(
+        num_bits: usize,
+        n_max: usize,
+        weights: &[usize],
+    ) -> Result<ShellMapper, AdEssError> {
+        if weights.iter().min() != Some(&0) {
+            return Err(AdEssError::InvalidWeights(
+                "`weights` must not be empty and the smallest weight must be 0",
+            ));
+        }
+        let trellis = trellis_utils::reverse_trellis_upto_num_sequences(
+            Integer::u_pow_u(2, num_bits as u32).complete(),
+            n_max,
+            weights,
+        )?;
+        Ok(ShellMapper {
+            trellis: Arc::new(trellis),
+        })
+    }
+
+    /// Returns a [ShellMapper] sharing the reverse trellis of `rts`
+    pub fn from_rts(rts: &RTS) -> ShellMapper {
+        ShellMapper {
+            trellis: Arc::clone(&rts.trellis),
+        }
+    }
+
+    /// Returns the amplitude value for a given weight index
+    fn weight_idx_to_amplitude(weight_index: usize) -> usize {
+        weight_index * 2 + 1
+    }
+
+    /// Returns the number of `n` amplitude tuples with sum weight `cost`
+    ///
+    /// Only the weight levels up to the threshold are stored, higher sum weights do not occur.
+    fn num_tuples(&self, n: usize, cost: usize) -> Integer {
+        if cost > self.trellis.threshold {
+            return Integer::new();
+        }
+        self.trellis.get_or_0(n, cost)
+    }
+
+    /// Returns the sum weights of the first half for a split of `cost`
+    fn first_half_costs(&self, cost: usize) -> impl Iterator<Item = usize> + '_ {
+        self.trellis.weight_levels_slice()[..self.trellis.get_num_weight_levels()]
+            .iter()
+            .copied()
+            .take_while(move |&cost_1| cost_1 <= cost)
+    }
+}
+
+impl ShellMapper {
+    /// Returns the number of sequences that can be encoded / decoded
+    pub fn num_sequences(&self) -> Integer {
+        self.trellis.stage_sum(self.trellis.n_max)
+    }
+    /// Returns the number of bits that can be encoded / decoded
+    pub fn num_bits(&self) -> u32 {
+        self.num_sequences().significant_bits().saturating_sub(1)
+    }
+    /// Returns the weights used by the internal trellis
+    pub fn get_weights(&self) -> Vec<usize> {
+        self.trellis.get_weights()
+    }
+    /// Returns the amplitude sequence for a given index (encode)
+    pub fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        assert!(
+            *index >= 0 && *index < self.num_sequences(),
+            "Index out of range!"
+        );
+        let n_max = self.trellis.n_max;
+        let mut remaining_index = index.clone();
+        let mut cost = 0;
+        for (&wl, node_value) in self
+            .trellis
+            .weight_levels_slice()
+            .iter()
+            .zip(self.trellis.stage_slice(n_max))
+        {
+            if remaining_index < *node_value {
+                cost = wl;
+                break;
+            }
+            remaining_index -= node_value;
+        }
+
+        let mut sequence = vec![0; n_max];
+        self.encode_cost_class(cost, remaining_index, &mut sequence);
+        sequence
+    }
+    /// Writes the tuple with `index` among all tuples of `out.len()` amplitudes with sum weight
+    /// `cost` into `out`
+    fn encode_cost_class(&self, cost: usize, mut index: Integer, out: &mut [usize]) {
+        if out.len() == 1 {
+            let index = index
+                .to_usize()
+                .expect("at most one tuple per weight index");
+            let (w_idx, _) = self
+                .trellis
+                .weights_slice()
+                .iter()
+                .enumerate()
+                .filter(|(_, &weight)| weight == cost)
+                .nth(index)
+                .expect("the index is smaller than the number of tuples");
+            out[0] = ShellMapper::weight_idx_to_amplitude(w_idx);
+            return;
+        }
+        let n_1 = out.len() / 2;
+        for cost_1 in self.first_half_costs(cost) {
+            let num_first = self.num_tuples(n_1, cost_1);
+            let num_split =
+                Integer::from(&num_first * &self.num_tuples(out.len() - n_1, cost - cost_1));
+            if index < num_split {
+                let (index_2, index_1) = index.div_rem_ref(&num_first).complete();
+                let (first, second) = out.split_at_mut(n_1);
+                self.encode_cost_class(cost_1, index_1, first);
+                self.encode_cost_class(cost - cost_1, index_2, second);
+                return;
+            }
+            index -= num_split;
+        }
+        unreachable!("the index is smaller than the number of tuples");
+    }
+    /// Returns the index for a given amplitude sequence (decode)
+    ///
+    /// Panics if the sequence is not in the trellis, see [ShellMapper::try_index_for_sequence()].
+    pub fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        self.try_index_for_sequence(amplitude_sequence).unwrap()
+    }
+    /// Returns the index for a given amplitude sequence (decode) or an error if the sequence is
+    /// not in the trellis
+    pub fn try_index_for_sequence(
+        &self,
+        amplitude_sequence: &[usize],
+    ) -> Result<Integer, AdEssError> {
+        let n_max = self.trellis.n_max;
+        if amplitude_sequence.len() != n_max {
+            return Err(AdEssError::InvalidSequence(
+                "The amplitude sequence must have `n_max` amplitudes",
+            ));
+        }
+        let num_weights = self.trellis.weights_slice().len();
+        if amplitude_sequence
+            .iter()
+            .any(|&amplitude| amplitude.is_multiple_of(2) || amplitude / 2 >= num_weights)
+        {
+            return Err(AdEssError::InvalidSequence("Amplitude without weight"));
+        }
+        let (cost, index_in_class) = self.decode_cost_class(amplitude_sequence);
+        if cost > self.trellis.threshold {
+            return Err(AdEssError::InvalidSequence(
+                "The sum weight exceeds the largest weight level",
+            ));
+        }
+        let num_lower_end_nodes = self.trellis.get_weight_level_index(cost);
+        let index: Integer = self
+            .trellis
+            .stage_slice(n_max)
+            .iter()
+            .take(num_lower_end_nodes)
+            .sum();
+        Ok(index + index_in_class)
+    }
+    /// Returns the sum weight of `amplitudes` and their index among all tuples with this sum
+    /// weight, see [ShellMapper::encode_cost_class()]
+    fn decode_cost_class(&self, amplitudes: &[usize]) -> (usize, Integer) {
+        let weights = self.trellis.weights_slice();
+        if let [amplitude] = amplitudes {
+            let w_idx = amplitude / 2;
+            let index = weights[..w_idx]
+                .iter()
+                .filter(|&&weight| weight == weights[w_idx])
+                .count();
+            return (weights[w_idx], Integer::from(index));
+        }
+        let n_1 = amplitudes.len() / 2;
+        let (cost_1, index_1) = self.decode_cost_class(&amplitudes[..n_1]);
+        let (cost_2, index_2) = self.decode_cost_class(&amplitudes[n_1..]);
+        let cost = cost_1 + cost_2;
+
+        let mut index = Integer::new();
+        for lower_cost_1 in self.first_half_costs(cost).take_while(|&c| c < cost_1) {
+            index += Integer::from(
+                &self.num_tuples(n_1, lower_cost_1)
+                    * &self.num_tuples(amplitudes.len() - n_1, cost - lower_cost_1),
+            );
+        }
+        index += index_2 * self.num_tuples(n_1, cost_1) + index_1;
+        (cost, index)
+    }
+    /// Returns the amplitude distribution as a [Vec]
+    ///
+    /// The amplitude distribution is valid if only sequences with indexes representable with
+    /// [ShellMapper::num_bits()] bits are used.
+    pub fn amplitude_distribution(&self) -> Vec<f32> {
+        let n_max = self.trellis.n_max;
+        let num_sequences_used = Integer::u_pow_u(2, self.num_bits()).complete();
+
+        // all cost classes below the one of the first unused index are used completely
+        let mut remaining_index = num_sequences_used.clone();
+        let mut counts = vec![Integer::new(); self.trellis.weights_slice().len()];
+        for (&wl, node_value) in self
+            .trellis
+            .weight_levels_slice()
+            .iter()
+            .zip(self.trellis.stage_slice(n_max))
+        {
+            if remaining_index < *node_value {
+                self.add_amplitude_counts(n_max, wl, &remaining_index, &mut counts);
+                break;
+            }
+            self.add_amplitude_counts(n_max, wl, node_value, &mut counts);
+            remaining_index -= node_value;
+        }
+
+        counts
+            .iter()
+            .map(|count| Rational::from((count, &num_sequences_used * n_max)).to_f32())
+            .collect()
+    }
+    /// Adds the amplitude occurrences in the first `num_used` tuples of `n` amplitudes with sum
+    /// weight `cost` to `counts`
+    fn add_amplitude_counts(
+        &self,
+        n: usize,
+        cost: usize,
+        num_used: &Integer,
+        counts: &mut [Integer],
+    ) {
+        let weights = self.trellis.weights_slice();
+        if *num_used == 0 {
+            return;
+        }
+        if *num_used == self.num_tuples(n, cost) {
+            // by symmetry, each position holds `w_idx` in `g_{n-1}(cost - weights[w_idx])` tuples
+            for (count, &weight) in counts.iter_mut().zip(weights) {
+                if weight <= cost {
+                    *count += self.num_tuples(n - 1, cost - weight) * n;
+                }
+            }
+            return;
+        }
+        if n == 1 {
+            let num_used = num_used
+                .to_usize()
+                .expect("at most one tuple per weight index");
+            for (count, _) in counts
+                .iter_mut()
+                .zip(weights)
+                .filter(|(_, &weight)| weight == cost)
+                .take(num_used)
+            {
+                *count += 1;
+            }
+            return;
+        }
+
+        let n_1 = n / 2;
+        let mut remaining = num_used.clone();
+        for cost_1 in self.first_half_costs(cost) {
+            let num_first = self.num_tuples(n_1, cost_1);
+            let num_second = self.num_tuples(n - n_1, cost - cost_1);
+            let num_split = Integer::from(&num_first * &num_second);
+            if remaining >= num_split {
+                let mut split_counts = vec![Integer::new(); counts.len()];
+                self.add_amplitude_counts(n_1, cost_1, &num_first, &mut split_counts);
+                for (count, split_count) in counts.iter_mut().zip(&split_counts) {
+                    *count += Integer::from(split_count * &num_second);
+                }
+                let mut split_counts = vec![Integer::new(); counts.len()];
+                self.add_amplitude_counts(n - n_1, cost - cost_1, &num_second, &mut split_counts);
+                for (count, split_count) in counts.iter_mut().zip(&split_counts) {
+                    *count += Integer::from(split_count * &num_first);
+                }
+                remaining -= num_split;
+                continue;
+            }
+
+            // `index_2` complete rows of first halves and `index_1` tuples of the next row
+            let (index_2, index_1) = remaining.div_rem_ref(&num_first).complete();
+            let mut split_counts = vec![Integer::new(); counts.len()];
+            self.add_amplitude_counts(n_1, cost_1, &num_first, &mut split_counts);
+            for (count, split_count) in counts.iter_mut().zip(&split_counts) {
+                *count += Integer::from(split_count * &index_2);
+            }
+            self.add_amplitude_counts(n_1, cost_1, &index_1, counts);
+            let mut split_counts = vec![Integer::new(); counts.len()];
+            self.add_amplitude_counts(n - n_1, cost - cost_1, &index_2, &mut split_counts);
+            for (count, split_count) in counts.iter_mut().zip(&split_counts) {
+                *count += Integer::from(split_count * &num_first);
+            }
+            let mut second = vec![0; n - n_1];
+            self.encode_cost_class(cost - cost_1, index_2, &mut second);
+            for amplitude in second {
+                counts[amplitude / 2] += &index_1;
+            }
+            return;
+        }
+    }
+}
+
+impl DistributionMatcher for ShellMapper {
+    fn n_max(&self) -> usize {
+        self.trellis.n_max
+    }
+    fn num_bits(&self) -> u32 {
+        ShellMapper::num_bits(self)
+    }
+    fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        ShellMapper::sequence_for_index(self, index)
+    }
+    fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        ShellMapper::index_for_sequence(self, amplitude_sequence)
+    }
+    fn try_index_for_sequence(&self, amplitude_sequence: &[usize]) -> Result<Integer, AdEssError> {
+        ShellMapper::try_index_for_sequence(self, amplitude_sequence)
+    }
+    fn amplitude_distribution(&self) -> Vec<f32> {
+        ShellMapper::amplitude_distribution(self)
+    }
+}
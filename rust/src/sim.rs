@@ -0,0 +1,151 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+
+use crate::error::AdEssError;
+use crate::labeling;
+use crate::matcher::DistributionMatcher;
+use crate::pas::{self, PasFramer};
+use crate::slicer::Slicer;
+use crate::utils;
+
+/// Demapping of the received PAM samples in [simulate_awgn()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Demapping {
+    /// Minimum distance decision of each sample
+    Hard,
+    /// Bitwise decisions on the LLRs of [labeling::llrs_for_samples()] with Gray labeling, i.e.,
+    /// the non-uniform amplitude prior is taken into account
+    Soft,
+}
+
+/// Error rates and rate of an end-to-end simulation, see [simulate_awgn()]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationResult {
+    /// Signal-to-noise ratio (average symbol energy to noise variance) in dB
+    pub snr_db: f32,
+    /// Number of simulated blocks of `n_max` PAM symbols
+    pub num_blocks: usize,
+    /// PAM symbol (amplitude and sign) error rate after demapping
+    pub ser: f64,
+    /// Payload bit error rate after decoding
+    pub ber: f64,
+    /// Fraction of blocks with at least one payload bit error
+    pub bler: f64,
+    /// Payload bits per PAM symbol, see [PasFramer::rate()]
+    pub rate: f32,
+}
+
+/// Simulates probabilistic amplitude shaping over a real AWGN channel
+///
+/// For each of the `num_blocks` blocks a random payload is encoded with the PAS framing of
+/// `matcher` ([PasFramer] with the levels `1, 3, 5, ...`), the PAM symbols are transmitted over
+/// an AWGN channel with the signal-to-noise ratio `snr_db` and demapped with `demapping`. The
+/// demapped amplitudes are decoded without forward error correction. If they do not form a valid
+/// sequence, all shaping index bits of the block are counted as erroneous.
+///
+/// [Demapping::Soft] requires a power of two number of amplitudes. The simulation is
+/// reproducible for a fixed `seed`.
+pub fn simulate_awgn<M: DistributionMatcher>(
+    matcher: &M,
+    snr_db: f32,
+    num_blocks: usize,
+    demapping: Demapping,
+    seed: u64,
+) -> Result<SimulationResult, AdEssError> {
+    let framer = PasFramer::new(matcher)?;
+    let levels = framer.get_levels();
+    let amplitude_distribution = matcher.amplitude_distribution();
+    let labels = labeling::gray_labeling(levels.len());
+    let num_label_bits = levels.len().trailing_zeros() as usize;
+    let average_energy = utils::average_energy(&amplitude_distribution, &levels);
+    let noise_variance = average_energy / 10f32.powf(snr_db / 10.0);
+    let noise = Normal::new(0.0, noise_variance.sqrt())
+        .map_err(|_| AdEssError::InvalidParameter("Invalid noise variance"))?;
+    let slicer = Slicer::for_levels(&levels).map_err(AdEssError::InvalidParameter)?;
+    if demapping == Demapping::Soft {
+        labeling::llrs_for_samples(
+            &amplitude_distribution,
+            &levels,
+            &labels,
+            noise_variance,
+            &[],
+        )?;
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let num_bits = matcher.num_bits() as usize;
+    let mut symbol_errors = 0;
+    let mut bit_errors = 0;
+    let mut block_errors = 0;
+    for _ in 0..num_blocks {
+        let payload: Vec<u8> = (0..framer.payload_bits())
+            .map(|_| rng.gen::<bool>() as u8)
+            .collect();
+        let symbols = framer.symbols_for_payload(&payload)?;
+        let samples: Vec<f32> = symbols
+            .iter()
+            .map(|symbol| symbol + noise.sample(&mut rng))
+            .collect();
+
+        let (amplitudes, sign_bits) = match demapping {
+            Demapping::Hard => pas::amplitudes_for_symbols(&samples, &slicer),
+            Demapping::Soft => {
+                let llrs = labeling::llrs_for_samples(
+                    &amplitude_distribution,
+                    &levels,
+                    &labels,
+                    noise_variance,
+                    &samples,
+                )?;
+                let bits: Vec<u8> = llrs.iter().map(|&llr| (llr < 0.0) as u8).collect();
+                let sign_bits = bits.iter().step_by(num_label_bits + 1).copied().collect();
+                let label_bits: Vec<u8> = bits
+                    .chunks(num_label_bits + 1)
+                    .flat_map(|symbol_bits| &symbol_bits[1..])
+                    .copied()
+                    .collect();
+                let amplitudes = labeling::label_bits_to_amplitudes(&label_bits, &labels)?;
+                (amplitudes, sign_bits)
+            }
+        };
+        let sent_amplitudes =
+            matcher.sequence_for_index(&utils::bits_to_integer(&payload[..num_bits]));
+        symbol_errors += sent_amplitudes
+            .iter()
+            .zip(&amplitudes)
+            .zip(payload[num_bits..].iter().zip(&sign_bits))
+            .filter(|((sent, decided), (sent_sign, decided_sign))| {
+                sent != decided || sent_sign != decided_sign
+            })
+            .count();
+
+        let block_bit_errors = match framer.payload_for_amplitudes(&amplitudes, &sign_bits) {
+            Ok(decoded) => payload
+                .iter()
+                .zip(&decoded)
+                .filter(|(sent, decoded)| sent != decoded)
+                .count(),
+            Err(_) => {
+                num_bits
+                    + payload[num_bits..]
+                        .iter()
+                        .zip(&sign_bits)
+                        .filter(|(sent, decided)| sent != decided)
+                        .count()
+            }
+        };
+        bit_errors += block_bit_errors;
+        block_errors += (block_bit_errors > 0) as usize;
+    }
+
+    let num_blocks_f64 = num_blocks.max(1) as f64;
+    Ok(SimulationResult {
+        snr_db,
+        num_blocks,
+        ser: symbol_errors as f64 / (num_blocks_f64 * matcher.n_max() as f64),
+        ber: bit_errors as f64 / (num_blocks_f64 * framer.payload_bits() as f64),
+        bler: block_errors as f64 / num_blocks_f64,
+        rate: framer.rate(),
+    })
+}
@@ -0,0 +1,122 @@
+use crate::utils;
+
+/// Hard-decision slicer mapping received samples to amplitudes
+///
+/// The magnitude of a sample is compared to the decision boundaries, the sign is handled
+/// separately as in probabilistic amplitude shaping (PAS). `levels[w_idx]` is the value of the
+/// amplitude `2 * w_idx + 1`, the resulting sequences can be passed to
+/// [AdEss::index_for_sequence()](crate::ad_ess::AdEss::index_for_sequence).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slicer {
+    levels: Vec<f32>,
+    boundaries: Vec<f32>,
+}
+
+impl Slicer {
+    /// Returns a minimum distance slicer for the amplitudes `1, 3, 5, ...`
+    pub fn new(num_amplitudes: usize) -> Result<Slicer, &'static str> {
+        Slicer::for_levels(&utils::uniform_levels(num_amplitudes))
+    }
+    /// Returns a minimum distance slicer for strictly increasing, positive `levels`
+    ///
+    /// The decision boundaries are the midpoints between neighboring levels.
+    pub fn for_levels(levels: &[f32]) -> Result<Slicer, &'static str> {
+        let boundaries = levels.windows(2).map(|x| (x[0] + x[1]) / 2.0).collect();
+        Slicer::with_boundaries(levels, boundaries)
+    }
+    /// Returns a maximum a posteriori (MAP) slicer accounting for the shaped prior
+    ///
+    /// A real AWGN channel with noise variance `noise_variance` is assumed, the amplitude with
+    /// weight index `w_idx` is transmitted with probability `amplitude_distribution[w_idx]`. As
+    /// the MAP metric is linear in the sample after dropping common terms, the decision regions
+    /// are intervals given by the upper envelope of these lines. Amplitudes which are never
+    /// decided get an empty interval.
+    pub fn map(
+        levels: &[f32],
+        amplitude_distribution: &[f32],
+        noise_variance: f32,
+    ) -> Result<Slicer, &'static str> {
+        if amplitude_distribution.len() != levels.len() {
+            return Err("One probability per level required");
+        }
+        if noise_variance <= 0.0 || noise_variance.is_nan() {
+            return Err("`noise_variance` must be positive");
+        }
+        // MAP metric: y * slope + offset
+        let slope = |w_idx: usize| levels[w_idx] as f64 / noise_variance as f64;
+        let offset = |w_idx: usize| {
+            (amplitude_distribution[w_idx] as f64).ln()
+                - (levels[w_idx] as f64).powi(2) / (2.0 * noise_variance as f64)
+        };
+        let crossing =
+            |low: usize, high: usize| (offset(low) - offset(high)) / (slope(high) - slope(low));
+
+        // upper envelope of the metric lines, sorted by slope
+        let mut envelope: Vec<usize> = vec![];
+        for w_idx in (0..levels.len()).filter(|&w_idx| amplitude_distribution[w_idx] > 0.0) {
+            while let Some(&last) = envelope.last() {
+                let is_hidden = match envelope.len() {
+                    1 => false,
+                    len => crossing(envelope[len - 2], w_idx) <= crossing(envelope[len - 2], last),
+                };
+                if is_hidden {
+                    envelope.pop();
+                } else {
+                    break;
+                }
+            }
+            envelope.push(w_idx);
+        }
+
+        // boundary between the amplitudes up to `w_idx` and above `w_idx`
+        let boundaries = (0..levels.len().saturating_sub(1))
+            .map(|w_idx| {
+                let below = envelope.iter().rev().find(|&&e| e <= w_idx);
+                let above = envelope.iter().find(|&&e| e > w_idx);
+                match (below, above) {
+                    (Some(&below), Some(&above)) => crossing(below, above) as f32,
+                    (None, _) => f32::NEG_INFINITY,
+                    (_, None) => f32::INFINITY,
+                }
+            })
+            .collect();
+        Slicer::with_boundaries(levels, boundaries)
+    }
+    /// Returns a slicer with custom decision boundaries
+    ///
+    /// `boundaries[w_idx]` separates the amplitudes with weight index up to `w_idx` from the
+    /// higher ones, the boundaries must not decrease.
+    pub fn with_boundaries(levels: &[f32], boundaries: Vec<f32>) -> Result<Slicer, &'static str> {
+        if levels.is_empty() || levels[0] <= 0.0 || levels.windows(2).any(|x| x[0] >= x[1]) {
+            return Err("`levels` must be non empty, positive and strictly increasing");
+        }
+        if boundaries.len() + 1 != levels.len() || boundaries.windows(2).any(|b| b[0] > b[1]) {
+            return Err("One non decreasing boundary between each pair of levels required");
+        }
+        Ok(Slicer {
+            levels: levels.to_vec(),
+            boundaries,
+        })
+    }
+    /// Returns the amplitude levels
+    pub fn get_levels(&self) -> Vec<f32> {
+        self.levels.clone()
+    }
+    /// Returns the decision boundaries
+    pub fn get_boundaries(&self) -> Vec<f32> {
+        self.boundaries.clone()
+    }
+    /// Returns the amplitude for the received `sample`
+    pub fn amplitude_for_sample(&self, sample: f32) -> usize {
+        let magnitude = sample.abs();
+        let w_idx = self.boundaries.partition_point(|&b| b < magnitude);
+        2 * w_idx + 1
+    }
+    /// Returns the amplitude sequence for the received `samples`
+    pub fn sequence_for_samples(&self, samples: &[f32]) -> Vec<usize> {
+        samples
+            .iter()
+            .map(|&sample| self.amplitude_for_sample(sample))
+            .collect()
+    }
+}
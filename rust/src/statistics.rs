@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+use crate::ad_ess::AdEss;
+use crate::bigint::{Complete, Integer, RandState};
+use crate::matcher::DistributionMatcher;
+
+/// Returns the normalized autocorrelation of the amplitude stream for lags `0..=max_lag`
+///
+/// The `sequences` (e.g. from [AdEss::sample_sequences()](crate::ad_ess::AdEss::sample_sequences))
+/// are concatenated to one amplitude stream. The mean amplitude is removed before the
+/// autocorrelation is calculated, so a white stream has values close to zero for all lags > 0.
+pub fn autocorrelation(sequences: &[Vec<usize>], max_lag: usize) -> Vec<f32> {
+    let stream: Vec<f64> = sequences.iter().flatten().map(|&a| a as f64).collect();
+    let mean = stream.iter().sum::<f64>() / stream.len() as f64;
+    let centered: Vec<f64> = stream.iter().map(|a| a - mean).collect();
+
+    let correlation: Vec<f64> = (0..=max_lag)
+        .map(|lag| {
+            let num_products = centered.len().saturating_sub(lag);
+            let sum: f64 = centered
+                .iter()
+                .zip(centered.iter().skip(lag))
+                .map(|(a, b)| a * b)
+                .sum();
+            sum / num_products.max(1) as f64
+        })
+        .collect();
+    correlation
+        .iter()
+        .map(|c| (c / correlation[0]) as f32)
+        .collect()
+}
+
+/// Returns the number of runs of equal amplitudes for each run length
+///
+/// `run_lengths[l]` is the number of runs with length `l`, runs do not cross sequence boundaries.
+pub fn run_length_distribution(sequences: &[Vec<usize>]) -> Vec<usize> {
+    let mut run_lengths = vec![0];
+    for sequence in sequences {
+        let mut run_length = 0;
+        for (n, a) in sequence.iter().enumerate() {
+            run_length += 1;
+            if sequence.get(n + 1) != Some(a) {
+                if run_lengths.len() <= run_length {
+                    run_lengths.resize(run_length + 1, 0);
+                }
+                run_lengths[run_length] += 1;
+                run_length = 0;
+            }
+        }
+    }
+    run_lengths
+}
+
+/// Returns the mean energy `a * a` at each position of the `sequences`
+pub fn mean_energy_per_position(sequences: &[Vec<usize>]) -> Vec<f32> {
+    let n_max = sequences.iter().map(Vec::len).max().unwrap_or(0);
+    let mut energy_sums = vec![0f64; n_max];
+    let mut counts = vec![0usize; n_max];
+    for sequence in sequences {
+        for (n, &a) in sequence.iter().enumerate() {
+            energy_sums[n] += (a * a) as f64;
+            counts[n] += 1;
+        }
+    }
+    energy_sums
+        .iter()
+        .zip(counts)
+        .map(|(sum, count)| (sum / count as f64) as f32)
+        .collect()
+}
+
+/// Distribution of the distances between pairs of distinct sequences
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceSpectrum {
+    /// `hamming[d]` is the number of pairs with Hamming distance `d`
+    pub hamming: Vec<usize>,
+    /// (squared Euclidean distance, number of pairs) sorted by distance
+    pub squared_euclidean: Vec<(usize, usize)>,
+    /// True if all pairs of the used codebook were evaluated
+    pub exact: bool,
+}
+
+/// Returns the distance spectrum of the sequences of `matcher`
+///
+/// If the used codebook (indexes with [DistributionMatcher::num_bits()] bits) holds at most
+/// `max_exact_sequences` sequences, all pairs of distinct sequences are evaluated. Otherwise
+/// `num_samples` pairs of uniformly random distinct indexes are drawn using `seed`.
+pub fn distance_spectrum(
+    matcher: &dyn DistributionMatcher,
+    max_exact_sequences: usize,
+    num_samples: usize,
+    seed: u64,
+) -> DistanceSpectrum {
+    let n_max = matcher.n_max();
+    let mut hamming = vec![0; n_max + 1];
+    let mut squared_euclidean = BTreeMap::new();
+    let mut add_pair = |a: &[usize], b: &[usize]| {
+        hamming[a.iter().zip(b).filter(|(a, b)| a != b).count()] += 1;
+        let distance: usize = a.iter().zip(b).map(|(&a, &b)| a.abs_diff(b).pow(2)).sum();
+        *squared_euclidean.entry(distance).or_insert(0) += 1;
+    };
+
+    let num_sequences_used = Integer::u_pow_u(2, matcher.num_bits()).complete();
+    // at least two sequences are needed to draw pairs of distinct indexes
+    let exact = num_sequences_used <= max_exact_sequences || num_sequences_used < 2;
+    if exact {
+        let sequences: Vec<Vec<usize>> = (0..num_sequences_used.to_usize().unwrap_or(0))
+            .map(|index| matcher.sequence_for_index(&Integer::from(index)))
+            .collect();
+        for (idx, a) in sequences.iter().enumerate() {
+            for b in sequences[idx + 1..].iter() {
+                add_pair(a, b);
+            }
+        }
+    } else {
+        let mut rand = RandState::new();
+        rand.seed(&Integer::from(seed));
+        for _ in 0..num_samples {
+            let index_a = Integer::from(num_sequences_used.random_below_ref(&mut rand));
+            let mut index_b = Integer::from(num_sequences_used.random_below_ref(&mut rand));
+            while index_b == index_a {
+                index_b = Integer::from(num_sequences_used.random_below_ref(&mut rand));
+            }
+            add_pair(
+                &matcher.sequence_for_index(&index_a),
+                &matcher.sequence_for_index(&index_b),
+            );
+        }
+    }
+
+    DistanceSpectrum {
+        hamming,
+        squared_euclidean: squared_euclidean.into_iter().collect(),
+        exact,
+    }
+}
+
+/// Effect of single amplitude substitution errors on the decoded index, see [error_propagation()]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorPropagation {
+    /// Expected number of flipped index bits for a substitution at each position, averaged over
+    /// all substitutions resulting in a sequence of the used codebook
+    pub flipped_bits: Vec<f32>,
+    /// Fraction of the substitutions at each position resulting in a sequence outside of the
+    /// used codebook, i.e., errors which can be detected by the inverse shaper
+    pub detectable: Vec<f32>,
+    /// True if all indexes of the used codebook were evaluated
+    pub exact: bool,
+}
+
+/// Returns the error propagation of the inverse shaper (decoder) of `adess`
+///
+/// For each index and position, the amplitude is replaced by each other amplitude and the
+/// resulting sequence is decoded. The number of flipped index bits quantifies the error
+/// multiplication of ESS. If the used codebook holds at most `max_exact_sequences` sequences, all
+/// indexes are evaluated. Otherwise `num_samples` uniformly random indexes are drawn using `seed`.
+pub fn error_propagation(
+    adess: &AdEss,
+    max_exact_sequences: usize,
+    num_samples: usize,
+    seed: u64,
+) -> ErrorPropagation {
+    let n_max = adess.trellis.n_max;
+    let weights = adess.get_weights();
+    let num_sequences_used = Integer::u_pow_u(2, adess.num_bits()).complete();
+
+    let mut flipped_bits = vec![0usize; n_max];
+    let mut num_decodable = vec![0usize; n_max];
+    let mut num_detectable = vec![0usize; n_max];
+    let mut add_index = |index: &Integer| {
+        let sequence = adess.sequence_for_index(index);
+        let weight: usize = sequence.iter().map(|a| weights[(a - 1) / 2]).sum();
+        for n in 0..n_max {
+            let original_weight = weights[(sequence[n] - 1) / 2];
+            for (w_idx, &w) in weights.iter().enumerate() {
+                let amplitude = 2 * w_idx + 1;
+                if amplitude == sequence[n] {
+                    continue;
+                }
+                if weight - original_weight + w > adess.trellis.threshold {
+                    num_detectable[n] += 1;
+                    continue;
+                }
+                let mut erroneous_sequence = sequence.clone();
+                erroneous_sequence[n] = amplitude;
+                let decoded_index = adess.index_for_sequence(&erroneous_sequence);
+                if decoded_index >= num_sequences_used {
+                    num_detectable[n] += 1;
+                    continue;
+                }
+                num_decodable[n] += 1;
+                flipped_bits[n] +=
+                    (decoded_index ^ index).complete().count_ones().unwrap() as usize;
+            }
+        }
+    };
+
+    let exact = num_sequences_used <= max_exact_sequences;
+    if exact {
+        for index in 0..num_sequences_used.to_usize().unwrap_or(0) {
+            add_index(&Integer::from(index));
+        }
+    } else {
+        let mut rand = RandState::new();
+        rand.seed(&Integer::from(seed));
+        for _ in 0..num_samples {
+            add_index(&Integer::from(
+                num_sequences_used.random_below_ref(&mut rand),
+            ));
+        }
+    }
+
+    ErrorPropagation {
+        flipped_bits: flipped_bits
+            .iter()
+            .zip(&num_decodable)
+            .map(|(&bits, &count)| bits as f32 / count.max(1) as f32)
+            .collect(),
+        detectable: num_detectable
+            .iter()
+            .zip(&num_decodable)
+            .map(|(&detectable, &decodable)| {
+                detectable as f32 / (detectable + decodable).max(1) as f32
+            })
+            .collect(),
+        exact,
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::VecDeque;
+
+use crate::ad_ess::{AdEss, SequenceReport};
+use crate::bigint::{Complete, Integer, Pow};
+use crate::error::AdEssError;
+use crate::utils::{
+    bits_to_integer, bits_to_integer_with_order, integer_to_bits, integer_to_bits_with_order,
+    BitOrder,
+};
+
+/// Experimental streaming distribution matcher with inter-block state
+///
+/// A single [AdEss] block only uses `2^num_bits` of its `num_sequences` indexes. The
+/// [StreamingMatcher] instead uses `fresh_bits = num_bits - residual_bits` bits per block for
+/// the lower part of the index and a digit `r` in base `Q = floor(num_sequences / 2^fresh_bits)`
+/// for the upper part. The digits of `chunk_length` consecutive blocks jointly carry
+/// `chunk_bits` additional bits, i.e., residual index information is carried across blocks.
+///
+/// Decoding is causal with a delay of at most `chunk_length` blocks. Encoder and decoder keep
+/// their state in explicit [StreamingEncoderState] and [StreamingDecoderState] objects.
+pub struct StreamingMatcher {
+    adess: AdEss,
+    fresh_bits: u32,
+    residual_base: Integer,
+    chunk_length: usize,
+    chunk_bits: usize,
+}
+
+/// State of a [StreamingMatcher] encoder
+pub struct StreamingEncoderState {
+    bit_buffer: VecDeque<u8>,
+    residual_digits: VecDeque<Integer>,
+}
+
+/// State of a [StreamingMatcher] decoder
+pub struct StreamingDecoderState {
+    residual_digits: Vec<Integer>,
+    fresh_bits: Vec<u8>,
+}
+
+impl StreamingMatcher {
+    /// Returns a new [StreamingMatcher] using `adess` for the individual blocks
+    ///
+    /// - `residual_bits`: number of index bits per block used to carry residual digits
+    /// - `chunk_length`: number of blocks that jointly carry one chunk of residual bits
+    pub fn new(
+        adess: AdEss,
+        residual_bits: u32,
+        chunk_length: usize,
+    ) -> Result<StreamingMatcher, AdEssError> {
+        if residual_bits > adess.num_bits() {
+            return Err(AdEssError::InvalidParameter(
+                "`residual_bits` must not exceed the number of bits per block",
+            ));
+        }
+        if chunk_length == 0 {
+            return Err(AdEssError::InvalidParameter(
+                "`chunk_length` must be at least 1",
+            ));
+        }
+        let fresh_bits = adess.num_bits() - residual_bits;
+        let residual_base = adess.num_sequences() >> fresh_bits;
+
+        // largest number of bits representable with `chunk_length` residual digits
+        let num_residual_values = residual_base.clone().pow(chunk_length as u32);
+        let chunk_bits = (num_residual_values.significant_bits() - 1) as usize;
+        if fresh_bits == 0 && chunk_bits == 0 {
+            return Err(AdEssError::InvalidParameter(
+                "A chunk must encode at least one bit",
+            ));
+        }
+
+        Ok(StreamingMatcher {
+            adess,
+            fresh_bits,
+            residual_base,
+            chunk_length,
+            chunk_bits,
+        })
+    }
+
+    /// Returns an empty encoder state
+    pub fn encoder_state(&self) -> StreamingEncoderState {
+        StreamingEncoderState {
+            bit_buffer: VecDeque::new(),
+            residual_digits: VecDeque::new(),
+        }
+    }
+
+    /// Returns an empty decoder state
+    pub fn decoder_state(&self) -> StreamingDecoderState {
+        StreamingDecoderState {
+            residual_digits: Vec::with_capacity(self.chunk_length),
+            fresh_bits: vec![],
+        }
+    }
+}
+
+impl StreamingMatcher {
+    /// Returns the [AdEss] of a single block
+    pub fn adess(&self) -> &AdEss {
+        &self.adess
+    }
+    /// Returns the number of blocks which jointly carry one chunk of residual bits
+    pub fn chunk_length(&self) -> usize {
+        self.chunk_length
+    }
+    /// Returns the number of bits transmitted per chunk of `chunk_length` blocks
+    pub fn bits_per_chunk(&self) -> usize {
+        self.chunk_bits + self.chunk_length * self.fresh_bits as usize
+    }
+    /// Returns the average number of bits transmitted per block
+    pub fn bits_per_block(&self) -> f64 {
+        self.bits_per_chunk() as f64 / self.chunk_length as f64
+    }
+    /// Adds `bits` to the encoder state and returns all amplitude sequences that are complete
+    ///
+    /// An error is returned and the state is not changed if `bits` contains values other than
+    /// `0` and `1`.
+    pub fn encode(
+        &self,
+        state: &mut StreamingEncoderState,
+        bits: &[u8],
+    ) -> Result<Vec<Vec<usize>>, AdEssError> {
+        if bits.iter().any(|&bit| bit > 1) {
+            return Err(AdEssError::InvalidParameter(
+                "`bits` must only contain 0 or 1",
+            ));
+        }
+        state.bit_buffer.extend(bits);
+        let fresh_bits = self.fresh_bits as usize;
+
+        let mut sequences = vec![];
+        loop {
+            if state.residual_digits.is_empty() {
+                if state.bit_buffer.len() < self.chunk_bits {
+                    break;
+                }
+                let chunk: Vec<u8> = state.bit_buffer.drain(..self.chunk_bits).collect();
+                state
+                    .residual_digits
+                    .extend(self.residual_digits(bits_to_integer(&chunk)));
+            }
+            if state.bit_buffer.len() < fresh_bits {
+                break;
+            }
+            let block: Vec<u8> = state.bit_buffer.drain(..fresh_bits).collect();
+            let digit = state
+                .residual_digits
+                .pop_front()
+                .expect("checked for emptiness above");
+            let index = (digit << self.fresh_bits) + bits_to_integer(&block);
+            sequences.push(self.adess.try_sequence_for_index(&index)?);
+        }
+        Ok(sequences)
+    }
+    /// Adds `amplitude_sequence` to the decoder state and returns all bits that are complete
+    ///
+    /// An error is returned and the state is not changed if the sequence is not in the trellis
+    /// or its residual digit is not smaller than `Q`, e.g., due to channel errors. If the
+    /// residual of a completed chunk exceeds `chunk_bits` bits, an error is returned and the
+    /// chunk is discarded.
+    pub fn decode(
+        &self,
+        state: &mut StreamingDecoderState,
+        amplitude_sequence: &[usize],
+    ) -> Result<Vec<u8>, AdEssError> {
+        let index = match self.adess.check_sequence(amplitude_sequence) {
+            SequenceReport::Used(index) | SequenceReport::Unused(index) => index,
+            SequenceReport::Invalid { reason, .. } => {
+                return Err(AdEssError::InvalidSequence(reason))
+            }
+        };
+        let digit = index.clone() >> self.fresh_bits;
+        if digit >= self.residual_base {
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        let fresh_mask = Integer::from(Integer::u_pow_u(2, self.fresh_bits)) - 1;
+        let fresh = Integer::from(&index & &fresh_mask);
+        state
+            .fresh_bits
+            .extend(integer_to_bits(&fresh, self.fresh_bits as usize));
+        state.residual_digits.push(digit);
+
+        if state.residual_digits.len() < self.chunk_length {
+            return Ok(vec![]);
+        }
+        let residual = self.residual_from_digits(state.residual_digits.drain(..));
+        if residual.significant_bits() as usize > self.chunk_bits {
+            state.fresh_bits.clear();
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        let mut bits = integer_to_bits(&residual, self.chunk_bits);
+        bits.append(&mut state.fresh_bits);
+        Ok(bits)
+    }
+    /// Splits the `residual` of a chunk into `chunk_length` digits in base `Q`, the least
+    /// significant digit first
+    pub(crate) fn residual_digits(&self, mut residual: Integer) -> Vec<Integer> {
+        let mut digits = Vec::with_capacity(self.chunk_length);
+        for _ in 0..self.chunk_length {
+            let (quotient, digit) = residual.div_rem_ref(&self.residual_base).complete();
+            digits.push(digit);
+            residual = quotient;
+        }
+        digits
+    }
+    /// Returns the residual of a chunk from its `digits`, the least significant digit first, see
+    /// [StreamingMatcher::residual_digits()]
+    pub(crate) fn residual_from_digits(
+        &self,
+        digits: impl DoubleEndedIterator<Item = Integer>,
+    ) -> Integer {
+        digits.rev().fold(Integer::new(), |residual, digit| {
+            residual * &self.residual_base + digit
+        })
+    }
+}
+
+/// Encodes a continuous bit stream into [AdEss] amplitude blocks
+///
+/// Bits (`0` or `1`) are added with [AdEssStreamEncoder::push_bits()] in portions of any length.
+/// Every `num_bits` buffered bits form the index of one block (most significant bit first by
+/// default, see [AdEssStreamEncoder::set_bit_order()]), the blocks are returned by
+/// [AdEssStreamEncoder::next_block()] or by iterating over the encoder.
+/// [AdEssStreamDecoder] recovers the bits.
+pub struct AdEssStreamEncoder {
+    adess: AdEss,
+    bit_buffer: VecDeque<u8>,
+    bit_order: BitOrder,
+}
+
+impl AdEssStreamEncoder {
+    /// Returns a new [AdEssStreamEncoder], `adess` must encode at least one bit per block
+    pub fn new(adess: AdEss) -> Result<AdEssStreamEncoder, AdEssError> {
+        if adess.num_bits() == 0 {
+            return Err(AdEssError::InvalidParameter(
+                "`adess` must encode at least one bit per block",
+            ));
+        }
+        Ok(AdEssStreamEncoder {
+            adess,
+            bit_buffer: VecDeque::new(),
+            bit_order: BitOrder::MsbFirst,
+        })
+    }
+    /// Sets the order of the index bits of each block, most significant bit first by default
+    pub fn set_bit_order(&mut self, bit_order: BitOrder) {
+        self.bit_order = bit_order;
+    }
+    /// Adds `bits` to the end of the stream
+    pub fn push_bits(&mut self, bits: &[u8]) {
+        self.bit_buffer.extend(bits);
+    }
+    /// Returns the number of bits which are not encoded yet
+    pub fn num_buffered_bits(&self) -> usize {
+        self.bit_buffer.len()
+    }
+    /// Returns the amplitude sequence for the next `num_bits` bits, `None` if less bits are
+    /// buffered
+    pub fn next_block(&mut self) -> Option<Vec<usize>> {
+        let num_bits = self.adess.num_bits() as usize;
+        if self.bit_buffer.len() < num_bits {
+            return None;
+        }
+        let bits: Vec<u8> = self.bit_buffer.drain(..num_bits).collect();
+        let index = bits_to_integer_with_order(&bits, self.bit_order);
+        Some(self.adess.sequence_for_index(&index))
+    }
+}
+
+/// Returns the complete blocks of the buffered bits, see [AdEssStreamEncoder::next_block()]
+impl Iterator for AdEssStreamEncoder {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        self.next_block()
+    }
+}
+
+/// Decodes [AdEss] amplitude blocks into a continuous bit stream, see [AdEssStreamEncoder]
+pub struct AdEssStreamDecoder {
+    adess: AdEss,
+    bit_buffer: Vec<u8>,
+    bit_order: BitOrder,
+}
+
+impl AdEssStreamDecoder {
+    /// Returns a new [AdEssStreamDecoder], `adess` must encode at least one bit per block
+    pub fn new(adess: AdEss) -> Result<AdEssStreamDecoder, AdEssError> {
+        if adess.num_bits() == 0 {
+            return Err(AdEssError::InvalidParameter(
+                "`adess` must encode at least one bit per block",
+            ));
+        }
+        Ok(AdEssStreamDecoder {
+            adess,
+            bit_buffer: vec![],
+            bit_order: BitOrder::MsbFirst,
+        })
+    }
+    /// Sets the order of the index bits of each block, see [AdEssStreamEncoder::set_bit_order()]
+    pub fn set_bit_order(&mut self, bit_order: BitOrder) {
+        self.bit_order = bit_order;
+    }
+    /// Decodes `amplitude_sequence` and appends its `num_bits` bits to the buffered bits
+    ///
+    /// An error is returned and no bits are added if the sequence is not in the trellis or its
+    /// index exceeds `num_bits` bits, e.g., due to channel errors.
+    pub fn push_block(&mut self, amplitude_sequence: &[usize]) -> Result<(), AdEssError> {
+        let index = match self.adess.check_sequence(amplitude_sequence) {
+            SequenceReport::Used(index) => index,
+            SequenceReport::Unused(_) => return Err(AdEssError::IndexOutOfRange),
+            SequenceReport::Invalid { reason, .. } => {
+                return Err(AdEssError::InvalidSequence(reason))
+            }
+        };
+        self.bit_buffer.extend(integer_to_bits_with_order(
+            &index,
+            self.adess.num_bits() as usize,
+            self.bit_order,
+        ));
+        Ok(())
+    }
+    /// Returns the number of decoded bits which are not taken yet
+    pub fn num_buffered_bits(&self) -> usize {
+        self.bit_buffer.len()
+    }
+    /// Returns and removes all decoded bits
+    pub fn take_bits(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.bit_buffer)
+    }
+}
@@ -0,0 +1,58 @@
+use crate::ad_ess::AdEss;
+use crate::bigint::{Complete, Integer};
+use crate::error::AdEssError;
+use crate::trellis_utils;
+use crate::utils::{distribution_from_weights, entropy};
+
+/// Operating point of a blocklength sweep, see [sweep_blocklength()]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlocklengthSweepPoint {
+    pub n_max: usize,
+    pub threshold: usize,
+    pub num_bits: u32,
+    /// Rate in bit/amplitude
+    pub rate: f32,
+    /// Rate loss `H(P_A) - rate` in bit/amplitude
+    pub rate_loss: f32,
+    pub average_energy: f32,
+}
+
+/// Returns the rate loss and average energy of [AdEss] for each blocklength in `n_max_list`
+///
+/// The weights are computed once via [AdEss::calc_weights()]. For each blocklength `n_max` the
+/// [AdEss] encodes `floor(n_max * H(P))` bits, where `P` is the target distribution
+/// [AdEss::get_distribution()]. The threshold is found with an incrementally expanded reverse
+/// trellis, see [trellis_utils::reverse_trellis_upto_num_sequences()].
+pub fn sweep_blocklength(
+    distribution: &[f32],
+    res_factor: f32,
+    n_max_list: &[usize],
+) -> Result<Vec<BlocklengthSweepPoint>, AdEssError> {
+    let weights = AdEss::calc_weights(distribution, res_factor)?;
+    let target_entropy = entropy(&distribution_from_weights(&weights, res_factor));
+
+    n_max_list
+        .iter()
+        .map(|&n_max| {
+            let num_bits = (n_max as f32 * target_entropy).floor() as u32;
+            let reverse_trellis = trellis_utils::reverse_trellis_upto_num_sequences(
+                Integer::u_pow_u(2, num_bits).complete(),
+                n_max,
+                &weights,
+            )?;
+            let threshold = reverse_trellis.threshold;
+
+            let adess = AdEss::new(threshold, n_max, &weights);
+            let amplitude_distribution = adess.amplitude_distribution();
+            let rate = adess.num_bits() as f32 / n_max as f32;
+            Ok(BlocklengthSweepPoint {
+                n_max,
+                threshold,
+                num_bits: adess.num_bits(),
+                rate,
+                rate_loss: entropy(&amplitude_distribution) - rate,
+                average_energy: adess.average_energy(),
+            })
+        })
+        .collect()
+}
@@ -1,13 +1,45 @@
-use rug::rand::RandState;
-use rug::{Complete, Integer};
-
-use crate::ad_ess::AdEss;
-use crate::trellis::Trellis;
+use crate::ad_ess::{AdEss, EncodingInfo, RoundingMode, SequenceReport};
+use crate::bigint::{Complete, Integer, Order, RandState};
+use crate::binary::BinaryDm;
+use crate::bounded_ess::BoundedAdEss;
+use crate::cancel::CancellationToken;
+use crate::capacity;
+use crate::ccdm::CCDM;
+use crate::chained::ChainedAdEss;
+use crate::compact::{AutoAdEss, CompactAdEss};
+use crate::compare;
+use crate::complexity::{self, ThresholdOrBits};
+use crate::dyadic;
+use crate::error::AdEssError;
+use crate::export;
+use crate::hidm::HiDm;
+use crate::inference;
+use crate::labeling;
+use crate::lattice::{self, Lattice, LatticeShellShaper};
+use crate::matcher::DistributionMatcher;
+use crate::npy;
+use crate::pas::{self, PasFramer, QamMapper, QamSymbol};
+use crate::pcdm::PCDM;
+use crate::pipeline::{self, PipelineConfig};
+use crate::presets;
+use crate::rate_ladder;
+use crate::registry::TrellisRegistry;
+use crate::report::ShapingReport;
+use crate::trellis::{self, Trellis};
 
 use crate::rts::RTS;
+use crate::sequential::{SequentialDecoder, SequentialEncoder};
+use crate::shell_mapping::ShellMapper;
+use crate::slicer::Slicer;
+use crate::statistics;
+use crate::streaming::{AdEssStreamDecoder, AdEssStreamEncoder, StreamingMatcher};
+use crate::sweep;
 
 use crate::trellis_utils;
-use crate::utils;
+use crate::types::{self, Amplitude, WeightIdx, WeightLevel};
+use crate::utils::{self, BitOrder};
+use crate::wedge::WedgeAdEss;
+use crate::workspace::Workspace;
 
 #[test]
 fn incremental_reverse_trellis_vs_traditional() {
@@ -103,7 +135,7 @@ fn reverse_trellis_lexicographically_bounded() {
 
 #[test]
 fn amplitude_distribution_paper_example() {
-    let adess = AdEss::new(7, 4, &vec![0, 1, 3, 6]);
+    let adess = AdEss::new(7, 4, &[0, 1, 3, 6]);
     let amp_dist = adess.amplitude_distribution();
     let amp_frequencies: Vec<f32> = amp_dist
         .iter()
@@ -120,7 +152,7 @@ fn amplitude_distribution_paper_example() {
 
 #[test]
 fn average_energy_paper_example() {
-    let adess = AdEss::new(7, 4, &vec![0, 1, 3, 6]);
+    let adess = AdEss::new(7, 4, &[0, 1, 3, 6]);
     let mut e_acc = 0;
     let num_sequences_used = 2_i32.pow(adess.num_bits());
     for idx in 0..num_sequences_used {
@@ -132,6 +164,20 @@ fn average_energy_paper_example() {
     assert_eq!(adess.average_energy(), e_avg);
 }
 
+#[test]
+fn rts_average_energy_enumeration() {
+    let rts = RTS::new(6, 4, &[0, 1, 3, 6]);
+    let mut e_acc = 0;
+    let num_sequences_used = 2_i32.pow(rts.num_bits());
+    for idx in 0..num_sequences_used {
+        let seq = rts.sequence_for_index(&Integer::from(idx));
+        let seq_energy: usize = seq.iter().map(|a| a * a).sum();
+        e_acc += seq_energy;
+    }
+    let e_avg = e_acc as f32 / num_sequences_used as f32 / rts.trellis.n_max as f32;
+    assert!((rts.average_energy() - e_avg).abs() < 1e-5);
+}
+
 #[test]
 fn average_energy_montecarlo() {
     let n_max = 224;
@@ -189,7 +235,7 @@ fn differentiate_static() {
 
 #[test]
 fn rts_toy_example() {
-    let rts = RTS::new(4, 4, &vec![0, 1, 3, 6]);
+    let rts = RTS::new(4, 4, &[0, 1, 3, 6]);
     let example_sequences = vec![
         vec![1, 1, 1, 1],
         vec![1, 1, 1, 3],
@@ -217,6 +263,27 @@ fn rts_toy_example() {
         println!("{} -> {:?} | {:?} -> {}", idx, seq_calc, seq, idx_calc);
         assert_eq!(seq_calc, *seq);
         assert_eq!(idx_calc, idx);
+        assert_eq!(rts.try_index_for_sequence(seq), Ok(idx_calc));
+    }
+
+    // invalid indexes and sequences
+    let num_sequences = Integer::from(example_sequences.len());
+    assert_eq!(
+        rts.try_sequence_for_index(&num_sequences),
+        Err(AdEssError::IndexOutOfRange)
+    );
+    assert!(rts.try_sequence_for_index(&Integer::from(-1)).is_err());
+    for seq in [
+        vec![1, 1, 1],
+        vec![0, 1, 1, 1],
+        vec![1, 1, 2, 1],
+        vec![1, 1, 1, 9],
+        vec![1, 1, 3, 5],
+    ] {
+        assert!(matches!(
+            rts.try_index_for_sequence(&seq),
+            Err(AdEssError::InvalidSequence(_))
+        ));
     }
 }
 
@@ -246,7 +313,7 @@ fn rts_non_unique_weights_toy_example() {
         vec![3, 3, 1],
         vec![7, 1, 1],
     ];
-    let rts = RTS::new(5, 3, &vec![0, 1, 1, 2]);
+    let rts = RTS::new(5, 3, &[0, 1, 1, 2]);
     for (idx, seq) in example_sequences.iter().enumerate() {
         let seq_calc = rts.sequence_for_index(&Integer::from(idx));
         let idx_calc = rts.index_for_sequence(seq);
@@ -258,7 +325,7 @@ fn rts_non_unique_weights_toy_example() {
 
 #[test]
 fn rts_non_unique_unordered_weights() {
-    let rts = RTS::new(7, 4, &vec![2, 0, 5, 2]);
+    let rts = RTS::new(7, 4, &[2, 0, 5, 2]);
     let num_seq = rts.num_sequences().to_usize().unwrap();
     for idx in 0..num_seq {
         let seq_calc = rts.sequence_for_index(&Integer::from(idx));
@@ -270,7 +337,7 @@ fn rts_non_unique_unordered_weights() {
 
 #[test]
 fn rts_multiple_non_unique_weights() {
-    let rts = RTS::new(10, 4, &vec![0, 0, 1, 1, 1, 2, 3]);
+    let rts = RTS::new(10, 4, &[0, 0, 1, 1, 1, 2, 3]);
     trellis_utils::pprint_trellis(&rts.trellis);
     let num_seqences = rts.num_sequences().to_u32().unwrap();
     for idx in 0..num_seqences {
@@ -282,7 +349,7 @@ fn rts_multiple_non_unique_weights() {
 
 #[test]
 fn rts_amplitude_distribution_full_utilization_toy_example() {
-    let rts = RTS::new(8, 5, &vec![0, 1, 1, 3]);
+    let rts = RTS::new(8, 5, &[0, 1, 1, 3]);
     let num_seqences = rts.num_sequences().to_u32().unwrap();
     let n_max = rts.trellis.n_max;
     trellis_utils::pprint_trellis(&rts.trellis);
@@ -312,10 +379,10 @@ fn rts_amplitude_distribution_full_utilization_toy_example() {
 #[test]
 fn rts_amplitude_distribution() {
     let rts_list = vec![
-        RTS::new(7, 4, &vec![2, 0, 2, 5]),
-        RTS::new(2, 3, &vec![2, 0, 2, 5]),
-        RTS::new(8, 5, &vec![2, 0, 2, 5]),
-        RTS::new(8, 5, &vec![0, 1, 1, 3]),
+        RTS::new(7, 4, &[2, 0, 2, 5]),
+        RTS::new(2, 3, &[2, 0, 2, 5]),
+        RTS::new(8, 5, &[2, 0, 2, 5]),
+        RTS::new(8, 5, &[0, 1, 1, 3]),
     ];
 
     for rts in rts_list {
@@ -367,11 +434,11 @@ fn rts_amplitude_distribution() {
 #[test]
 fn adess_encoding_decoding() {
     let adess_list = vec![
-        AdEss::new(9, 4, &vec![2, 0, 2, 5]),
-        AdEss::new(8, 5, &vec![2, 0, 2, 5]),
-        AdEss::new(6, 3, &vec![5, 0, 2, 0]),
-        AdEss::new(4, 5, &vec![0, 1, 1, 3]),
-        AdEss::new(30, 5, &vec![0, 1, 3, 6]),
+        AdEss::new(9, 4, &[2, 0, 2, 5]),
+        AdEss::new(8, 5, &[2, 0, 2, 5]),
+        AdEss::new(6, 3, &[5, 0, 2, 0]),
+        AdEss::new(4, 5, &[0, 1, 1, 3]),
+        AdEss::new(30, 5, &[0, 1, 3, 6]),
     ];
     for adess in adess_list {
         let num_seq = adess.num_sequences().to_u32().unwrap();
@@ -386,11 +453,11 @@ fn adess_encoding_decoding() {
 #[test]
 fn adess_amplitude_distribution_full_utilization() {
     let adess_list = vec![
-        AdEss::new(9, 4, &vec![2, 0, 2, 5]),
-        AdEss::new(8, 5, &vec![2, 0, 2, 5]),
-        AdEss::new(6, 3, &vec![5, 0, 2, 0]),
-        AdEss::new(4, 5, &vec![0, 1, 1, 3]),
-        AdEss::new(30, 5, &vec![0, 1, 3, 6]),
+        AdEss::new(9, 4, &[2, 0, 2, 5]),
+        AdEss::new(8, 5, &[2, 0, 2, 5]),
+        AdEss::new(6, 3, &[5, 0, 2, 0]),
+        AdEss::new(4, 5, &[0, 1, 1, 3]),
+        AdEss::new(30, 5, &[0, 1, 3, 6]),
     ];
     for adess in adess_list {
         let num_seq = adess.num_sequences().to_usize().unwrap();
@@ -417,11 +484,11 @@ fn adess_amplitude_distribution_full_utilization() {
 #[test]
 fn adess_amplitude_distribution() {
     let adess_list = vec![
-        AdEss::new(9, 4, &vec![2, 0, 2, 5]),
-        AdEss::new(8, 5, &vec![2, 0, 2, 5]), // full utilization
-        AdEss::new(6, 3, &vec![5, 0, 2, 0]),
-        AdEss::new(4, 5, &vec![0, 1, 1, 3]),  // full utilization
-        AdEss::new(30, 5, &vec![0, 1, 3, 6]), // ESS
+        AdEss::new(9, 4, &[2, 0, 2, 5]),
+        AdEss::new(8, 5, &[2, 0, 2, 5]), // full utilization
+        AdEss::new(6, 3, &[5, 0, 2, 0]),
+        AdEss::new(4, 5, &[0, 1, 1, 3]),  // full utilization
+        AdEss::new(30, 5, &[0, 1, 3, 6]), // ESS
     ];
     for adess in adess_list {
         let num_bits = adess.num_bits();
@@ -451,11 +518,11 @@ fn adess_amplitude_distribution() {
 #[test]
 fn adess_average_energy() {
     let adess_list = vec![
-        AdEss::new(9, 4, &vec![2, 0, 2, 5]),
-        AdEss::new(8, 5, &vec![2, 0, 2, 5]), // full utilization
-        AdEss::new(6, 3, &vec![5, 0, 2, 0]),
-        AdEss::new(4, 5, &vec![0, 1, 1, 3]),  // full utilization
-        AdEss::new(30, 5, &vec![0, 1, 3, 6]), // ESS
+        AdEss::new(9, 4, &[2, 0, 2, 5]),
+        AdEss::new(8, 5, &[2, 0, 2, 5]), // full utilization
+        AdEss::new(6, 3, &[5, 0, 2, 0]),
+        AdEss::new(4, 5, &[0, 1, 1, 3]),  // full utilization
+        AdEss::new(30, 5, &[0, 1, 3, 6]), // ESS
     ];
     for adess in adess_list {
         let num_bits = adess.num_bits();
@@ -475,3 +542,3145 @@ fn adess_average_energy() {
         assert_eq!(energy, (num_amplitudes * avg_energy).round() as usize)
     }
 }
+
+#[test]
+fn bounded_adess_encoding_decoding() {
+    let configs = vec![
+        (9, 4, vec![2, 0, 2, 5]),
+        (6, 3, vec![5, 0, 2, 0]),
+        (30, 5, vec![0, 1, 3, 6]),
+    ];
+    for (threshold, n_max, weights) in configs {
+        let bounded = BoundedAdEss::new(threshold, n_max, &weights, 3).unwrap();
+        let num_seq = bounded.num_sequences().to_u32().unwrap();
+        for i in 0..num_seq {
+            let seq = bounded.sequence_for_index(&Integer::from(i));
+            let decoded_i = bounded.index_for_sequence(&seq).to_u32().unwrap();
+            assert_eq!(i, decoded_i);
+        }
+
+        // all sequences below the threshold, only the encoded ones are decoded
+        let num_weights = weights.len();
+        let mut num_decoded = 0;
+        for combination in 0..num_weights.pow(n_max as u32) {
+            let weight_idxs: Vec<usize> = (0..n_max)
+                .map(|n| combination / num_weights.pow(n as u32) % num_weights)
+                .collect();
+            let seq: Vec<usize> = weight_idxs.iter().map(|w_idx| 2 * w_idx + 1).collect();
+            let sum_weight: usize = weight_idxs.iter().map(|&w_idx| weights[w_idx]).sum();
+            match bounded.try_index_for_sequence(&seq) {
+                Ok(index) => {
+                    assert_eq!(bounded.sequence_for_index(&index), seq);
+                    num_decoded += 1;
+                }
+                Err(err) => assert!(matches!(err, AdEssError::InvalidSequence(_))),
+            }
+            assert!(sum_weight <= threshold || bounded.try_index_for_sequence(&seq).is_err());
+        }
+        assert_eq!(num_decoded, num_seq);
+
+        // the amplitude distribution counts the encoded sequences
+        let num_used = 1u32 << bounded.num_bits();
+        let mut counts = vec![0u32; num_weights];
+        for i in 0..num_used {
+            for a in bounded.sequence_for_index(&Integer::from(i)) {
+                counts[(a - 1) / 2] += 1;
+            }
+        }
+        let expected: Vec<f32> = counts
+            .iter()
+            .map(|&count| count as f32 / (num_used as usize * n_max) as f32)
+            .collect();
+        let matcher: &dyn DistributionMatcher = &bounded;
+        assert_eq!(matcher.amplitude_distribution(), expected);
+        assert_eq!(matcher.num_bits(), bounded.num_bits());
+    }
+}
+
+#[test]
+fn bounded_adess_vs_exact() {
+    let weights = [0, 1, 3, 6];
+    let exact = AdEss::new(40, 24, &weights);
+
+    // with enough precision the bounded trellis equals the exact trellis
+    let bounded = BoundedAdEss::new(40, 24, &weights, 64).unwrap();
+    assert_eq!(bounded.num_sequences(), exact.num_sequences());
+    for precision in [0, 65] {
+        assert!(matches!(
+            BoundedAdEss::new(40, 24, &weights, precision),
+            Err(AdEssError::InvalidParameter(_))
+        ));
+    }
+    assert!(BoundedAdEss::new(40, 24, &[1, 3], 8).is_err());
+
+    // with low precision the number of sequences can only decrease
+    let bounded = BoundedAdEss::new(40, 24, &weights, 8).unwrap();
+    println!(
+        "exact: {} bits, bounded: {} bits",
+        exact.num_bits(),
+        bounded.num_bits()
+    );
+    assert!(bounded.num_sequences() <= exact.num_sequences());
+    assert!(exact.num_bits() - bounded.num_bits() <= 1);
+
+    let index = Integer::from(123456);
+    let seq = bounded.sequence_for_index(&index);
+    assert_eq!(bounded.index_for_sequence(&seq), index);
+
+    // node values with several limbs
+    let bounded = BoundedAdEss::new(400, 200, &weights, 16).unwrap();
+    assert!(bounded.num_bits() > 200);
+    let mut rand = RandState::new();
+    for index in (0..20)
+        .map(|_| Integer::from(bounded.num_sequences().random_below_ref(&mut rand)))
+        .chain([Integer::new(), bounded.num_sequences() - 1u32])
+    {
+        let seq = bounded.sequence_for_index(&index);
+        assert_eq!(bounded.index_for_sequence(&seq), index);
+    }
+    assert!(bounded
+        .try_sequence_for_index(&bounded.num_sequences())
+        .is_err());
+    assert!(bounded.try_sequence_for_index(&Integer::from(-1)).is_err());
+}
+
+#[test]
+fn dyadic_approximation_and_matcher() {
+    // a dyadic distribution is its own best approximation
+    assert_eq!(
+        dyadic::dyadic_approximation(&[0.5, 0.25, 0.125, 0.125]),
+        vec![0.5, 0.25, 0.125, 0.125]
+    );
+    // very unlikely amplitudes are dropped
+    assert_eq!(
+        dyadic::geometric_huffman_lengths(&[0.5, 0.4, 0.05, 0.05]),
+        vec![Some(1), Some(1), None, None]
+    );
+
+    let distribution = [0.4, 0.3, 0.2, 0.1];
+    let matcher = dyadic::DyadicMatcher::new(&distribution).unwrap();
+    let dyadic_distribution = matcher.get_distribution();
+    assert_eq!(dyadic_distribution.iter().sum::<f32>(), 1.0);
+    assert_eq!(matcher.rate(), utils::entropy(&dyadic_distribution));
+
+    let bits = [1, 0, 0, 1, 1, 1, 0, 1, 0, 0, 0, 1, 1];
+    let (amplitudes, consumed) = matcher.encode(&bits);
+    assert_eq!(matcher.decode(&amplitudes), bits[..consumed].to_vec());
+}
+
+#[test]
+fn streaming_matcher_encoding_decoding() {
+    let adess = AdEss::new(30, 10, &[0, 1, 3, 6]);
+    let matcher = StreamingMatcher::new(adess.clone(), 6, 8).unwrap();
+    println!(
+        "num_bits: {}, bits per block: {}",
+        adess.num_bits(),
+        matcher.bits_per_block()
+    );
+    assert!(matcher.bits_per_block() > adess.num_bits() as f64);
+    assert!(matcher.bits_per_block() <= adess.num_sequences().to_f64().log2());
+
+    let mut rand = RandState::new();
+    let num_bits = 10 * matcher.bits_per_chunk() + 7;
+    let tx_bits: Vec<u8> = (0..num_bits).map(|_| rand.bits(1) as u8).collect();
+
+    let mut encoder_state = matcher.encoder_state();
+    let mut decoder_state = matcher.decoder_state();
+    let mut rx_bits = vec![];
+    // feed the bits in irregular portions
+    for bits in tx_bits.chunks(13) {
+        for sequence in matcher.encode(&mut encoder_state, bits).unwrap() {
+            rx_bits.extend(matcher.decode(&mut decoder_state, &sequence).unwrap());
+        }
+    }
+    assert_eq!(rx_bits.len(), 10 * matcher.bits_per_chunk());
+    assert_eq!(rx_bits, tx_bits[..rx_bits.len()].to_vec());
+
+    // invalid bits and received sequences are rejected without changing the states
+    assert!(matcher.encode(&mut encoder_state, &[0, 2]).is_err());
+    let mut invalid_sequence = adess.sequence_for_index(&Integer::from(0));
+    invalid_sequence[0] = 2;
+    assert!(matches!(
+        matcher.decode(&mut decoder_state, &invalid_sequence),
+        Err(AdEssError::InvalidSequence(_))
+    ));
+    // the last index carries a residual digit `Q` which is never encoded
+    let last_index = adess.num_sequences() - 1;
+    let last_sequence = adess.sequence_for_index(&last_index);
+    assert!(matches!(
+        matcher.decode(&mut decoder_state, &last_sequence),
+        Err(AdEssError::IndexOutOfRange)
+    ));
+
+    // a chunk without bits would never be completed
+    let adess = AdEss::new(0, 4, &[0, 1]);
+    assert_eq!(adess.num_bits(), 0);
+    assert!(StreamingMatcher::new(adess, 0, 4).is_err());
+}
+
+#[test]
+fn stream_encoder_decoder() {
+    let adess = AdEss::new(30, 10, &[0, 1, 3, 6]);
+    let num_bits = adess.num_bits() as usize;
+    let mut encoder = AdEssStreamEncoder::new(adess.clone()).unwrap();
+    let mut decoder = AdEssStreamDecoder::new(adess.clone()).unwrap();
+
+    let mut rand = RandState::new();
+    let tx_bits: Vec<u8> = (0..12 * num_bits + 5).map(|_| rand.bits(1) as u8).collect();
+    let mut num_blocks = 0;
+    for bits in tx_bits.chunks(7) {
+        encoder.push_bits(bits);
+        for sequence in encoder.by_ref() {
+            decoder.push_block(&sequence).unwrap();
+            num_blocks += 1;
+        }
+    }
+    assert_eq!(num_blocks, 12);
+    assert_eq!(encoder.num_buffered_bits(), 5);
+    assert!(encoder.next_block().is_none());
+    assert_eq!(decoder.num_buffered_bits(), 12 * num_bits);
+    assert_eq!(decoder.take_bits(), tx_bits[..12 * num_bits].to_vec());
+    assert_eq!(decoder.num_buffered_bits(), 0);
+
+    let unused = adess.sequence_for_index(&(adess.num_sequences() - 1u32));
+    assert!(decoder.push_block(&unused).is_err());
+    assert!(decoder.push_block(&[1, 3]).is_err());
+    assert_eq!(decoder.num_buffered_bits(), 0);
+}
+
+#[test]
+fn index_bytes_conversion() {
+    let adess = AdEss::new(30, 10, &[0, 1, 3, 6]);
+    let rts = RTS::from_adess(&adess);
+    let num_bits = adess.num_bits();
+    let num_bytes = (num_bits as usize).div_ceil(8);
+    assert_eq!(num_bits, rts.num_bits());
+    assert!(!num_bits.is_multiple_of(8));
+
+    let index = (Integer::from(1) << (num_bits - 1)) + 0x1234u32;
+    let mut bytes = vec![0u8; num_bytes];
+    utils::integer_to_bytes(&index, BitOrder::MsbFirst, &mut bytes);
+    assert_eq!(utils::bytes_to_integer(&bytes, BitOrder::MsbFirst), index);
+
+    let sequence = adess
+        .sequence_for_index_bytes(&bytes, BitOrder::MsbFirst)
+        .unwrap();
+    assert_eq!(sequence, adess.sequence_for_index(&index));
+    let mut out = vec![0xffu8; num_bytes];
+    adess
+        .index_for_sequence_bytes(&sequence, BitOrder::MsbFirst, &mut out)
+        .unwrap();
+    assert_eq!(out, bytes);
+
+    let sequence = rts
+        .sequence_for_index_bytes(&bytes, BitOrder::MsbFirst)
+        .unwrap();
+    assert_eq!(sequence, rts.sequence_for_index(&index));
+    rts.index_for_sequence_bytes(&sequence, BitOrder::MsbFirst, &mut out)
+        .unwrap();
+    assert_eq!(out, bytes);
+
+    // leading padding bits must be 0
+    let mut too_large = bytes.clone();
+    too_large[0] |= 0x80;
+    assert_eq!(
+        adess.sequence_for_index_bytes(&too_large, BitOrder::MsbFirst),
+        Err(AdEssError::IndexOutOfRange)
+    );
+    assert!(rts
+        .sequence_for_index_bytes(&bytes[1..], BitOrder::MsbFirst)
+        .is_err());
+    let unused = adess.sequence_for_index(&(adess.num_sequences() - 1u32));
+    assert!(adess
+        .index_for_sequence_bytes(&unused, BitOrder::MsbFirst, &mut out)
+        .is_err());
+    assert!(adess
+        .index_for_sequence_bytes(&sequence, BitOrder::MsbFirst, &mut [0u8; 1])
+        .is_err());
+    let mut invalid = sequence.clone();
+    invalid[0] = 2;
+    assert_eq!(
+        rts.index_for_sequence_bytes(&invalid, BitOrder::MsbFirst, &mut out),
+        Err(AdEssError::InvalidSequence("Amplitude without weight"))
+    );
+    assert!(rts
+        .index_for_sequence_bytes(&sequence[1..], BitOrder::MsbFirst, &mut out)
+        .is_err());
+}
+
+#[test]
+fn bit_order_conversion() {
+    let index = Integer::from(0b1101_0000_0110u32);
+    let msb_first = utils::integer_to_bits_with_order(&index, 12, BitOrder::MsbFirst);
+    let lsb_first = utils::integer_to_bits_with_order(&index, 12, BitOrder::LsbFirst);
+    assert_eq!(msb_first, [1, 1, 0, 1, 0, 0, 0, 0, 0, 1, 1, 0]);
+    assert_eq!(
+        lsb_first,
+        msb_first.iter().rev().copied().collect::<Vec<u8>>()
+    );
+    assert_eq!(
+        utils::bits_to_integer_with_order(&lsb_first, BitOrder::LsbFirst),
+        index
+    );
+
+    // LSB first bytes are the LSB first bits packed into bytes
+    let mut bytes = [0xffu8; 2];
+    utils::integer_to_bytes(&index, BitOrder::LsbFirst, &mut bytes);
+    assert_eq!(bytes, [0b0000_0110, 0b0000_1101]);
+    utils::integer_to_bytes(&index, BitOrder::MsbFirst, &mut bytes);
+    assert_eq!(bytes, [0b0000_1101, 0b0000_0110]);
+
+    let adess = std::sync::Arc::new(AdEss::new(30, 10, &[0, 1, 3, 6]));
+    let num_bits = adess.num_bits() as usize;
+    let mut workspace = Workspace::new(adess.clone());
+    workspace.set_bit_order(BitOrder::LsbFirst);
+    let index = Integer::from(0b1011u32);
+    let bits = utils::integer_to_bits_with_order(&index, num_bits, BitOrder::LsbFirst);
+    let mut sequence = vec![0; 10];
+    workspace.encode_bits_into(&bits, &mut sequence).unwrap();
+    assert_eq!(sequence, adess.sequence_for_index(&index));
+    let mut decoded = vec![0; num_bits];
+    workspace.decode_bits_into(&sequence, &mut decoded).unwrap();
+    assert_eq!(decoded, bits);
+
+    let mut bytes = vec![0u8; num_bits.div_ceil(8)];
+    adess
+        .index_for_sequence_bytes(&sequence, BitOrder::LsbFirst, &mut bytes)
+        .unwrap();
+    assert_eq!(bytes[0], 0b1011);
+    assert_eq!(
+        adess
+            .sequence_for_index_bytes(&bytes, BitOrder::LsbFirst)
+            .unwrap(),
+        sequence
+    );
+
+    let mut encoder = AdEssStreamEncoder::new((*adess).clone()).unwrap();
+    encoder.set_bit_order(BitOrder::LsbFirst);
+    encoder.push_bits(&bits);
+    assert_eq!(encoder.next_block().unwrap(), sequence);
+}
+
+#[test]
+fn pas_framing() {
+    let adess = AdEss::new(30, 10, &[0, 1, 3, 6]);
+    let framer = PasFramer::new(&adess).unwrap();
+    assert_eq!(framer.payload_bits(), adess.num_bits() as usize + 10);
+
+    let mut rand = RandState::new();
+    let payload: Vec<u8> = (0..framer.payload_bits())
+        .map(|_| rand.bits(1) as u8)
+        .collect();
+    let (index_bits, sign_bits) = framer.split_payload(&payload).unwrap();
+    assert_eq!(sign_bits.len(), 10);
+    let symbols = framer.symbols_for_payload(&payload).unwrap();
+    let amplitudes = adess.sequence_for_index(&utils::bits_to_integer(index_bits));
+    for ((symbol, &amplitude), &sign_bit) in symbols.iter().zip(&amplitudes).zip(sign_bits) {
+        assert_eq!(symbol.abs(), amplitude as f32);
+        assert_eq!(symbol.is_sign_negative(), sign_bit == 1);
+    }
+
+    // small noise is corrected by the slicer
+    let noisy: Vec<f32> = symbols.iter().map(|symbol| symbol * 1.1).collect();
+    assert_eq!(framer.payload_for_symbols(&noisy).unwrap(), payload);
+    assert!(framer.payload_for_symbols(&noisy[1..]).is_err());
+    assert!(framer.payload_for_symbols(&[-7.0; 10]).is_err());
+    assert!(framer.symbols_for_payload(&payload[1..]).is_err());
+
+    assert!(pas::symbols_for_amplitudes(&[1, 3], &[0, 2], &[1.0, 3.0]).is_err());
+    assert!(pas::symbols_for_amplitudes(&[1, 5], &[0, 1], &[1.0, 3.0]).is_err());
+    let framer = PasFramer::with_levels(&adess, &[0.5, 1.0, 2.0, 2.5]).unwrap();
+    let symbols = framer.symbols_for_payload(&payload).unwrap();
+    assert_eq!(framer.payload_for_symbols(&symbols).unwrap(), payload);
+}
+
+#[test]
+fn qam_mapping() {
+    let mapper = QamMapper::new(4).unwrap();
+    assert_eq!(mapper.bits_per_symbol(), 6);
+    let symbols = mapper
+        .symbols_for_amplitudes(&[1, 7, 5, 3], &[0, 1, 1, 0])
+        .unwrap();
+    assert_eq!(
+        symbols,
+        vec![
+            QamSymbol { re: 1.0, im: -7.0 },
+            QamSymbol { re: -5.0, im: 3.0 }
+        ]
+    );
+    let noisy: Vec<QamSymbol> = symbols
+        .iter()
+        .map(|symbol| QamSymbol {
+            re: symbol.re + 0.9,
+            im: symbol.im - 0.9,
+        })
+        .collect();
+    assert_eq!(
+        mapper.amplitudes_for_symbols(&noisy),
+        (vec![1, 7, 5, 3], vec![0, 1, 1, 0])
+    );
+    assert!(mapper
+        .symbols_for_amplitudes(&[1, 3, 5], &[0, 0, 0])
+        .is_err());
+
+    // neighboring symbols of each component differ in one bit
+    let levels: Vec<f32> = (-7..=7).step_by(2).map(|level| level as f32).collect();
+    let labels: Vec<Vec<u8>> = levels
+        .iter()
+        .map(|&level| mapper.bits_for_symbols(&[QamSymbol { re: level, im: 1.0 }])[..3].to_vec())
+        .collect();
+    for pair in labels.windows(2) {
+        let num_diffs = pair[0].iter().zip(&pair[1]).filter(|(a, b)| a != b).count();
+        assert_eq!(num_diffs, 1);
+    }
+
+    let mut rand = RandState::new();
+    let bits: Vec<u8> = (0..60).map(|_| rand.bits(1) as u8).collect();
+    let symbols = mapper.symbols_for_bits(&bits).unwrap();
+    assert_eq!(symbols.len(), 10);
+    assert_eq!(mapper.bits_for_symbols(&symbols), bits);
+    assert!(mapper.symbols_for_bits(&bits[1..]).is_err());
+
+    let natural = QamMapper::with_labeling(&[0, 1, 2, 3], &[0.5, 1.0, 2.0, 2.5]).unwrap();
+    let symbols = natural.symbols_for_bits(&bits).unwrap();
+    assert_eq!(natural.bits_for_symbols(&symbols), bits);
+    assert!(QamMapper::with_labeling(&[0], &[1.0]).is_err());
+    assert!(QamMapper::with_labeling(&[0, 1], &[1.0]).is_err());
+}
+
+#[test]
+fn llr_demapping() {
+    let levels = utils::uniform_levels(4);
+    let labels = labeling::gray_labeling(4);
+    let adess = AdEss::new(30, 10, &[0, 1, 3, 6]);
+    let distribution = adess.amplitude_distribution();
+    let samples = [-7.5, -2.0, 0.0, 2.0, 4.2];
+    let llrs = labeling::llrs_for_samples(&distribution, &levels, &labels, 0.5, &samples).unwrap();
+    assert_eq!(llrs.len(), 3 * samples.len());
+    let llrs: Vec<&[f32]> = llrs.chunks(3).collect();
+
+    // sign bit 1 for negative samples, symmetric label bits
+    assert!(llrs[0][0] < -10.0 && llrs[4][0] > 10.0);
+    assert!(llrs[2][0].abs() < 1e-6);
+    assert!((llrs[1][0] + llrs[3][0]).abs() < 1e-4);
+    assert!((llrs[1][1] - llrs[3][1]).abs() < 1e-4);
+    assert!((llrs[1][2] - llrs[3][2]).abs() < 1e-4);
+    // the more likely amplitude 1 wins at the decision boundary to amplitude 3
+    let skewed = [0.6, 0.2, 0.15, 0.05];
+    let llrs = labeling::llrs_for_samples(&skewed, &levels, &labels, 0.5, &[2.0]).unwrap();
+    assert!(llrs[2] > 1.0);
+    let uniform = labeling::llrs_for_samples(&[0.25; 4], &levels, &labels, 0.5, &[2.0]).unwrap();
+    assert!(uniform[2].abs() < 1e-4);
+
+    assert!(labeling::llrs_for_samples(&distribution, &levels, &labels, 0.0, &samples).is_err());
+    assert!(labeling::llrs_for_samples(&distribution[1..], &levels, &labels, 0.5, &[]).is_err());
+}
+
+#[cfg(feature = "sim")]
+#[test]
+fn awgn_simulation() {
+    use crate::sim::{self, Demapping};
+
+    let adess = AdEss::new(30, 10, &[0, 1, 3, 6]);
+    for demapping in [Demapping::Hard, Demapping::Soft] {
+        let result = sim::simulate_awgn(&adess, 30.0, 50, demapping, 1).unwrap();
+        assert_eq!((result.ser, result.ber, result.bler), (0.0, 0.0, 0.0));
+        assert_eq!(result.rate, (adess.num_bits() as f32 + 10.0) / 10.0);
+
+        let result = sim::simulate_awgn(&adess, 5.0, 50, demapping, 1).unwrap();
+        assert!(result.ser > 0.0 && result.ber > 0.0 && result.bler > 0.0);
+        assert!(result.ser < 1.0 && result.ber < 1.0);
+        assert_eq!(
+            sim::simulate_awgn(&adess, 5.0, 50, demapping, 1),
+            Ok(result)
+        );
+    }
+    let rts = RTS::new(12, 10, &[0, 1, 3, 6]);
+    let result = sim::simulate_awgn(&rts, 30.0, 20, Demapping::Hard, 1).unwrap();
+    assert_eq!(result.ber, 0.0);
+}
+
+#[test]
+fn pcdm_encoding_decoding() {
+    let pcdm = PCDM::new(&[0.4, 0.3, 0.2, 0.1], 6, 10).unwrap();
+    assert_eq!(pcdm.get_termination_amplitudes(), vec![1, 3, 5, 7]);
+
+    let num_indexes = 1usize << pcdm.num_bits();
+    let mut amplitude_counts = vec![0usize; 4];
+    for index in 0..num_indexes {
+        let index = Integer::from(index);
+        let amplitude_sequence = pcdm.sequence_for_index(&index);
+        assert_eq!(amplitude_sequence.len(), 6);
+        assert_eq!(pcdm.index_for_sequence(&amplitude_sequence), index);
+        for a in amplitude_sequence {
+            amplitude_counts[(a - 1) / 2] += 1;
+        }
+    }
+
+    // the calculated distribution matches the enumerated one
+    let amplitude_distribution = pcdm.amplitude_distribution();
+    for (p, count) in amplitude_distribution.iter().zip(amplitude_counts) {
+        let p_enumerated = count as f32 / (num_indexes * 6) as f32;
+        assert!((p - p_enumerated).abs() < 1e-6);
+    }
+
+    assert!(PCDM::new(&[0.4, 0.3, 0.2, 0.1], 6, 13).is_err());
+}
+
+#[test]
+fn ccdm_encoding_decoding() {
+    let ccdm = CCDM::new(&[3, 2, 1], 5).unwrap();
+    assert_eq!(ccdm.n_max(), 6);
+    assert_eq!(ccdm.num_sequences(), 60);
+    assert_eq!(ccdm.max_num_bits(), 5);
+    assert_eq!(
+        ccdm.amplitude_distribution(),
+        vec![0.5, 2.0 / 6.0, 1.0 / 6.0]
+    );
+
+    let mut sequences = std::collections::HashSet::new();
+    for index in 0..32 {
+        let index = Integer::from(index);
+        let sequence = ccdm.sequence_for_index(&index);
+        let mut counts = [0; 3];
+        sequence
+            .iter()
+            .for_each(|&amplitude| counts[amplitude / 2] += 1);
+        assert_eq!(counts, [3, 2, 1]);
+        assert_eq!(ccdm.index_for_sequence(&sequence), index);
+        sequences.insert(sequence);
+    }
+    assert_eq!(sequences.len(), 32);
+    // index `i` selects the sequence with the lexicographic rank `floor(i * 60 / 32)`
+    assert_eq!(
+        ccdm.sequence_for_index(&Integer::from(0)),
+        vec![1, 1, 1, 3, 3, 5]
+    );
+    assert_eq!(
+        ccdm.sequence_for_index(&Integer::from(2)),
+        vec![1, 1, 3, 1, 3, 5]
+    );
+    assert_eq!(
+        ccdm.sequence_for_index(&Integer::from(31)),
+        vec![5, 3, 1, 3, 1, 1]
+    );
+    // unused ranks and sequences with another composition
+    assert!(ccdm.try_index_for_sequence(&[1, 1, 1, 3, 5, 3]).is_ok());
+    assert!(ccdm.try_index_for_sequence(&[1, 1, 1, 5, 3, 3]).is_err());
+    assert!(ccdm.try_index_for_sequence(&[5, 3, 3, 1, 1, 1]).is_err());
+    assert!(ccdm.try_index_for_sequence(&[1, 1, 1, 3, 3, 3]).is_err());
+    assert!(CCDM::new(&[3, 2, 1], 6).is_err());
+
+    let distribution = [0.4, 0.3, 0.2, 0.1];
+    assert_eq!(
+        CCDM::composition_for_distribution(&distribution, 24).unwrap(),
+        vec![10, 7, 5, 2]
+    );
+    let ccdm = CCDM::for_distribution(&distribution, 64, 100).unwrap();
+    let mut rand = RandState::new();
+    for _ in 0..20 {
+        let index = Integer::from(Integer::random_bits(100, &mut rand));
+        assert_eq!(
+            ccdm.index_for_sequence(&ccdm.sequence_for_index(&index)),
+            index
+        );
+    }
+}
+
+#[test]
+fn shell_mapping_encoding_decoding() {
+    let weights = [0, 1, 1, 3];
+    let shell_mapper = ShellMapper::new(20, 12, &weights);
+    let rts = RTS::from_trellis(shell_mapper.trellis.clone()).unwrap();
+    assert_eq!(shell_mapper.num_sequences(), rts.num_sequences());
+    assert!(shell_mapper.num_bits() >= 20);
+
+    let mut rand = RandState::new();
+    let mut last_energy = 0;
+    for index in (0..2000).chain((0..200).map(|_| {
+        Integer::from(shell_mapper.num_sequences().random_below_ref(&mut rand))
+            .to_usize()
+            .unwrap()
+    })) {
+        let index = Integer::from(index);
+        let sequence = shell_mapper.sequence_for_index(&index);
+        assert_eq!(shell_mapper.index_for_sequence(&sequence), index);
+        // same set of sequences as RTS, ordered by sum weight
+        let energy: usize = sequence.iter().map(|&a| weights[a / 2]).sum();
+        if index < 2000 {
+            assert!(energy >= last_energy);
+            last_energy = energy;
+        }
+        assert_eq!(
+            rts.sequence_for_index(&rts.index_for_sequence(&sequence)),
+            sequence
+        );
+    }
+    assert!(shell_mapper.try_index_for_sequence(&[7; 12]).is_err());
+    assert!(shell_mapper.try_index_for_sequence(&[1; 11]).is_err());
+    assert!(shell_mapper.try_index_for_sequence(&[9; 12]).is_err());
+
+    // the amplitude distribution is exact
+    let shell_mapper = ShellMapper::new(9, 5, &weights);
+    let num_used = 1 << shell_mapper.num_bits();
+    let mut counts = [0usize; 4];
+    for index in 0..num_used {
+        for amplitude in shell_mapper.sequence_for_index(&Integer::from(index)) {
+            counts[amplitude / 2] += 1;
+        }
+    }
+    let expected: Vec<f32> = counts
+        .iter()
+        .map(|&count| count as f32 / (5 * num_used) as f32)
+        .collect();
+    let distribution = shell_mapper.amplitude_distribution();
+    for (p, p_expected) in distribution.iter().zip(&expected) {
+        assert!((p - p_expected).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn hierarchical_distribution_matching() {
+    // two outer amplitudes select the low or high energy half of the inner sequences
+    let hidm = HiDm::new(RTS::new(2, 3, &[0, 2]), RTS::new(4, 2, &[0, 1, 3, 6])).unwrap();
+    assert_eq!(hidm.block_bits(), hidm.inner().num_bits() - 1);
+    assert_eq!(hidm.n_max(), 6);
+    assert_eq!(
+        hidm.num_bits(),
+        hidm.outer().num_bits() + 3 * hidm.block_bits()
+    );
+
+    let num_indexes = 1usize << hidm.num_bits();
+    let mut counts = vec![0usize; 4];
+    let mut sequences = std::collections::HashSet::new();
+    for index in 0..num_indexes {
+        let index = Integer::from(index);
+        let sequence = hidm.sequence_for_index(&index);
+        assert_eq!(hidm.index_for_sequence(&sequence), index);
+        sequence
+            .iter()
+            .for_each(|&amplitude| counts[amplitude / 2] += 1);
+        sequences.insert(sequence);
+    }
+    assert_eq!(sequences.len(), num_indexes);
+    let distribution = hidm.amplitude_distribution();
+    for (p, count) in distribution.iter().zip(&counts) {
+        assert!((p - *count as f32 / (6 * num_indexes) as f32).abs() < 1e-6);
+    }
+    // the low energy group has a smaller average energy
+    let energy = |distribution: &[f32]| utils::average_energy(distribution, &[1.0, 3.0, 5.0, 7.0]);
+    assert!(energy(&hidm.group_distribution(0)) < energy(&hidm.group_distribution(1)));
+
+    // long effective block lengths with small matchers
+    let hidm = HiDm::new(
+        AdEss::new(40, 16, &[0, 1, 3, 6]),
+        RTS::new(24, 12, &[0, 1, 3, 6]),
+    )
+    .unwrap();
+    assert_eq!(hidm.n_max(), 192);
+    let mut rand = RandState::new();
+    for _ in 0..20 {
+        let index = Integer::from(Integer::random_bits(hidm.num_bits(), &mut rand));
+        let sequence = hidm.sequence_for_index(&index);
+        assert_eq!(hidm.try_index_for_sequence(&sequence), Ok(index));
+    }
+    assert!(hidm.try_index_for_sequence(&[1; 191]).is_err());
+    assert!(hidm.try_index_for_sequence(&[7; 192]).is_err());
+    assert!(HiDm::new(RTS::new(8, 4, &[0, 1, 3, 6]), RTS::new(2, 1, &[0, 1, 3, 6])).is_err());
+}
+
+#[test]
+fn compare_matchers() {
+    let distribution = [0.4, 0.3, 0.2, 0.1];
+    let comparisons = compare::compare(&distribution, 1.5, 24, 4.0).unwrap();
+
+    let names: Vec<&str> = comparisons.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["AD-ESS", "RTS", "Shell", "PCDM", "CCDM"]);
+    for comparison in comparisons.iter() {
+        assert!(comparison.num_bits >= 36);
+        assert!(comparison.rate_loss > 0.0);
+        assert!(comparison.kl_divergence >= 0.0);
+        assert!(comparison.storage_bits > 0);
+    }
+    // RTS has the smallest energy among all matchers with the same weights and rate
+    assert!(comparisons[1].average_energy <= comparisons[0].average_energy + 1e-4);
+
+    let table = compare::ComparisonTable(&comparisons).to_string();
+    assert_eq!(table.lines().count(), 6);
+}
+
+#[test]
+fn non_uniform_amplitude_levels() {
+    let distribution = [0.4, 0.3, 0.2, 0.1];
+    let (adess, _) = AdEss::new_for_distribution_num_bits(30, 20, &distribution, 4.0).unwrap();
+
+    // uniformly spaced levels give the usual average energy
+    let uniform = utils::uniform_levels(4);
+    assert_eq!(uniform, vec![1.0, 3.0, 5.0, 7.0]);
+    assert!((adess.average_energy_for_levels(&uniform) - adess.average_energy()).abs() < 1e-4);
+
+    // geometrically shaped levels
+    let levels = [0.5, 1.2, 2.1, 3.5];
+    let amplitude_distribution = adess.amplitude_distribution();
+    let expected: f32 = amplitude_distribution
+        .iter()
+        .zip(levels)
+        .map(|(p, l)| p * l * l)
+        .sum();
+    assert!((adess.average_energy_for_levels(&levels) - expected).abs() < 1e-6);
+
+    let index = Integer::from(12345);
+    let level_sequence = adess.levels_for_index(&index, &levels);
+    let amplitude_sequence = adess.sequence_for_index(&index);
+    for (level, a) in level_sequence.iter().zip(amplitude_sequence) {
+        assert_eq!(*level, levels[(a - 1) / 2]);
+    }
+}
+
+#[test]
+fn maxwell_boltzmann_solvers() {
+    let uniform = utils::mb(8, 0.0);
+    assert!(uniform.iter().all(|&p| (p - 0.125).abs() < 1e-6));
+    let mb = utils::mb(8, 0.02);
+    assert!(mb.windows(2).all(|p| p[0] > p[1]));
+    assert!((mb.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+
+    let mb = utils::mb_for_rate(8, 2.5).unwrap();
+    assert!((utils::entropy(&mb) - 2.5).abs() < 1e-3);
+    assert!(utils::mb_for_rate(8, 3.0).is_err());
+
+    let mb = utils::mb_for_average_energy(8, 20.0).unwrap();
+    assert!((utils::average_energy(&mb, &utils::uniform_levels(8)) - 20.0).abs() < 1e-2);
+
+    let (adess, _) = AdEss::new_for_distribution_num_bits(40, 64, &mb, 4.0).unwrap();
+    assert_eq!(adess.num_bits(), 40);
+}
+
+#[test]
+fn optimal_distribution_for_snr() {
+    let levels = utils::uniform_levels(8);
+    let snr_db = 15.0;
+    let snr = 10f32.powf(snr_db / 10.0);
+    let mi_at_snr = |distribution: &[f32]| {
+        let noise_variance = utils::average_energy(distribution, &levels) / snr;
+        capacity::mutual_information(distribution, &levels, noise_variance)
+    };
+
+    let optimal = capacity::optimal_distribution_for_snr(8, snr_db).unwrap();
+    let optimal_mi = mi_at_snr(&optimal);
+    let awgn_capacity = (1.0 + snr).log2() / 2.0;
+    println!(
+        "{optimal:?} {optimal_mi} {} {awgn_capacity}",
+        mi_at_snr(&utils::mb(8, 0.0))
+    );
+    assert!(optimal_mi < awgn_capacity);
+    assert!(optimal_mi > awgn_capacity - 0.01);
+    assert!(optimal_mi > mi_at_snr(&utils::mb(8, 0.0)) + 0.05);
+    for energy in [10.0, 20.0, 30.0] {
+        let mb = utils::mb_for_average_energy(8, energy).unwrap();
+        assert!(optimal_mi >= mi_at_snr(&mb));
+    }
+
+    // at low SNR the distribution concentrates on the smallest amplitudes
+    let low_snr = capacity::optimal_distribution_for_snr(8, 0.0).unwrap();
+    assert!(low_snr[0] > optimal[0]);
+    assert!(capacity::optimal_distribution_for_snr(8, f32::NAN).is_err());
+
+    let (adess, _) = AdEss::new_for_distribution_num_bits(100, 64, &optimal, 4.0).unwrap();
+    assert_eq!(adess.num_bits(), 100);
+}
+
+#[test]
+fn adess_for_average_energy() {
+    let levels = utils::uniform_levels(4);
+    let mb = utils::maxwell_boltzmann_for_energy(&levels, 10.0).unwrap();
+    assert!((utils::average_energy(&mb, &levels) - 10.0).abs() < 1e-3);
+    assert!(utils::maxwell_boltzmann_for_energy(&levels, 1.0).is_err());
+
+    let (adess, _) = AdEss::new_for_average_energy(20, 4, 10.0).unwrap();
+    assert!(adess.average_energy() <= 10.0);
+
+    // the next larger trellis violates the energy target
+    let weights = adess.get_weights();
+    let larger = (adess.trellis.threshold + 1..)
+        .map(|threshold| AdEss::new(threshold, 20, &weights))
+        .find(|larger| larger.num_sequences() > adess.num_sequences())
+        .unwrap();
+    assert!(larger.average_energy() > 10.0);
+
+    // the resolution factor search is at least as good as the resolution factor 1
+    let weights = AdEss::calc_weights(&mb, 1.0).unwrap();
+    let thresholds: Vec<usize> = (0..=20 * weights.iter().max().unwrap()).collect();
+    let best_num_bits = AdEss::threshold_sweep(&weights, 20, &thresholds)
+        .iter()
+        .filter(|&&(_, _, energy, _)| energy <= 10.0)
+        .map(|&(_, num_bits, _, _)| num_bits)
+        .max()
+        .unwrap();
+    assert!(adess.num_bits() >= best_num_bits);
+}
+
+#[test]
+fn rate_ladder() {
+    let distribution = [0.4, 0.3, 0.2, 0.1];
+    let ladder =
+        rate_ladder::generate_rate_ladder(&distribution, 20, &[1.6, 1.2, 1.4, 1.4], 4.0).unwrap();
+
+    let bits: Vec<usize> = ladder.rungs.iter().map(|rung| rung.num_bits).collect();
+    assert_eq!(bits, vec![24, 28, 32]);
+    for (rung_idx, rung) in ladder.rungs.iter().enumerate() {
+        let (adess, _) =
+            AdEss::new_for_distribution_num_bits(rung.num_bits, 20, &distribution, 4.0).unwrap();
+        assert_eq!(adess.trellis.threshold, rung.threshold);
+        assert_eq!(
+            ladder.adess(rung_idx).num_sequences(),
+            adess.num_sequences()
+        );
+    }
+}
+
+#[test]
+fn trellis_reuse_between_adess_and_rts() {
+    let weights = [0, 1, 3, 6];
+    let rts = RTS::new(20, 12, &weights);
+    let adess = AdEss::from_rts(&rts);
+    let reference = AdEss::new(rts.trellis.threshold, 12, &weights);
+    assert_eq!(adess.num_sequences(), rts.num_sequences());
+    for n in 0..=12 {
+        assert_eq!(adess.trellis.get_stage(n), reference.trellis.get_stage(n));
+    }
+
+    let rts_from_adess = RTS::from_adess(&adess);
+    for n in 0..=12 {
+        assert_eq!(
+            rts_from_adess.trellis.get_stage(n),
+            adess.reverse_trellis().get_stage(n)
+        );
+    }
+    let index = Integer::from(123456);
+    assert_eq!(
+        rts_from_adess.sequence_for_index(&index),
+        rts.sequence_for_index(&index)
+    );
+}
+
+#[test]
+fn joint_shaping_and_labeling() {
+    let levels = utils::uniform_levels(4);
+    let distribution = [0.4, 0.3, 0.2, 0.1];
+    assert_eq!(labeling::gray_labeling(4), vec![0, 1, 3, 2]);
+
+    // at high SNR the GMI approaches the entropy of the symbols
+    let gray = labeling::gray_labeling(4);
+    let gmi = labeling::bicm_gmi(&distribution, &levels, &gray, 0.01);
+    assert!((gmi - (utils::entropy(&distribution) + 1.0)).abs() < 1e-3);
+
+    let noise_variance = 1.5;
+    let gray_gmi = labeling::bicm_gmi(&distribution, &levels, &gray, noise_variance);
+    let (_, optimized_gmi) = labeling::optimize_labeling(&distribution, &levels, noise_variance);
+    assert!(optimized_gmi >= gray_gmi);
+
+    let result = labeling::optimize_shaping_and_labeling(16, 4, 24, 10.0, 4.0, 5).unwrap();
+    assert!(result.adess.num_bits() >= 24);
+    assert_eq!(result.labeling.len(), 4);
+    assert!(result.gmi > 0.0 && result.gmi <= 3.0);
+}
+
+#[test]
+fn adess_single_shell() {
+    let weights = [0, 1, 3, 6];
+    let adess = AdEss::new_single_shell(12, 8, &weights).unwrap();
+
+    let num_sequences = adess.num_sequences().to_usize().unwrap();
+    let mut amplitude_counts = vec![0usize; 4];
+    for index in 0..num_sequences {
+        let index = Integer::from(index);
+        let amplitude_sequence = adess.sequence_for_index(&index);
+        let sum_weight: usize = amplitude_sequence
+            .iter()
+            .map(|a| weights[(a - 1) / 2])
+            .sum();
+        assert_eq!(sum_weight, 12);
+        assert_eq!(adess.index_for_sequence(&amplitude_sequence), index);
+        if index < Integer::u_pow_u(2, adess.num_bits()).complete() {
+            for a in amplitude_sequence {
+                amplitude_counts[(a - 1) / 2] += 1;
+            }
+        }
+    }
+
+    let num_used = 1usize << adess.num_bits();
+    for (p, count) in adess.amplitude_distribution().iter().zip(amplitude_counts) {
+        assert!((p - count as f32 / (num_used * 8) as f32).abs() < 1e-6);
+    }
+
+    assert!(AdEss::new_single_shell(49, 8, &weights).is_err());
+}
+
+#[test]
+fn adess_iter_sequences() {
+    let adess = AdEss::new(12, 8, &[0, 1, 3, 6]);
+    let start = Integer::from(100);
+    let end = adess.num_sequences();
+
+    let mut count = 0;
+    for (index, amplitude_sequence) in adess.iter_sequences(start.clone()..end.clone()) {
+        assert_eq!(amplitude_sequence, adess.sequence_for_index(&index));
+        count += 1;
+    }
+    assert_eq!(Integer::from(count), end - &start);
+
+    // single shell trellises contain nodes without sequences
+    let shell = AdEss::new_single_shell(12, 8, &[0, 1, 3, 6]).unwrap();
+    for (index, amplitude_sequence) in shell.iter_sequences(Integer::from(0)..shell.num_sequences())
+    {
+        assert_eq!(amplitude_sequence, shell.sequence_for_index(&index));
+    }
+}
+
+#[test]
+fn adess_sample_sequences() {
+    let adess = AdEss::new(30, 16, &[0, 1, 3, 6]);
+    let samples = adess.sample_sequences(200, 42);
+    assert_eq!(samples.len(), 200);
+    assert_eq!(samples, adess.sample_sequences(200, 42));
+    assert_ne!(samples, adess.sample_sequences(200, 43));
+
+    let num_sequences_used = Integer::u_pow_u(2, adess.num_bits()).complete();
+    for amplitude_sequence in samples {
+        assert!(adess.index_for_sequence(&amplitude_sequence) < num_sequences_used);
+    }
+}
+
+#[test]
+fn adess_energy_of_index() {
+    let adess = AdEss::new(30, 16, &[0, 1, 3, 6]);
+    let indexes: Vec<Integer> = (0..500).map(|i| Integer::from(i * 7919)).collect();
+    let energies = adess.energies_of_indexes(&indexes).unwrap();
+    for (index, energy) in indexes.iter().zip(energies) {
+        let amplitude_sequence = adess.sequence_for_index(index);
+        assert_eq!(
+            energy,
+            amplitude_sequence.iter().map(|a| a * a).sum::<usize>()
+        );
+        assert_eq!(adess.energy_of_index(index), Ok(energy));
+    }
+    for index in [Integer::from(-1), adess.num_sequences()] {
+        assert_eq!(
+            adess.energy_of_index(&index),
+            Err(AdEssError::IndexOutOfRange)
+        );
+        assert!(adess.energies_of_indexes(&[Integer::new(), index]).is_err());
+    }
+}
+
+#[test]
+fn adess_energy_distribution() {
+    let weights = [0, 1, 3, 6];
+    let adess = AdEss::new(14, 8, &weights);
+    let num_used = Integer::u_pow_u(2, adess.num_bits()).complete();
+
+    let mut energy_counts = std::collections::BTreeMap::new();
+    let mut weight_counts = std::collections::BTreeMap::new();
+    for (_, amplitude_sequence) in adess.iter_sequences(Integer::from(0)..num_used) {
+        let energy: usize = amplitude_sequence.iter().map(|a| a * a).sum();
+        let weight: usize = amplitude_sequence
+            .iter()
+            .map(|a| weights[(a - 1) / 2])
+            .sum();
+        *energy_counts.entry(energy).or_insert(0usize) += 1;
+        *weight_counts.entry(weight).or_insert(0usize) += 1;
+    }
+    let to_vec = |counts: std::collections::BTreeMap<usize, usize>| -> Vec<(usize, Integer)> {
+        counts
+            .into_iter()
+            .map(|(v, c)| (v, Integer::from(c)))
+            .collect()
+    };
+    assert_eq!(adess.energy_distribution(), to_vec(energy_counts));
+    assert_eq!(adess.weight_distribution(), to_vec(weight_counts));
+
+    let ccdf = adess.energy_ccdf();
+    assert_eq!(ccdf[0].1, 1.0);
+    assert!(ccdf.windows(2).all(|w| w[0].1 > w[1].1));
+}
+
+#[test]
+fn export_comparison_csv() {
+    let comparisons = compare::compare(&[0.4, 0.3, 0.2, 0.1], 1.5, 16, 4.0).unwrap();
+    let mut csv = vec![];
+    export::write_csv(&mut csv, &comparisons).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), comparisons.len() + 1);
+    assert!(lines[0].starts_with("matcher,n_max,num_bits,rate"));
+    assert!(lines[1].starts_with("AD-ESS,16,"));
+    assert!(lines
+        .iter()
+        .all(|line| line.split(',').count() == lines[0].split(',').count()));
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn export_comparison_parquet() {
+    let comparisons = compare::compare(&[0.4, 0.3, 0.2, 0.1], 1.5, 16, 4.0).unwrap();
+    let path = std::env::temp_dir().join("ad_ess_export_comparison.parquet");
+    export::write_parquet_file(&path, &comparisons).unwrap();
+    let content = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(&content[..4], b"PAR1");
+}
+
+#[test]
+fn amplitude_stream_statistics() {
+    let sequences = vec![vec![1, 1, 3, 5, 5, 5], vec![3, 1, 1, 1, 1, 7]];
+    assert_eq!(
+        statistics::run_length_distribution(&sequences),
+        vec![0, 3, 1, 1, 1]
+    );
+    assert_eq!(
+        statistics::mean_energy_per_position(&sequences),
+        vec![5.0, 1.0, 5.0, 13.0, 13.0, 37.0]
+    );
+
+    let adess = AdEss::new(30, 16, &[0, 1, 3, 6]);
+    let samples = adess.sample_sequences(2000, 1);
+    let correlation = statistics::autocorrelation(&samples, 4);
+    assert_eq!(correlation[0], 1.0);
+    assert!(correlation[1..].iter().all(|c| c.abs() < 0.1));
+
+    // the mean energy per position matches the average energy
+    let mean_energy = statistics::mean_energy_per_position(&samples);
+    let average = mean_energy.iter().sum::<f32>() / mean_energy.len() as f32;
+    assert!((average - adess.average_energy()).abs() < 0.2);
+}
+
+#[test]
+fn blocklength_sweep() {
+    let distribution = [0.4, 0.3, 0.2, 0.1];
+    let points = sweep::sweep_blocklength(&distribution, 4.0, &[8, 16, 32, 64]).unwrap();
+    assert_eq!(points.len(), 4);
+    for point in points.iter() {
+        let (adess, _) = AdEss::new_for_distribution_num_bits(
+            point.num_bits as usize,
+            point.n_max,
+            &distribution,
+            4.0,
+        )
+        .unwrap();
+        assert_eq!(adess.trellis.threshold, point.threshold);
+        assert!(point.rate_loss > 0.0);
+    }
+    // the rate loss decreases with the blocklength
+    assert!(points.windows(2).all(|p| p[1].rate_loss < p[0].rate_loss));
+}
+
+#[test]
+fn adess_rts_side_by_side() {
+    let comparison = compare::compare_adess_rts(&[0, 1, 3, 6], 24, 36);
+    assert_eq!(comparison.adess.rate, comparison.rts.rate);
+    assert_eq!(comparison.adess.storage_dimensions.0, 25);
+    for summary in [&comparison.adess, &comparison.rts] {
+        assert!((summary.amplitude_distribution.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        assert!(summary.rate_loss > 0.0);
+    }
+    // energy based ordering of RTS is optimal
+    assert!(comparison.rts.average_energy <= comparison.adess.average_energy + 1e-4);
+}
+
+#[test]
+fn amplitude_distribution_per_position() {
+    let weights = [0, 1, 3, 6];
+    let n_max = 8;
+    let adess = AdEss::new(14, n_max, &weights);
+    let rts = RTS::from_adess(&adess);
+    let num_used = 1usize << adess.num_bits();
+
+    let mut adess_counts = vec![vec![0usize; 4]; n_max];
+    let mut rts_counts = vec![vec![0usize; 4]; n_max];
+    for index in 0..num_used {
+        let index = Integer::from(index);
+        for (n, a) in adess.sequence_for_index(&index).iter().enumerate() {
+            adess_counts[n][(a - 1) / 2] += 1;
+        }
+        for (n, a) in rts.sequence_for_index(&index).iter().enumerate() {
+            rts_counts[n][(a - 1) / 2] += 1;
+        }
+    }
+
+    for (distribution, counts) in [
+        (adess.amplitude_distribution_per_position(), adess_counts),
+        (rts.amplitude_distribution_per_position(), rts_counts),
+    ] {
+        for (position_distribution, position_counts) in distribution.iter().zip(counts) {
+            for (p, count) in position_distribution.iter().zip(position_counts) {
+                assert!((p - count as f32 / num_used as f32).abs() < 1e-6);
+            }
+        }
+    }
+}
+
+#[test]
+fn amplitude_distribution_for_num_indices() {
+    let adess = AdEss::new(14, 8, &[0, 1, 3, 6]);
+    let rts = RTS::from_adess(&adess);
+    let num_indices = 1000;
+
+    let mut adess_counts = [0usize; 4];
+    let mut rts_counts = [0usize; 4];
+    for index in 0..num_indices {
+        let index = Integer::from(index);
+        for a in adess.sequence_for_index(&index) {
+            adess_counts[(a - 1) / 2] += 1;
+        }
+        for a in rts.sequence_for_index(&index) {
+            rts_counts[(a - 1) / 2] += 1;
+        }
+    }
+    let num_indices = Integer::from(num_indices);
+    for (distribution, counts) in [
+        (
+            adess.amplitude_distribution_for_num_indices(&num_indices),
+            adess_counts,
+        ),
+        (
+            rts.amplitude_distribution_for_num_indices(&num_indices),
+            rts_counts,
+        ),
+    ] {
+        for (p, count) in distribution.unwrap().iter().zip(counts) {
+            assert!((p - count as f32 / 8000.0).abs() < 1e-6);
+        }
+    }
+
+    let num_used = Integer::from(1) << adess.num_bits();
+    assert_eq!(
+        adess.amplitude_distribution_for_num_indices(&num_used),
+        Ok(adess.amplitude_distribution())
+    );
+    let num_sequences = adess.num_sequences();
+    assert_eq!(
+        rts.amplitude_distribution_for_num_indices(&num_sequences),
+        Ok(rts.amplitude_distribution_full_utilization())
+    );
+    assert!(adess
+        .amplitude_distribution_for_num_indices(&Integer::new())
+        .is_err());
+    assert!(rts
+        .amplitude_distribution_for_num_indices(&(num_sequences + 1u32))
+        .is_err());
+}
+
+#[test]
+fn adess_threshold_sweep() {
+    let weights = [0, 1, 3, 6];
+    let thresholds = [0, 5, 17, 30, 31, 60];
+    let sweep = AdEss::threshold_sweep(&weights, 20, &thresholds);
+    assert_eq!(sweep.len(), thresholds.len());
+    for (&threshold, (swept_threshold, num_bits, average_energy, rate_loss)) in
+        thresholds.iter().zip(sweep)
+    {
+        let adess = AdEss::new(threshold, 20, &weights);
+        assert_eq!(swept_threshold, threshold);
+        assert_eq!(num_bits, adess.num_bits());
+        assert!((average_energy - adess.average_energy()).abs() < 1e-4);
+        let rate = adess.num_bits() as f32 / 20.0;
+        assert!(
+            (rate_loss - (utils::entropy(&adess.amplitude_distribution()) - rate)).abs() < 1e-5
+        );
+    }
+}
+
+#[test]
+fn adess_energy_moments() {
+    let adess = AdEss::new(14, 8, &[0, 1, 3, 6]);
+    let num_used = 1usize << adess.num_bits();
+    let energies: Vec<f64> = (0..num_used)
+        .map(|index| adess.energy_of_index(&Integer::from(index)).unwrap() as f64)
+        .collect();
+
+    let moments = adess.energy_moments(3);
+    for (j, moment) in moments.iter().enumerate() {
+        let expected = energies.iter().map(|e| e.powi(j as i32 + 1)).sum::<f64>() / num_used as f64;
+        assert!((moment - expected).abs() / expected < 1e-9);
+    }
+    // the first moment is the average energy of a block
+    assert!((moments[0] / 8.0 - adess.average_energy() as f64).abs() < 1e-4);
+
+    let mean = moments[0];
+    let variance = energies.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / num_used as f64;
+    assert!((adess.energy_variance() - variance).abs() / variance < 1e-9);
+}
+
+#[test]
+fn pairwise_distance_spectrum() {
+    let adess = AdEss::new(6, 4, &[0, 1, 3, 6]);
+    let num_used = 1usize << adess.num_bits();
+    let spectrum = statistics::distance_spectrum(&adess, 1 << 10, 0, 0);
+    assert!(spectrum.exact);
+    assert_eq!(spectrum.hamming[0], 0);
+    assert_eq!(
+        spectrum.hamming.iter().sum::<usize>(),
+        num_used * (num_used - 1) / 2
+    );
+    assert_eq!(
+        spectrum
+            .squared_euclidean
+            .iter()
+            .map(|(_, c)| c)
+            .sum::<usize>(),
+        num_used * (num_used - 1) / 2
+    );
+    // distinct sequences differ in at least one amplitude by at least 2
+    assert!(spectrum.squared_euclidean[0].0 >= 4);
+
+    let sampled = statistics::distance_spectrum(&adess, 4, 500, 1);
+    assert!(!sampled.exact);
+    assert_eq!(sampled.hamming.iter().sum::<usize>(), 500);
+}
+
+#[test]
+fn fractional_num_bits() {
+    let adess = AdEss::new(60, 24, &[0, 1, 3, 6]);
+    let rts = RTS::from_adess(&adess);
+    let log2_num_sequences = adess.num_sequences().to_f64().log2();
+    for (num_bits, num_bits_fractional, rate) in [
+        (
+            adess.num_bits(),
+            adess.num_bits_fractional(),
+            adess.rate_bits_per_amplitude(),
+        ),
+        (
+            rts.num_bits(),
+            rts.num_bits_fractional(),
+            rts.rate_bits_per_amplitude(),
+        ),
+    ] {
+        assert!((num_bits_fractional - log2_num_sequences).abs() < 1e-9);
+        assert!(num_bits_fractional >= num_bits as f64);
+        assert!(num_bits_fractional < num_bits as f64 + 1.0);
+        assert!((rate - num_bits_fractional / 24.0).abs() < 1e-12);
+    }
+
+    let large = Integer::from(3) << 2000u32;
+    assert!((utils::integer_log2(&large) - (2000.0 + 3f64.log2())).abs() < 1e-9);
+    assert_eq!(utils::integer_log2(&Integer::from(1)), 0.0);
+}
+
+#[test]
+fn optimal_threshold_search() {
+    let distribution = [0.4, 0.3, 0.2, 0.1];
+    let n_max = 32;
+    let weights = AdEss::calc_weights(&distribution, 4.0).unwrap();
+    let threshold = AdEss::optimal_threshold(n_max, &distribution, 4.0, 3).unwrap();
+
+    let weight_levels =
+        AdEss::calc_reverse_trellis(2 * threshold, n_max, &weights).get_weight_levels();
+    let position = weight_levels
+        .iter()
+        .position(|&wl| wl == threshold)
+        .unwrap();
+    let mi_loss = |threshold| AdEss::new(threshold, n_max, &weights).mi_loss_bound(&distribution);
+    for &neighbor in &weight_levels[position - 1..position + 2] {
+        assert!(mi_loss(threshold) <= mi_loss(neighbor));
+    }
+    let (adess, _) =
+        AdEss::new_for_distribution_optimal_threshold(n_max, &distribution, 4.0, 3).unwrap();
+    assert_eq!(adess.trellis.threshold, threshold);
+
+    // the estimated size exceeds the number of sequences without failing
+    assert_eq!(AdEss::optimal_threshold(8, &[0.25; 4], 4.0, 2), Ok(0));
+
+    let diagnostics =
+        AdEss::optimal_threshold_with_diagnostics(n_max, &distribution, 4.0, 3).unwrap();
+    assert_eq!(diagnostics.threshold, threshold);
+    assert_eq!(diagnostics.tested_thresholds.len(), 7);
+    assert_eq!(
+        diagnostics.tested_thresholds[3],
+        diagnostics.estimated_threshold
+    );
+    let position = diagnostics
+        .tested_thresholds
+        .iter()
+        .position(|&t| t == threshold)
+        .unwrap();
+    assert_eq!(diagnostics.mi_loss_bounds[position], mi_loss(threshold));
+    assert!(diagnostics.warnings.is_empty());
+
+    let diagnostics =
+        AdEss::optimal_threshold_with_diagnostics(n_max, &[0.3, 0.3, 0.2, 0.2], 4.0, 0).unwrap();
+    assert_eq!(diagnostics.tested_thresholds, vec![diagnostics.threshold]);
+    assert_eq!(diagnostics.warnings.len(), 2);
+}
+
+#[test]
+fn optimal_threshold_adaptive() {
+    let distribution = [0.4, 0.3, 0.2, 0.1];
+    let n_max = 32;
+    let diagnostics = AdEss::optimal_threshold_adaptive(n_max, &distribution, 4.0).unwrap();
+    let wide_search =
+        AdEss::optimal_threshold_with_diagnostics(n_max, &distribution, 4.0, 20).unwrap();
+    assert_eq!(diagnostics.threshold, wide_search.threshold);
+    assert!(diagnostics.warnings.is_empty());
+
+    // the tested thresholds are sorted and the loss increases on both sides of the optimum
+    assert!(diagnostics
+        .tested_thresholds
+        .windows(2)
+        .all(|pair| pair[0] < pair[1]));
+    let position = diagnostics
+        .tested_thresholds
+        .iter()
+        .position(|&t| t == diagnostics.threshold)
+        .unwrap();
+    assert!(position > 0 && position + 1 < diagnostics.tested_thresholds.len());
+    let min_mi_loss_bound = diagnostics.mi_loss_bounds[position];
+    assert!(diagnostics
+        .mi_loss_bounds
+        .iter()
+        .all(|&loss| loss >= min_mi_loss_bound));
+
+    // the losses read from the reverse trellis sums match the full trellises
+    let weights = AdEss::calc_weights(&distribution, 4.0).unwrap();
+    for (&threshold, &loss) in diagnostics
+        .tested_thresholds
+        .iter()
+        .zip(&diagnostics.mi_loss_bounds)
+    {
+        let adess = AdEss::new(threshold, n_max, &weights);
+        assert_eq!(loss, adess.mi_loss_bound(&distribution));
+    }
+}
+
+#[test]
+fn adess_new_for_budget() {
+    let distribution = [0.4, 0.3, 0.2, 0.1];
+    let max_storage_bytes = 100_000;
+    let (adess, p_goal) = AdEss::new_for_budget(1.5, &distribution, max_storage_bytes).unwrap();
+    assert!(adess.rate_bits_per_amplitude() >= 1.5);
+    assert!(adess.trellis.memory_bytes() <= max_storage_bytes);
+    assert_eq!(p_goal.len(), distribution.len());
+
+    // a larger budget allows a smaller mutual information loss
+    let (larger, _) = AdEss::new_for_budget(1.5, &distribution, 10 * max_storage_bytes).unwrap();
+    assert!(larger.mi_loss_bound(&distribution) <= adess.mi_loss_bound(&distribution));
+
+    assert!(AdEss::new_for_budget(1.5, &distribution, 100).is_err());
+    assert!(AdEss::new_for_budget(2.5, &distribution, max_storage_bytes).is_err());
+    assert!(AdEss::new_for_budget(-1.0, &distribution, max_storage_bytes).is_err());
+}
+
+#[test]
+fn mi_loss_bound() {
+    let distribution = vec![0.4, 0.3, 0.2, 0.1];
+    let (adess, _) = AdEss::new_for_distribution_num_bits(30, 20, &distribution, 4.0).unwrap();
+    let amplitude_distribution = adess.amplitude_distribution();
+    let rate = adess.num_bits() as f32 / 20.0;
+
+    let bound = adess.mi_loss_bound(&distribution);
+    assert_eq!(
+        bound,
+        utils::mi_loss_bound(&amplitude_distribution, &distribution, rate)
+    );
+    // the bound is at least the rate loss
+    assert!(bound >= utils::entropy(&amplitude_distribution) - rate);
+}
+
+#[test]
+fn shaping_report() {
+    let distribution = utils::mb_for_rate(4, 1.6).unwrap();
+    let (adess, _) = AdEss::new_for_distribution_num_bits(96, 64, &distribution, 4.0).unwrap();
+    let report: ShapingReport = adess.analyze(&distribution);
+    assert_eq!(report.num_bits, adess.num_bits());
+    assert_eq!(report.rate, 1.5);
+    assert_eq!(
+        report.amplitude_distribution,
+        adess.amplitude_distribution()
+    );
+    assert_eq!(report.mi_loss_bound, adess.mi_loss_bound(&distribution));
+    assert!(report.rate_loss > 0.0 && report.rate_loss < report.mi_loss_bound);
+    assert!((report.target_entropy - 1.6).abs() < 1e-3);
+    assert!(report.shaping_gain_db > 0.0);
+    assert_eq!(report.storage_bits, adess.trellis.storage_bits());
+    assert!(report.to_string().contains("Shaping gain"));
+
+    let rts = RTS::from_adess(&adess);
+    let report = rts.analyze(&distribution);
+    assert_eq!(report.average_energy, rts.average_energy());
+    assert_eq!(
+        report.storage_dimensions,
+        rts.trellis.get_storage_dimensions()
+    );
+
+    // the uniform reference is exact for integer rates
+    let uniform = RTS::new(128, 64, &[0, 0, 0, 0]).analyze(&[0.25; 4]);
+    assert_eq!(uniform.uniform_average_energy, 21.0);
+    assert!(uniform.shaping_gain_db.abs() < 1e-5);
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ShapingReport>(&json).unwrap(),
+            report
+        );
+    }
+}
+
+#[test]
+fn adess_amplitude_distribution_with_prior() {
+    let adess = AdEss::new(14, 8, &[0, 1, 3, 6]);
+    let num_used = 1usize << adess.num_bits();
+
+    // without prior, the usual amplitude distribution results
+    let uniform = adess.amplitude_distribution_with_prior(&[]).unwrap();
+    for (p, p_ref) in uniform.iter().zip(adess.amplitude_distribution()) {
+        assert!((p - p_ref).abs() < 1e-6);
+    }
+
+    let prior = [(Integer::from(0), 0.3), (Integer::from(num_used - 1), 0.2)];
+    let distribution = adess.amplitude_distribution_with_prior(&prior).unwrap();
+
+    let mut expected = vec![0f64; 4];
+    for index in 0..num_used {
+        let p_index = match index {
+            0 => 0.3,
+            i if i == num_used - 1 => 0.2,
+            _ => 0.5 / (num_used - 2) as f64,
+        };
+        for a in adess.sequence_for_index(&Integer::from(index)) {
+            expected[(a - 1) / 2] += p_index / 8.0;
+        }
+    }
+    for (p, p_ref) in distribution.iter().zip(expected) {
+        assert!((*p as f64 - p_ref).abs() < 1e-5);
+    }
+
+    assert!(adess
+        .amplitude_distribution_with_prior(&[(Integer::from(num_used), 0.1)])
+        .is_err());
+}
+
+#[test]
+fn adess_kl_divergence_per_position() {
+    let distribution = vec![0.4, 0.3, 0.2, 0.1];
+    let (adess, _) = AdEss::new_for_distribution_num_bits(40, 32, &distribution, 2.0).unwrap();
+    let profile = adess.kl_divergence_per_position(&distribution);
+    assert_eq!(profile.len(), 32);
+    for (kl, position_distribution) in profile
+        .iter()
+        .zip(adess.amplitude_distribution_per_position())
+    {
+        assert!(*kl >= -1e-6);
+        assert!((kl - utils::kl_divergence(&position_distribution, &distribution)).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn error_propagation() {
+    let adess = AdEss::new(9, 4, &[0, 1, 3, 6]);
+    let num_used = 1usize << adess.num_bits();
+    let propagation = statistics::error_propagation(&adess, 1 << 10, 0, 0);
+    assert!(propagation.exact);
+    assert_eq!(propagation.flipped_bits.len(), 4);
+
+    // reference for the first position
+    let (mut flipped_bits, mut num_decodable, mut num_substitutions) = (0, 0, 0);
+    for index in 0..num_used {
+        let sequence = adess.sequence_for_index(&Integer::from(index));
+        for amplitude in [1, 3, 5, 7] {
+            if amplitude == sequence[0] {
+                continue;
+            }
+            num_substitutions += 1;
+            let mut erroneous_sequence = sequence.clone();
+            erroneous_sequence[0] = amplitude;
+            let weight: usize = erroneous_sequence
+                .iter()
+                .map(|a| [0, 1, 3, 6][(a - 1) / 2])
+                .sum();
+            if weight > adess.trellis.threshold {
+                continue;
+            }
+            let decoded_index = adess.index_for_sequence(&erroneous_sequence);
+            if decoded_index < num_used {
+                num_decodable += 1;
+                flipped_bits += (decoded_index.to_usize().unwrap() ^ index).count_ones();
+            }
+        }
+    }
+    assert!(
+        (propagation.flipped_bits[0] - flipped_bits as f32 / num_decodable as f32).abs() < 1e-6
+    );
+    assert!(
+        (propagation.detectable[0]
+            - (num_substitutions - num_decodable) as f32 / num_substitutions as f32)
+            .abs()
+            < 1e-6
+    );
+    // any decodable substitution flips at least one bit
+    assert!(propagation.flipped_bits.iter().all(|&bits| bits >= 1.0));
+
+    let sampled = statistics::error_propagation(&adess, 0, 100, 5);
+    assert!(!sampled.exact);
+    assert!(sampled.flipped_bits.iter().all(|&bits| bits >= 1.0));
+}
+
+#[test]
+fn estimate_complexity() {
+    let distribution = [0.4, 0.3, 0.2, 0.1];
+    let n_max = 24;
+
+    let estimate =
+        complexity::estimate_complexity(&distribution, 2.0, n_max, ThresholdOrBits::NumBits(30))
+            .unwrap();
+    let (adess, _) = AdEss::new_for_distribution_num_bits(30, n_max, &distribution, 2.0).unwrap();
+    assert_eq!(estimate.weights, adess.get_weights());
+    assert_eq!(estimate.threshold, adess.trellis.threshold);
+    assert_eq!(estimate.num_bits, adess.num_bits());
+    let (num_stages, num_weight_levels) = adess.trellis.get_storage_dimensions();
+    assert_eq!(estimate.num_weight_levels, num_weight_levels);
+    assert_eq!(estimate.num_nodes, num_stages * num_weight_levels);
+    assert!(estimate.memory_bytes >= estimate.num_nodes * 24);
+    let storage = complexity::estimate_storage(estimate.threshold, n_max, &estimate.weights);
+    assert_eq!(storage.num_nodes, estimate.num_nodes);
+    assert_eq!(storage.memory_bytes, estimate.memory_bytes);
+
+    let estimate = complexity::estimate_complexity(
+        &distribution,
+        2.0,
+        n_max,
+        ThresholdOrBits::Threshold(adess.trellis.threshold),
+    )
+    .unwrap();
+    assert_eq!(estimate.num_bits, adess.num_bits());
+
+    assert!(complexity::estimate_complexity(
+        &distribution,
+        2.0,
+        n_max,
+        ThresholdOrBits::NumBits(100)
+    )
+    .is_err());
+}
+
+#[test]
+fn estimate_storage() {
+    let weights = [0, 1, 3, 6];
+    for (threshold, n_max) in [(12, 10), (40, 32), (90, 64)] {
+        let estimate = complexity::estimate_storage(threshold, n_max, &weights);
+        let adess = AdEss::new(threshold, n_max, &weights);
+        let (num_stages, num_weight_levels) = adess.trellis.get_storage_dimensions();
+        assert_eq!(estimate.num_weight_levels, num_weight_levels);
+        assert_eq!(estimate.num_nodes, num_stages * num_weight_levels);
+        assert_eq!(estimate.storage_bits, adess.trellis.storage_bits());
+        assert_eq!(
+            estimate.bits_per_node,
+            estimate.storage_bits as f64 / estimate.num_nodes as f64
+        );
+        assert!(estimate.memory_bytes >= estimate.num_nodes * 16);
+    }
+}
+
+#[test]
+fn adess_extend() {
+    let weights = [0, 1, 3, 6];
+    let adess = AdEss::new(12, 10, &weights);
+    for (threshold, n_max) in [(12, 10), (13, 10), (20, 10), (12, 16), (25, 14)] {
+        let extended = adess.extend(threshold, n_max).unwrap();
+        let reference = AdEss::new(threshold, n_max, &weights);
+        assert_eq!(
+            extended.trellis.get_weight_levels(),
+            reference.trellis.get_weight_levels()
+        );
+        for n in 0..=n_max {
+            assert_eq!(
+                extended.trellis.get_stage(n),
+                reference.trellis.get_stage(n)
+            );
+        }
+    }
+    assert!(adess.extend(11, 10).is_err());
+    assert!(adess.extend(12, 9).is_err());
+}
+
+#[test]
+fn trellis_serialization() {
+    let adess = AdEss::new(40, 16, &[0, 1, 3, 6]);
+    let mut bytes = vec![];
+    adess.trellis.write_to(&mut bytes).unwrap();
+    let trellis = Trellis::read_from(bytes.as_slice()).unwrap();
+    assert_eq!(trellis, *adess.trellis);
+    assert_eq!(trellis.threshold, adess.trellis.threshold);
+    assert_eq!(
+        trellis.get_weight_levels(),
+        adess.trellis.get_weight_levels()
+    );
+
+    // expandable trellises store fewer weight levels than they hold
+    let reverse_trellis =
+        trellis_utils::reverse_trellis_upto_num_sequences(Integer::from(1000), 8, &[0, 1, 3])
+            .unwrap();
+    let mut bytes = vec![];
+    reverse_trellis.write_to(&mut bytes).unwrap();
+    assert_eq!(
+        Trellis::read_from(bytes.as_slice()).unwrap(),
+        reverse_trellis
+    );
+
+    bytes[0] = 0;
+    assert!(Trellis::read_from(bytes.as_slice()).is_err());
+    assert!(Trellis::read_from(&bytes[..20]).is_err());
+
+    // untrusted input: lengths, dimensions and weight levels are validated
+    // threshold 4 with a single stored weight level of 3 stages, each node value is one byte
+    let serialize = |n_max: u64, weights: &[u64], weight_levels: &[u64], node_length: u32| {
+        let mut values = vec![4, n_max, weights.len() as u64];
+        values.extend(weights);
+        values.push(weight_levels.len() as u64);
+        values.extend(weight_levels);
+        values.push(1);
+        let mut bytes = b"ADESSTRL\x01".to_vec();
+        bytes.extend(values.iter().flat_map(|value| value.to_le_bytes()));
+        for _ in 0..3 {
+            bytes.extend(node_length.to_le_bytes());
+            bytes.push(1);
+        }
+        bytes
+    };
+    let valid = serialize(2, &[0, 1], &[0, 1, 2], 1);
+    assert_eq!(Trellis::read_from(valid.as_slice()).unwrap().n_max, 2);
+    let invalid_inputs = [
+        serialize(2, &[0, 1], &[0, 1, 2], u32::MAX),
+        serialize(u64::MAX, &[0, 1], &[0, 1, 2], 1),
+        serialize(2, &[1, 2], &[0, 1, 2], 1),
+        serialize(2, &[0, 1], &[0, 2, 1], 1),
+        serialize(2, &[0, 1], &[1, 2], 1),
+        serialize(2, &[0, 1], &[0, 2], 1),
+        serialize(2, &[0, 2], &[0, 1, 2], 1),
+        serialize(2, &[0, 1], &[0, 1, 2, 1 << 40], 1),
+    ];
+    for bytes in invalid_inputs {
+        assert!(Trellis::read_from(bytes.as_slice()).is_err());
+    }
+    let mut trailing = valid.clone();
+    trailing.push(0);
+    assert!(Trellis::read_from(trailing.as_slice()).is_err());
+
+    let path = std::env::temp_dir().join("ad_ess_trellis_serialization.bin");
+    adess.trellis.save(&path).unwrap();
+    assert_eq!(Trellis::load(&path).unwrap(), *adess.trellis);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn adess_from_trellis_file() {
+    let adess = AdEss::new(80, 32, &[0, 1, 3, 6]);
+    let path = std::env::temp_dir().join("ad_ess_from_trellis_file.bin");
+    adess.trellis.save(&path).unwrap();
+    let loaded = AdEss::from_trellis_file(&path).unwrap();
+    let index = adess.num_sequences() - 1u32;
+    assert_eq!(
+        loaded.sequence_for_index(&index),
+        adess.sequence_for_index(&index)
+    );
+
+    std::fs::write(&path, b"ADESSTRL").unwrap();
+    assert!(AdEss::from_trellis_file(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+    assert!(AdEss::from_trellis_file(&path).is_err());
+}
+
+#[test]
+fn adess_and_rts_from_trellis() {
+    let weights = [0, 1, 3, 6];
+    let adess = AdEss::new(80, 32, &weights);
+    assert!(matches!(
+        AdEss::from_trellis(Trellis::new_like(&adess.trellis)),
+        Err(AdEssError::InvalidParameter(_))
+    ));
+    let mut bytes = vec![];
+    adess.trellis.write_to(&mut bytes).unwrap();
+    let copy = AdEss::from_trellis(Trellis::read_from(bytes.as_slice()).unwrap()).unwrap();
+    assert_eq!(copy.num_sequences(), adess.num_sequences());
+
+    let rts = RTS::from_adess(&adess);
+    let reverse = AdEss::calc_reverse_trellis(80, 32, &weights);
+    assert_eq!(RTS::from_trellis(reverse).unwrap().trellis, rts.trellis);
+    assert!(RTS::from_trellis(Trellis::new(80, 32, &weights)).is_err());
+    assert!(matches!(
+        AdEss::from_trellis(Trellis::new_expandable(32, &weights)),
+        Err(AdEssError::TrellisTooSmall(_))
+    ));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn trellis_compressed_archival() {
+    let adess = AdEss::new(120, 64, &[0, 1, 3, 6]);
+    let path = std::env::temp_dir().join("ad_ess_trellis_compressed.bin.zst");
+    adess.trellis.save_compressed(&path, 0).unwrap();
+    assert_eq!(Trellis::load_compressed(&path).unwrap(), *adess.trellis);
+
+    let mut bytes = vec![];
+    adess.trellis.write_to(&mut bytes).unwrap();
+    assert!((std::fs::metadata(&path).unwrap().len() as usize) < bytes.len());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    let adess = AdEss::new(200, 64, &[0, 1, 3, 6]);
+    let json = serde_json::to_string(&adess).unwrap();
+    let restored: AdEss = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.trellis, adess.trellis);
+    let index = adess.num_sequences() - 1u32;
+    assert_eq!(
+        restored.sequence_for_index(&index),
+        adess.sequence_for_index(&index)
+    );
+
+    let rts = RTS::new(100, 64, &[0, 1, 3, 6]);
+    let restored: RTS = serde_json::from_str(&serde_json::to_string(&rts).unwrap()).unwrap();
+    assert_eq!(restored.trellis, rts.trellis);
+
+    let invalid = json.replacen("\"n_max\":64", "\"n_max\":63", 1);
+    assert!(serde_json::from_str::<AdEss>(&invalid).is_err());
+}
+
+#[test]
+fn shared_trellis() {
+    let weights = [0, 1, 3, 6];
+    let adess = AdEss::new(60, 24, &weights);
+    let mut clone = adess.clone();
+    assert!(std::sync::Arc::ptr_eq(&adess.trellis, &clone.trellis));
+
+    clone.trellis_mut().build_successor_sums();
+    assert!(!std::sync::Arc::ptr_eq(&adess.trellis, &clone.trellis));
+    assert!(adess.trellis.successor_sums(0, 0).is_none());
+    assert_eq!(clone.trellis, adess.trellis);
+
+    let registry = TrellisRegistry::new();
+    let trellis = registry.get(&weights, 24, 60);
+    let from_registry = AdEss::from_trellis(trellis.clone()).unwrap();
+    assert!(std::sync::Arc::ptr_eq(&from_registry.trellis, &trellis));
+    assert_eq!(from_registry.num_sequences(), adess.num_sequences());
+}
+
+#[test]
+fn encoders_in_threads() {
+    let adess = AdEss::new(60, 24, &[0, 1, 3, 6]);
+    let rts = RTS::from_adess(&adess);
+    let rts_clone = rts.clone();
+    assert!(std::sync::Arc::ptr_eq(&rts.trellis, &rts_clone.trellis));
+
+    let index = adess.num_sequences() - 1u32;
+    let sequences: Vec<Vec<usize>> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let adess = adess.clone();
+                let index = &index;
+                scope.spawn(move || adess.sequence_for_index(index))
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+    assert!(sequences
+        .iter()
+        .all(|sequence| *sequence == adess.sequence_for_index(&index)));
+    let rts_index = rts.num_sequences() - 1u32;
+    let sequence = std::thread::scope(|scope| {
+        scope
+            .spawn(|| rts_clone.sequence_for_index(&rts_index))
+            .join()
+            .unwrap()
+    });
+    assert_eq!(rts.index_for_sequence(&sequence), rts_index);
+}
+
+#[test]
+fn trellis_registry() {
+    let weights = [0, 1, 3, 6];
+    let registry = TrellisRegistry::new();
+    assert!(registry.is_empty());
+
+    let trellis = registry.get(&weights, 16, 30);
+    assert_eq!(trellis, AdEss::new(30, 16, &weights).trellis);
+    assert!(registry.contains(&weights, 16, 30));
+    assert!(std::sync::Arc::ptr_eq(
+        &trellis,
+        &registry.get(&weights, 16, 30)
+    ));
+
+    let other = registry.get(&weights, 16, 31);
+    assert!(!std::sync::Arc::ptr_eq(&trellis, &other));
+    assert_eq!(registry.len(), 2);
+
+    registry.clear();
+    assert!(registry.is_empty());
+    assert_eq!(trellis, AdEss::new(30, 16, &weights).trellis);
+
+    let global = TrellisRegistry::global().get(&weights, 8, 12);
+    assert!(std::sync::Arc::ptr_eq(
+        &global,
+        &TrellisRegistry::global().get(&weights, 8, 12)
+    ));
+}
+
+#[test]
+fn adess_float_weights() {
+    let distribution = [0.4f64, 0.3, 0.2, 0.1];
+    let log_probabilities: Vec<f64> = distribution.iter().map(|p| -p.log2()).collect();
+    assert_eq!(
+        AdEss::quantize_weights(&log_probabilities, 0.5).unwrap(),
+        AdEss::calc_weights(&[0.4, 0.3, 0.2, 0.1], 2.0).unwrap()
+    );
+    // weights are shifted by the smallest weight before quantization
+    assert_eq!(
+        AdEss::quantize_weights(&[2.5, 2.0, 3.26], 0.25).unwrap(),
+        vec![2, 0, 5]
+    );
+
+    let adess = AdEss::new_for_float_weights(10.2, 12, &log_probabilities, 0.5).unwrap();
+    let weights = AdEss::quantize_weights(&log_probabilities, 0.5).unwrap();
+    assert_eq!(adess.trellis, AdEss::new(20, 12, &weights).trellis);
+
+    let adess = AdEss::new_for_float_weights_num_bits(16, 12, &log_probabilities, 0.5).unwrap();
+    let (reference, _) =
+        AdEss::new_for_distribution_num_bits(16, 12, &[0.4, 0.3, 0.2, 0.1], 2.0).unwrap();
+    assert_eq!(adess.trellis, reference.trellis);
+
+    assert!(AdEss::quantize_weights(&log_probabilities, 0.0).is_err());
+    assert!(AdEss::quantize_weights(&[0.0, f64::INFINITY], 1.0).is_err());
+    assert!(AdEss::new_for_float_weights(-1.0, 12, &log_probabilities, 0.5).is_err());
+}
+
+#[test]
+fn degenerate_configurations() {
+    // single amplitude alphabet: a single sequence, zero bits
+    let adess = AdEss::new(5, 4, &[0]);
+    assert_eq!(adess.num_bits(), 0);
+    assert_eq!(adess.sequence_for_index(&Integer::from(0)), vec![1; 4]);
+    assert_eq!(adess.index_for_sequence(&[1; 4]), 0);
+    assert_eq!(adess.amplitude_distribution(), vec![1.0]);
+    let rts = RTS::new(0, 4, &[0]);
+    assert_eq!(rts.num_bits(), 0);
+    assert_eq!(rts.amplitude_distribution(), vec![1.0]);
+    assert!(RTS::try_new(1, 4, &[0]).is_err());
+    let (adess, distribution) = AdEss::new_for_distribution_num_bits(0, 4, &[1.0], 2.0).unwrap();
+    assert_eq!((adess.num_bits(), distribution), (0, vec![1.0]));
+
+    // n_max = 0: the empty sequence
+    let adess = AdEss::new(5, 0, &[0, 1]);
+    assert_eq!(adess.num_bits(), 0);
+    assert!(adess.sequence_for_index(&Integer::from(0)).is_empty());
+    assert_eq!(adess.index_for_sequence(&[]), 0);
+    assert_eq!(adess.amplitude_distribution(), vec![0.0, 0.0]);
+    let rts = RTS::new(0, 0, &[0, 1]);
+    assert_eq!(rts.num_bits(), 0);
+    assert!(rts.sequence_for_index(&Integer::from(0)).is_empty());
+    assert_eq!(rts.amplitude_distribution(), vec![0.0, 0.0]);
+
+    // n_max = 1
+    let adess = AdEss::new(3, 1, &[0, 1, 3]);
+    assert_eq!(adess.num_bits(), 1);
+    assert_eq!(adess.amplitude_distribution(), vec![0.5, 0.5, 0.0]);
+
+    // invalid weights and thresholds
+    assert!(AdEss::calc_weights(&[], 2.0).is_err());
+    assert!(AdEss::try_new(5, 4, &[]).is_err());
+    assert!(AdEss::try_new(7, 4, &[2, 3]).is_err());
+    assert!(AdEss::try_new(usize::MAX, 4, &[usize::MAX / 2, usize::MAX / 2 + 1]).is_err());
+    assert!(RTS::try_new(1, 4, &[1, 2]).is_err());
+    let adess = AdEss::try_new(9, 4, &[2, 3]).unwrap();
+    assert_eq!(adess.get_weights(), vec![0, 1]);
+    assert_eq!(adess.trellis, AdEss::new(1, 4, &[0, 1]).trellis);
+}
+
+#[test]
+fn trellis_stage_slice() {
+    let adess = AdEss::new(20, 10, &[0, 1, 3, 6]);
+    for n in 0..=10 {
+        assert_eq!(
+            adess.trellis.stage_slice(n),
+            adess.trellis.get_stage(n).as_slice()
+        );
+    }
+    assert_eq!(adess.trellis.stage_slice(0)[0], adess.num_sequences());
+}
+
+#[test]
+fn adess_index_range_for_prefix() {
+    let adess = AdEss::new(12, 6, &[0, 1, 3, 6]);
+    let num_used = 1usize << adess.num_bits();
+    let sequences: Vec<Vec<usize>> = (0..num_used)
+        .map(|index| adess.sequence_for_index(&Integer::from(index)))
+        .collect();
+
+    for prefix in [
+        vec![],
+        vec![1],
+        vec![3, 5],
+        vec![7, 1, 3],
+        vec![5, 5, 1, 1, 1, 1],
+    ] {
+        let range = adess.index_range_for_prefix(&prefix).unwrap();
+        let expected: Vec<usize> = (0..num_used)
+            .filter(|&index| sequences[index].starts_with(&prefix))
+            .collect();
+        if expected.is_empty() {
+            assert!(range.start >= range.end);
+        } else {
+            assert_eq!(range.start, expected[0]);
+            assert_eq!(range.end, expected[expected.len() - 1] + 1);
+            assert_eq!(
+                expected.len(),
+                expected[expected.len() - 1] + 1 - expected[0]
+            );
+        }
+    }
+    assert_eq!(
+        adess.index_range_for_prefix(&[]).unwrap(),
+        Integer::from(0)..Integer::from(num_used)
+    );
+    assert!(adess.index_range_for_prefix(&[7, 7, 7]).is_err());
+    assert!(adess.index_range_for_prefix(&[2]).is_err());
+    assert!(adess.index_range_for_prefix(&[1; 7]).is_err());
+}
+
+#[test]
+fn adess_sequence_for_index_with_info() {
+    let weights = [0, 1, 3, 6];
+    let adess = AdEss::new(20, 10, &weights);
+    for index in [0, 1, 17, 1000, (1 << adess.num_bits()) - 1] {
+        let index = Integer::from(index);
+        let sequence = adess.sequence_for_index(&index);
+        let sequence_weights: Vec<usize> = sequence.iter().map(|a| weights[(a - 1) / 2]).collect();
+        let weight_levels = utils::cumsum(&sequence_weights);
+        assert_eq!(
+            adess.sequence_for_index_with_info(&index),
+            Ok(EncodingInfo {
+                weight: sequence_weights.iter().sum(),
+                energy: adess.energy_of_index(&index).unwrap(),
+                sequence,
+                weight_levels,
+            })
+        );
+    }
+    for index in [Integer::from(-1), adess.num_sequences()] {
+        assert_eq!(
+            adess.sequence_for_index_with_info(&index),
+            Err(AdEssError::IndexOutOfRange)
+        );
+    }
+}
+
+#[test]
+fn slicer() {
+    let slicer = Slicer::new(4).unwrap();
+    assert_eq!(slicer.get_boundaries(), vec![2.0, 4.0, 6.0]);
+    assert_eq!(
+        slicer.sequence_for_samples(&[0.1, -1.9, 2.1, -4.5, 6.5, 100.0]),
+        vec![1, 1, 3, 5, 7, 7]
+    );
+
+    // noiseless samples are sliced to the transmitted sequence
+    let adess = AdEss::new(20, 10, &[0, 1, 3, 6]);
+    let index = Integer::from(321);
+    let samples: Vec<f32> = adess
+        .sequence_for_index(&index)
+        .iter()
+        .enumerate()
+        .map(|(n, &a)| if n % 2 == 0 { a as f32 } else { -(a as f32) })
+        .collect();
+    assert_eq!(
+        adess.index_for_sequence(&slicer.sequence_for_samples(&samples)),
+        index
+    );
+
+    // a uniform prior results in the minimum distance slicer
+    let levels = utils::uniform_levels(4);
+    let uniform_map = Slicer::map(&levels, &[0.25; 4], 2.0).unwrap();
+    for (b, b_ref) in uniform_map.get_boundaries().iter().zip([2.0, 4.0, 6.0]) {
+        assert!((b - b_ref).abs() < 1e-5);
+    }
+
+    // MAP decisions equal the brute force maximization of the a posteriori probability
+    let noise_variance = 4.0;
+    for distribution in [
+        vec![0.4, 0.3, 0.2, 0.1],
+        vec![0.7, 0.01, 0.28, 0.01],
+        vec![0.5, 0.0, 0.3, 0.2],
+    ] {
+        let map = Slicer::map(&levels, &distribution, noise_variance).unwrap();
+        for k in 0..400 {
+            let y = -10.0 + k as f32 * 0.05;
+            let (w_idx, _) = levels
+                .iter()
+                .zip(&distribution)
+                .map(|(x, p)| p * (-(y.abs() - x).powi(2) / (2.0 * noise_variance)).exp())
+                .enumerate()
+                .fold((0, f32::NEG_INFINITY), |best, (w_idx, metric)| {
+                    if metric > best.1 {
+                        (w_idx, metric)
+                    } else {
+                        best
+                    }
+                });
+            assert_eq!(
+                map.amplitude_for_sample(y),
+                2 * w_idx + 1,
+                "{distribution:?} {y}"
+            );
+        }
+    }
+
+    assert!(Slicer::for_levels(&[1.0, 1.0]).is_err());
+    assert!(Slicer::with_boundaries(&[1.0, 3.0, 5.0], vec![4.0, 2.0]).is_err());
+    assert!(Slicer::map(&levels, &[0.5, 0.5], 1.0).is_err());
+}
+
+#[test]
+fn llr_table() {
+    let distribution = [0.4, 0.3, 0.2, 0.1];
+    let levels = utils::uniform_levels(4);
+    let labeling = labeling::gray_labeling(4);
+    let snr_db = 10.0;
+    let table = labeling::llr_table(&distribution, &levels, &labeling, snr_db, 201, 8).unwrap();
+    assert_eq!(table.llrs.len(), 201);
+    assert!(table.llrs.iter().flatten().all(|llr| llr.abs() <= 127));
+    assert_eq!(table.entry_for_sample(-1000.0), 0);
+    assert_eq!(table.entry_for_sample(1000.0), 200);
+    assert_eq!(table.entry_for_sample(0.0), 100);
+
+    // sign bit: positive samples favor 0, the table is antisymmetric
+    for entry in 0..201 {
+        assert_eq!(table.llrs[entry][0], -table.llrs[200 - entry][0]);
+        assert_eq!(table.llrs[entry][1..], table.llrs[200 - entry][1..]);
+    }
+    assert!(table.llrs_for_sample(3.0)[0] > 0);
+
+    // dequantized LLRs match the exact LLRs
+    let noise_variance = utils::average_energy(&distribution, &levels) / 10f32.powf(snr_db / 10.0);
+    let y = table.y_min + 150.0 * table.y_step;
+    let likelihood = |x: f32| (-(y - x).powi(2) / (2.0 * noise_variance)).exp();
+    let (mut p_0, mut p_1) = (0.0, 0.0);
+    for (w_idx, (&p, &level)) in distribution.iter().zip(&levels).enumerate() {
+        // most significant label bit
+        let symbol_likelihood = p * (likelihood(level) + likelihood(-level));
+        if labeling[w_idx] >> 1 == 0 {
+            p_0 += symbol_likelihood;
+        } else {
+            p_1 += symbol_likelihood;
+        }
+    }
+    let llr = (p_0 / p_1).ln();
+    assert!((table.llrs[150][1] as f32 * table.llr_step - llr).abs() <= table.llr_step);
+
+    assert!(labeling::llr_table(&distribution, &levels, &labeling, snr_db, 1, 8).is_err());
+    assert!(labeling::llr_table(&[0.5, 0.3, 0.2], &levels, &labeling, snr_db, 10, 8).is_err());
+}
+
+#[test]
+fn cancellation_token() {
+    let weights = [0, 1, 3, 6];
+    let token = CancellationToken::new();
+    let adess = AdEss::new_cancellable(20, 10, &weights, &token).unwrap();
+    assert_eq!(adess.trellis, AdEss::new(20, 10, &weights).trellis);
+    let indexes: Vec<Integer> = (0..10).map(Integer::from).collect();
+    assert_eq!(
+        adess
+            .sequences_for_indices_cancellable(&indexes, &token)
+            .unwrap()[7],
+        adess.sequence_for_index(&indexes[7])
+    );
+
+    // cancelling a clone cancels all operations using the token
+    token.clone().cancel();
+    assert!(token.is_cancelled());
+    assert!(AdEss::new_cancellable(20, 10, &weights, &token).is_err());
+    assert!(adess
+        .sequences_for_indices_cancellable(&indexes, &token)
+        .is_err());
+    assert!(AdEss::new_for_distribution_num_bits_cancellable(
+        20,
+        16,
+        &[0.4, 0.3, 0.2, 0.1],
+        2.0,
+        &token
+    )
+    .is_err());
+
+    // cancellation from another thread
+    let token = CancellationToken::new();
+    let handle = {
+        let token = token.clone();
+        std::thread::spawn(move || {
+            while !token.is_cancelled() {
+                std::thread::yield_now();
+            }
+            AdEss::new_cancellable(20, 10, &weights, &token).is_err()
+        })
+    };
+    token.cancel();
+    assert!(handle.join().unwrap());
+}
+
+#[test]
+fn adess_memory_limit() {
+    let weights = [0, 1, 3, 6];
+    let adess = AdEss::new(60, 40, &weights);
+    let memory_bytes = adess.trellis.memory_bytes();
+    let (num_stages, num_weight_levels) = adess.trellis.get_storage_dimensions();
+    assert!(memory_bytes > num_stages * num_weight_levels * std::mem::size_of::<Integer>());
+
+    let limited = AdEss::new_with_memory_limit(60, 40, &weights, memory_bytes).unwrap();
+    assert_eq!(limited.trellis, adess.trellis);
+    // the limit is exceeded by the node values
+    assert!(AdEss::new_with_memory_limit(60, 40, &weights, memory_bytes - 1).is_err());
+    // the limit is exceeded by the nodes, nothing is allocated
+    assert!(AdEss::new_with_memory_limit(60, 40, &weights, 100).is_err());
+}
+
+#[test]
+fn wedge_trellis_per_stage_thresholds() {
+    let weights = [0, 1, 3, 5];
+    let stage_thresholds = [2, 4, 5, 8, 8];
+    let wedge = WedgeAdEss::new(&stage_thresholds, &weights).unwrap();
+
+    // brute force enumeration of all sequences within the per stage bounds
+    let num_amplitudes = weights.len();
+    let mut valid_sequences = vec![];
+    for code in 0..num_amplitudes.pow(stage_thresholds.len() as u32) {
+        let w_idxs: Vec<usize> = (0..stage_thresholds.len())
+            .rev()
+            .map(|n| code / num_amplitudes.pow(n as u32) % num_amplitudes)
+            .collect();
+        let mut weight = 0;
+        let is_valid = w_idxs.iter().zip(stage_thresholds).all(|(&w_idx, bound)| {
+            weight += weights[w_idx];
+            weight <= bound
+        });
+        if is_valid {
+            valid_sequences.push(w_idxs);
+        }
+    }
+    assert_eq!(wedge.num_sequences(), valid_sequences.len());
+
+    // lexicographic order, only the used sequences contribute to the distributions
+    let num_used = 1usize << wedge.num_bits();
+    let mut counts = vec![vec![0usize; num_amplitudes]; stage_thresholds.len()];
+    for (index, w_idxs) in valid_sequences.iter().enumerate().take(num_used) {
+        let amplitudes: Vec<usize> = w_idxs.iter().map(|w_idx| 2 * w_idx + 1).collect();
+        assert_eq!(wedge.sequence_for_index(&Integer::from(index)), amplitudes);
+        assert_eq!(wedge.index_for_sequence(&amplitudes), index);
+        for (n, &w_idx) in w_idxs.iter().enumerate() {
+            counts[n][w_idx] += 1;
+        }
+    }
+    let per_position = wedge.amplitude_distribution_per_position();
+    for (position_counts, distribution) in counts.iter().zip(per_position.iter()) {
+        for (&count, &probability) in position_counts.iter().zip(distribution) {
+            assert!((count as f32 / num_used as f32 - probability).abs() < 1e-6);
+        }
+    }
+    let total: f32 = wedge.amplitude_distribution().iter().sum();
+    assert!((total - 1.0).abs() < 1e-5);
+
+    // constant thresholds equal the sphere of AdEss
+    let wedge = WedgeAdEss::new(&[9; 6], &weights).unwrap();
+    let adess = AdEss::new(9, 6, &weights);
+    assert_eq!(wedge.num_sequences(), adess.num_sequences());
+    for (p_wedge, p_adess) in wedge
+        .amplitude_distribution()
+        .iter()
+        .zip(adess.amplitude_distribution())
+    {
+        assert!((p_wedge - p_adess).abs() < 1e-6);
+    }
+    assert!((wedge.average_energy() - adess.average_energy()).abs() < 1e-4);
+}
+
+#[test]
+fn lattice_shell_enumeration() {
+    let points = lattice::lattice_points(Lattice::Checkerboard, 2, 4);
+    assert_eq!(points.len(), 9);
+    assert_eq!(points[0], vec![0, 0]);
+    assert!(points.iter().all(|p| (p[0] + p[1]) % 2 == 0));
+
+    let max_energy = 12;
+    let shaper =
+        LatticeShellShaper::for_lattice(Lattice::Checkerboard, 2, 4, max_energy, 3).unwrap();
+    assert_eq!(shaper.get_weights(), vec![0, 1, 1, 1, 1, 2, 2, 2, 2]);
+
+    // brute force count of all point sequences within the sphere
+    let energy = |p: &Vec<i32>| (p[0] * p[0] + p[1] * p[1]) as u64;
+    let mut num_sequences = 0;
+    for p_0 in points.iter() {
+        for p_1 in points.iter() {
+            for p_2 in points.iter() {
+                if energy(p_0) + energy(p_1) + energy(p_2) <= max_energy {
+                    num_sequences += 1;
+                }
+            }
+        }
+    }
+    assert_eq!(shaper.num_sequences(), num_sequences);
+
+    for index in 0..(1u32 << shaper.num_bits()) {
+        let index = Integer::from(index);
+        let point_sequence = shaper.points_for_index(&index);
+        assert!(point_sequence.iter().map(energy).sum::<u64>() <= max_energy);
+        assert_eq!(shaper.index_for_points(&point_sequence).unwrap(), index);
+    }
+    assert!(shaper.index_for_points(&vec![vec![1, 0]; 3]).is_err());
+    let total: f32 = shaper.point_distribution().iter().sum();
+    assert!((total - 1.0).abs() < 1e-5);
+
+    // one dimensional cubic lattice points are the signed PAM amplitudes
+    let shaper = LatticeShellShaper::for_lattice(Lattice::Cubic, 1, 9, 30, 4).unwrap();
+    assert_eq!(shaper.get_weights(), vec![0, 0, 1, 1]);
+    assert_eq!(shaper.num_sequences(), 16 * (1 + 4 + 6 + 4));
+}
+
+#[test]
+fn sequence_counts_and_utilization() {
+    let adess = AdEss::new(8, 5, &[0, 1, 1, 3]);
+    let counts = adess.sequence_counts();
+    assert_eq!(counts.total, adess.num_sequences());
+    assert_eq!(counts.used, Integer::from(1) << adess.num_bits());
+    assert!(counts.used <= counts.total && counts.total < 2 * counts.used.clone());
+    assert_eq!(adess.num_sequences_used(), counts.used);
+    let expected = counts.used.to_f64() / counts.total.to_f64();
+    assert!((adess.utilization() - expected).abs() < 1e-12);
+
+    let rts = RTS::new(12, 8, &[0, 1, 2, 4]);
+    let counts = rts.sequence_counts();
+    assert_eq!(counts.total, rts.num_sequences());
+    assert_eq!(rts.num_sequences_used(), Integer::from(1) << rts.num_bits());
+    assert!(rts.utilization() > 0.5 && rts.utilization() <= 1.0);
+
+    let full = utils::SequenceCounts::for_total(Integer::from(64));
+    assert_eq!(full.used, 64);
+    assert_eq!(full.utilization(), 1.0);
+    assert_eq!(utils::SequenceCounts::for_total(Integer::new()).used, 0);
+}
+
+/// Returns a `.npy` file with the given dtype, shape and raw data
+fn npy_bytes(descr: &str, shape: &str, data: &[u8]) -> Vec<u8> {
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend((header.len() as u16).to_le_bytes());
+    bytes.extend(header.bytes());
+    bytes.extend(data);
+    bytes
+}
+
+/// Returns an uncompressed zip archive of the `files` (without checksums)
+fn zip_bytes(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut archive = vec![];
+    let mut central_directory = vec![];
+    for (name, data) in files {
+        let offset = archive.len() as u32;
+        let mut header = vec![0u8; 30];
+        header[..4].copy_from_slice(&0x04034b50u32.to_le_bytes());
+        header[18..22].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        header[22..26].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        header[26..28].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        archive.extend(header);
+        archive.extend(name.bytes());
+        archive.extend(data);
+
+        let mut entry = vec![0u8; 46];
+        entry[..4].copy_from_slice(&0x02014b50u32.to_le_bytes());
+        entry[20..24].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        entry[24..28].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        entry[28..30].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        entry[42..46].copy_from_slice(&offset.to_le_bytes());
+        central_directory.extend(entry);
+        central_directory.extend(name.bytes());
+    }
+    let mut end = vec![0u8; 22];
+    end[..4].copy_from_slice(&0x06054b50u32.to_le_bytes());
+    end[8..10].copy_from_slice(&(files.len() as u16).to_le_bytes());
+    end[10..12].copy_from_slice(&(files.len() as u16).to_le_bytes());
+    end[12..16].copy_from_slice(&(central_directory.len() as u32).to_le_bytes());
+    end[16..20].copy_from_slice(&(archive.len() as u32).to_le_bytes());
+    archive.extend(central_directory);
+    archive.extend(end);
+    archive
+}
+
+#[test]
+fn trellis_import_from_arrays() {
+    let (threshold, n_max, weights) = (9, 4, [0, 2, 3, 7]);
+    let adess = AdEss::new(threshold, n_max, &weights);
+    let weight_levels = adess.trellis.get_weight_levels();
+    let node_values: Vec<Vec<Integer>> = (0..=n_max)
+        .map(|stage| {
+            (0..=threshold)
+                .map(|wl| match weight_levels.contains(&wl) {
+                    true => adess.trellis.get(stage, wl),
+                    false => Integer::from(1234),
+                })
+                .collect()
+        })
+        .collect();
+    let trellis = Trellis::from_arrays(&weights, threshold, &node_values).unwrap();
+    assert_eq!(trellis, *adess.trellis);
+    assert!(Trellis::from_arrays(&weights, threshold + 1, &node_values).is_err());
+    assert!(Trellis::from_arrays(&[1, 2], threshold, &node_values).is_err());
+
+    // node values as int64 and as unicode strings
+    let int_data: Vec<u8> = node_values
+        .iter()
+        .flatten()
+        .flat_map(|value| value.to_i64().unwrap().to_le_bytes())
+        .collect();
+    let string_data: Vec<u8> = node_values
+        .iter()
+        .flatten()
+        .flat_map(|value| format!("{value:0>4}").chars().collect::<Vec<char>>())
+        .flat_map(|c| (c as u32).to_le_bytes())
+        .collect();
+    let weight_data: Vec<u8> = weights
+        .iter()
+        .flat_map(|&w| (w as u32).to_le_bytes())
+        .collect();
+    let shape = format!("({}, {})", n_max + 1, threshold + 1);
+    for node_value_npy in [
+        npy_bytes("<i8", &shape, &int_data),
+        npy_bytes("<U4", &shape, &string_data),
+    ] {
+        let archive = zip_bytes(&[
+            ("weights.npy", npy_bytes("<u4", "(4,)", &weight_data)),
+            (
+                "threshold.npy",
+                npy_bytes("<i2", "()", &(threshold as i16).to_le_bytes()),
+            ),
+            ("node_values.npy", node_value_npy),
+        ]);
+        let path = std::env::temp_dir().join("ad_ess_trellis_import.npz");
+        std::fs::write(&path, archive).unwrap();
+        let imported = AdEss::from_trellis(Trellis::load_npz(&path).unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(imported.trellis, adess.trellis);
+        let index = Integer::from(17);
+        assert_eq!(
+            imported.sequence_for_index(&index),
+            adess.sequence_for_index(&index)
+        );
+    }
+
+    let negative = npy::read_npy(&npy_bytes("|i1", "(2,)", &[0xfe, 0x05])[..]).unwrap();
+    assert_eq!(negative.values, vec![-2, 5]);
+    assert!(npy::read_npy(&b"NUMPY"[..]).is_err());
+}
+
+#[test]
+fn calc_weights_rounding_modes() {
+    let distribution = [0.35, 0.3, 0.2, 0.1, 0.05];
+    let res_factor = 2.3;
+    let exact: Vec<f32> = distribution
+        .iter()
+        .map(|p: &f32| (p.log2() - distribution[0].log2()) * -res_factor)
+        .collect();
+
+    let round =
+        AdEss::calc_weights_with_rounding(&distribution, res_factor, RoundingMode::Round).unwrap();
+    assert_eq!(
+        round.weights,
+        AdEss::calc_weights(&distribution, res_factor).unwrap()
+    );
+    let floor =
+        AdEss::calc_weights_with_rounding(&distribution, res_factor, RoundingMode::Floor).unwrap();
+    let ceil =
+        AdEss::calc_weights_with_rounding(&distribution, res_factor, RoundingMode::Ceil).unwrap();
+    for (w_idx, exact) in exact.iter().enumerate() {
+        assert_eq!(floor.weights[w_idx], exact.floor() as usize);
+        assert_eq!(ceil.weights[w_idx], exact.ceil() as usize);
+        assert!(round.errors[w_idx].abs() <= 0.5 + 1e-5);
+        assert!((floor.errors[w_idx] - (floor.weights[w_idx] as f32 - exact)).abs() < 1e-5);
+    }
+
+    // the accumulated error of error diffusion stays within half a step
+    let diffusion =
+        AdEss::calc_weights_with_rounding(&distribution, res_factor, RoundingMode::ErrorDiffusion)
+            .unwrap();
+    assert_eq!(diffusion.weights.iter().min(), Some(&0));
+    let mut accumulated_error = 0.0;
+    for error in diffusion.errors.iter() {
+        accumulated_error += error;
+        assert!(accumulated_error.abs() <= 0.5 + 1e-4);
+    }
+    for quantization in [round, floor, ceil, diffusion] {
+        let quantized = utils::distribution_from_weights(&quantization.weights, res_factor);
+        let kl = utils::kl_divergence(&quantized, &distribution.to_vec());
+        assert!((quantization.kl_divergence - kl).abs() < 1e-6);
+        assert!(quantization.kl_divergence >= 0.0);
+    }
+}
+
+#[test]
+fn binary_distribution_matcher() {
+    // at most 3 ones in 10 output bits
+    let dm = BinaryDm::new(3, 10, [0, 1]).unwrap();
+    assert_eq!(dm.adess().num_sequences(), 1 + 10 + 45 + 120);
+    assert_eq!(dm.num_bits(), 7);
+
+    let mut ones = 0;
+    for index in 0..(1u32 << dm.num_bits()) {
+        let data_bits = utils::integer_to_bits(&Integer::from(index), 7);
+        let output_bits = dm.encode(&data_bits).unwrap();
+        assert_eq!(output_bits.len(), 10);
+        assert!(output_bits.iter().filter(|&&b| b == 1).count() <= 3);
+        assert_eq!(dm.decode(&output_bits).unwrap(), data_bits);
+        ones += output_bits.iter().map(|&b| b as usize).sum::<usize>();
+    }
+    let p_one = ones as f32 / (10 << dm.num_bits()) as f32;
+    assert!((dm.ones_probability() - p_one).abs() < 1e-6);
+    assert!(dm.encode(&[0; 6]).is_err());
+    assert!(dm.decode(&[2; 10]).is_err());
+
+    let dm = BinaryDm::new_for_ones_probability(0.2, 60, 96, 4.0).unwrap();
+    assert!(dm.num_bits() >= 60);
+    assert!((dm.ones_probability() - 0.2).abs() < 0.05);
+    assert!(BinaryDm::new_for_ones_probability(1.0, 60, 96, 4.0).is_err());
+}
+
+#[test]
+fn trellis_stage_and_weight_level_sums() {
+    let adess = AdEss::new(12, 6, &[0, 2, 3, 5]);
+    let trellis = &adess.trellis;
+    let (num_stages, num_weight_levels) = trellis.get_storage_dimensions();
+    for stage in 0..num_stages {
+        let values = trellis.get_stage(stage);
+        assert_eq!(trellis.stage_sum(stage), values.iter().sum::<Integer>());
+        assert_eq!(
+            trellis.stage_cumulative_sums(stage),
+            utils::cumsum(&values)[1..]
+        );
+    }
+    let totals = trellis.weight_level_totals();
+    assert_eq!(totals.len(), num_weight_levels);
+    for (wl_idx, total) in totals.iter().enumerate() {
+        let column: Integer = (0..num_stages)
+            .map(|stage| trellis.stage_slice(stage)[wl_idx].clone())
+            .sum();
+        assert_eq!(*total, column);
+    }
+
+    let rts = RTS::new(10, 6, &[0, 2, 3, 5]);
+    assert_eq!(rts.num_sequences(), rts.trellis.stage_sum(6));
+}
+
+#[test]
+fn typed_amplitudes_weight_indexes_and_levels() {
+    assert_eq!(Amplitude::new(5), Ok(Amplitude(5)));
+    assert!(matches!(
+        Amplitude::new(4),
+        Err(AdEssError::InvalidSequence(_))
+    ));
+    assert!(Amplitude::new(0).is_err());
+    assert_eq!(Amplitude(7).weight_idx(), WeightIdx(3));
+    assert_eq!(Amplitude::from(WeightIdx(2)), Amplitude(5));
+    assert_eq!(
+        types::amplitudes(&[1, 3, 6]),
+        Err(AdEssError::InvalidSequence(
+            "Amplitudes must be positive odd numbers"
+        ))
+    );
+
+    let adess = AdEss::new(9, 5, &[0, 2, 3, 7]);
+    let trellis = &adess.trellis;
+    for (w_idx, wl) in trellis.successors(WeightLevel(2)) {
+        assert_eq!(wl.0, 2 + trellis.weight(w_idx));
+        assert_eq!(trellis.value(1, wl), trellis.get(1, wl.0));
+        assert!(trellis.predecessors(wl).contains(&(w_idx, WeightLevel(2))));
+    }
+
+    let index = Integer::from(42);
+    let amplitudes = adess.amplitudes_for_index(&index);
+    assert_eq!(
+        types::amplitude_values(&amplitudes),
+        adess.sequence_for_index(&index)
+    );
+    assert_eq!(adess.index_for_amplitudes(&amplitudes), Ok(index));
+    assert!(matches!(
+        adess.index_for_amplitudes(&amplitudes[1..]),
+        Err(AdEssError::InvalidSequence(_))
+    ));
+    // the typed error of AdEss is kept by the shared decoding interface
+    let matcher: &dyn DistributionMatcher = &adess;
+    assert!(matches!(
+        matcher.try_index_for_sequence(&[2; 10]),
+        Err(AdEssError::InvalidSequence(_))
+    ));
+}
+
+#[test]
+fn encoding_pipeline_keeps_order() {
+    let adess = std::sync::Arc::new(AdEss::new(30, 16, &[0, 1, 3, 6]));
+    let config = PipelineConfig {
+        adess: adess.clone(),
+        num_workers: 4,
+        queue_capacity: 2,
+    };
+    let (index_sender, sequence_receiver) = pipeline::spawn_encoder(config.clone());
+    let (sequence_sender, index_receiver) = pipeline::spawn_decoder(config.clone());
+
+    let mut rng = RandState::new();
+    let indexes: Vec<Integer> = (0..200)
+        .map(|_| Integer::from(Integer::random_bits(adess.num_bits(), &mut rng)))
+        .collect();
+    let feeder = {
+        let indexes = indexes.clone();
+        std::thread::spawn(move || {
+            for index in indexes {
+                index_sender.send(index).unwrap();
+            }
+        })
+    };
+    let sequences: Vec<Vec<usize>> = sequence_receiver.iter().collect();
+    feeder.join().unwrap();
+    assert_eq!(sequences.len(), indexes.len());
+    for (sequence, index) in sequences.iter().zip(indexes.iter()) {
+        assert_eq!(*sequence, adess.sequence_for_index(index));
+    }
+
+    let feeder = std::thread::spawn(move || {
+        for sequence in sequences {
+            sequence_sender.send(sequence).unwrap();
+        }
+    });
+    assert_eq!(index_receiver.iter().collect::<Vec<Integer>>(), indexes);
+    feeder.join().unwrap();
+
+    // an invalid index ends the pipeline
+    let (index_sender, sequence_receiver) = pipeline::spawn_encoder(config);
+    index_sender.send(Integer::from(3)).unwrap();
+    index_sender.send(adess.num_sequences()).unwrap();
+    index_sender.send(Integer::from(5)).unwrap();
+    assert_eq!(sequence_receiver.iter().count(), 1);
+}
+
+#[test]
+fn zero_probability_amplitudes() {
+    for distribution in [[0.5, 0.3, 0.2, 0.0], [0.5, 0.0, 0.3, 0.2]] {
+        let excluded_amplitude = 2 * distribution.iter().position(|&p| p == 0.0).unwrap() + 1;
+        let weights = AdEss::calc_weights(&distribution, 4.0).unwrap();
+        assert!(weights.contains(&trellis::EXCLUDED_WEIGHT));
+
+        let (adess, p_goal) =
+            AdEss::new_for_distribution_num_bits(20, 24, &distribution, 4.0).unwrap();
+        assert!(adess.num_bits() >= 20);
+        assert_eq!(p_goal.len(), 4);
+        let amplitude_distribution = adess.amplitude_distribution();
+        assert_eq!(amplitude_distribution[(excluded_amplitude - 1) / 2], 0.0);
+        let mut rng = RandState::new();
+        for _ in 0..100 {
+            let index = Integer::from(Integer::random_bits(adess.num_bits(), &mut rng));
+            let sequence = adess.sequence_for_index(&index);
+            assert!(!sequence.contains(&excluded_amplitude));
+            assert_eq!(adess.index_for_sequence(&sequence), index);
+        }
+
+        let (adess, _) = AdEss::new_for_distribution_threshold(30, 12, &distribution, 4.0).unwrap();
+        assert_eq!(
+            adess.amplitude_distribution()[(excluded_amplitude - 1) / 2],
+            0.0
+        );
+        let rts = RTS::new(14, 12, &weights);
+        assert!(!rts
+            .sequence_for_index(&Integer::from(1000))
+            .contains(&excluded_amplitude));
+        let diffusion =
+            AdEss::calc_weights_with_rounding(&distribution, 4.0, RoundingMode::ErrorDiffusion)
+                .unwrap();
+        assert!(diffusion.errors.iter().all(|e| e.is_finite()));
+        assert!(complexity::estimate_complexity(
+            &distribution,
+            4.0,
+            24,
+            ThresholdOrBits::NumBits(20)
+        )
+        .is_ok());
+    }
+    assert!(AdEss::calc_weights(&[0.0, 0.0], 4.0).is_err());
+}
+
+#[test]
+fn sequential_encoder_and_decoder() {
+    let adess = AdEss::new(14, 8, &[0, 1, 3, 5]);
+    let mut rng = RandState::new();
+    for _ in 0..50 {
+        let index = Integer::from(adess.num_sequences().random_below_ref(&mut rng));
+        let sequence = adess.sequence_for_index(&index);
+
+        let mut encoder = SequentialEncoder::new(&adess, index.clone()).unwrap();
+        let mut decoder = SequentialDecoder::new(&adess);
+        for &amplitude in sequence.iter() {
+            assert!(!encoder.is_done());
+            assert_eq!(encoder.step(), amplitude);
+            decoder.step(amplitude).unwrap();
+            assert_eq!(encoder.weight_level(), decoder.weight_level());
+            assert_eq!(encoder.stage(), decoder.stage());
+            // the partial index plus the remaining index is the full index
+            assert_eq!(
+                Integer::from(decoder.index() + encoder.remaining_index()),
+                index
+            );
+        }
+        assert!(encoder.is_done());
+        assert_eq!(decoder.finish().unwrap(), index);
+    }
+
+    assert!(SequentialEncoder::new(&adess, adess.num_sequences()).is_err());
+    let mut decoder = SequentialDecoder::new(&adess);
+    assert!(decoder.step(2).is_err());
+    for _ in 0..2 {
+        decoder.step(7).unwrap();
+    }
+    // a third amplitude 7 exceeds the threshold
+    assert!(decoder.step(7).is_err());
+    assert!(decoder.finish().is_err());
+}
+
+#[test]
+fn preset_configurations() {
+    assert_eq!(presets::presets().len(), 12);
+    assert!(presets::preset_by_name("1024qam").is_err());
+    for preset in presets::presets() {
+        let distribution = preset.distribution();
+        assert_eq!(distribution.len(), preset.num_amplitudes);
+        assert!((utils::entropy(&distribution) - preset.rate).abs() < 1e-3);
+    }
+
+    let preset = presets::preset_by_name("64qam-r1.5-n96").unwrap();
+    assert_eq!(
+        (preset.num_amplitudes, preset.n_max, preset.num_bits()),
+        (4, 96, 144)
+    );
+    let adess = presets::adess_for_preset("64qam-r1.5-n96").unwrap();
+    assert!(adess.num_bits() >= 144);
+    let adess = presets::adess_for_preset("256qam-r2.5-n96").unwrap();
+    assert!(adess.num_bits() >= 240);
+    assert_eq!(adess.get_weights().len(), 8);
+}
+
+#[test]
+fn infer_configuration_from_sequences() {
+    let adess = AdEss::new(20, 8, &[0, 1, 3, 6]);
+    let num_sequences_used = Integer::from(1) << adess.num_bits();
+    let sequences: Vec<Vec<usize>> = (0..num_sequences_used.to_usize().unwrap())
+        .map(|index| adess.sequence_for_index(&Integer::from(index)))
+        .collect();
+
+    let inferred = inference::infer_configuration(&sequences, 1.0).unwrap();
+    assert_eq!((inferred.num_amplitudes, inferred.n_max), (4, 8));
+    assert!((inferred.distribution.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+    let max_block_weight = sequences
+        .iter()
+        .map(|sequence| {
+            sequence
+                .iter()
+                .map(|a| inferred.weights[(a - 1) / 2])
+                .sum::<usize>()
+        })
+        .max()
+        .unwrap();
+    assert_eq!(inferred.threshold, max_block_weight);
+
+    // with the weights of the source, the sequences are the first ones of the trellis
+    let inferred = inference::infer_configuration_for_weights(&sequences, &[0, 1, 3, 6]).unwrap();
+    assert!(inferred.threshold <= 20);
+    assert_eq!(inferred.min_num_bits, adess.num_bits());
+    assert!(inferred.num_bits >= adess.num_bits());
+    assert!(inference::infer_configuration_for_weights(&sequences, &[0, 1]).is_err());
+
+    assert!(inference::infer_configuration(&[], 1.0).is_err());
+    assert!(inference::infer_configuration(&[vec![1, 3], vec![1]], 1.0).is_err());
+    assert!(inference::infer_configuration(&[vec![1, 2]], 1.0).is_err());
+}
+
+#[test]
+fn check_sequence_membership() {
+    let adess = AdEss::new(6, 3, &[0, 1, 3, 5]);
+    for index in 0..adess.num_sequences().to_usize().unwrap() {
+        let index = Integer::from(index);
+        let expected = if index < adess.num_sequences_used() {
+            SequenceReport::Used(index.clone())
+        } else {
+            SequenceReport::Unused(index.clone())
+        };
+        assert_eq!(
+            adess.check_sequence(&adess.sequence_for_index(&index)),
+            expected
+        );
+    }
+
+    let invalid_at = |sequence: &[usize]| match adess.check_sequence(sequence) {
+        SequenceReport::Invalid { position, .. } => Some(position),
+        _ => None,
+    };
+    assert_eq!(invalid_at(&[1, 2, 1]), Some(1));
+    assert_eq!(invalid_at(&[1, 1, 9]), Some(2));
+    assert_eq!(invalid_at(&[7, 5, 1]), Some(1));
+    assert_eq!(invalid_at(&[1, 1]), Some(2));
+    assert_eq!(invalid_at(&[1, 1, 1, 1]), Some(3));
+}
+
+#[test]
+fn num_bits_per_threshold_table() {
+    let weights = [0, 1, 3, 6];
+    let n_max = 8;
+    let num_bits = trellis_utils::num_bits_per_threshold(30, n_max, &weights);
+    assert_eq!(num_bits.len(), 31);
+    for (threshold, &bits) in num_bits.iter().enumerate() {
+        assert_eq!(bits, AdEss::new(threshold, n_max, &weights).num_bits());
+    }
+}
+
+#[test]
+fn expand_reverse_trellis_in_place() {
+    let weights = [0, 1, 3, 6];
+    let num_sequences = Integer::from(1000);
+    let mut reverse_trellis =
+        trellis_utils::reverse_trellis_beyond_num_sequences(&num_sequences, 2, 8, &weights);
+    assert_eq!(
+        trellis_utils::expand_reverse_trellis_by(&mut reverse_trellis, 5),
+        5
+    );
+    assert_eq!(
+        reverse_trellis,
+        trellis_utils::reverse_trellis_beyond_num_sequences(&num_sequences, 7, 8, &weights)
+    );
+
+    // the expansion stops at the largest weight level
+    let num_weight_levels = reverse_trellis.weight_levels_slice().len();
+    let num_missing = num_weight_levels - reverse_trellis.get_num_weight_levels();
+    assert_eq!(
+        trellis_utils::expand_reverse_trellis_by(&mut reverse_trellis, usize::MAX),
+        num_missing
+    );
+    assert_eq!(reverse_trellis.get_num_weight_levels(), num_weight_levels);
+}
+
+#[test]
+fn chained_blocks_encode_decode() {
+    // 6 sequences per block, 2 bits for a single block
+    let adess = AdEss::new(3, 2, &[0, 1, 3]);
+    assert_eq!(adess.num_sequences(), 6);
+    let last_sequence = adess.sequence_for_index(&Integer::from(5));
+    assert!(ChainedAdEss::new(AdEss::new(3, 2, &[0, 1, 3]), 0).is_err());
+    let chained = ChainedAdEss::new(adess, 3).unwrap();
+    // floor(3 * log2(6)) = 7 bits
+    assert_eq!((chained.num_bits(), chained.num_bits_gain()), (7, 1));
+
+    for index in 0..128 {
+        let index = Integer::from(index);
+        let sequences = chained.sequences_for_index(&index).unwrap();
+        assert_eq!(sequences.len(), 3);
+        assert_eq!(chained.index_for_sequences(&sequences).unwrap(), index);
+    }
+    assert!(chained.sequences_for_index(&Integer::from(128)).is_err());
+    // the residual 5 of the last block exceeds 127 / 36
+    let out_of_range = vec![vec![1, 1], vec![1, 1], last_sequence];
+    assert_eq!(
+        chained.index_for_sequences(&out_of_range),
+        Err(AdEssError::IndexOutOfRange)
+    );
+    assert!(matches!(
+        chained.index_for_sequences(&[vec![1, 1], vec![1, 1], vec![2, 1]]),
+        Err(AdEssError::InvalidSequence(_))
+    ));
+    assert!(chained.index_for_sequences(&[vec![1, 1]]).is_err());
+
+    let data_bits = vec![1, 0, 1, 1, 0, 0, 1];
+    let sequences = chained.encode(&data_bits).unwrap();
+    assert_eq!(chained.decode(&sequences).unwrap(), data_bits);
+
+    // 17 bits are framed into 3 groups of 7 bits
+    let bitstream: Vec<u8> = (0..17).map(|n| (n % 3 == 0) as u8).collect();
+    assert_eq!(chained.num_blocks_for_bits(bitstream.len()), 9);
+    let sequences = chained.encode_bitstream(&bitstream).unwrap();
+    assert_eq!(sequences.len(), 9);
+    assert_eq!(chained.decode_bitstream(&sequences, 17).unwrap(), bitstream);
+    assert!(chained.decode_bitstream(&sequences, 22).is_err());
+    assert!(chained.decode_bitstream(&sequences[..8], 17).is_err());
+}
+
+#[test]
+fn workspace_encode_decode_into() {
+    let adess = std::sync::Arc::new(AdEss::new(12, 6, &[0, 1, 3, 6]));
+    let mut workspace = Workspace::new(adess.clone());
+    let num_bits = adess.num_bits() as usize;
+    let mut sequence = vec![0; 6];
+    let mut index_bits = vec![0; num_bits];
+    for index in 0..adess.num_sequences().to_usize().unwrap() {
+        let index = Integer::from(index);
+        workspace.encode_into(&index, &mut sequence).unwrap();
+        assert_eq!(sequence, adess.sequence_for_index(&index));
+        assert_eq!(workspace.decode(&sequence).unwrap(), &index);
+    }
+    for index in [0, 5, (1 << num_bits) - 1] {
+        let bits = utils::integer_to_bits(&Integer::from(index), num_bits);
+        workspace.encode_bits_into(&bits, &mut sequence).unwrap();
+        workspace
+            .decode_bits_into(&sequence, &mut index_bits)
+            .unwrap();
+        assert_eq!(index_bits, bits);
+    }
+
+    assert!(workspace
+        .encode_into(&adess.num_sequences(), &mut sequence)
+        .is_err());
+    assert!(workspace.encode_into(&Integer::new(), &mut [0; 5]).is_err());
+    assert!(workspace.decode(&[7, 7, 7, 1, 1, 1]).is_err());
+    assert!(workspace.decode(&[1, 1, 1, 1, 1, 2]).is_err());
+}
+
+#[test]
+fn benchmark_throughput() {
+    let adess = AdEss::new(30, 16, &[0, 1, 3, 6]);
+    let result = adess.benchmark(std::time::Duration::from_millis(20));
+    assert!(result.num_encoded_blocks > 0 && result.num_decoded_blocks > 0);
+    assert!(result.encode_blocks_per_second > 0.0);
+    let mbit_per_block = adess.num_bits() as f64 / 1e6;
+    assert!(
+        (result.decode_mbit_per_second - result.decode_blocks_per_second * mbit_per_block).abs()
+            < 1e-9 * result.decode_mbit_per_second
+    );
+}
+
+#[test]
+fn label_bits_round_trip() {
+    assert_eq!(labeling::natural_labeling(4), vec![0, 1, 2, 3]);
+    let amplitudes = vec![1, 3, 5, 7, 7, 1];
+    let gray = labeling::gray_labeling(4);
+    let bits = labeling::amplitudes_to_label_bits(&amplitudes, &gray).unwrap();
+    assert_eq!(bits, vec![0, 0, 0, 1, 1, 1, 1, 0, 1, 0, 0, 0]);
+    assert_eq!(
+        labeling::label_bits_to_amplitudes(&bits, &gray).unwrap(),
+        amplitudes
+    );
+
+    let natural = labeling::natural_labeling(4);
+    let bits = labeling::amplitudes_to_label_bits(&amplitudes, &natural).unwrap();
+    assert_eq!(&bits[4..8], &[1, 0, 1, 1]);
+    assert_eq!(
+        labeling::label_bits_to_amplitudes(&bits, &natural).unwrap(),
+        amplitudes
+    );
+
+    assert!(labeling::amplitudes_to_label_bits(&[9], &natural).is_err());
+    assert!(labeling::amplitudes_to_label_bits(&[1], &[0, 1, 2]).is_err());
+    assert!(labeling::amplitudes_to_label_bits(&[1], &[0, 0, 1, 2]).is_err());
+    assert!(labeling::label_bits_to_amplitudes(&[0, 1, 1], &natural).is_err());
+    assert!(labeling::label_bits_to_amplitudes(&[0, 2], &natural).is_err());
+}
+
+#[test]
+fn encode_decode_with_limbs() {
+    let adess = AdEss::new(200, 96, &[0, 1, 3, 6]);
+    assert!(adess.num_bits() > 64);
+    let num_limbs = (adess.num_bits() as usize).div_ceil(64);
+    for mut limbs in [vec![0], vec![5], vec![u64::MAX, 1]] {
+        limbs.resize(num_limbs, 0);
+        let index = Integer::from_digits(&limbs, Order::Lsf);
+        let sequence = adess.sequence_for_index_from_limbs(&limbs);
+        assert_eq!(sequence, adess.sequence_for_index(&index));
+        let decoded = adess.index_for_sequence_to_limbs(&sequence);
+        assert_eq!(decoded.len(), num_limbs);
+        assert_eq!(decoded, limbs);
+    }
+}
+
+#[test]
+fn try_sequence_for_index_out_of_range() {
+    let adess = AdEss::new(12, 6, &[0, 1, 3, 6]);
+    let last_index = adess.num_sequences() - 1u32;
+    assert_eq!(
+        adess.try_sequence_for_index(&last_index).unwrap(),
+        adess.sequence_for_index(&last_index)
+    );
+    assert_eq!(
+        adess.try_sequence_for_index(&adess.num_sequences()),
+        Err(AdEssError::IndexOutOfRange)
+    );
+    assert_eq!(
+        adess.try_sequence_for_index(&Integer::from(-1)),
+        Err(AdEssError::IndexOutOfRange)
+    );
+    assert_eq!(
+        AdEssError::IndexOutOfRange.to_string(),
+        "Index out of range"
+    );
+}
+
+#[test]
+fn typed_errors() {
+    assert_eq!(
+        AdEss::calc_weights(&[0.0, 0.0], 1.0),
+        Err(AdEssError::InvalidDistribution(
+            "`distribution` must contain a positive probability"
+        ))
+    );
+    assert!(matches!(
+        RTS::try_new(4, 2, &[1, 2]),
+        Err(AdEssError::InvalidWeights(_))
+    ));
+    assert!(matches!(
+        trellis_utils::reverse_trellis_upto_num_sequences(Integer::from(100), 2, &[0, 1]),
+        Err(AdEssError::TrellisTooSmall(_))
+    ));
+    let token = CancellationToken::new();
+    token.cancel();
+    assert_eq!(
+        AdEss::new_cancellable(10, 4, &[0, 1, 3], &token).err(),
+        Some(AdEssError::Cancelled)
+    );
+    let error: Box<dyn std::error::Error> = Box::new(AdEssError::MemoryLimitExceeded);
+    assert_eq!(error.to_string(), "Memory limit exceeded");
+}
+
+#[test]
+fn try_index_for_sequence_validation() {
+    let adess = AdEss::new(6, 3, &[0, 1, 3, 5]);
+    for index in 0..adess.num_sequences().to_usize().unwrap() {
+        let sequence = adess.sequence_for_index(&Integer::from(index));
+        assert!(adess.is_valid_sequence(&sequence));
+        assert_eq!(adess.try_index_for_sequence(&sequence).unwrap(), index);
+    }
+    for sequence in [vec![7, 5, 1], vec![1, 1, 9], vec![1, 1], vec![1, 4, 1]] {
+        assert!(!adess.is_valid_sequence(&sequence));
+        assert!(matches!(
+            adess.try_index_for_sequence(&sequence),
+            Err(AdEssError::InvalidSequence(_))
+        ));
+    }
+}
+
+#[test]
+fn batch_encode_decode() {
+    let indices: Vec<Integer> = [0, 7, 3, 31].iter().map(|&i| Integer::from(i)).collect();
+
+    let adess = AdEss::new(12, 6, &[0, 1, 3, 6]);
+    let sequences = adess.sequences_for_indices(&indices).unwrap();
+    for (index, sequence) in indices.iter().zip(&sequences) {
+        assert_eq!(sequence, &adess.sequence_for_index(index));
+    }
+    assert_eq!(adess.indices_for_sequences(&sequences), Ok(indices.clone()));
+
+    let rts = RTS::new(5, 6, &[0, 1, 3, 6]);
+    let sequences = rts.sequences_for_indices(&indices).unwrap();
+    for (index, sequence) in indices.iter().zip(&sequences) {
+        assert_eq!(sequence, &rts.sequence_for_index(index));
+    }
+    assert_eq!(rts.indices_for_sequences(&sequences), Ok(indices.clone()));
+    assert_eq!(adess.sequences_for_indices(&[]), Ok(vec![]));
+
+    // a single invalid entry fails the batch
+    let mut invalid_indices = indices.clone();
+    invalid_indices.push(Integer::from(-1));
+    assert_eq!(
+        adess.sequences_for_indices(&invalid_indices),
+        Err(AdEssError::IndexOutOfRange)
+    );
+    assert!(rts.sequences_for_indices(&invalid_indices).is_err());
+    let mut invalid_sequences = sequences.clone();
+    invalid_sequences.push(vec![2; 6]);
+    assert!(adess.indices_for_sequences(&invalid_sequences).is_err());
+    assert!(rts.indices_for_sequences(&invalid_sequences).is_err());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_batch_encode_decode() {
+    let adess = AdEss::new(60, 32, &[0, 1, 3, 6]);
+    let indices: Vec<Integer> = (0..1000).map(|i| Integer::from(i * 7919)).collect();
+    let sequences = adess.par_sequences_for_indices(&indices, 4).unwrap();
+    assert_eq!(sequences, adess.sequences_for_indices(&indices).unwrap());
+    assert_eq!(
+        adess.par_indices_for_sequences(&sequences, 0),
+        Ok(indices.clone())
+    );
+
+    let rts = RTS::new(40, 32, &[0, 1, 3, 6]);
+    let sequences = rts.par_sequences_for_indices(&indices, 2).unwrap();
+    assert_eq!(sequences, rts.sequences_for_indices(&indices).unwrap());
+    assert_eq!(
+        rts.par_indices_for_sequences(&sequences, 2),
+        Ok(indices.clone())
+    );
+    assert!(adess
+        .par_sequences_for_indices(&[adess.num_sequences()], 2)
+        .is_err());
+}
+
+#[test]
+fn encode_into_caller_buffer() {
+    let adess = AdEss::new(12, 6, &[0, 1, 3, 6]);
+    let rts = RTS::new(6, 6, &[0, 1, 3, 6]);
+    let mut out = vec![0; 6];
+    for index in 0..adess.num_sequences().to_usize().unwrap() {
+        let index = Integer::from(index);
+        adess.sequence_for_index_into(&index, &mut out).unwrap();
+        assert_eq!(out, adess.sequence_for_index(&index));
+    }
+    for index in 0..rts.num_sequences().to_usize().unwrap() {
+        let index = Integer::from(index);
+        rts.sequence_for_index_into(&index, &mut out).unwrap();
+        assert_eq!(rts.index_for_sequence(&out), index);
+    }
+    assert_eq!(
+        adess.sequence_for_index_into(&adess.num_sequences(), &mut out),
+        Err(AdEssError::IndexOutOfRange)
+    );
+    assert!(rts
+        .sequence_for_index_into(&rts.num_sequences(), &mut out)
+        .is_err());
+    assert!(adess
+        .sequence_for_index_into(&Integer::new(), &mut [0; 5])
+        .is_err());
+}
+
+#[test]
+fn encode_with_successor_sums() {
+    let linear = AdEss::new(14, 6, &[0, 1, 2, 5, 7]);
+    let mut binary_search = AdEss::new(14, 6, &[0, 1, 2, 5, 7]);
+    assert!(binary_search.trellis.successor_sums(0, 0).is_none());
+    binary_search.trellis_mut().build_successor_sums();
+    assert_eq!(
+        binary_search.trellis.successor_sums(0, 0).unwrap().last(),
+        Some(&linear.num_sequences())
+    );
+    let mut out = vec![0; 6];
+    for index in 0..linear.num_sequences().to_usize().unwrap() {
+        let index = Integer::from(index);
+        let sequence = binary_search.sequence_for_index(&index);
+        assert_eq!(sequence, linear.sequence_for_index(&index));
+        binary_search
+            .sequence_for_index_into(&index, &mut out)
+            .unwrap();
+        assert_eq!(out, sequence);
+    }
+
+    // modifying the trellis drops the sums
+    binary_search.trellis_mut().set(6, 0, Integer::from(1));
+    assert!(binary_search.trellis.successor_sums(0, 0).is_none());
+}
+
+#[test]
+fn cached_successors_and_predecessors() {
+    let weights = [0, 2, 3, 3];
+    let trellis = Trellis::new(8, 3, &weights);
+    for wl in trellis.get_weight_levels() {
+        let successors: Vec<(usize, usize)> = [(0, 0), (1, 2), (2, 3), (3, 3)]
+            .iter()
+            .map(|&(w_idx, w)| (w_idx, wl + w))
+            .filter(|&(_, next_wl)| next_wl <= 8)
+            .collect();
+        assert_eq!(trellis.successors_slice(wl), successors.as_slice());
+        assert_eq!(trellis.get_successors(wl), successors);
+
+        let predecessors: Vec<(usize, usize)> = [(3, 3), (2, 3), (1, 2), (0, 0)]
+            .iter()
+            .filter(|&&(_, w)| wl >= w && trellis.is_weight_level(wl - w))
+            .map(|&(w_idx, w)| (w_idx, wl - w))
+            .collect();
+        assert_eq!(trellis.predecessors_slice(wl), predecessors.as_slice());
+        assert_eq!(trellis.get_predecessors(wl), predecessors);
+    }
+
+    // the successors of an expandable trellis follow the current threshold
+    let mut expandable = Trellis::new_expandable(3, &weights);
+    assert!(expandable
+        .successors_slice(0)
+        .iter()
+        .all(|&(_, wl)| wl == 0));
+    for _ in 0..2 {
+        expandable
+            .expand_with(&mut vec![Integer::from(1); 4])
+            .unwrap();
+    }
+    assert_eq!(expandable.threshold, 2);
+    assert_eq!(expandable.successors_slice(0), &[(0, 0), (1, 2)]);
+}
+
+#[test]
+fn decode_sequence_over_threshold() {
+    let adess = AdEss::new(12, 6, &[0, 1, 3, 6]);
+    let compact = AutoAdEss::new(12, 6, &[0, 1, 3, 6]);
+    // the prefix crosses the threshold after the third amplitude
+    for sequence in [[7, 7, 3, 1, 1, 1], [7, 7, 7, 1, 1, 1], [7, 7, 7, 7, 7, 7]] {
+        assert!(adess.try_index_for_sequence(&sequence).is_err());
+        // the unvalidated decoders return a meaningless index instead of panicking
+        adess.index_for_sequence(&sequence);
+        compact.index_for_sequence(&sequence);
+        assert_eq!(
+            compact.try_index_for_sequence(&sequence),
+            Err(AdEssError::InvalidSequence("Threshold exceeded"))
+        );
+    }
+}
+
+#[test]
+fn auto_adess_backends() {
+    let adess = AdEss::new(12, 6, &[0, 1, 3, 6]);
+    let small = AutoAdEss::new(12, 6, &[0, 1, 3, 6]);
+    assert!(matches!(small, AutoAdEss::U64(_)));
+    assert_eq!(small.num_sequences(), adess.num_sequences());
+    assert_eq!(small.num_bits(), adess.num_bits());
+    assert!(small.memory_bytes() < adess.trellis.memory_bytes());
+    for index in 0..adess.num_sequences().to_usize().unwrap() {
+        let index = Integer::from(index);
+        let sequence = small.sequence_for_index(&index);
+        assert_eq!(sequence, adess.sequence_for_index(&index));
+        assert_eq!(small.index_for_sequence(&sequence), index);
+        assert_eq!(small.try_index_for_sequence(&sequence), Ok(index));
+    }
+    assert!(small.try_index_for_sequence(&[1, 1, 1, 1, 1]).is_err());
+    assert!(small.try_index_for_sequence(&[1, 1, 1, 1, 1, 2]).is_err());
+    assert!(small.try_index_for_sequence(&[1, 1, 1, 1, 1, 9]).is_err());
+    assert_eq!(
+        small.try_sequence_for_index(&adess.num_sequences()),
+        Err(AdEssError::IndexOutOfRange)
+    );
+    assert!(small.try_sequence_for_index(&Integer::from(-1)).is_err());
+
+    let mut compact: Trellis<u64> = adess.trellis.to_backend().unwrap();
+    compact.build_successor_sums();
+    assert_eq!(
+        compact.successor_sums(0, 0).unwrap().last(),
+        adess.num_sequences().to_u64().as_ref()
+    );
+
+    // the fixed width trellis is built directly
+    let direct = CompactAdEss::<u64>::new(12, 6, &[0, 1, 3, 6]).unwrap();
+    assert_eq!(*direct.trellis(), compact);
+    let weights = [0, 1, trellis::EXCLUDED_WEIGHT, 6];
+    assert_eq!(
+        trellis_utils::count_sequences(12, 6, &weights),
+        AdEss::new(12, 6, &weights).num_sequences()
+    );
+    assert!(CompactAdEss::<u64>::new(60, 64, &[0, 1, 2, 4]).is_none());
+
+    assert!(matches!(
+        AutoAdEss::new(60, 64, &[0, 1, 2, 4]),
+        AutoAdEss::U128(_)
+    ));
+    let large = AutoAdEss::new(400, 200, &[0, 1, 2, 4]);
+    assert!(matches!(large, AutoAdEss::Integer(_)));
+    let index = large.num_sequences() - 1u32;
+    assert_eq!(
+        large.index_for_sequence(&large.sequence_for_index(&index)),
+        index
+    );
+}
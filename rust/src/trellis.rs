@@ -1,23 +1,72 @@
-use rug::Integer;
 use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::bigint::{Integer, Order};
+use crate::error::AdEssError;
+use crate::npy;
+use crate::trellis_value::TrellisValue;
+use crate::types::{WeightIdx, WeightLevel};
+
+/// Identifies serialized [Trellis] data, see [Trellis::write_to()]
+const MAGIC: &[u8; 8] = b"ADESSTRL";
+/// Version of the serialization format
+const FORMAT_VERSION: u8 = 1;
+
+/// Weight of amplitudes which never occur, e.g., amplitudes with probability 0
+///
+/// The weight is replaced by a weight above the threshold when a [Trellis] is created, so the
+/// amplitude is excluded from all sequences while the weight indexes of the other amplitudes
+/// are kept.
+pub const EXCLUDED_WEIGHT: usize = usize::MAX;
+
+/// Returns the largest weight which is not [EXCLUDED_WEIGHT]
+pub(crate) fn max_used_weight(weights: &[usize]) -> Option<usize> {
+    weights
+        .iter()
+        .copied()
+        .filter(|&w| w != EXCLUDED_WEIGHT)
+        .max()
+}
+
+/// Replaces [EXCLUDED_WEIGHT] by `threshold + 1`, which exceeds all weight levels
+fn replace_excluded_weights(weights: &[usize], threshold: usize) -> Vec<usize> {
+    weights
+        .iter()
+        .map(|&w| match w {
+            EXCLUDED_WEIGHT => threshold + 1,
+            w => w,
+        })
+        .collect()
+}
 
 /// [Trellis] is a data structure to hold a bounded trellis
 ///
-/// Trellis nodes hold a [rug::Integer] and are indexed by `stage` (0..n_max)
-/// and `weight_level` (one of the accepted weight levels).
+/// Trellis nodes hold a [Integer](crate::bigint::Integer) by default and are indexed by `stage` (0..n_max)
+/// and `weight_level` (one of the accepted weight levels). Trellises with fixed width node
+/// values are converted from it via [Trellis::to_backend()].
 ///
 /// `weight_levels` for each `stage` are returned by [Trellis::get_weight_levels()].
 /// Node values can be read and set by using the [Trellis::get()] and [Trellis::set()]
 /// methods.
-#[derive(Debug)]
-pub struct Trellis {
+///
+/// A trellis is `Send + Sync`, node values are only modified via `&mut self`.
+#[derive(Debug, Clone)]
+pub struct Trellis<T: TrellisValue = Integer> {
     pub threshold: usize,
     pub n_max: usize,
     weights: Vec<usize>,
     weight_levels: Vec<usize>,
     weight_level_lookup: Vec<i64>,
     sorted_weights: Vec<(usize, usize)>,
-    data: Vec<Vec<Integer>>,
+    data: Vec<Vec<T>>,
+    /// Successors of each weight level regardless of the threshold, see [Trellis::successors_slice()]
+    successors: Vec<Vec<(usize, usize)>>,
+    /// Predecessors of each weight level, see [Trellis::predecessors_slice()]
+    predecessors: Vec<Vec<(usize, usize)>>,
+    /// Cumulative sums of the successor values, see [Trellis::build_successor_sums()]
+    successor_sums: Vec<Vec<Vec<T>>>,
 }
 
 impl Trellis {
@@ -25,7 +74,18 @@ impl Trellis {
     ///
     /// The smallest weight must be 0
     pub fn new(threshold: usize, n_max: usize, weights: &[usize]) -> Trellis {
+        let mut trellis = Trellis::new_structure_only(threshold, n_max, weights);
+        trellis.data = vec![vec![Integer::from(0); trellis.weight_levels.len()]; 1 + n_max];
+        trellis
+    }
+
+    /// Create a new [Trellis] without storage for node values
+    ///
+    /// Only the structural methods (weights, weight levels, successors and predecessors)
+    /// may be used on the returned instance.
+    pub(crate) fn new_structure_only(threshold: usize, n_max: usize, weights: &[usize]) -> Trellis {
         assert_eq!(*weights.iter().min().unwrap(), 0);
+        let weights = &replace_excluded_weights(weights, threshold);
 
         let mut sorted_weights: Vec<(usize, usize)> = weights.iter().copied().enumerate().collect();
         sorted_weights.sort_by_key(|&w_tuple| w_tuple.1);
@@ -33,9 +93,9 @@ impl Trellis {
         let weight_levels = Trellis::calc_weight_levels(threshold, weights);
         let weight_level_lookup = Trellis::make_weight_level_lookup(&weight_levels);
 
-        let data = vec![vec![Integer::from(0); weight_levels.len()]; 1 + n_max];
+        let data = vec![Vec::<Integer>::new(); 1 + n_max];
 
-        Trellis {
+        let mut trellis = Trellis {
             threshold,
             n_max,
             weights: weights.to_vec(),
@@ -43,24 +103,72 @@ impl Trellis {
             weight_level_lookup,
             sorted_weights,
             data,
-        }
+            successors: Vec::new(),
+            predecessors: Vec::new(),
+            successor_sums: Vec::new(),
+        };
+        trellis.build_adjacency();
+        trellis
+    }
+
+    /// Create a new zero initialized [Trellis] with node values of type `T`
+    ///
+    /// The node values of the trellis must fit into `T`, see [TrellisValue].
+    pub fn new_with_backend<T: TrellisValue>(
+        threshold: usize,
+        n_max: usize,
+        weights: &[usize],
+    ) -> Trellis<T> {
+        let mut trellis = Trellis::new_structure_only(threshold, n_max, weights)
+            .to_backend::<T>()
+            .expect("a trellis without node values fits into every backend");
+        trellis.data = vec![vec![T::zero(); trellis.weight_levels.len()]; 1 + n_max];
+        trellis
     }
 
     pub fn new_like(trellis: &Trellis) -> Trellis {
         Trellis::new(trellis.threshold, trellis.n_max, &trellis.get_weights())
     }
 
+    /// Create a new zero initialized [Trellis] with the same structure as `self`
+    ///
+    /// Weights and weight levels are copied instead of being recalculated. Weight levels above
+    /// the threshold (e.g., of an expandable trellis) are dropped.
+    pub fn structure_like(&self) -> Trellis {
+        let weight_levels: Vec<usize> = self
+            .weight_levels
+            .iter()
+            .copied()
+            .take_while(|&wl| wl <= self.threshold)
+            .collect();
+        let mut weight_level_lookup = self.weight_level_lookup.clone();
+        weight_level_lookup.truncate(self.threshold + 1);
+
+        let mut trellis = Trellis {
+            threshold: self.threshold,
+            n_max: self.n_max,
+            weights: self.weights.clone(),
+            data: vec![vec![Integer::from(0); weight_levels.len()]; 1 + self.n_max],
+            weight_levels,
+            weight_level_lookup,
+            sorted_weights: self.sorted_weights.clone(),
+            successors: Vec::new(),
+            predecessors: Vec::new(),
+            successor_sums: Vec::new(),
+        };
+        trellis.build_adjacency();
+        trellis
+    }
+
     pub fn new_expandable(n_max: usize, weights: &[usize]) -> Trellis {
         assert_eq!(*weights.iter().min().unwrap(), 0);
 
+        let max_weight =
+            max_used_weight(weights).expect("Already checked if empty in assert above");
+        let max_threshold = n_max * max_weight;
+        let weights = &replace_excluded_weights(weights, max_threshold);
         let mut sorted_weights: Vec<(usize, usize)> = weights.iter().copied().enumerate().collect();
         sorted_weights.sort_by_key(|&w_tuple| w_tuple.1);
-
-        let max_weight = weights
-            .iter()
-            .max()
-            .expect("Already checked if empty in assert above");
-        let max_threshold = n_max * max_weight;
         let all_wls = Trellis::calc_weight_levels(max_threshold, weights);
         let wl_lookup = Trellis::make_weight_level_lookup(&all_wls);
 
@@ -68,7 +176,7 @@ impl Trellis {
 
         let threshold = all_wls[0];
 
-        Trellis {
+        let mut trellis = Trellis {
             threshold,
             n_max,
             weights: weights.to_vec(),
@@ -76,7 +184,36 @@ impl Trellis {
             weight_level_lookup: wl_lookup,
             sorted_weights,
             data,
-        }
+            successors: Vec::new(),
+            predecessors: Vec::new(),
+            successor_sums: Vec::new(),
+        };
+        trellis.build_adjacency();
+        trellis
+    }
+
+    /// Returns a copy of the trellis with the node values converted to `T`, see [TrellisValue]
+    ///
+    /// `None` is returned if a node value does not fit into `T`. The successor sums (see
+    /// [Trellis::build_successor_sums()]) are not copied.
+    pub fn to_backend<T: TrellisValue>(&self) -> Option<Trellis<T>> {
+        let data = self
+            .data
+            .iter()
+            .map(|stage| stage.iter().map(T::from_integer).collect())
+            .collect::<Option<Vec<Vec<T>>>>()?;
+        Some(Trellis {
+            threshold: self.threshold,
+            n_max: self.n_max,
+            weights: self.weights.clone(),
+            weight_levels: self.weight_levels.clone(),
+            weight_level_lookup: self.weight_level_lookup.clone(),
+            sorted_weights: self.sorted_weights.clone(),
+            data,
+            successors: self.successors.clone(),
+            predecessors: self.predecessors.clone(),
+            successor_sums: Vec::new(),
+        })
     }
 
     fn calc_weight_levels(threshold: usize, weights: &[usize]) -> Vec<usize> {
@@ -113,16 +250,56 @@ impl Trellis {
         weight_levels
     }
 
+    /// Caches the successors and predecessors of all weight levels
+    ///
+    /// The successors are stored without the threshold, which changes when an expandable
+    /// trellis is expanded, and are cut off in [Trellis::successors_slice()].
+    fn build_adjacency(&mut self) {
+        let max_wl = self.weight_level_lookup.len() - 1;
+        self.successors = self
+            .weight_levels
+            .iter()
+            .map(|&wl| {
+                self.sorted_weights
+                    .iter()
+                    .filter(|&&(_, w)| wl + w <= max_wl && self.wl_valid(wl + w))
+                    .map(|&(weight_index, w)| (weight_index, wl + w))
+                    .collect()
+            })
+            .collect();
+        self.predecessors = self
+            .weight_levels
+            .iter()
+            .map(|&wl| {
+                self.sorted_weights
+                    .iter()
+                    .rev()
+                    .filter(|&&(_, w)| wl >= w && self.wl_valid(wl - w))
+                    .map(|&(weight_index, w)| (weight_index, wl - w))
+                    .collect()
+            })
+            .collect();
+    }
+
     fn make_weight_level_lookup(weight_levels: &[usize]) -> Vec<i64> {
+        Trellis::try_make_weight_level_lookup(weight_levels).unwrap_or_else(|err| panic!("{err}"))
+    }
+    /// Returns the weight level lookup or an error if it cannot be allocated, e.g., for the
+    /// weight levels of untrusted serialized data
+    fn try_make_weight_level_lookup(weight_levels: &[usize]) -> Result<Vec<i64>, AdEssError> {
         let max_wl = weight_levels
             .iter()
             .max()
             .expect("weight_levels must be non empty");
-        let mut wl_lookup = vec![-1; *max_wl + 1];
+        let mut wl_lookup = vec![];
+        wl_lookup
+            .try_reserve_exact(*max_wl + 1)
+            .map_err(|_| AdEssError::MemoryLimitExceeded)?;
+        wl_lookup.resize(*max_wl + 1, -1);
         for (wl_idx, &wl) in weight_levels.iter().enumerate() {
             wl_lookup[wl] = wl_idx as i64;
         }
-        wl_lookup
+        Ok(wl_lookup)
     }
     fn wl_idx_valid(weight_level_index: i64) -> bool {
         // use not negative as 0 is a valid index
@@ -130,44 +307,91 @@ impl Trellis {
     }
 }
 
-impl Trellis {
+impl<T: TrellisValue> Trellis<T> {
     fn wl_valid(&self, weight_level: usize) -> bool {
         let weight_level_index = self.weight_level_lookup[weight_level];
         Trellis::wl_idx_valid(weight_level_index)
     }
     /// Get function for trellis values
-    pub fn get(&self, stage: usize, weight_level: usize) -> Integer {
+    pub fn get(&self, stage: usize, weight_level: usize) -> T {
         let weight_level_index = self.weight_level_lookup[weight_level];
         assert!(Trellis::wl_idx_valid(weight_level_index));
         self.data[stage][weight_level_index as usize].clone()
     }
+    /// Returns a reference to a trellis value, see [Trellis::get()]
+    pub fn get_ref(&self, stage: usize, weight_level: usize) -> &T {
+        &self.data[stage][self.get_weight_level_index(weight_level)]
+    }
+    /// Returns true if `weight_level` is a weight level of this trellis
+    pub fn is_weight_level(&self, weight_level: usize) -> bool {
+        weight_level < self.weight_level_lookup.len() && self.wl_valid(weight_level)
+    }
     /// Get function for trellis values, returns 0 if `weight_level` is invalid
-    pub fn get_or_0(&self, stage: usize, weight_level: usize) -> Integer {
+    pub fn get_or_0(&self, stage: usize, weight_level: usize) -> T {
         if weight_level >= self.weight_level_lookup.len() {
-            return Integer::from(0);
+            return T::zero();
         }
         let weight_level_index = self.weight_level_lookup[weight_level];
 
         if Trellis::wl_idx_valid(weight_level_index) {
             self.data[stage][weight_level_index as usize].clone()
         } else {
-            Integer::from(0)
+            T::zero()
         }
     }
-    pub fn get_stage(&self, stage: usize) -> Vec<Integer> {
+    pub fn get_stage(&self, stage: usize) -> Vec<T> {
         self.data[stage].clone()
     }
+    /// Returns the node values of `stage` without cloning them, see [Trellis::get_stage()]
+    pub fn stage_slice(&self, stage: usize) -> &[T] {
+        &self.data[stage]
+    }
+    /// Returns the sum of all node values of `stage`
+    pub fn stage_sum(&self, stage: usize) -> T {
+        let mut sum = T::zero();
+        for value in self.data[stage].iter() {
+            sum += value;
+        }
+        sum
+    }
+    /// Returns the running sums of the node values of `stage` over the weight levels
+    ///
+    /// Entry `wl_idx` is the sum of the values of the weight levels up to
+    /// `get_weight_levels()[wl_idx]`, e.g., for a reverse trellis the number of sequences with
+    /// a sum weight up to that weight level.
+    pub fn stage_cumulative_sums(&self, stage: usize) -> Vec<T> {
+        let mut sum = T::zero();
+        self.data[stage]
+            .iter()
+            .map(|value| {
+                sum += value;
+                sum.clone()
+            })
+            .collect()
+    }
+    /// Returns the sum of the node values over all stages for each stored weight level
+    pub fn weight_level_totals(&self) -> Vec<T> {
+        let mut totals = vec![T::zero(); self.get_num_weight_levels()];
+        for stage in self.data.iter() {
+            for (total, value) in totals.iter_mut().zip(stage) {
+                *total += value;
+            }
+        }
+        totals
+    }
     /// Set function for trellis values
-    pub fn set(&mut self, stage: usize, weight_level: usize, value: Integer) {
+    pub fn set(&mut self, stage: usize, weight_level: usize, value: T) {
         let weight_level_index = self.weight_level_lookup[weight_level];
         assert!(Trellis::wl_idx_valid(weight_level_index));
         self.data[stage][weight_level_index as usize] = value;
+        self.successor_sums.clear();
     }
     /// Function to add a value to an existing trellis value
-    pub fn add(&mut self, stage: usize, weight_level: usize, value: Integer) {
+    pub fn add(&mut self, stage: usize, weight_level: usize, value: T) {
         let weight_level_index = self.weight_level_lookup[weight_level];
         assert!(Trellis::wl_idx_valid(weight_level_index));
-        self.data[stage][weight_level_index as usize] += value;
+        self.data[stage][weight_level_index as usize] += &value;
+        self.successor_sums.clear();
     }
     /// Returns the weight for the given weight index
     pub fn get_weight(&self, weight_index: usize) -> usize {
@@ -177,10 +401,22 @@ impl Trellis {
     pub fn get_weights(&self) -> Vec<usize> {
         self.weights.clone()
     }
+    /// Returns the weights without cloning them, see [Trellis::get_weights()]
+    pub fn weights_slice(&self) -> &[usize] {
+        &self.weights
+    }
+    /// Returns (weight_index, weight) for all weights sorted by increasing weight
+    pub fn get_sorted_weights(&self) -> &[(usize, usize)] {
+        &self.sorted_weights
+    }
     /// Returns the weight levels of this trellis
     pub fn get_weight_levels(&self) -> Vec<usize> {
         self.weight_levels.clone()
     }
+    /// Returns the weight levels without cloning them, see [Trellis::get_weight_levels()]
+    pub fn weight_levels_slice(&self) -> &[usize] {
+        &self.weight_levels
+    }
     /// Returns the number of weight levels used by the stored data
     pub fn get_num_weight_levels(&self) -> usize {
         self.data[0].len()
@@ -194,10 +430,46 @@ impl Trellis {
     pub fn get_storage_dimensions(&self) -> (usize, usize) {
         (self.data.len(), self.get_num_weight_levels())
     }
+    /// Returns an error if not all nodes up to the threshold have stored values
+    pub(crate) fn check_storage(&self) -> Result<(), AdEssError> {
+        let num_weight_levels = self
+            .weight_levels
+            .partition_point(|&wl| wl <= self.threshold);
+        if self.data.len() != self.n_max + 1 || self.get_num_weight_levels() < num_weight_levels {
+            return Err(AdEssError::TrellisTooSmall(
+                "The trellis must store values for all weight levels up to the threshold",
+            ));
+        }
+        Ok(())
+    }
+    /// Returns the number of significant bits of all stored node values
+    pub fn storage_bits(&self) -> usize {
+        self.data
+            .iter()
+            .flatten()
+            .map(|value| TrellisValue::significant_bits(value) as usize)
+            .sum()
+    }
+    /// Returns the memory footprint of the node values of `stage` in bytes
+    ///
+    /// Each node needs an [Integer] plus its limbs of 64 bit or the size of a fixed width value,
+    /// see [TrellisValue::memory_bytes()].
+    pub fn stage_memory_bytes(&self, stage: usize) -> usize {
+        self.data[stage]
+            .iter()
+            .map(|value| value.memory_bytes())
+            .sum()
+    }
+    /// Returns the memory footprint of all node values in bytes, see [Trellis::stage_memory_bytes()]
+    pub fn memory_bytes(&self) -> usize {
+        (0..self.data.len())
+            .map(|stage| self.stage_memory_bytes(stage))
+            .sum()
+    }
     /// Increase the trellis size by one weight level mooving in the provided trellis values
     ///
     /// Note: the values are removed from `new_values`
-    pub fn expand_with(&mut self, new_values: &mut Vec<Integer>) -> Result<(), &'static str> {
+    pub fn expand_with(&mut self, new_values: &mut Vec<T>) -> Result<(), AdEssError> {
         assert_eq!(new_values.len(), self.data.len());
 
         let current_num_wls = self.get_num_weight_levels();
@@ -209,9 +481,12 @@ impl Trellis {
                 stage.push(new_values.pop().expect("checked lenghts in assert"))
             }
             self.threshold = self.weight_levels[new_num_wls - 1];
+            self.successor_sums.clear();
             Ok(())
         } else {
-            Err("Impossible to add another weight level, trellis to small")
+            Err(AdEssError::TrellisTooSmall(
+                "Impossible to add another weight level, trellis to small",
+            ))
         }
     }
     /// Returns a [Vec] of (weight_index, weight_level) for each weight level reachable
@@ -220,6 +495,9 @@ impl Trellis {
     /// The weight levels are sorted in ascending order.
     /// Multiple entries with the same weight level are sorted by weight index in ascending order.
     pub fn get_successors(&self, weight_level: usize) -> Vec<(usize, usize)> {
+        if self.is_weight_level(weight_level) {
+            return self.successors_slice(weight_level).to_vec();
+        }
         let mut successors = Vec::with_capacity(self.weights.len());
         for (weight_index, w) in self.sorted_weights.iter() {
             let possible_successor = weight_level + w;
@@ -229,6 +507,52 @@ impl Trellis {
         }
         successors
     }
+    /// Returns the cached successors of `weight_level` without allocation, see
+    /// [Trellis::get_successors()]
+    ///
+    /// Panics if `weight_level` is not a weight level of this trellis.
+    pub fn successors_slice(&self, weight_level: usize) -> &[(usize, usize)] {
+        let successors = &self.successors[self.get_weight_level_index(weight_level)];
+        let num_successors = successors.partition_point(|&(_, wl)| wl <= self.threshold);
+        &successors[..num_successors]
+    }
+    /// Precomputes the cumulative sums of the successor values of all nodes
+    ///
+    /// The sums allow to find the branch of an index by binary search instead of a linear scan
+    /// over all successors, see [Trellis::successor_sums()]. They need as much memory as the
+    /// node values times the number of weights and are dropped when the trellis is modified.
+    pub fn build_successor_sums(&mut self) {
+        let num_wls = self.get_num_weight_levels();
+        let mut successor_sums = Vec::with_capacity(self.n_max);
+        for stage in 0..self.n_max {
+            let stage_sums = self.weight_levels[..num_wls]
+                .iter()
+                .map(|&wl| {
+                    let mut sum = T::zero();
+                    self.successors_slice(wl)
+                        .iter()
+                        .map(|&(_, next_wl)| {
+                            sum += self.get_ref(stage + 1, next_wl);
+                            sum.clone()
+                        })
+                        .collect()
+                })
+                .collect();
+            successor_sums.push(stage_sums);
+        }
+        self.successor_sums = successor_sums;
+    }
+    /// Returns the cumulative sums of the successor values of the node at `stage` and
+    /// `weight_level` if they were built by [Trellis::build_successor_sums()]
+    ///
+    /// Entry `k` is the sum of the values of the first `k + 1` successors in the order of
+    /// [Trellis::get_successors()]. The successor of entry `k` is the `k`-th entry of
+    /// [Trellis::get_sorted_weights()].
+    pub fn successor_sums(&self, stage: usize, weight_level: usize) -> Option<&[T]> {
+        self.successor_sums
+            .get(stage)
+            .map(|stage_sums| stage_sums[self.get_weight_level_index(weight_level)].as_slice())
+    }
     /// Returns a [Vec] of (weight_index, weight_level) for each weight level which
     /// can reach `weight_level` with a single step
     ///
@@ -236,6 +560,9 @@ impl Trellis {
     /// Multiple tuples with the same `weight_level` are sorted in descending order wrt. the
     /// `weight_index`.
     pub fn get_predecessors(&self, weight_level: usize) -> Vec<(usize, usize)> {
+        if self.is_weight_level(weight_level) {
+            return self.predecessors_slice(weight_level).to_vec();
+        }
         let mut predecessors = Vec::with_capacity(self.weights.len());
         for (weight_index, w) in self.sorted_weights.iter().rev() {
             if weight_level >= *w {
@@ -247,9 +574,38 @@ impl Trellis {
         }
         predecessors
     }
+    /// Returns the cached predecessors of `weight_level` without allocation, see
+    /// [Trellis::get_predecessors()]
+    ///
+    /// Panics if `weight_level` is not a weight level of this trellis.
+    pub fn predecessors_slice(&self, weight_level: usize) -> &[(usize, usize)] {
+        &self.predecessors[self.get_weight_level_index(weight_level)]
+    }
+    /// Returns the value of the node at `stage` and `weight_level`, see [Trellis::get()]
+    pub fn value(&self, stage: usize, weight_level: WeightLevel) -> T {
+        self.get(stage, weight_level.0)
+    }
+    /// Returns the weight of `w_idx`, see [Trellis::get_weight()]
+    pub fn weight(&self, w_idx: WeightIdx) -> usize {
+        self.get_weight(w_idx.0)
+    }
+    /// Returns the typed successors of `weight_level`, see [Trellis::get_successors()]
+    pub fn successors(&self, weight_level: WeightLevel) -> Vec<(WeightIdx, WeightLevel)> {
+        self.get_successors(weight_level.0)
+            .into_iter()
+            .map(|(w_idx, wl)| (WeightIdx(w_idx), WeightLevel(wl)))
+            .collect()
+    }
+    /// Returns the typed predecessors of `weight_level`, see [Trellis::get_predecessors()]
+    pub fn predecessors(&self, weight_level: WeightLevel) -> Vec<(WeightIdx, WeightLevel)> {
+        self.get_predecessors(weight_level.0)
+            .into_iter()
+            .map(|(w_idx, wl)| (WeightIdx(w_idx), WeightLevel(wl)))
+            .collect()
+    }
 }
 
-impl PartialEq for Trellis {
+impl<T: TrellisValue> PartialEq for Trellis<T> {
     fn eq(&self, other: &Self) -> bool {
         if self.get_storage_dimensions() != other.get_storage_dimensions()
             || self.get_weights() != other.get_weights()
@@ -268,4 +624,375 @@ impl PartialEq for Trellis {
     }
 }
 
-impl Eq for Trellis {}
+impl<T: TrellisValue> Eq for Trellis<T> {}
+
+/// Serialization
+impl Trellis {
+    /// Writes the trellis in a compact binary format to `writer`
+    ///
+    /// The format starts with a magic number and a format version, followed by the threshold,
+    /// `n_max`, the weights, the weight levels and all node values. All numbers are little endian,
+    /// each node value is stored as its number of bytes (`u32`) followed by its bytes.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let write_u64 =
+            |writer: &mut dyn Write, value: usize| writer.write_all(&(value as u64).to_le_bytes());
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        write_u64(&mut writer, self.threshold)?;
+        write_u64(&mut writer, self.n_max)?;
+        write_u64(&mut writer, self.weights.len())?;
+        for &w in self.weights.iter() {
+            write_u64(&mut writer, w)?;
+        }
+        write_u64(&mut writer, self.weight_levels.len())?;
+        for &wl in self.weight_levels.iter() {
+            write_u64(&mut writer, wl)?;
+        }
+        write_u64(&mut writer, self.get_num_weight_levels())?;
+        for value in self.data.iter().flatten() {
+            let bytes = value.to_digits::<u8>(Order::Lsf);
+            writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+            writer.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+    /// Reads a trellis written by [Trellis::write_to()] from `reader`
+    ///
+    /// The reader is read to its end. The input is untrusted: all lengths are checked against
+    /// the remaining input before anything is allocated and the structure is validated, see
+    /// [Trellis::from_parts()].
+    pub fn read_from(mut reader: impl Read) -> io::Result<Trellis> {
+        let invalid_data = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        let mut input = bytes.as_slice();
+
+        let read_u64 = |input: &mut &[u8]| -> io::Result<usize> {
+            let mut bytes = [0u8; 8];
+            input.read_exact(&mut bytes)?;
+            usize::try_from(u64::from_le_bytes(bytes))
+                .map_err(|_| invalid_data("Value does not fit into usize"))
+        };
+        let read_u64_vec = |input: &mut &[u8]| -> io::Result<Vec<usize>> {
+            let length = read_u64(input)?;
+            if length > input.len() / 8 {
+                return Err(invalid_data("Length exceeds the input"));
+            }
+            (0..length).map(|_| read_u64(input)).collect()
+        };
+
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("No serialized trellis"));
+        }
+        let mut version = [0u8; 1];
+        input.read_exact(&mut version)?;
+        if version[0] != FORMAT_VERSION {
+            return Err(invalid_data("Unsupported trellis format version"));
+        }
+
+        let threshold = read_u64(&mut input)?;
+        let n_max = read_u64(&mut input)?;
+        let weights = read_u64_vec(&mut input)?;
+        let weight_levels = read_u64_vec(&mut input)?;
+        let num_stored_weight_levels = read_u64(&mut input)?;
+        // each node value takes at least the 4 bytes of its length
+        let num_nodes = n_max
+            .checked_add(1)
+            .and_then(|num_stages| num_stages.checked_mul(num_stored_weight_levels));
+        if num_stored_weight_levels == 0
+            || num_stored_weight_levels > weight_levels.len()
+            || num_nodes.is_none_or(|num_nodes| num_nodes > input.len() / 4)
+        {
+            return Err(invalid_data("Inconsistent trellis dimensions"));
+        }
+
+        let mut data = Vec::with_capacity(n_max + 1);
+        for _ in 0..n_max + 1 {
+            let mut stage = Vec::with_capacity(num_stored_weight_levels);
+            for _ in 0..num_stored_weight_levels {
+                let mut length = [0u8; 4];
+                input.read_exact(&mut length)?;
+                let length = u32::from_le_bytes(length) as usize;
+                if length > input.len() {
+                    return Err(invalid_data("Length exceeds the input"));
+                }
+                let (value_bytes, remaining) = input.split_at(length);
+                stage.push(Integer::from_digits(value_bytes, Order::Lsf));
+                input = remaining;
+            }
+            data.push(stage);
+        }
+        if !input.is_empty() {
+            return Err(invalid_data("Trailing data after the trellis"));
+        }
+
+        Trellis::from_parts(threshold, n_max, weights, weight_levels, data)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+    /// Creates a [Trellis] from its serialized parts, see [Trellis::read_from()]
+    ///
+    /// The weights must already have [EXCLUDED_WEIGHT] replaced, each of the `n_max + 1` stages
+    /// holds the values of the first weight levels. The parts are untrusted: the smallest weight
+    /// must be 0 and the weight levels must be increasing, start at 0 and hold exactly the sums
+    /// of the weights up to the largest weight level, which is reachable within `n_max` stages.
+    fn from_parts(
+        threshold: usize,
+        n_max: usize,
+        weights: Vec<usize>,
+        weight_levels: Vec<usize>,
+        data: Vec<Vec<Integer>>,
+    ) -> Result<Trellis, AdEssError> {
+        if weights.iter().min() != Some(&0) {
+            return Err(AdEssError::InvalidWeights(
+                "`weights` must not be empty and the smallest weight must be 0",
+            ));
+        }
+        if weight_levels.first() != Some(&0) || weight_levels.windows(2).any(|wls| wls[0] >= wls[1])
+        {
+            return Err(AdEssError::InvalidParameter(
+                "The weight levels must be increasing and start at 0",
+            ));
+        }
+        if n_max.checked_add(1) != Some(data.len())
+            || data[0].is_empty()
+            || data.iter().any(|stage| stage.len() != data[0].len())
+            || data[0].len() > weight_levels.len()
+        {
+            return Err(AdEssError::InvalidParameter(
+                "Inconsistent trellis dimensions",
+            ));
+        }
+        let max_wl = *weight_levels.last().expect("checked above");
+        let max_weight = *weights.iter().max().expect("checked above");
+        if n_max
+            .checked_mul(max_weight)
+            .is_some_and(|max_sum_weight| max_wl > max_sum_weight)
+        {
+            return Err(AdEssError::InvalidParameter(
+                "The weight levels exceed the largest sum weight",
+            ));
+        }
+        let weight_level_lookup = Trellis::try_make_weight_level_lookup(&weight_levels)?;
+        let is_weight_level =
+            |wl: usize| wl <= max_wl && Trellis::wl_idx_valid(weight_level_lookup[wl]);
+        for &wl in weight_levels.iter() {
+            let reachable = wl == 0
+                || weights
+                    .iter()
+                    .any(|&w| w > 0 && w <= wl && is_weight_level(wl - w));
+            let complete = weights.iter().all(|&w| {
+                wl.checked_add(w)
+                    .is_none_or(|next_wl| next_wl > max_wl || is_weight_level(next_wl))
+            });
+            if !reachable || !complete {
+                return Err(AdEssError::InvalidParameter(
+                    "The weight levels do not match the weights",
+                ));
+            }
+        }
+
+        let mut sorted_weights: Vec<(usize, usize)> = weights.iter().copied().enumerate().collect();
+        sorted_weights.sort_by_key(|&w_tuple| w_tuple.1);
+        let mut trellis = Trellis {
+            threshold,
+            n_max,
+            weight_level_lookup,
+            weights,
+            weight_levels,
+            sorted_weights,
+            data,
+            successors: Vec::new(),
+            predecessors: Vec::new(),
+            successor_sums: Vec::new(),
+        };
+        trellis.build_adjacency();
+        Ok(trellis)
+    }
+    /// Creates a [Trellis] from node values computed by external tools
+    ///
+    /// `node_values[stage][wl]` is the value of the node at `stage` and weight level `wl`, i.e.,
+    /// each of the `n_max + 1` rows holds `threshold + 1` values indexed by the weight level
+    /// directly. For a forward trellis as used by [AdEss](crate::ad_ess::AdEss) this is the number
+    /// of sequences with `n_max - stage` amplitudes and a sum weight up to `threshold - wl`.
+    /// Values at unreachable weight levels are ignored.
+    pub fn from_arrays(
+        weights: &[usize],
+        threshold: usize,
+        node_values: &[Vec<Integer>],
+    ) -> Result<Trellis, AdEssError> {
+        if weights.iter().min() != Some(&0) {
+            return Err(AdEssError::InvalidWeights(
+                "`weights` must not be empty and the smallest weight must be 0",
+            ));
+        }
+        if node_values.is_empty() || node_values.iter().any(|row| row.len() != threshold + 1) {
+            return Err(AdEssError::InvalidParameter(
+                "`node_values` must have `n_max + 1` rows of `threshold + 1` values",
+            ));
+        }
+        let mut trellis = Trellis::new(threshold, node_values.len() - 1, weights);
+        for (stage, row) in node_values.iter().enumerate() {
+            for wl in trellis.get_weight_levels() {
+                trellis.set(stage, wl, row[wl].clone());
+            }
+        }
+        Ok(trellis)
+    }
+    /// Reads a trellis from the NumPy `.npz` file at `path`, see [Trellis::from_arrays()]
+    ///
+    /// The archive holds the arrays `weights` (1D), `threshold` (scalar) and `node_values` (2D).
+    /// Values exceeding 64 bits can be stored as unicode string arrays of decimal numbers, see
+    /// [npy::read_npy()].
+    pub fn load_npz(path: impl AsRef<Path>) -> io::Result<Trellis> {
+        let invalid_data = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let arrays = npy::load_npz(path)?;
+        let array = |name: &str| {
+            arrays
+                .get(name)
+                .ok_or_else(|| invalid_data("Missing array in npz file"))
+        };
+        let weights = array("weights")?.to_usize().map_err(invalid_data)?;
+        let threshold = match array("threshold")?.to_usize().map_err(invalid_data)?[..] {
+            [threshold] => threshold,
+            _ => return Err(invalid_data("`threshold` must be a single value")),
+        };
+        let node_values = array("node_values")?.rows().map_err(invalid_data)?;
+        Trellis::from_arrays(&weights, threshold, &node_values)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+    /// Writes the trellis to the file at `path`, see [Trellis::write_to()]
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+    /// Reads a trellis from the file at `path`, see [Trellis::read_from()]
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Trellis> {
+        Trellis::read_from(io::BufReader::new(File::open(path)?))
+    }
+    /// Writes the zstd compressed trellis to the file at `path`, see [Trellis::write_to()]
+    ///
+    /// `level` is the zstd compression level, `0` selects the default level.
+    #[cfg(feature = "zstd")]
+    pub fn save_compressed(&self, path: impl AsRef<Path>, level: i32) -> io::Result<()> {
+        let mut encoder = zstd::Encoder::new(File::create(path)?, level)?;
+        self.write_to(&mut encoder)?;
+        encoder.finish()?.flush()
+    }
+    /// Reads a trellis written by [Trellis::save_compressed()] from the file at `path`
+    #[cfg(feature = "zstd")]
+    pub fn load_compressed(path: impl AsRef<Path>) -> io::Result<Trellis> {
+        Trellis::read_from(zstd::Decoder::new(File::open(path)?)?)
+    }
+}
+
+/// Node value serialized as a byte string (least significant byte first)
+#[cfg(feature = "serde")]
+struct NodeBytes(Vec<u8>);
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for NodeBytes {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for NodeBytes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NodeBytesVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for NodeBytesVisitor {
+            type Value = NodeBytes;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a byte string")
+            }
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<NodeBytes, E> {
+                Ok(NodeBytes(bytes.to_vec()))
+            }
+            fn visit_byte_buf<E: serde::de::Error>(self, bytes: Vec<u8>) -> Result<NodeBytes, E> {
+                Ok(NodeBytes(bytes))
+            }
+            /// Self-describing formats without byte strings (e.g., JSON) store a sequence
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<NodeBytes, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(NodeBytes(bytes))
+            }
+        }
+
+        deserializer.deserialize_bytes(NodeBytesVisitor)
+    }
+}
+
+/// Serialized form of a [Trellis], holds the same parts as [Trellis::write_to()]
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedTrellis {
+    threshold: usize,
+    n_max: usize,
+    weights: Vec<usize>,
+    weight_levels: Vec<usize>,
+    node_values: Vec<Vec<NodeBytes>>,
+}
+
+/// Node values are serialized as byte strings, the lookup tables are rebuilt on deserialization
+#[cfg(feature = "serde")]
+impl serde::Serialize for Trellis {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let node_values = self
+            .data
+            .iter()
+            .map(|stage| {
+                stage
+                    .iter()
+                    .map(|value| NodeBytes(value.to_digits::<u8>(Order::Lsf)))
+                    .collect()
+            })
+            .collect();
+        serde::Serialize::serialize(
+            &SerializedTrellis {
+                threshold: self.threshold,
+                n_max: self.n_max,
+                weights: self.weights.clone(),
+                weight_levels: self.weight_levels.clone(),
+                node_values,
+            },
+            serializer,
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Trellis {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized: SerializedTrellis = serde::Deserialize::deserialize(deserializer)?;
+        let data = serialized
+            .node_values
+            .iter()
+            .map(|stage| {
+                stage
+                    .iter()
+                    .map(|bytes| Integer::from_digits(&bytes.0, Order::Lsf))
+                    .collect()
+            })
+            .collect();
+        Trellis::from_parts(
+            serialized.threshold,
+            serialized.n_max,
+            serialized.weights,
+            serialized.weight_levels,
+            data,
+        )
+        .map_err(serde::de::Error::custom)
+    }
+}
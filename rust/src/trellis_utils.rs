@@ -1,5 +1,6 @@
-use rug::Integer;
-
+use crate::bigint::Integer;
+use crate::cancel::CancellationToken;
+use crate::error::AdEssError;
 use crate::trellis::Trellis;
 use crate::utils;
 
@@ -7,52 +8,207 @@ pub fn reverse_trellis_upto_num_sequences(
     num_sequences: Integer,
     n_max: usize,
     weights: &[usize],
-) -> Result<Trellis, &'static str> {
-    let mut reverse_trellis = Trellis::new_expandable(n_max, weights);
-    let weight_levels = reverse_trellis.get_weight_levels();
+) -> Result<Trellis, AdEssError> {
+    reverse_trellis_upto_num_sequences_cancellable(
+        num_sequences,
+        n_max,
+        weights,
+        &CancellationToken::new(),
+    )
+}
 
-    // calculate values for higher weight levels
-    let mut expand_values: Vec<Integer> = vec![];
+/// Like [reverse_trellis_upto_num_sequences()] but returns an error if `token` is cancelled
+///
+/// The token is checked once per added weight level.
+pub fn reverse_trellis_upto_num_sequences_cancellable(
+    num_sequences: Integer,
+    n_max: usize,
+    weights: &[usize],
+    token: &CancellationToken,
+) -> Result<Trellis, AdEssError> {
     let mut current_num_sequences = Integer::from(0);
-    for &wl in weight_levels.iter() {
-        let predecessors = reverse_trellis.get_predecessors(wl);
-        let predecessor_wls: Vec<usize> =
-            predecessors.iter().map(|(_, pred_wl)| *pred_wl).collect();
-        for stage in 0..n_max + 1 {
-            let node_value: Integer = if wl == 0 && stage == 0 {
-                // node at (0, 0) has value 1
-                Integer::from(1)
-            } else {
-                // reverse trellis node value equals sum of its predecessors
-                predecessor_wls
-                    .iter()
-                    .map(|&predecessor_wl| {
-                        if stage == 0 {
-                            // No predecessors in first stage
-                            Integer::from(0)
-                        } else if predecessor_wl == wl {
-                            // value for predecessor not yet stored in the reverse trellis
-                            expand_values
-                                .last()
-                                .expect("already added one element")
-                                .clone()
-                        } else {
-                            reverse_trellis.get(stage - 1, predecessor_wl)
-                        }
-                    })
-                    .sum()
-            };
-            expand_values.push(node_value);
+    let reverse_trellis =
+        expand_reverse_trellis(n_max, weights, usize::MAX, token, |_, wl_num_sequences| {
+            current_num_sequences += wl_num_sequences;
+            current_num_sequences >= num_sequences
+        })?;
+    if current_num_sequences >= num_sequences {
+        Ok(reverse_trellis)
+    } else {
+        Err(AdEssError::TrellisTooSmall("`num_sequences` is to large"))
+    }
+}
+
+/// Like [reverse_trellis_upto_num_sequences()] but adds up to `num_extra_weight_levels` weight
+/// levels after `num_sequences` sequences are reached
+///
+/// The expansion stops at the largest weight level, so no error is returned if the trellis holds
+/// less than `num_sequences` sequences.
+pub fn reverse_trellis_beyond_num_sequences(
+    num_sequences: &Integer,
+    num_extra_weight_levels: usize,
+    n_max: usize,
+    weights: &[usize],
+) -> Trellis {
+    let mut current_num_sequences = Integer::from(0);
+    let mut num_added_extra_levels: Option<usize> = None;
+    expand_reverse_trellis(
+        n_max,
+        weights,
+        usize::MAX,
+        &CancellationToken::new(),
+        |_, wl_num_sequences| match num_added_extra_levels.as_mut() {
+            Some(num_added) => {
+                *num_added += 1;
+                *num_added >= num_extra_weight_levels
+            }
+            None => {
+                current_num_sequences += wl_num_sequences;
+                if current_num_sequences >= *num_sequences {
+                    num_added_extra_levels = Some(0);
+                    num_extra_weight_levels == 0
+                } else {
+                    false
+                }
+            }
+        },
+    )
+    .expect("the token is never cancelled")
+}
+
+/// Returns the number of bits of [crate::ad_ess::AdEss] for each threshold up to `max_threshold`
+///
+/// Entry `threshold` of the returned [Vec] is the number of bits encoded with `threshold`,
+/// `n_max` and `weights`. All entries are derived from a single incrementally expanded reverse
+/// trellis, its last stage holds the number of sequences for each sum weight.
+pub fn num_bits_per_threshold(max_threshold: usize, n_max: usize, weights: &[usize]) -> Vec<u32> {
+    let mut num_bits = Vec::with_capacity(max_threshold + 1);
+    let mut num_sequences = Integer::from(0);
+    expand_reverse_trellis(
+        n_max,
+        weights,
+        max_threshold,
+        &CancellationToken::new(),
+        |wl, wl_num_sequences| {
+            // thresholds between two weight levels have the sequences of the lower level
+            let current_num_bits = num_sequences.significant_bits().saturating_sub(1);
+            num_bits.resize(wl, current_num_bits);
+            num_sequences += wl_num_sequences;
+            false
+        },
+    )
+    .expect("the token is never cancelled");
+    let current_num_bits = num_sequences.significant_bits().saturating_sub(1);
+    num_bits.resize(max_threshold + 1, current_num_bits);
+    num_bits
+}
+
+/// Returns the number of sequences of `AdEss::new(threshold, n_max, weights)` without allocating
+/// a trellis
+///
+/// Only the current stage of the reverse trellis is kept, i.e., the number of sequences for each
+/// sum weight up to `threshold`. The total of the last stage is the value of the node `(0, 0)`
+/// of the forward trellis, which bounds all its node values.
+pub fn count_sequences(threshold: usize, n_max: usize, weights: &[usize]) -> Integer {
+    let mut counts = vec![Integer::from(0); threshold + 1];
+    counts[0] = Integer::from(1);
+    for _ in 0..n_max {
+        let mut next_counts = vec![Integer::from(0); threshold + 1];
+        for (wl, count) in counts.iter().enumerate() {
+            for &w in weights.iter().filter(|&&w| w <= threshold - wl) {
+                next_counts[wl + w] += count;
+            }
         }
-        reverse_trellis.expand_with(&mut expand_values)?;
+        counts = next_counts;
+    }
+    counts
+        .iter()
+        .fold(Integer::from(0), |total, count| total + count)
+}
 
-        current_num_sequences += reverse_trellis.get(n_max, wl);
-        if current_num_sequences >= num_sequences {
-            return Ok(reverse_trellis);
+/// Expands a reverse trellis by one weight level at a time until `is_done` returns `true`
+///
+/// `is_done` is called with each added weight level and the number of sequences with this sum
+/// weight. Weight levels above `max_weight_level` are not added.
+fn expand_reverse_trellis(
+    n_max: usize,
+    weights: &[usize],
+    max_weight_level: usize,
+    token: &CancellationToken,
+    mut is_done: impl FnMut(usize, &Integer) -> bool,
+) -> Result<Trellis, AdEssError> {
+    let mut reverse_trellis = Trellis::new_expandable(n_max, weights);
+    while let Some(&wl) = reverse_trellis
+        .weight_levels_slice()
+        .get(reverse_trellis.get_num_weight_levels())
+    {
+        if wl > max_weight_level {
+            break;
+        }
+        token.check()?;
+        add_reverse_weight_level(&mut reverse_trellis)?;
+
+        if is_done(wl, &reverse_trellis.get(n_max, wl)) {
+            break;
         }
     }
 
-    Err("`num_sequences` is to large")
+    Ok(reverse_trellis)
+}
+
+/// Adds up to `num_weight_levels` weight levels to the expandable `reverse_trellis`
+///
+/// The trellis is grown in place via [Trellis::expand_with()], the stored weight levels are not
+/// recalculated. The expansion stops at the largest weight level, the number of added weight
+/// levels is returned.
+pub fn expand_reverse_trellis_by(reverse_trellis: &mut Trellis, num_weight_levels: usize) -> usize {
+    let num_stored_weight_levels = reverse_trellis.get_num_weight_levels();
+    let num_weight_levels = num_weight_levels
+        .min(reverse_trellis.weight_levels_slice().len() - num_stored_weight_levels);
+    for _ in 0..num_weight_levels {
+        add_reverse_weight_level(reverse_trellis).expect("checked the number of weight levels");
+    }
+    num_weight_levels
+}
+
+/// Calculates the values of the next weight level of `reverse_trellis` and adds them
+fn add_reverse_weight_level(reverse_trellis: &mut Trellis) -> Result<(), AdEssError> {
+    let wl = *reverse_trellis
+        .weight_levels_slice()
+        .get(reverse_trellis.get_num_weight_levels())
+        .ok_or(AdEssError::TrellisTooSmall(
+            "Impossible to add another weight level, trellis to small",
+        ))?;
+    let predecessors = reverse_trellis.get_predecessors(wl);
+    let predecessor_wls: Vec<usize> = predecessors.iter().map(|(_, pred_wl)| *pred_wl).collect();
+    let mut expand_values: Vec<Integer> = vec![];
+    for stage in 0..reverse_trellis.n_max + 1 {
+        let node_value: Integer = if wl == 0 && stage == 0 {
+            // node at (0, 0) has value 1
+            Integer::from(1)
+        } else {
+            // reverse trellis node value equals sum of its predecessors
+            predecessor_wls
+                .iter()
+                .map(|&predecessor_wl| {
+                    if stage == 0 {
+                        // No predecessors in first stage
+                        Integer::from(0)
+                    } else if predecessor_wl == wl {
+                        // value for predecessor not yet stored in the reverse trellis
+                        expand_values
+                            .last()
+                            .expect("already added one element")
+                            .clone()
+                    } else {
+                        reverse_trellis.get(stage - 1, predecessor_wl)
+                    }
+                })
+                .sum()
+        };
+        expand_values.push(node_value);
+    }
+    reverse_trellis.expand_with(&mut expand_values)
 }
 
 pub fn reverse_trellis_lexicographically_bounded(
@@ -0,0 +1,79 @@
+use std::fmt::Debug;
+use std::ops::{AddAssign, SubAssign};
+
+use crate::bigint::Integer;
+
+/// Value type of the nodes of a [Trellis](crate::trellis::Trellis)
+///
+/// Besides the arbitrary precision [Integer], the fixed width types `u64` and `u128` are
+/// implemented. They avoid allocations and need less memory, but may only be used if all node
+/// values fit, see [Trellis::to_backend()](crate::trellis::Trellis::to_backend).
+pub trait TrellisValue:
+    Clone + Debug + Ord + Send + Sync + for<'a> AddAssign<&'a Self> + for<'a> SubAssign<&'a Self>
+{
+    /// Returns the value 0
+    fn zero() -> Self;
+    /// Converts `value`, returns `None` if it does not fit
+    fn from_integer(value: &Integer) -> Option<Self>;
+    /// Converts the value to an [Integer]
+    fn to_integer(&self) -> Integer;
+    /// Returns the number of significant bits of the value
+    fn significant_bits(&self) -> u32;
+    /// Returns the memory footprint of the value in bytes, including heap allocations
+    fn memory_bytes(&self) -> usize;
+}
+
+impl TrellisValue for Integer {
+    fn zero() -> Self {
+        Integer::new()
+    }
+    fn from_integer(value: &Integer) -> Option<Self> {
+        Some(value.clone())
+    }
+    fn to_integer(&self) -> Integer {
+        self.clone()
+    }
+    fn significant_bits(&self) -> u32 {
+        Integer::significant_bits(self)
+    }
+    /// An [Integer] needs its limbs of 64 bit in addition to the inline part
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<Integer>() + (Integer::significant_bits(self) as usize).div_ceil(64) * 8
+    }
+}
+
+impl TrellisValue for u64 {
+    fn zero() -> Self {
+        0
+    }
+    fn from_integer(value: &Integer) -> Option<Self> {
+        value.to_u64()
+    }
+    fn to_integer(&self) -> Integer {
+        Integer::from(*self)
+    }
+    fn significant_bits(&self) -> u32 {
+        u64::BITS - self.leading_zeros()
+    }
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<u64>()
+    }
+}
+
+impl TrellisValue for u128 {
+    fn zero() -> Self {
+        0
+    }
+    fn from_integer(value: &Integer) -> Option<Self> {
+        value.to_u128()
+    }
+    fn to_integer(&self) -> Integer {
+        Integer::from(*self)
+    }
+    fn significant_bits(&self) -> u32 {
+        u128::BITS - self.leading_zeros()
+    }
+    fn memory_bytes(&self) -> usize {
+        std::mem::size_of::<u128>()
+    }
+}
@@ -0,0 +1,79 @@
+use crate::error::AdEssError;
+
+/// Amplitude value `1, 3, 5, ...`
+///
+/// The amplitude `2 * w_idx + 1` belongs to the weight index `w_idx`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amplitude(pub usize);
+
+/// Index into the weights of a trellis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WeightIdx(pub usize);
+
+/// Sum weight of a trellis path, i.e., the weight level of a trellis node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WeightLevel(pub usize);
+
+impl Amplitude {
+    /// Returns the [Amplitude] or an error if `value` is not a positive odd number
+    pub fn new(value: usize) -> Result<Amplitude, AdEssError> {
+        if value % 2 == 1 {
+            Ok(Amplitude(value))
+        } else {
+            Err(AdEssError::InvalidSequence(
+                "Amplitudes must be positive odd numbers",
+            ))
+        }
+    }
+    /// Returns the weight index of the amplitude
+    pub fn weight_idx(self) -> WeightIdx {
+        WeightIdx((self.0 - 1) / 2)
+    }
+}
+
+impl WeightIdx {
+    /// Returns the amplitude of the weight index
+    pub fn amplitude(self) -> Amplitude {
+        Amplitude(2 * self.0 + 1)
+    }
+}
+
+impl From<WeightIdx> for Amplitude {
+    fn from(w_idx: WeightIdx) -> Amplitude {
+        w_idx.amplitude()
+    }
+}
+
+impl From<Amplitude> for WeightIdx {
+    fn from(amplitude: Amplitude) -> WeightIdx {
+        amplitude.weight_idx()
+    }
+}
+
+impl From<Amplitude> for usize {
+    fn from(amplitude: Amplitude) -> usize {
+        amplitude.0
+    }
+}
+
+impl From<WeightIdx> for usize {
+    fn from(w_idx: WeightIdx) -> usize {
+        w_idx.0
+    }
+}
+
+impl From<WeightLevel> for usize {
+    fn from(wl: WeightLevel) -> usize {
+        wl.0
+    }
+}
+
+/// Returns the amplitudes for raw amplitude values or an error for invalid values
+pub fn amplitudes(values: &[usize]) -> Result<Vec<Amplitude>, AdEssError> {
+    values.iter().map(|&value| Amplitude::new(value)).collect()
+}
+
+/// Returns the raw values of `amplitudes`
+pub fn amplitude_values(amplitudes: &[Amplitude]) -> Vec<usize> {
+    amplitudes.iter().map(|a| a.0).collect()
+}
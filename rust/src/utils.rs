@@ -1,13 +1,32 @@
 use std::ops::{Add, Sub};
 
+use crate::bigint::{Integer, Order, Rational};
+use crate::error::AdEssError;
+
 pub fn kl_divergence(p_1: &[f32], p_2: &Vec<f32>) -> f32 {
-    p_1.iter().zip(p_2).fold(0.0, |total, (pi_1, pi_2)| {
-        total + pi_1 * (pi_1 / pi_2).log2()
-    })
+    p_1.iter()
+        .zip(p_2)
+        .filter(|(pi_1, _)| **pi_1 > 0.0)
+        .fold(0.0, |total, (pi_1, pi_2)| {
+            total + pi_1 * (pi_1 / pi_2).log2()
+        })
+}
+
+/// Returns the upper bound on the reduction in mutual information of a distribution matcher
+///
+/// According to formulas (13) and (14) in <https://doi.org/10.1109/LWC.2018.2890595> the bound is
+/// `H(P_A) - rate + D(P_A||P)` where `P_A` is the `amplitude_distribution` of the matcher, `P` the
+/// target `distribution` and `rate` the matcher rate in bit/amplitude.
+pub fn mi_loss_bound(amplitude_distribution: &[f32], distribution: &[f32], rate: f32) -> f32 {
+    entropy(amplitude_distribution) - rate
+        + kl_divergence(amplitude_distribution, &distribution.to_vec())
 }
 
 pub fn entropy(p: &[f32]) -> f32 {
-    p.iter().map(|pi| -pi * pi.log2()).sum()
+    p.iter()
+        .filter(|pi| **pi > 0.0)
+        .map(|pi| -pi * pi.log2())
+        .sum()
 }
 
 pub fn information(p: &[f32]) -> Vec<f32> {
@@ -18,10 +37,10 @@ pub fn cumsum<T>(list: &[T]) -> Vec<T>
 where
     T: Clone,
     T: From<u8>,
-    for<'a> &'a T: Add<&'a T, Output = T>,
+    T: Add<Output = T>,
 {
     list.iter().fold(vec![T::from(0u8)], |mut acc, val| {
-        acc.push(acc.last().unwrap() + val);
+        acc.push(acc.last().unwrap().clone() + val.clone());
         acc
     })
 }
@@ -48,3 +67,318 @@ pub fn distribution_from_weights(weights: &[usize], res_factor: f32) -> Vec<f32>
 
     p_goal
 }
+
+/// Returns the average energy of amplitudes with values `levels` and probabilities `distribution`
+///
+/// `levels[w_idx]` is the value of the amplitude with weight index `w_idx`, the levels can be
+/// arbitrary positive reals, e.g., geometrically shaped levels.
+pub fn average_energy(distribution: &[f32], levels: &[f32]) -> f32 {
+    assert_eq!(
+        distribution.len(),
+        levels.len(),
+        "One level per amplitude required!"
+    );
+    distribution
+        .iter()
+        .zip(levels)
+        .map(|(p, level)| level * level * p)
+        .sum()
+}
+
+/// Returns the uniformly spaced levels `1, 3, 5, ...` of `num_amplitudes` amplitudes
+pub fn uniform_levels(num_amplitudes: usize) -> Vec<f32> {
+    (0..num_amplitudes)
+        .map(|w_idx| (w_idx * 2 + 1) as f32)
+        .collect()
+}
+
+/// Returns the Maxwell-Boltzmann distribution `p_i ~ exp(-nu * levels[i]^2)`
+pub fn maxwell_boltzmann(levels: &[f32], nu: f32) -> Vec<f32> {
+    let min_energy = levels.iter().map(|l| l * l).fold(f32::INFINITY, f32::min);
+    let exps: Vec<f32> = levels
+        .iter()
+        .map(|l| (-nu * (l * l - min_energy)).exp())
+        .collect();
+    let exps_sum = exps.iter().sum::<f32>();
+    exps.iter().map(|exp| exp / exps_sum).collect()
+}
+
+/// Returns the Maxwell-Boltzmann distribution with the given `average_energy`
+///
+/// This distribution has maximum entropy among all distributions on `levels` with the same
+/// average energy. The parameter `nu` is found by bisection.
+pub fn maxwell_boltzmann_for_energy(
+    levels: &[f32],
+    average_energy: f32,
+) -> Result<Vec<f32>, &'static str> {
+    let min_energy = levels.iter().map(|l| l * l).fold(f32::INFINITY, f32::min);
+    if average_energy <= min_energy {
+        return Err("The average energy must be larger than the smallest amplitude energy");
+    }
+    if average_energy >= self::average_energy(&maxwell_boltzmann(levels, 0.0), levels) {
+        // uniform distribution
+        return Ok(maxwell_boltzmann(levels, 0.0));
+    }
+
+    // the average energy decreases with increasing `nu`
+    let mut nu_low = 0.0;
+    let mut nu_high = 1.0;
+    while self::average_energy(&maxwell_boltzmann(levels, nu_high), levels) > average_energy {
+        nu_high *= 2.0;
+    }
+    for _ in 0..64 {
+        let nu = (nu_low + nu_high) / 2.0;
+        if self::average_energy(&maxwell_boltzmann(levels, nu), levels) > average_energy {
+            nu_low = nu;
+        } else {
+            nu_high = nu;
+        }
+    }
+    Ok(maxwell_boltzmann(levels, nu_high))
+}
+
+/// Returns the Maxwell-Boltzmann distribution with the given `entropy` in bit
+///
+/// The entropy decreases with increasing `nu`, which is found by bisection.
+pub fn maxwell_boltzmann_for_entropy(
+    levels: &[f32],
+    entropy: f32,
+) -> Result<Vec<f32>, &'static str> {
+    if !(entropy > 0.0 && entropy < (levels.len() as f32).log2()) {
+        return Err(
+            "The entropy must be positive and below the entropy of the uniform distribution",
+        );
+    }
+    let mut nu_low = 0.0;
+    let mut nu_high = 1.0;
+    while self::entropy(&maxwell_boltzmann(levels, nu_high)) > entropy {
+        nu_high *= 2.0;
+    }
+    for _ in 0..64 {
+        let nu = (nu_low + nu_high) / 2.0;
+        if self::entropy(&maxwell_boltzmann(levels, nu)) > entropy {
+            nu_low = nu;
+        } else {
+            nu_high = nu;
+        }
+    }
+    Ok(maxwell_boltzmann(levels, nu_high))
+}
+
+/// Returns the Maxwell-Boltzmann distribution `p_i ~ exp(-lambda * (2 * i + 1)^2)` of
+/// `num_amplitudes` amplitudes, see [maxwell_boltzmann()]
+///
+/// The result can be passed to, e.g.,
+/// [AdEss::new_for_distribution_num_bits()](crate::ad_ess::AdEss::new_for_distribution_num_bits).
+pub fn mb(num_amplitudes: usize, lambda: f32) -> Vec<f32> {
+    maxwell_boltzmann(&uniform_levels(num_amplitudes), lambda)
+}
+
+/// Returns the Maxwell-Boltzmann distribution of `num_amplitudes` amplitudes with the entropy
+/// `bits_per_amp` in bit, see [maxwell_boltzmann_for_entropy()]
+pub fn mb_for_rate(num_amplitudes: usize, bits_per_amp: f32) -> Result<Vec<f32>, AdEssError> {
+    maxwell_boltzmann_for_entropy(&uniform_levels(num_amplitudes), bits_per_amp)
+        .map_err(AdEssError::InvalidParameter)
+}
+
+/// Returns the Maxwell-Boltzmann distribution of `num_amplitudes` amplitudes with the average
+/// energy `e_avg`, see [maxwell_boltzmann_for_energy()]
+pub fn mb_for_average_energy(num_amplitudes: usize, e_avg: f32) -> Result<Vec<f32>, AdEssError> {
+    maxwell_boltzmann_for_energy(&uniform_levels(num_amplitudes), e_avg)
+        .map_err(AdEssError::InvalidParameter)
+}
+
+/// Replaces the amplitudes `1, 3, 5, ...` in a sequence by the corresponding `levels`
+pub fn amplitude_seq_to_levels(amplitude_sequence: &[usize], levels: &[f32]) -> Vec<f32> {
+    amplitude_sequence
+        .iter()
+        .map(|a| levels[(a - 1) / 2])
+        .collect()
+}
+
+/// Order of the bits when an index is converted from / to bits or bytes
+///
+/// DSP chains differ on this convention, a mismatch between encoder and decoder silently
+/// corrupts the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitOrder {
+    /// The most significant bit comes first, bytes are big endian
+    #[default]
+    MsbFirst,
+    /// The least significant bit comes first, bytes are little endian with the least
+    /// significant bit of each byte first
+    LsbFirst,
+}
+
+/// Returns `log2(integer)` of a positive [Integer] (negative infinity for 0)
+///
+/// Unlike `integer.to_f64().log2()` this does not overflow for integers above `2^1024`.
+pub fn integer_log2(integer: &Integer) -> f64 {
+    let shift = integer.significant_bits().saturating_sub(64);
+    Integer::from(integer >> shift).to_f64().log2() + shift as f64
+}
+
+/// Converts bits (most significant bit first) into an [Integer]
+pub fn bits_to_integer(bits: &[u8]) -> Integer {
+    bits_to_integer_with_order(bits, BitOrder::MsbFirst)
+}
+
+/// Converts bits in `bit_order` into an [Integer]
+pub fn bits_to_integer_with_order(bits: &[u8], bit_order: BitOrder) -> Integer {
+    let fold = |integer: Integer, &bit: &u8| (integer << 1) + bit;
+    match bit_order {
+        BitOrder::MsbFirst => bits.iter().fold(Integer::new(), fold),
+        BitOrder::LsbFirst => bits.iter().rev().fold(Integer::new(), fold),
+    }
+}
+
+/// Converts an [Integer] into `num_bits` bits (most significant bit first)
+pub fn integer_to_bits(integer: &Integer, num_bits: usize) -> Vec<u8> {
+    integer_to_bits_with_order(integer, num_bits, BitOrder::MsbFirst)
+}
+
+/// Converts an [Integer] into `num_bits` bits in `bit_order`
+pub fn integer_to_bits_with_order(
+    integer: &Integer,
+    num_bits: usize,
+    bit_order: BitOrder,
+) -> Vec<u8> {
+    let mut bits = vec![0; num_bits];
+    integer_to_bits_into(integer, bit_order, &mut bits);
+    bits
+}
+
+/// Writes the lowest `bits.len()` bits of `integer` in `bit_order` into `bits`
+pub fn integer_to_bits_into(integer: &Integer, bit_order: BitOrder, bits: &mut [u8]) {
+    let num_bits = bits.len();
+    for (i, bit) in bits.iter_mut().enumerate() {
+        let bit_idx = match bit_order {
+            BitOrder::MsbFirst => num_bits - 1 - i,
+            BitOrder::LsbFirst => i,
+        };
+        *bit = integer.get_bit(bit_idx as u32) as u8;
+    }
+}
+
+/// Returns the digit order of bytes in `bit_order`
+fn byte_order(bit_order: BitOrder) -> Order {
+    match bit_order {
+        BitOrder::MsbFirst => Order::Msf,
+        BitOrder::LsbFirst => Order::Lsf,
+    }
+}
+
+/// Converts bytes in `bit_order` into an [Integer]
+///
+/// The bytes hold the bits of [integer_to_bits_with_order()], eight bits per byte.
+pub fn bytes_to_integer(bytes: &[u8], bit_order: BitOrder) -> Integer {
+    Integer::from_digits(bytes, byte_order(bit_order))
+}
+
+/// Writes the non-negative `integer` in `bit_order` into `out`, see [bytes_to_integer()]
+///
+/// Panics if `integer` does not fit into `out`.
+pub fn integer_to_bytes(integer: &Integer, bit_order: BitOrder, out: &mut [u8]) {
+    let bytes = integer.to_digits::<u8>(byte_order(bit_order));
+    assert!(bytes.len() <= out.len(), "Integer does not fit into `out`");
+    let num_padding = out.len() - bytes.len();
+    match bit_order {
+        BitOrder::MsbFirst => {
+            out[..num_padding].fill(0);
+            out[num_padding..].copy_from_slice(&bytes);
+        }
+        BitOrder::LsbFirst => {
+            out[..bytes.len()].copy_from_slice(&bytes);
+            out[bytes.len()..].fill(0);
+        }
+    }
+}
+
+/// Returns the index of `num_bits` bits held by `bytes`, see [bytes_to_integer()]
+///
+/// `bytes` must have `ceil(num_bits / 8)` entries and the unused padding bits must be 0.
+pub(crate) fn index_from_bytes(
+    bytes: &[u8],
+    num_bits: u32,
+    bit_order: BitOrder,
+) -> Result<Integer, AdEssError> {
+    if bytes.len() != (num_bits as usize).div_ceil(8) {
+        return Err(AdEssError::InvalidParameter(
+            "`bytes` must have `ceil(num_bits / 8)` entries",
+        ));
+    }
+    let index = bytes_to_integer(bytes, bit_order);
+    if index.significant_bits() > num_bits {
+        return Err(AdEssError::IndexOutOfRange);
+    }
+    Ok(index)
+}
+
+/// Writes the `index` of `num_bits` bits into `out`, see [index_from_bytes()]
+pub(crate) fn index_to_bytes(
+    index: &Integer,
+    num_bits: u32,
+    bit_order: BitOrder,
+    out: &mut [u8],
+) -> Result<(), AdEssError> {
+    if out.len() != (num_bits as usize).div_ceil(8) {
+        return Err(AdEssError::InvalidParameter(
+            "`out` must have `ceil(num_bits / 8)` entries",
+        ));
+    }
+    if index.significant_bits() > num_bits {
+        return Err(AdEssError::InvalidSequence(
+            "The index of the sequence exceeds `num_bits` bits",
+        ));
+    }
+    integer_to_bytes(index, bit_order, out);
+    Ok(())
+}
+
+/// Number of sequences of a trellis based distribution matcher
+///
+/// Only the `used` sequences (the largest power of two up to `total`) are addressed by indexes
+/// with [AdEss::num_bits()](crate::ad_ess::AdEss::num_bits) bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceCounts {
+    /// Number of sequences held by the trellis
+    pub total: Integer,
+    /// Number of sequences used for encoding
+    pub used: Integer,
+}
+
+impl SequenceCounts {
+    /// Returns the counts for a trellis holding `total` sequences
+    pub fn for_total(total: Integer) -> SequenceCounts {
+        let used = match total.significant_bits() {
+            0 => Integer::new(),
+            bits => Integer::from(1) << (bits - 1),
+        };
+        SequenceCounts { total, used }
+    }
+    /// Returns the fraction of used sequences `used / total`
+    pub fn utilization(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        Rational::from((&self.used, &self.total)).to_f64()
+    }
+}
+
+/// Applies `f` to all `items` on `num_threads` threads and returns the results in order
+///
+/// With `num_threads == 0` the number of threads is chosen by rayon.
+#[cfg(feature = "rayon")]
+pub(crate) fn par_map<T: Sync, O: Send>(
+    items: &[T],
+    num_threads: usize,
+    f: impl Fn(&T) -> O + Sync + Send,
+) -> Vec<O> {
+    use rayon::prelude::*;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .expect("failed to spawn the worker threads");
+    pool.install(|| items.par_iter().map(f).collect())
+}
@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use crate::ad_ess::AdEss;
+use crate::bigint::{Complete, Integer, Rational};
+use crate::cancel::CancellationToken;
+use crate::error::AdEssError;
+use crate::trellis::Trellis;
+
+/// AD-ESS with a different threshold for each stage (wedge-shaped bounding region)
+///
+/// `stage_thresholds[n]` bounds the sum weight of the first `n + 1` amplitudes, i.e., the
+/// trellis holds all sequences whose cumulative weights stay below the bounds of all stages.
+/// Nodes outside the bounds hold the value 0, so encoding and decoding work like for
+/// [AdEss]. Unlike the sphere of [AdEss] the bounding region is not invariant to permutations
+/// of the amplitudes, the amplitude distributions are thus calculated position by position.
+pub struct WedgeAdEss {
+    adess: AdEss,
+    stage_thresholds: Vec<usize>,
+}
+
+impl WedgeAdEss {
+    /// Returns a new [WedgeAdEss] instance given the per stage thresholds and the weights
+    ///
+    /// `n_max` is the number of stage thresholds, the smallest weight must be 0.
+    pub fn new(stage_thresholds: &[usize], weights: &[usize]) -> Result<WedgeAdEss, AdEssError> {
+        if weights.iter().min() != Some(&0) {
+            return Err(AdEssError::InvalidWeights(
+                "`weights` must not be empty and the smallest weight must be 0",
+            ));
+        }
+        let n_max = stage_thresholds.len();
+        let threshold = stage_thresholds.iter().copied().max().unwrap_or(0);
+        let mut adess = AdEss {
+            trellis: Arc::new(Trellis::new(threshold, n_max, weights)),
+        };
+        adess.fill_forward_trellis(
+            |n, wl| n == 0 || wl <= stage_thresholds[n - 1],
+            &CancellationToken::new(),
+            None,
+        )?;
+        Ok(WedgeAdEss {
+            adess,
+            stage_thresholds: stage_thresholds.to_vec(),
+        })
+    }
+    /// Returns the threshold of each stage
+    pub fn get_stage_thresholds(&self) -> Vec<usize> {
+        self.stage_thresholds.clone()
+    }
+    /// Returns the weights used by the internal trellis
+    pub fn get_weights(&self) -> Vec<usize> {
+        self.adess.get_weights()
+    }
+    /// Returns the internal trellis, nodes outside the bounds hold the value 0
+    pub fn trellis(&self) -> &Trellis {
+        &self.adess.trellis
+    }
+    /// Returns the number of amplitudes per sequence
+    pub fn n_max(&self) -> usize {
+        self.stage_thresholds.len()
+    }
+    /// Returns the number of sequences that can be encoded / decoded
+    pub fn num_sequences(&self) -> Integer {
+        self.adess.num_sequences()
+    }
+    /// Returns the number of bits that can be encoded / decoded
+    pub fn num_bits(&self) -> u32 {
+        self.adess.num_bits()
+    }
+    /// Returns the amplitude sequence for a given `index` (encode)
+    pub fn sequence_for_index(&self, index: &Integer) -> Vec<usize> {
+        self.adess.sequence_for_index(index)
+    }
+    /// Returns the index for a given `amplitude_sequence` (decode)
+    pub fn index_for_sequence(&self, amplitude_sequence: &[usize]) -> Integer {
+        self.adess.index_for_sequence(amplitude_sequence)
+    }
+    /// Returns the number of used sequences with each amplitude at each position
+    ///
+    /// `counts[n][w_idx]` counts the sequences with the amplitude of weight index `w_idx` at
+    /// position `n`, only indexes with [WedgeAdEss::num_bits()] bits are used.
+    ///
+    /// The used sequences are all completions of the nodes which split off the path of the first
+    /// abandoned sequence (FAS) below it. The number of paths from these nodes to each node is
+    /// propagated forward through the trellis, each branch then contributes the number of paths
+    /// reaching it times the number of completions after it.
+    fn amplitude_counts_per_position(&self) -> Vec<Vec<Integer>> {
+        let trellis = &self.adess.trellis;
+        let n_max = trellis.n_max;
+        let weight_levels = trellis.get_weight_levels();
+        let num_sequences_used = Integer::u_pow_u(2, self.num_bits()).complete();
+        let mut counts = vec![vec![Integer::new(); trellis.get_weights().len()]; n_max];
+
+        // split_nodes[n]: nodes at stage n whose completions are all used
+        let mut split_nodes = vec![vec![]; n_max + 1];
+        if num_sequences_used == self.num_sequences() {
+            split_nodes[0].push(0);
+        } else {
+            let first_abandoned_sequence = self.sequence_for_index(&num_sequences_used);
+            let fas_w_idxs: Vec<usize> = first_abandoned_sequence
+                .iter()
+                .map(|a| (a - 1) / 2)
+                .collect();
+            let mut current_wl = 0;
+            for n in 0..n_max {
+                for (w_idx, next_wl) in trellis.get_successors(current_wl) {
+                    if w_idx == fas_w_idxs[n] {
+                        current_wl = next_wl;
+                        break;
+                    }
+                    let value = trellis.get(n + 1, next_wl);
+                    if value == 0 {
+                        continue;
+                    }
+                    // the branch itself and the FAS prefix before it
+                    counts[n][w_idx] += &value;
+                    for (k, &fas_w_idx) in fas_w_idxs.iter().enumerate().take(n) {
+                        counts[k][fas_w_idx] += &value;
+                    }
+                    split_nodes[n + 1].push(next_wl);
+                }
+            }
+        }
+
+        // paths[wl_idx]: number of paths from split nodes to node (n, wl)
+        let mut paths = vec![Integer::new(); weight_levels.len()];
+        for n in 0..n_max {
+            for &wl in split_nodes[n].iter() {
+                paths[trellis.get_weight_level_index(wl)] += 1;
+            }
+            let mut next_paths = vec![Integer::new(); weight_levels.len()];
+            for (wl_idx, &wl) in weight_levels.iter().enumerate() {
+                if paths[wl_idx] == 0 {
+                    continue;
+                }
+                for (w_idx, next_wl) in trellis.get_successors(wl) {
+                    let value = trellis.get(n + 1, next_wl);
+                    if value == 0 {
+                        continue;
+                    }
+                    counts[n][w_idx] += (&paths[wl_idx] * &value).complete();
+                    next_paths[trellis.get_weight_level_index(next_wl)] += &paths[wl_idx];
+                }
+            }
+            paths = next_paths;
+        }
+        counts
+    }
+    /// Returns the amplitude distribution for each position of the sequences
+    ///
+    /// `distribution[n][w_idx]` is the probability of the amplitude with weight index `w_idx` at
+    /// position `n` if indexes with [WedgeAdEss::num_bits()] bits are used equiprobably.
+    pub fn amplitude_distribution_per_position(&self) -> Vec<Vec<f32>> {
+        let num_sequences_used = Integer::u_pow_u(2, self.num_bits()).complete();
+        self.amplitude_counts_per_position()
+            .into_iter()
+            .map(|position_counts| {
+                position_counts
+                    .into_iter()
+                    .map(|count| Rational::from((count, &num_sequences_used)).to_f32())
+                    .collect()
+            })
+            .collect()
+    }
+    /// Returns the amplitude distribution if indexes with [WedgeAdEss::num_bits()] bits are used
+    /// equiprobably
+    pub fn amplitude_distribution(&self) -> Vec<f32> {
+        let num_amplitudes_used =
+            Integer::u_pow_u(2, self.num_bits()).complete() * self.n_max() as u32;
+        let mut counts = vec![Integer::new(); self.get_weights().len()];
+        for position_counts in self.amplitude_counts_per_position() {
+            for (count, position_count) in counts.iter_mut().zip(position_counts) {
+                *count += position_count;
+            }
+        }
+        if self.n_max() == 0 {
+            return vec![0f32; counts.len()];
+        }
+        counts
+            .into_iter()
+            .map(|count| Rational::from((count, &num_amplitudes_used)).to_f32())
+            .collect()
+    }
+}
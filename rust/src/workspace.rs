@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use crate::ad_ess::AdEss;
+use crate::bigint::{Assign, Integer};
+use crate::error::AdEssError;
+use crate::utils::{self, BitOrder};
+
+/// Reusable scratch memory for encoding / decoding without allocations
+///
+/// The index is kept in a preallocated [Integer] and amplitudes / bits are written into
+/// caller-provided buffers, so repeated calls in real-time loops do not allocate once the
+/// [Integer] has grown to `num_bits` bits. Trellis values are compared by reference.
+pub struct Workspace {
+    adess: Arc<AdEss>,
+    index: Integer,
+    bit_order: BitOrder,
+}
+
+impl Workspace {
+    /// Returns a [Workspace] for `adess`
+    pub fn new(adess: Arc<AdEss>) -> Workspace {
+        let index = Integer::with_capacity(adess.num_sequences().significant_bits() as usize);
+        Workspace {
+            adess,
+            index,
+            bit_order: BitOrder::MsbFirst,
+        }
+    }
+    /// Sets the order of the index bits of [Workspace::encode_bits_into()] and
+    /// [Workspace::decode_bits_into()], most significant bit first by default
+    pub fn set_bit_order(&mut self, bit_order: BitOrder) {
+        self.bit_order = bit_order;
+    }
+    /// Returns the order of the index bits, see [Workspace::set_bit_order()]
+    pub fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+    /// Returns the [AdEss] of the workspace
+    pub fn adess(&self) -> &AdEss {
+        &self.adess
+    }
+    /// Returns the index of the last decoded sequence
+    pub fn index(&self) -> &Integer {
+        &self.index
+    }
+    /// Writes the amplitude sequence for `index` into `sequence` (encode)
+    ///
+    /// An error is returned if `sequence` does not have `n_max` entries or `index` is out of
+    /// range.
+    pub fn encode_into(
+        &mut self,
+        index: &Integer,
+        sequence: &mut [usize],
+    ) -> Result<(), AdEssError> {
+        self.index.assign(index);
+        self.encode_index_into(sequence)
+    }
+    /// Writes the amplitude sequence for the `index_bits` (see [Workspace::set_bit_order()]) into
+    /// `sequence`, see [Workspace::encode_into()]
+    pub fn encode_bits_into(
+        &mut self,
+        index_bits: &[u8],
+        sequence: &mut [usize],
+    ) -> Result<(), AdEssError> {
+        if index_bits.len() != self.adess.num_bits() as usize {
+            return Err(AdEssError::InvalidParameter(
+                "`index_bits` must have `num_bits` bits",
+            ));
+        }
+        if index_bits.iter().any(|&bit| bit > 1) {
+            return Err(AdEssError::InvalidParameter("Bits must be 0 or 1"));
+        }
+        self.index.assign(0);
+        let mut push_bit = |&bit: &u8| {
+            self.index <<= 1;
+            self.index += bit;
+        };
+        match self.bit_order {
+            BitOrder::MsbFirst => index_bits.iter().for_each(&mut push_bit),
+            BitOrder::LsbFirst => index_bits.iter().rev().for_each(&mut push_bit),
+        }
+        self.encode_index_into(sequence)
+    }
+    /// Returns the index for `sequence` (decode), see [Workspace::index()]
+    ///
+    /// An error is returned if `sequence` does not have `n_max` amplitudes or leaves the
+    /// trellis.
+    pub fn decode(&mut self, sequence: &[usize]) -> Result<&Integer, AdEssError> {
+        let trellis = &self.adess.trellis;
+        if sequence.len() != trellis.n_max {
+            return Err(AdEssError::InvalidSequence(
+                "`sequence` must have `n_max` amplitudes",
+            ));
+        }
+        self.index.assign(0);
+        let mut current_wl = 0;
+        for (n, &amplitude) in sequence.iter().enumerate() {
+            if amplitude.is_multiple_of(2) {
+                return Err(AdEssError::InvalidSequence(
+                    "Amplitudes must be positive odd numbers",
+                ));
+            }
+            let target_w_idx = (amplitude - 1) / 2;
+            let mut next_wl = None;
+            for &(w_idx, weight) in trellis.get_sorted_weights() {
+                let successor = current_wl + weight;
+                if successor > trellis.threshold {
+                    break;
+                }
+                if w_idx == target_w_idx {
+                    next_wl = Some(successor);
+                    break;
+                }
+                self.index += trellis.get_ref(n + 1, successor);
+            }
+            current_wl =
+                next_wl.ok_or(AdEssError::InvalidSequence("Amplitude leaves the trellis"))?;
+        }
+        Ok(&self.index)
+    }
+    /// Writes the `num_bits` index bits (see [Workspace::set_bit_order()]) for `sequence` into
+    /// `index_bits`, see [Workspace::decode()]
+    pub fn decode_bits_into(
+        &mut self,
+        sequence: &[usize],
+        index_bits: &mut [u8],
+    ) -> Result<(), AdEssError> {
+        if index_bits.len() != self.adess.num_bits() as usize {
+            return Err(AdEssError::InvalidParameter(
+                "`index_bits` must have `num_bits` bits",
+            ));
+        }
+        let bit_order = self.bit_order;
+        let index = self.decode(sequence)?;
+        utils::integer_to_bits_into(index, bit_order, index_bits);
+        Ok(())
+    }
+    /// Writes the amplitude sequence of the stored index into `sequence`
+    ///
+    /// The stored index is consumed during the encoding.
+    fn encode_index_into(&mut self, sequence: &mut [usize]) -> Result<(), AdEssError> {
+        let trellis = &self.adess.trellis;
+        if sequence.len() != trellis.n_max {
+            return Err(AdEssError::InvalidParameter(
+                "`sequence` must have `n_max` entries",
+            ));
+        }
+        if self.index < 0 || self.index >= *trellis.get_ref(0, 0) {
+            return Err(AdEssError::IndexOutOfRange);
+        }
+        self.adess
+            .encode_remaining_index_into(&mut self.index, sequence);
+        Ok(())
+    }
+}